@@ -0,0 +1,3 @@
+-- Fingerprint of the issue body, used to spot accidental re-sends
+ALTER TABLE issues ADD COLUMN content_hash TEXT NULL;
+CREATE INDEX issues_content_hash_idx ON issues (content_hash);
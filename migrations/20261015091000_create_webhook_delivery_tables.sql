@@ -0,0 +1,22 @@
+-- Outbound webhook events waiting to be delivered
+CREATE TABLE webhook_delivery_queue (
+    id uuid NOT NULL,
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    n_retries SMALLINT NOT NULL DEFAULT 0,
+    execute_after TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (id)
+);
+
+-- Outbound webhook events that exhausted their retries
+CREATE TABLE webhook_dead_letter (
+    id uuid NOT NULL,
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    n_retries SMALLINT NOT NULL,
+    last_error TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    failed_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (id)
+);
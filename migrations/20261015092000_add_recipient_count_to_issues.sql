@@ -0,0 +1,2 @@
+-- Number of delivery tasks enqueued when the issue was published
+ALTER TABLE issues ADD COLUMN recipient_count INTEGER NULL;
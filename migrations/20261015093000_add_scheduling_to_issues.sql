@@ -0,0 +1,6 @@
+-- Issues can be drafted or scheduled before they are published
+ALTER TABLE issues ADD COLUMN status TEXT NOT NULL DEFAULT 'published';
+ALTER TABLE issues ADD COLUMN scheduled_at TIMESTAMPTZ NULL;
+ALTER TABLE issues ALTER COLUMN published_at DROP NOT NULL;
+CREATE INDEX issues_scheduled_at_idx ON issues (scheduled_at)
+    WHERE status = 'scheduled';
@@ -0,0 +1,3 @@
+-- Allow a queued webhook to override the configured target URL
+ALTER TABLE webhook_delivery_queue ADD COLUMN target_url TEXT NULL;
+ALTER TABLE webhook_dead_letter ADD COLUMN target_url TEXT NULL;
@@ -0,0 +1,19 @@
+-- Retry failed issue deliveries with backoff and keep per-issue counters
+ALTER TABLE issue_delivery_queue
+    ADD COLUMN n_retries SMALLINT NOT NULL DEFAULT 0,
+    ADD COLUMN execute_after TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE TABLE issue_delivery_dead_letter (
+    issue_id uuid NOT NULL REFERENCES issues(issue_id),
+    subscriber_email TEXT NOT NULL,
+    n_retries SMALLINT NOT NULL,
+    last_error TEXT NOT NULL,
+    failed_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (issue_id, subscriber_email)
+);
+
+ALTER TABLE issues
+    ADD COLUMN delivery_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN delivery_retries INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN delivery_successes INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN delivery_dead_letters INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+-- Per-recipient events reported back about sent emails (bounces, complaints)
+CREATE TABLE email_events (
+    id uuid NOT NULL,
+    issue_id uuid NULL REFERENCES issues(issue_id),
+    subscriber_email TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (id)
+);
+CREATE INDEX email_events_issue_id_idx ON email_events (issue_id, event_type);
+
+-- Set once the bounce/complaint alarm has fired for an issue
+ALTER TABLE issues ADD COLUMN alarm_raised_at TIMESTAMPTZ NULL;
@@ -0,0 +1,10 @@
+-- Recent signups, used to throttle automated mass signups
+CREATE TABLE signup_attempts (
+    ip TEXT NOT NULL,
+    email_pattern TEXT NOT NULL,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX signup_attempts_ip_idx ON signup_attempts (ip, attempted_at);
+CREATE INDEX signup_attempts_email_pattern_idx
+    ON signup_attempts (email_pattern, attempted_at);
+CREATE INDEX signup_attempts_attempted_at_idx ON signup_attempts (attempted_at);
@@ -0,0 +1,16 @@
+-- Runtime overrides of feature flags, on top of the configured defaults
+CREATE TABLE feature_flags (
+    feature TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (feature)
+);
+
+CREATE TABLE feature_flag_audit (
+    id uuid NOT NULL,
+    feature TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    changed_by uuid NOT NULL REFERENCES users(user_id),
+    changed_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (id)
+);
@@ -0,0 +1,8 @@
+-- Address changes waiting for the new address to be confirmed
+CREATE TABLE email_change_requests (
+    confirmation_token TEXT NOT NULL,
+    subscriber_id uuid NOT NULL REFERENCES subscriptions(id),
+    new_email TEXT NOT NULL,
+    requested_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (confirmation_token)
+);
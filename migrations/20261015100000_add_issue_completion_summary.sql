@@ -0,0 +1,2 @@
+-- Set once the author has been sent the delivery summary of an issue
+ALTER TABLE issues ADD COLUMN completion_summary_sent_at TIMESTAMPTZ NULL;
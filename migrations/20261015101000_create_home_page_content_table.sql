@@ -0,0 +1,9 @@
+-- Editable copy of the landing page; the page falls back to the built-in
+-- content while this table is empty
+CREATE TABLE home_page_content (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    heading TEXT NOT NULL,
+    body TEXT NOT NULL,
+    updated_by uuid NOT NULL REFERENCES users(user_id),
+    updated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,3 @@
+-- The request id of the publish request, carried over to its deliveries
+ALTER TABLE issues ADD COLUMN correlation_id uuid NULL;
+ALTER TABLE issue_delivery_queue ADD COLUMN correlation_id uuid NULL;
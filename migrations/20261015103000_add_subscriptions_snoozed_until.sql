@@ -0,0 +1,2 @@
+-- Deliveries to a snoozed subscriber wait until this time
+ALTER TABLE subscriptions ADD COLUMN snoozed_until TIMESTAMPTZ NULL;
@@ -0,0 +1,4 @@
+-- Keyset pagination order of the admin event listings
+CREATE INDEX email_events_occurred_at_idx ON email_events (occurred_at, id);
+CREATE INDEX feature_flag_audit_changed_at_idx
+    ON feature_flag_audit (changed_at, id);
@@ -0,0 +1,8 @@
+-- Provider webhook events that have already been handled, so a retried
+-- delivery is acknowledged without being processed twice
+CREATE TABLE processed_webhook_events (
+    record_type TEXT NOT NULL,
+    event_id TEXT NOT NULL,
+    processed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (record_type, event_id)
+);
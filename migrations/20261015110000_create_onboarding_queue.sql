@@ -0,0 +1,11 @@
+-- Onboarding emails, one row per subscriber and step of the sequence, so
+-- scheduling the sequence again never duplicates a step
+CREATE TABLE onboarding_queue (
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    step TEXT NOT NULL,
+    execute_after TIMESTAMPTZ NOT NULL,
+    n_retries SMALLINT NOT NULL DEFAULT 0,
+    sent_at TIMESTAMPTZ NULL,
+    PRIMARY KEY (subscriber_id, step)
+);
@@ -0,0 +1,11 @@
+-- Long-lived keys for server-to-server calls; only a SHA-256 digest of each
+-- key is stored, so a leaked table cannot be replayed
+CREATE TABLE api_keys (
+    id uuid PRIMARY KEY,
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    created_by uuid NOT NULL REFERENCES users (user_id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    revoked_at TIMESTAMPTZ NULL
+);
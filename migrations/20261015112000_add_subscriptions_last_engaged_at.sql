@@ -0,0 +1,2 @@
+-- The last time the subscriber opened or clicked an email, if ever
+ALTER TABLE subscriptions ADD COLUMN last_engaged_at TIMESTAMPTZ NULL;
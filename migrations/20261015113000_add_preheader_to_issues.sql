@@ -0,0 +1,2 @@
+-- Inbox preview text; derived from the body at send time when NULL
+ALTER TABLE issues ADD COLUMN preheader TEXT NULL;
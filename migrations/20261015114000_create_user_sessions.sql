@@ -0,0 +1,12 @@
+-- Login sessions, so the number a user holds can be capped; an evicted
+-- session stays invalid even though its cookie is still around
+CREATE TABLE user_sessions (
+    session_id uuid PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (user_id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    revoked_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX user_sessions_active_idx
+    ON user_sessions (user_id, created_at)
+    WHERE revoked_at IS NULL;
@@ -0,0 +1,10 @@
+-- One row per newsletter email the provider accepted, keeping the
+-- provider's message ID so individual sends can be looked up
+CREATE TABLE issue_deliveries (
+    issue_id uuid NOT NULL REFERENCES issues(issue_id),
+    subscriber_email TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    delivered_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (issue_id, subscriber_email)
+);
+CREATE INDEX issue_deliveries_message_id_idx ON issue_deliveries (message_id);
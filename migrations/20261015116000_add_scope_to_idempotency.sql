@@ -0,0 +1,7 @@
+-- Idempotency keys are unique per endpoint, so the same key sent to two
+-- endpoints does not replay one's response on the other
+ALTER TABLE idempotency
+    ADD COLUMN scope TEXT NOT NULL DEFAULT 'publish_newsletter';
+ALTER TABLE idempotency ALTER COLUMN scope DROP DEFAULT;
+ALTER TABLE idempotency DROP CONSTRAINT idempotency_pkey;
+ALTER TABLE idempotency ADD PRIMARY KEY (user_id, scope, idempotency_key);
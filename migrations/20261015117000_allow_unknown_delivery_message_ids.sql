@@ -0,0 +1,2 @@
+-- The provider may accept an email with a response we cannot read
+ALTER TABLE issue_deliveries ALTER COLUMN message_id DROP NOT NULL;
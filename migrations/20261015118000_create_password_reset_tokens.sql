@@ -0,0 +1,11 @@
+-- Where a user's password reset links are sent
+ALTER TABLE users ADD COLUMN email TEXT NULL UNIQUE;
+
+-- Single-use password reset links; only a digest of each token is stored,
+-- so a leaked table cannot be used to take over accounts
+CREATE TABLE password_reset_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (user_id),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ NULL
+);
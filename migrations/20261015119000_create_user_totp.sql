@@ -0,0 +1,10 @@
+-- TOTP secrets for two-factor authentication, encrypted with the configured
+-- key; a secret only counts once the user confirmed it with a code
+CREATE TABLE user_totp (
+    user_id uuid PRIMARY KEY REFERENCES users (user_id),
+    encrypted_secret BYTEA NOT NULL,
+    enabled_at TIMESTAMPTZ NULL,
+    -- The time step of the last accepted code, so it cannot be replayed
+    last_used_step BIGINT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
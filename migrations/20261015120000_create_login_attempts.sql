@@ -0,0 +1,10 @@
+-- Recent failed logins, used to lock out password guessing
+CREATE TABLE login_attempts (
+    username TEXT NOT NULL,
+    ip TEXT NOT NULL,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX login_attempts_username_idx
+    ON login_attempts (username, attempted_at);
+CREATE INDEX login_attempts_ip_idx ON login_attempts (ip, attempted_at);
+CREATE INDEX login_attempts_attempted_at_idx ON login_attempts (attempted_at);
@@ -0,0 +1,3 @@
+-- Send-time optimization looks up each recipient's past opens
+CREATE INDEX email_events_subscriber_email_idx
+    ON email_events (subscriber_email, event_type);
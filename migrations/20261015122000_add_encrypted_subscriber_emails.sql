@@ -0,0 +1,3 @@
+-- Encrypted copies of subscriber addresses, set when addresses are stored hashed.
+ALTER TABLE subscriptions ADD COLUMN encrypted_email BYTEA NULL;
+ALTER TABLE email_change_requests ADD COLUMN encrypted_new_email BYTEA NULL;
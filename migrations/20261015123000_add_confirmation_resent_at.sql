@@ -0,0 +1,2 @@
+-- When a pending subscriber last had their confirmation email resent.
+ALTER TABLE subscriptions ADD COLUMN confirmation_resent_at TIMESTAMPTZ NULL;
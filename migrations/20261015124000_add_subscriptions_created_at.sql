@@ -0,0 +1,7 @@
+-- When each row was created; unlike subscribed_at, never backdated by imports.
+-- Existing rows count from the migration.
+ALTER TABLE subscriptions
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
+CREATE INDEX subscriptions_pending_created_at_idx
+    ON subscriptions (created_at)
+    WHERE status = 'pending_confirmation';
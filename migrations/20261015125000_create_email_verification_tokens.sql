@@ -0,0 +1,9 @@
+-- Whether the user proved they own their email address
+ALTER TABLE users ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT false;
+
+-- Single-use email verification links; only a digest of each token is stored
+CREATE TABLE email_verification_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (user_id),
+    expires_at TIMESTAMPTZ NOT NULL
+);
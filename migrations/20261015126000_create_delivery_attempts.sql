@@ -0,0 +1,15 @@
+-- Every attempt to deliver a newsletter email, kept after the queue row is
+-- gone so each recipient's delivery history can be audited
+CREATE TABLE delivery_attempts (
+    issue_id uuid NOT NULL REFERENCES issues(issue_id),
+    subscriber_email TEXT NOT NULL,
+    attempt SMALLINT NOT NULL,
+    outcome TEXT NOT NULL,
+    message_id TEXT,
+    error TEXT,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX delivery_attempts_issue_id_idx
+    ON delivery_attempts (issue_id, subscriber_email);
+CREATE INDEX delivery_attempts_subscriber_email_idx
+    ON delivery_attempts (subscriber_email, attempted_at);
@@ -0,0 +1,7 @@
+-- Recent password reset requests, used to throttle them per IP address
+CREATE TABLE password_reset_requests (
+    ip TEXT NOT NULL,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX password_reset_requests_ip_idx
+    ON password_reset_requests (ip, requested_at);
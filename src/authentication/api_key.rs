@@ -0,0 +1,185 @@
+use std::fmt;
+use std::iter;
+
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::{FromRequest, HttpRequest, HttpResponse, ResponseError, web};
+use anyhow::Context;
+use futures::future::LocalBoxFuture;
+use rand::{Rng, distr::Alphanumeric};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::error_chain_fmt;
+
+/// Prefix of every generated key, so a leaked key is easy to recognise.
+const API_KEY_PREFIX: &str = "mlx_";
+
+/// What an API key is allowed to do.
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq,
+)]
+pub enum ApiKeyScope {
+    #[serde(rename = "subscribers:read")]
+    SubscribersRead,
+    #[serde(rename = "subscribers:write")]
+    SubscribersWrite,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SubscribersRead => "subscribers:read",
+            Self::SubscribersWrite => "subscribers:write",
+        }
+    }
+}
+
+/// Error type for API key authentication failures.
+#[derive(thiserror::Error)]
+pub enum ApiKeyError {
+    #[error("Missing or invalid API key.")]
+    Unauthorized,
+    #[error("The API key lacks the `{}` scope.", .0.as_str())]
+    InsufficientScope(ApiKeyScope),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl fmt::Debug for ApiKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ApiKeyError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::InsufficientScope(_) => StatusCode::FORBIDDEN,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let Self::Unauthorized = self {
+            response.insert_header((WWW_AUTHENTICATE, "Bearer"));
+        }
+        response.body(self.to_string())
+    }
+}
+
+/// An active API key, authenticated from the `Authorization: Bearer` header.
+/// Extracting it rejects requests without a valid, unrevoked key; handlers
+/// then check the scope they need with `require`.
+#[derive(Debug)]
+pub struct ApiKey {
+    pub id: Uuid,
+    scopes: Vec<String>,
+}
+
+impl ApiKey {
+    /// Check that the key was granted a scope.
+    /// # Arguments
+    /// * `scope` - The scope the endpoint needs.
+    /// # Returns
+    /// A Result that is an `InsufficientScope` error if it was not.
+    pub fn require(&self, scope: ApiKeyScope) -> Result<(), ApiKeyError> {
+        if self.scopes.iter().any(|s| s == scope.as_str()) {
+            Ok(())
+        } else {
+            Err(ApiKeyError::InsufficientScope(scope))
+        }
+    }
+}
+
+impl FromRequest for ApiKey {
+    type Error = ApiKeyError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let key = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .map(|key| key.trim().to_owned());
+        let pool = req.app_data::<web::Data<PgPool>>().cloned();
+        Box::pin(async move {
+            let key = key.ok_or(ApiKeyError::Unauthorized)?;
+            let pool = pool.context("The database pool is not configured")?;
+            let record = sqlx::query!(
+                r#"
+                SELECT id, scopes
+                FROM api_keys
+                WHERE key_hash = $1 AND revoked_at IS NULL
+                "#,
+                hash_api_key(&key)
+            )
+            .fetch_optional(pool.get_ref())
+            .await
+            .context("Failed to look up the API key")?
+            .ok_or(ApiKeyError::Unauthorized)?;
+            Ok(ApiKey {
+                id: record.id,
+                scopes: record.scopes,
+            })
+        })
+    }
+}
+
+/// Generate a new random API key.
+pub fn generate_api_key() -> String {
+    let mut rng = rand::rng();
+    let secret: String = iter::repeat_with(|| rng.sample(Alphanumeric))
+        .take(40)
+        .map(char::from)
+        .collect();
+    format!("{API_KEY_PREFIX}{secret}")
+}
+
+/// The digest stored for a key.
+/// Keys are long random strings, so a fast hash is enough to make a leaked
+/// digest useless; a slow password hash would only slow down every request.
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiKey, ApiKeyScope, generate_api_key, hash_api_key};
+
+    #[test]
+    fn generated_keys_are_prefixed_and_unique() {
+        let first = generate_api_key();
+        let second = generate_api_key();
+
+        assert!(first.starts_with("mlx_"));
+        assert_eq!(first.len(), 44);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn the_digest_does_not_contain_the_key() {
+        let key = generate_api_key();
+        let digest = hash_api_key(&key);
+
+        assert_eq!(digest.len(), 64);
+        assert!(!digest.contains(&key[4..]));
+        assert_eq!(digest, hash_api_key(&key));
+    }
+
+    #[test]
+    fn a_key_only_has_the_scopes_it_was_granted() {
+        let key = ApiKey {
+            id: uuid::Uuid::new_v4(),
+            scopes: vec!["subscribers:read".into()],
+        };
+
+        assert!(key.require(ApiKeyScope::SubscribersRead).is_ok());
+        assert!(key.require(ApiKeyScope::SubscribersWrite).is_err());
+    }
+}
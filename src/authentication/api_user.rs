@@ -0,0 +1,198 @@
+use std::fmt;
+
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::http::header::{AUTHORIZATION, HeaderMap, WWW_AUTHENTICATE};
+use actix_web::{FromRequest, HttpRequest, HttpResponse, ResponseError, web};
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures::future::LocalBoxFuture;
+use secrecy::SecretString;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{
+    AuthError, Credentials, TokenError, UserId, clear_failed_logins,
+    is_login_locked, is_totp_enabled, record_failed_login,
+    validate_credentials, validate_token,
+};
+use crate::clock_skew::ClockSkewTolerance;
+use crate::configuration::{ApiTokenSettings, LoginThrottleSettings};
+use crate::routes::error_chain_fmt;
+use crate::startup::HmacSecret;
+use crate::utils::client_ip;
+
+/// Error type for API authentication failures.
+#[derive(thiserror::Error)]
+pub enum ApiAuthError {
+    #[error("Authenticate with `Authorization: Basic` or `Bearer`.")]
+    MissingCredentials,
+    #[error("Invalid credentials.")]
+    InvalidCredentials(#[source] anyhow::Error),
+    #[error(transparent)]
+    InvalidToken(#[from] TokenError),
+    #[error("Too many failed login attempts - please try again later.")]
+    TooManyAttempts,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl fmt::Debug for ApiAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ApiAuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::MissingCredentials
+            | Self::InvalidCredentials(_)
+            | Self::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            Self::TooManyAttempts => StatusCode::TOO_MANY_REQUESTS,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if self.status_code() == StatusCode::UNAUTHORIZED {
+            response.insert_header((
+                WWW_AUTHENTICATE,
+                r#"Basic realm="publish", Bearer"#,
+            ));
+        }
+        response.body(self.to_string())
+    }
+}
+
+/// A user authenticated on an API call, either with their username and
+/// password (`Authorization: Basic`) or with a token from `/auth/token`
+/// (`Authorization: Bearer`).
+#[derive(Debug)]
+pub struct ApiUser(pub UserId);
+
+impl FromRequest for ApiUser {
+    type Error = ApiAuthError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let bearer = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_owned());
+        let Some(token) = bearer else {
+            let req = req.clone();
+            return Box::pin(async move {
+                let credentials = basic_credentials(req.headers())?;
+                let user_id = authenticate_basic(&req, credentials).await?;
+                Ok(ApiUser(UserId(user_id)))
+            });
+        };
+        let settings = req.app_data::<web::Data<ApiTokenSettings>>().cloned();
+        let secret = req.app_data::<web::Data<HmacSecret>>().cloned();
+        let clock_skew =
+            req.app_data::<web::Data<ClockSkewTolerance>>().cloned();
+        Box::pin(async move {
+            let settings = settings
+                .context("The API token settings are not configured")?;
+            let secret = secret.context("The HMAC secret is not configured")?;
+            let clock_skew = clock_skew
+                .context("The clock skew tolerance is not configured")?;
+            let user_id =
+                validate_token(&token, &settings, &secret.0, &clock_skew)?;
+            Ok(ApiUser(UserId(user_id)))
+        })
+    }
+}
+
+/// Read the username and password of an `Authorization: Basic` header.
+/// # Arguments
+/// * `headers` - The request headers.
+/// # Returns
+/// A Result containing the credentials, or `MissingCredentials` if there
+/// are none.
+pub fn basic_credentials(
+    headers: &HeaderMap,
+) -> Result<Credentials, ApiAuthError> {
+    let encoded = headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Basic "))
+        .ok_or(ApiAuthError::MissingCredentials)?;
+    let decoded = STANDARD
+        .decode(encoded.trim())
+        .context("The Basic credentials are not valid base64")
+        .map_err(ApiAuthError::InvalidCredentials)?;
+    let decoded = String::from_utf8(decoded)
+        .context("The Basic credentials are not valid UTF-8")
+        .map_err(ApiAuthError::InvalidCredentials)?;
+    let (username, password) = decoded
+        .split_once(':')
+        .context("The Basic credentials lack a password")
+        .map_err(ApiAuthError::InvalidCredentials)?;
+    Ok(Credentials {
+        username: username.to_owned(),
+        password: SecretString::from(password),
+    })
+}
+
+/// Check a username and password sent to the API.
+/// Failures count towards the same lockout as logins, and users with
+/// two-factor authentication enabled are refused, as a password alone
+/// would get around their second factor.
+/// # Arguments
+/// * `request` - The incoming request, to identify the client.
+/// * `credentials` - The username and password.
+/// # Returns
+/// A Result containing the user's ID.
+#[tracing::instrument(
+    name = "Authenticate an API call",
+    skip(request, credentials),
+    fields(username = %credentials.username)
+)]
+pub async fn authenticate_basic(
+    request: &HttpRequest,
+    credentials: Credentials,
+) -> Result<Uuid, ApiAuthError> {
+    let pool = request
+        .app_data::<web::Data<PgPool>>()
+        .context("The database pool is not configured")?;
+    let throttle = request
+        .app_data::<web::Data<LoginThrottleSettings>>()
+        .context("The login throttle settings are not configured")?;
+    let username = credentials.username.clone();
+    let ip = client_ip(request, throttle.trust_forwarded_for);
+    if is_login_locked(pool, throttle, &username, &ip)
+        .await
+        .context("Failed to check for a login lockout")?
+    {
+        tracing::warn!(%ip, "Refusing API credentials during a lockout.");
+        return Err(ApiAuthError::TooManyAttempts);
+    }
+    let user_id = match validate_credentials(pool, credentials).await {
+        Ok(user_id) => user_id,
+        Err(AuthError::InvalidCredentials(e)) => {
+            record_failed_login(pool, throttle, &username, &ip)
+                .await
+                .context("Failed to record a failed login")?;
+            return Err(ApiAuthError::InvalidCredentials(e));
+        }
+        Err(AuthError::UnexpectedError(e)) => return Err(e.into()),
+    };
+    if is_totp_enabled(pool, user_id)
+        .await
+        .context("Failed to check for two-factor authentication")?
+    {
+        return Err(ApiAuthError::InvalidCredentials(anyhow::anyhow!(
+            "The user has two-factor authentication enabled."
+        )));
+    }
+    clear_failed_logins(pool, &username)
+        .await
+        .context("Failed to reset the failed login count")?;
+    Ok(user_id)
+}
@@ -0,0 +1,254 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::clock_skew::ClockSkewTolerance;
+use crate::configuration::ApiTokenSettings;
+
+/// The only header tokens are issued with; anything else is refused, so a
+/// token cannot pick a weaker algorithm such as `none`.
+const HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+/// The claims of an API token.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Claims {
+    /// The user the token was issued to.
+    sub: Uuid,
+    iss: String,
+    /// When the token was issued, in seconds since the epoch.
+    iat: i64,
+    /// When the token expires, in seconds since the epoch.
+    exp: i64,
+}
+
+/// Why a bearer token was refused.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum TokenError {
+    #[error("The token is malformed.")]
+    Malformed,
+    #[error("The token's signature is invalid.")]
+    InvalidSignature,
+    #[error("The token has expired.")]
+    Expired,
+    #[error("The token was not issued by this application.")]
+    WrongIssuer,
+}
+
+/// Issue a signed JWT (HS256) for a user.
+/// # Arguments
+/// * `user_id` - The user the token authenticates.
+/// * `issued_at` - When the token is issued; it expires `ttl_minutes` later.
+/// * `settings` - The token issuer and lifetime.
+/// * `secret` - The key the token is signed with.
+/// # Returns
+/// The encoded token.
+pub fn issue_token(
+    user_id: Uuid,
+    issued_at: DateTime<Utc>,
+    settings: &ApiTokenSettings,
+    secret: &SecretString,
+) -> String {
+    let claims = Claims {
+        sub: user_id,
+        iss: settings.issuer.clone(),
+        iat: issued_at.timestamp(),
+        exp: (issued_at + settings.ttl()).timestamp(),
+    };
+    let claims =
+        serde_json::to_vec(&claims).expect("Failed to serialize the claims");
+    let signed = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(HEADER),
+        URL_SAFE_NO_PAD.encode(claims)
+    );
+    let signature = mac(secret, &signed).finalize().into_bytes();
+    format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature))
+}
+
+/// Check a token's signature, expiry and issuer.
+/// # Arguments
+/// * `token` - The encoded token.
+/// * `settings` - The issuer the token must carry.
+/// * `secret` - The key the token must be signed with.
+/// * `clock_skew` - How long past its expiry the token is still accepted.
+/// # Returns
+/// A Result containing the ID of the user the token was issued to.
+pub fn validate_token(
+    token: &str,
+    settings: &ApiTokenSettings,
+    secret: &SecretString,
+    clock_skew: &ClockSkewTolerance,
+) -> Result<Uuid, TokenError> {
+    let (signed, signature) =
+        token.rsplit_once('.').ok_or(TokenError::Malformed)?;
+    let (header, claims) =
+        signed.split_once('.').ok_or(TokenError::Malformed)?;
+    let decode = |part: &str| {
+        URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| TokenError::Malformed)
+    };
+    if decode(header)? != HEADER.as_bytes() {
+        return Err(TokenError::Malformed);
+    }
+    // Compared in constant time
+    mac(secret, signed)
+        .verify_slice(&decode(signature)?)
+        .map_err(|_| TokenError::InvalidSignature)?;
+    let claims: Claims = serde_json::from_slice(&decode(claims)?)
+        .map_err(|_| TokenError::Malformed)?;
+    let expires_at =
+        DateTime::from_timestamp(claims.exp, 0).ok_or(TokenError::Malformed)?;
+    if clock_skew.has_expired(expires_at, Utc::now()) {
+        return Err(TokenError::Expired);
+    }
+    if claims.iss != settings.issuer {
+        return Err(TokenError::WrongIssuer);
+    }
+    Ok(claims.sub)
+}
+
+fn mac(secret: &SecretString, signed: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any length");
+    mac.update(signed.as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use secrecy::SecretString;
+    use uuid::Uuid;
+
+    use super::{TokenError, issue_token, validate_token};
+    use crate::clock_skew::ClockSkewTolerance;
+    use crate::configuration::ApiTokenSettings;
+
+    fn secret() -> SecretString {
+        SecretString::from("long-and-very-secret-random-key-value")
+    }
+
+    #[test]
+    fn a_fresh_token_yields_the_user_it_was_issued_to() {
+        let settings = ApiTokenSettings::default();
+        let user_id = Uuid::new_v4();
+
+        let token = issue_token(user_id, Utc::now(), &settings, &secret());
+
+        assert_eq!(
+            validate_token(
+                &token,
+                &settings,
+                &secret(),
+                &ClockSkewTolerance::default()
+            ),
+            Ok(user_id)
+        );
+    }
+
+    #[test]
+    fn a_token_past_its_lifetime_is_expired() {
+        let settings = ApiTokenSettings::default();
+        let issued_at = Utc::now()
+            - settings.ttl()
+            - ClockSkewTolerance::default().tolerance()
+            - Duration::seconds(1);
+
+        let token =
+            issue_token(Uuid::new_v4(), issued_at, &settings, &secret());
+
+        assert_eq!(
+            validate_token(
+                &token,
+                &settings,
+                &secret(),
+                &ClockSkewTolerance::default()
+            ),
+            Err(TokenError::Expired)
+        );
+    }
+
+    #[test]
+    fn a_token_that_expired_within_the_clock_skew_is_accepted() {
+        let settings = ApiTokenSettings::default();
+        let user_id = Uuid::new_v4();
+        let issued_at = Utc::now() - settings.ttl() - Duration::seconds(10);
+
+        let token = issue_token(user_id, issued_at, &settings, &secret());
+
+        assert_eq!(
+            validate_token(
+                &token,
+                &settings,
+                &secret(),
+                &ClockSkewTolerance::default()
+            ),
+            Ok(user_id)
+        );
+    }
+
+    #[test]
+    fn a_token_signed_with_another_key_is_refused() {
+        let settings = ApiTokenSettings::default();
+        let other = SecretString::from("another-key");
+
+        let token = issue_token(Uuid::new_v4(), Utc::now(), &settings, &other);
+
+        assert_eq!(
+            validate_token(
+                &token,
+                &settings,
+                &secret(),
+                &ClockSkewTolerance::default()
+            ),
+            Err(TokenError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn a_token_from_another_issuer_is_refused() {
+        let settings = ApiTokenSettings::default();
+        let other = ApiTokenSettings {
+            issuer: "someone-else".into(),
+            ..ApiTokenSettings::default()
+        };
+
+        let token = issue_token(Uuid::new_v4(), Utc::now(), &other, &secret());
+
+        assert_eq!(
+            validate_token(
+                &token,
+                &settings,
+                &secret(),
+                &ClockSkewTolerance::default()
+            ),
+            Err(TokenError::WrongIssuer)
+        );
+    }
+
+    #[test]
+    fn an_unsigned_token_is_refused() {
+        let settings = ApiTokenSettings::default();
+        let token =
+            issue_token(Uuid::new_v4(), Utc::now(), &settings, &secret());
+        let claims = token.split('.').nth(1).unwrap();
+        // {"alg":"none","typ":"JWT"}
+        let unsigned = format!("eyJhbGciOiJub25lIiwidHlwIjoiSldUIn0.{claims}.");
+
+        assert_eq!(
+            validate_token(
+                &unsigned,
+                &settings,
+                &secret(),
+                &ClockSkewTolerance::default()
+            ),
+            Err(TokenError::Malformed)
+        );
+    }
+}
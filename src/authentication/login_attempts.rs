@@ -0,0 +1,94 @@
+use sqlx::PgPool;
+
+use crate::configuration::LoginThrottleSettings;
+
+/// Check whether logins for a username, or from an IP address, are locked
+/// out after too many recent failures.
+/// The lockout lifts once enough failures have aged out of the window.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `settings` - The lockout thresholds.
+/// * `username` - The username being logged into.
+/// * `ip` - The client's IP address.
+/// # Returns
+/// A Result containing true if the login must be refused.
+#[tracing::instrument(skip(pool, settings))]
+pub async fn is_login_locked(
+    pool: &PgPool,
+    settings: &LoginThrottleSettings,
+    username: &str,
+    ip: &str,
+) -> Result<bool, sqlx::Error> {
+    let recent = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE username = $1) AS "by_username!",
+            COUNT(*) FILTER (WHERE ip = $2) AS "by_ip!"
+        FROM login_attempts
+        WHERE
+            (username = $1 OR ip = $2) AND
+            attempted_at >= now() - make_interval(secs => $3)
+        "#,
+        username,
+        ip,
+        f64::from(settings.window_seconds)
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(recent.by_username >= settings.max_failures_per_username
+        || recent.by_ip >= settings.max_failures_per_ip)
+}
+
+/// Record a failed login, pruning the ones that fell out of the window.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `settings` - The lockout window.
+/// * `username` - The username that was tried.
+/// * `ip` - The client's IP address.
+#[tracing::instrument(skip(pool, settings))]
+pub async fn record_failed_login(
+    pool: &PgPool,
+    settings: &LoginThrottleSettings,
+    username: &str,
+    ip: &str,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM login_attempts
+        WHERE attempted_at < now() - make_interval(secs => $1)
+        "#,
+        f64::from(settings.window_seconds)
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO login_attempts (username, ip)
+        VALUES ($1, $2)
+        "#,
+        username,
+        ip
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Forget the failed logins of a username after a successful one.
+/// Failures counted against the IP address are kept, so one known password
+/// cannot be used to keep guessing others from the same address.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `username` - The username that logged in.
+#[tracing::instrument(skip(pool))]
+pub async fn clear_failed_logins(
+    pool: &PgPool,
+    username: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM login_attempts WHERE username = $1", username)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
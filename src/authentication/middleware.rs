@@ -5,15 +5,18 @@ use actix_web::body::MessageBody;
 use actix_web::dev::{ServiceRequest, ServiceResponse};
 use actix_web::error::InternalError;
 use actix_web::middleware::Next;
-use actix_web::{FromRequest, HttpMessage};
+use actix_web::{FromRequest, HttpMessage, web};
+use anyhow::Context;
+use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::authentication::is_session_active;
 use crate::session_state::TypedSession;
 use crate::utils::{e500, see_other};
 
 /// A newtype for the user ID extracted from the session.
 #[derive(Debug, Clone, Copy)]
-pub struct UserId(Uuid);
+pub struct UserId(pub(super) Uuid);
 
 impl fmt::Display for UserId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -38,7 +41,24 @@ pub async fn reject_anonymous_users(
         TypedSession::from_request(http_request, payload).await
     }?;
 
-    match session.get_user_id().map_err(e500)? {
+    let user_id = match session.get_session_id().map_err(e500)? {
+        // Sessions evicted by the session limit, or ended elsewhere, are
+        // refused even though their cookie is still valid.
+        Some(session_id) => {
+            let pool = req
+                .app_data::<web::Data<PgPool>>()
+                .context("The database pool is not configured")
+                .map_err(e500)?;
+            if is_session_active(pool, session_id).await.map_err(e500)? {
+                session.get_user_id().map_err(e500)?
+            } else {
+                session.log_out();
+                None
+            }
+        }
+        None => session.get_user_id().map_err(e500)?,
+    };
+    match user_id {
         Some(user_id) => {
             req.extensions_mut().insert(UserId(user_id));
             next.call(req).await
@@ -1,8 +1,32 @@
+mod api_key;
+mod api_user;
+mod jwt;
+mod login_attempts;
 mod middleware;
 mod password;
+mod sessions;
+mod totp;
 
+pub use api_key::{
+    ApiKey, ApiKeyError, ApiKeyScope, generate_api_key, hash_api_key,
+};
+pub use api_user::{
+    ApiAuthError, ApiUser, authenticate_basic, basic_credentials,
+};
+pub use jwt::{TokenError, issue_token, validate_token};
+pub use login_attempts::{
+    clear_failed_logins, is_login_locked, record_failed_login,
+};
 pub use middleware::UserId;
 pub use middleware::reject_anonymous_users;
 pub use password::{
-    AuthError, Credentials, change_password, validate_credentials,
+    AuthError, Credentials, change_password, change_password_in_transaction,
+    create_bootstrap_admin, validate_credentials,
+};
+pub use sessions::{
+    end_all_sessions, end_session, is_session_active, start_session,
+};
+pub use totp::{
+    TotpEnrollment, confirm_totp_enrollment, disable_totp, is_totp_enabled,
+    start_totp_enrollment, verify_totp_code,
 };
@@ -3,7 +3,7 @@ use argon2::password_hash::{SaltString, rand_core::OsRng};
 use argon2::{Algorithm, Params, PasswordHasher, Version};
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use secrecy::{ExposeSecret, SecretString};
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 use crate::telemetry::spawn_blocking_with_tracing;
@@ -74,6 +74,35 @@ pub async fn change_password(
     pool: &PgPool,
     user_id: Uuid,
     password: SecretString,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a new database transaction.")?;
+    change_password_in_transaction(&mut transaction, user_id, password).await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the password change.")?;
+    Ok(())
+}
+
+/// Change the password for a given user as part of the caller's
+/// transaction, so it only takes effect if the rest of it does.
+/// # Arguments
+/// * `transaction` - The database transaction.
+/// * `user_id` - The ID of the user whose password is to be changed.
+/// * `password` - The new password.
+/// # Returns
+/// A Result indicating success or an anyhow::Error otherwise.
+#[tracing::instrument(
+    name = "Change password in transaction",
+    skip(transaction, password)
+)]
+pub async fn change_password_in_transaction(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    password: SecretString,
 ) -> Result<(), anyhow::Error> {
     let password_hash =
         spawn_blocking_with_tracing(move || compute_password_hash(password))
@@ -89,12 +118,62 @@ pub async fn change_password(
         password_hash.expose_secret(),
         user_id
     )
-    .execute(pool)
+    .execute(transaction.as_mut())
     .await
     .context("Failed to execute query to change password.")?;
     Ok(())
 }
 
+/// Create the first admin user of a new deployment.
+/// Nothing is created once any user exists, so the admin is not recreated
+/// after it was renamed or removed.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `username` - The admin's username.
+/// * `password` - The admin's initial password.
+/// * `email` - Where the admin's password reset links are sent, if anywhere.
+/// # Returns
+/// A Result containing the new user's ID, or None if there already are
+/// users.
+#[tracing::instrument(name = "Create bootstrap admin", skip(pool, password))]
+pub async fn create_bootstrap_admin(
+    pool: &PgPool,
+    username: &str,
+    password: SecretString,
+    email: Option<&str>,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let has_users =
+        sqlx::query_scalar!(r#"SELECT EXISTS (SELECT 1 FROM users) AS "e!""#)
+            .fetch_one(pool)
+            .await
+            .context("Failed to check for existing users.")?;
+    if has_users {
+        return Ok(None);
+    }
+    let password_hash =
+        spawn_blocking_with_tracing(move || compute_password_hash(password))
+            .await
+            .context("Failed to compute password hash.")??;
+    // Another instance may be booting at the same time.
+    let user_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO users (user_id, username, password_hash, email)
+        SELECT $1, $2, $3, $4
+        WHERE NOT EXISTS (SELECT 1 FROM users)
+        ON CONFLICT DO NOTHING
+        RETURNING user_id
+        "#,
+        Uuid::new_v4(),
+        username,
+        password_hash.expose_secret(),
+        email
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to insert the bootstrap admin.")?;
+    Ok(user_id)
+}
+
 /// Verify the provided password against the expected password hash.
 /// # Arguments
 /// * `expected_password_hash` - The expected password hash.
@@ -0,0 +1,129 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Record a new login session for a user.
+/// When the user would hold more than `max_per_user` active sessions, the
+/// oldest ones are revoked to make room; 0 means no limit.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `user_id` - The ID of the user logging in.
+/// * `max_per_user` - How many sessions a user may hold at once.
+/// # Returns
+/// A Result containing the ID of the new session.
+#[tracing::instrument(skip(pool))]
+pub async fn start_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    max_per_user: u32,
+) -> Result<Uuid, anyhow::Error> {
+    let session_id = Uuid::new_v4();
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO user_sessions (session_id, user_id)
+        VALUES ($1, $2)
+        "#,
+        session_id,
+        user_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    if max_per_user > 0 {
+        let evicted = sqlx::query_scalar!(
+            r#"
+            UPDATE user_sessions
+            SET revoked_at = now()
+            WHERE session_id IN (
+                SELECT session_id
+                FROM user_sessions
+                WHERE user_id = $1 AND revoked_at IS NULL
+                ORDER BY created_at DESC, session_id = $2 DESC
+                OFFSET $3
+            )
+            RETURNING session_id
+            "#,
+            user_id,
+            session_id,
+            i64::from(max_per_user)
+        )
+        .fetch_all(transaction.as_mut())
+        .await?;
+        for evicted_session_id in evicted {
+            tracing::warn!(
+                %evicted_session_id,
+                max_per_user,
+                "Evicted the oldest session of a user beyond the session limit."
+            );
+        }
+    }
+    transaction.commit().await?;
+    Ok(session_id)
+}
+
+/// Whether a session is still valid, i.e. was not evicted or logged out.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `session_id` - The ID of the session.
+#[tracing::instrument(skip(pool))]
+pub async fn is_session_active(
+    pool: &PgPool,
+    session_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let active = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM user_sessions
+            WHERE session_id = $1 AND revoked_at IS NULL
+        ) AS "active!"
+        "#,
+        session_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(active)
+}
+
+/// Revoke a session, e.g. on logout.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `session_id` - The ID of the session.
+#[tracing::instrument(skip(pool))]
+pub async fn end_session(
+    pool: &PgPool,
+    session_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE user_sessions
+        SET revoked_at = now()
+        WHERE session_id = $1 AND revoked_at IS NULL
+        "#,
+        session_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Revoke every session of a user, e.g. when their password is reset.
+/// # Arguments
+/// * `transaction` - The transaction changing the user's credentials.
+/// * `user_id` - The ID of the user.
+#[tracing::instrument(skip(transaction))]
+pub async fn end_all_sessions(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE user_sessions
+        SET revoked_at = now()
+        WHERE user_id = $1 AND revoked_at IS NULL
+        "#,
+        user_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
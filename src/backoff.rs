@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// How much randomness is mixed into the exponential retry delay.
+/// Jitter spreads out retries that failed together, e.g. during a provider
+/// outage, so they do not all hit the provider again at the same moment.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackoffStrategy {
+    /// The exponential delay itself, with no randomness.
+    #[default]
+    Fixed,
+    /// Anywhere between zero and the exponential delay.
+    FullJitter,
+    /// At least half the exponential delay, plus up to another half.
+    EqualJitter,
+}
+
+/// Retry timing shared by the email and webhook delivery workers.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    strategy: BackoffStrategy,
+    base: Duration,
+    cap: Duration,
+}
+
+impl Backoff {
+    pub fn new(
+        strategy: BackoffStrategy,
+        base: Duration,
+        cap: Duration,
+    ) -> Self {
+        Self {
+            strategy,
+            base,
+            cap,
+        }
+    }
+
+    /// How long to wait before the next attempt.
+    /// # Arguments
+    /// * `n_retries` - How many times the task was retried already.
+    /// # Returns
+    /// The delay, never more than the configured cap.
+    pub fn delay(&self, n_retries: i16) -> Duration {
+        self.delay_with(n_retries, &mut rand::rng())
+    }
+
+    fn delay_with(&self, n_retries: i16, rng: &mut impl Rng) -> Duration {
+        let ceiling = self.exponential(n_retries);
+        match self.strategy {
+            BackoffStrategy::Fixed => ceiling,
+            BackoffStrategy::FullJitter => ceiling.mul_f64(rng.random()),
+            BackoffStrategy::EqualJitter => {
+                let half = ceiling / 2;
+                half + (ceiling - half).mul_f64(rng.random())
+            }
+        }
+    }
+
+    /// `base * 2^n_retries`, at most the cap.
+    fn exponential(&self, n_retries: i16) -> Duration {
+        let exponent = u32::try_from(n_retries).unwrap_or(0);
+        let factor = 2u32.checked_pow(exponent).unwrap_or(u32::MAX);
+        self.base.saturating_mul(factor).min(self.cap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::{Backoff, BackoffStrategy};
+
+    const STRATEGIES: [BackoffStrategy; 3] = [
+        BackoffStrategy::Fixed,
+        BackoffStrategy::FullJitter,
+        BackoffStrategy::EqualJitter,
+    ];
+
+    fn backoff(strategy: BackoffStrategy) -> Backoff {
+        Backoff::new(strategy, Duration::from_secs(1), Duration::from_secs(60))
+    }
+
+    #[test]
+    fn fixed_delays_double_with_each_retry() {
+        let backoff = backoff(BackoffStrategy::Fixed);
+
+        assert_eq!(backoff.delay(0), Duration::from_secs(1));
+        assert_eq!(backoff.delay(1), Duration::from_secs(2));
+        assert_eq!(backoff.delay(4), Duration::from_secs(16));
+    }
+
+    #[test]
+    fn fixed_delays_stop_growing_at_the_cap() {
+        let backoff = backoff(BackoffStrategy::Fixed);
+
+        assert_eq!(backoff.delay(6), Duration::from_secs(60));
+        assert_eq!(backoff.delay(i16::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn full_jitter_stays_below_the_exponential_delay() {
+        let backoff = backoff(BackoffStrategy::FullJitter);
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..1_000 {
+            let delay = backoff.delay_with(3, &mut rng);
+            assert!(delay <= Duration::from_secs(8), "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn equal_jitter_waits_at_least_half_the_exponential_delay() {
+        let backoff = backoff(BackoffStrategy::EqualJitter);
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..1_000 {
+            let delay = backoff.delay_with(3, &mut rng);
+            assert!(delay >= Duration::from_secs(4), "{delay:?}");
+            assert!(delay <= Duration::from_secs(8), "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn jittered_delays_vary() {
+        let backoff = backoff(BackoffStrategy::FullJitter);
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let first = backoff.delay_with(5, &mut rng);
+        let delays_differ =
+            (0..10).any(|_| backoff.delay_with(5, &mut rng) != first);
+
+        assert!(delays_differ);
+    }
+
+    #[test]
+    fn a_negative_retry_count_is_treated_as_the_first_retry() {
+        let backoff = backoff(BackoffStrategy::Fixed);
+
+        assert_eq!(backoff.delay(-1), Duration::from_secs(1));
+    }
+
+    #[quickcheck_macros::quickcheck]
+    fn delays_never_exceed_the_cap(
+        n_retries: i16,
+        base_milliseconds: u32,
+        cap_milliseconds: u32,
+        seed: u64,
+    ) -> bool {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let cap = Duration::from_millis(cap_milliseconds.into());
+        STRATEGIES.into_iter().all(|strategy| {
+            let backoff = Backoff::new(
+                strategy,
+                Duration::from_millis(base_milliseconds.into()),
+                cap,
+            );
+            backoff.delay_with(n_retries, &mut rng) <= cap
+        })
+    }
+}
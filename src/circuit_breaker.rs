@@ -0,0 +1,144 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Calls go through; failures are counted.
+    Closed { consecutive_failures: u32 },
+    /// Calls fail fast until the cooldown has elapsed.
+    Open { until: Instant },
+    /// A single trial call is in flight to probe for recovery.
+    HalfOpen,
+}
+
+/// A consecutive-failure circuit breaker.
+/// After `failure_threshold` failures in a row the breaker opens and rejects
+/// calls for `cooldown`. It then lets a single trial call through: success
+/// closes it again, failure re-opens it for another cooldown.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            state: Mutex::new(State::Closed {
+                consecutive_failures: 0,
+            }),
+        }
+    }
+
+    /// How long calls are rejected for once the breaker opens.
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    /// Ask whether a call may go ahead.
+    /// Once the cooldown has elapsed the first caller is let through as the
+    /// half-open trial; everyone else keeps failing fast until it reports.
+    /// # Returns
+    /// True if the call may go ahead.
+    pub fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } if Instant::now() >= until => {
+                *state = State::HalfOpen;
+                true
+            }
+            State::Open { .. } | State::HalfOpen => false,
+        }
+    }
+
+    /// Report that a call succeeded, closing the breaker.
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = State::Closed {
+            consecutive_failures: 0,
+        };
+    }
+
+    /// Report that a call failed, opening the breaker if needed.
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        *state = match *state {
+            State::Closed {
+                consecutive_failures,
+            } if consecutive_failures + 1 < self.failure_threshold => {
+                State::Closed {
+                    consecutive_failures: consecutive_failures + 1,
+                }
+            }
+            _ => {
+                tracing::warn!(
+                    cooldown = ?self.cooldown,
+                    "Opening the circuit breaker."
+                );
+                State::Open {
+                    until: Instant::now() + self.cooldown,
+                }
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::CircuitBreaker;
+
+    #[test]
+    fn the_breaker_opens_after_the_failure_threshold() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+
+        assert!(breaker.try_acquire());
+        breaker.record_failure();
+        assert!(breaker.try_acquire());
+        breaker.record_failure();
+
+        assert!(!breaker.try_acquire());
+    }
+
+    #[test]
+    fn a_success_resets_the_failure_count() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+
+        assert!(breaker.try_acquire());
+    }
+
+    #[test]
+    fn only_one_trial_call_is_let_through_after_the_cooldown() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        breaker.record_failure();
+
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+
+        breaker.record_success();
+        assert!(breaker.try_acquire());
+    }
+
+    #[test]
+    fn a_failed_trial_call_reopens_the_breaker() {
+        let breaker = CircuitBreaker::new(3, Duration::ZERO);
+        for _ in 0..3 {
+            breaker.record_failure();
+        }
+        assert!(breaker.try_acquire());
+
+        breaker.record_failure();
+
+        // Open again, but the zero cooldown lets the next trial through
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+    }
+}
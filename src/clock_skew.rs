@@ -0,0 +1,84 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+/// How far the clocks of the servers that issue and check time-limited
+/// credentials may drift apart.
+/// Every expiry check goes through it: API tokens, TOTP codes, password
+/// reset, email verification and email change links.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockSkewTolerance {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub tolerance_seconds: u32,
+}
+
+impl Default for ClockSkewTolerance {
+    fn default() -> Self {
+        Self {
+            tolerance_seconds: 30,
+        }
+    }
+}
+
+impl ClockSkewTolerance {
+    pub fn tolerance(&self) -> Duration {
+        Duration::seconds(self.tolerance_seconds.into())
+    }
+
+    /// The earliest expiry time still accepted at `now`; anything expiring
+    /// after it is valid.
+    pub fn expiry_cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - self.tolerance()
+    }
+
+    /// Whether something expiring at `expires_at` can no longer be used at
+    /// `now`.
+    pub fn has_expired(
+        &self,
+        expires_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        expires_at <= self.expiry_cutoff(now)
+    }
+
+    /// How many time steps of `step_seconds` either side of the current one
+    /// the tolerance covers, rounded up.
+    pub fn steps(&self, step_seconds: i64) -> i64 {
+        (i64::from(self.tolerance_seconds) + step_seconds - 1) / step_seconds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::ClockSkewTolerance;
+
+    fn tolerance(seconds: u32) -> ClockSkewTolerance {
+        ClockSkewTolerance {
+            tolerance_seconds: seconds,
+        }
+    }
+
+    #[test]
+    fn a_credential_that_expired_within_the_tolerance_is_still_valid() {
+        let now = Utc::now();
+        let expires_at = now - Duration::seconds(29);
+
+        assert!(!tolerance(30).has_expired(expires_at, now));
+    }
+
+    #[test]
+    fn a_credential_that_expired_beyond_the_tolerance_is_rejected() {
+        let now = Utc::now();
+
+        assert!(tolerance(30).has_expired(now - Duration::seconds(30), now));
+        assert!(tolerance(0).has_expired(now, now));
+    }
+
+    #[test]
+    fn the_tolerance_rounds_up_to_whole_steps() {
+        assert_eq!(tolerance(0).steps(30), 0);
+        assert_eq!(tolerance(30).steps(30), 1);
+        assert_eq!(tolerance(31).steps(30), 2);
+    }
+}
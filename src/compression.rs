@@ -0,0 +1,38 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{CONTENT_ENCODING, CONTENT_TYPE, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::web;
+
+use crate::configuration::CompressionSettings;
+
+/// Keep responses whose content type is not configured for compression
+/// uncompressed.
+/// Marking them `Content-Encoding: identity` makes the outer `Compress`
+/// middleware pass them through untouched, the same way it leaves alone a
+/// response a handler has already encoded.
+/// # Arguments
+/// * `req` - The incoming request.
+/// * `next` - The next service in the chain.
+/// # Returns
+/// A Result containing the downstream response.
+pub async fn limit_compression_to_content_types(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let settings = req.app_data::<web::Data<CompressionSettings>>().cloned();
+    let mut response = next.call(req).await?;
+    let headers = response.headers();
+    let compressible = settings.is_some_and(|settings| {
+        headers
+            .get(CONTENT_TYPE)
+            .and_then(|h| h.to_str().ok())
+            .is_some_and(|content_type| settings.compresses(content_type))
+    });
+    if !compressible && !headers.contains_key(CONTENT_ENCODING) {
+        response
+            .headers_mut()
+            .insert(CONTENT_ENCODING, HeaderValue::from_static("identity"));
+    }
+    Ok(response)
+}
@@ -1,3 +1,4 @@
+use std::collections::{HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::env;
 use std::time::Duration;
@@ -6,8 +7,19 @@ use secrecy::{ExposeSecret, SecretString};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 
+use crate::backoff::{Backoff, BackoffStrategy};
+use crate::circuit_breaker::CircuitBreaker;
+use crate::clock_skew::ClockSkewTolerance;
 use crate::domain::SubscriberEmail;
-use crate::email_client::EmailClient;
+use crate::email_client::{
+    EmailClient, EmailProvider, PostmarkClient, SenderVerification, SmtpClient,
+};
+use crate::idempotency::{FailureMode, IdempotencyKeyFormat};
+use crate::migrations::MigrationMode;
+use crate::routes::{CountedSubscribers, SortOrder, SubscriberSortField};
+use crate::send_cap::DailySendCap;
+use crate::telemetry::RequestLogLevel;
+use crate::webhooks::{SignupNotificationTrigger, WebhookClient, WebhookEvent};
 
 /// Environment enum to distinguish between local and production settings.
 pub enum Environment {
@@ -47,6 +59,73 @@ pub struct ApplicationSettings {
     pub port: u16,
     pub base_url: String,
     pub hmac_secret: SecretString,
+    pub require_utf8_forms: bool,
+    /// Accept signups from anyone; disable for invite-only deployments.
+    pub public_signup_enabled: bool,
+    pub compression: CompressionSettings,
+    pub request_logging: RequestLogSettings,
+    /// Admin created at startup while there are no users yet, so a new
+    /// deployment can be logged into; ignored once any user exists.
+    /// Set with `APP_APPLICATION__BOOTSTRAP_ADMIN_USERNAME` and
+    /// `APP_APPLICATION__BOOTSTRAP_ADMIN_PASSWORD`.
+    #[serde(default)]
+    pub bootstrap_admin_username: Option<String>,
+    #[serde(default)]
+    pub bootstrap_admin_password: Option<SecretString>,
+    /// Where the bootstrap admin's password reset links are sent.
+    #[serde(default)]
+    pub bootstrap_admin_email: Option<String>,
+}
+
+/// Access log settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct RequestLogSettings {
+    /// The level of the request span for routes without an override.
+    pub default_level: RequestLogLevel,
+    #[serde(default)]
+    pub routes: Vec<RouteLogLevel>,
+}
+
+/// The access log level of a single route.
+#[derive(serde::Deserialize, Clone)]
+pub struct RouteLogLevel {
+    /// The route pattern as registered, e.g. `/subscriptions/confirm` or
+    /// `/admin/newsletters/{issue_id}/delivery`.
+    pub path: String,
+    pub level: RequestLogLevel,
+}
+
+impl RequestLogSettings {
+    /// The level requests to a route are logged at.
+    /// # Arguments
+    /// * `route` - The matched route pattern, or the path if none matched.
+    pub fn level_for(&self, route: &str) -> RequestLogLevel {
+        self.routes
+            .iter()
+            .find(|r| r.path == route)
+            .map_or(self.default_level, |r| r.level)
+    }
+}
+
+/// Response compression settings structure.
+/// Responses are compressed with whichever encoding the client accepts.
+#[derive(serde::Deserialize, Clone)]
+pub struct CompressionSettings {
+    pub enabled: bool,
+    /// Media types to compress, e.g. `application/json`. Anything else,
+    /// notably already-compressed images and archives, is sent as is.
+    pub content_types: Vec<String>,
+}
+
+impl CompressionSettings {
+    /// Whether responses of the given `Content-Type` are compressed.
+    /// Parameters such as `charset` are ignored.
+    pub fn compresses(&self, content_type: &str) -> bool {
+        let media_type = content_type.split(';').next().unwrap_or("").trim();
+        self.content_types
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(media_type))
+    }
 }
 
 /// Database settings structure.
@@ -59,9 +138,87 @@ pub struct DatabaseSettings {
     pub username: String,
     pub password: SecretString,
     pub require_ssl: bool,
+    #[serde(default)]
+    pub maintenance: MaintenanceSettings,
+    /// Whether to check or apply pending migrations at startup.
+    #[serde(default)]
+    pub migrations: MigrationMode,
+    #[serde(default)]
+    pub pool: ConnectionPoolSettings,
+}
+
+/// Connection pool settings structure.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ConnectionPoolSettings {
+    /// How long a query waits for a free connection before failing.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub acquire_timeout_milliseconds: u64,
+    /// How many more times read-only endpoints try to get a connection after
+    /// timing out. Writes are never retried.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub read_acquire_retries: i16,
+    /// The delay before the first retry, doubled for each one after it.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub read_acquire_retry_delay_milliseconds: u64,
+}
+
+impl Default for ConnectionPoolSettings {
+    fn default() -> Self {
+        Self {
+            acquire_timeout_milliseconds: 30_000,
+            read_acquire_retries: 2,
+            read_acquire_retry_delay_milliseconds: 50,
+        }
+    }
+}
+
+impl ConnectionPoolSettings {
+    pub fn acquire_timeout(&self) -> Duration {
+        Duration::from_millis(self.acquire_timeout_milliseconds)
+    }
+
+    pub fn read_acquire_retry_delay(&self) -> Duration {
+        Duration::from_millis(self.read_acquire_retry_delay_milliseconds)
+    }
+}
+
+/// Credentials for the maintenance database used to create new databases,
+/// e.g. one per test case.
+/// Defaults to the `postgres` superuser set up by `scripts/init_db.sh`;
+/// override with `APP_DATABASE__MAINTENANCE__USERNAME` and friends.
+#[derive(serde::Deserialize, Clone)]
+#[serde(default)]
+pub struct MaintenanceSettings {
+    pub database_name: String,
+    pub username: String,
+    pub password: SecretString,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        Self {
+            database_name: "postgres".into(),
+            username: "postgres".into(),
+            password: SecretString::from("password"),
+        }
+    }
 }
 
 impl DatabaseSettings {
+    /// Settings for connecting to the maintenance database on the same server.
+    pub fn maintenance(&self) -> DatabaseSettings {
+        DatabaseSettings {
+            database_name: self.maintenance.database_name.clone(),
+            username: self.maintenance.username.clone(),
+            password: self.maintenance.password.clone(),
+            ..self.clone()
+        }
+    }
+
+    /// Options for connecting to the database.
+    /// sqlx negotiates TLS through rustls, which never offers anything older
+    /// than TLS 1.2, so database connections meet the same floor as
+    /// `EmailClientSettings::min_tls_version` without further configuration.
     pub fn connect_options(&self) -> PgConnectOptions {
         let ssl_mode = if self.require_ssl {
             PgSslMode::Require
@@ -82,11 +239,186 @@ impl DatabaseSettings {
 /// Email client settings structure.
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
+    /// The service emails are handed to.
+    #[serde(default)]
+    pub provider: EmailProviderKind,
+    /// Postmark's API base URL.
     pub base_url: String,
     pub sender_email: String,
+    /// Shown next to the sender address, e.g. `Melierx Newsletter`.
+    #[serde(default)]
+    pub sender_name: Option<String>,
+    /// Where replies go, e.g. a support inbox. Replies go to the sender
+    /// address when unset.
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// Postmark's server API token.
     pub authorization_token: SecretString,
+    /// The SMTP server, when `provider` is `smtp`.
+    #[serde(default)]
+    pub smtp: Option<SmtpSettings>,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub timeout_milliseconds: u64,
+    pub circuit_breaker: CircuitBreakerSettings,
+    /// The oldest TLS version accepted when talking to the email provider,
+    /// over its API or SMTP.
+    #[serde(default)]
+    pub min_tls_version: TlsVersion,
+    /// Whether to check the sender with the provider at startup.
+    pub sender_verification: SenderVerification,
+    /// Account-level API token, needed to list the verified senders.
+    #[serde(default)]
+    pub account_token: Option<SecretString>,
+    /// Emails sent per UTC day before every further send is refused.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub daily_send_cap: u64,
+    /// Capture emails in memory for `/dev/emails` instead of sending them.
+    /// For local development only; refused in production.
+    #[serde(default)]
+    pub test_mode: bool,
+    /// How long failed newsletter and onboarding emails wait to be retried.
+    pub retry_backoff: BackoffSettings,
+    /// Upper bound on confirmation emails being sent at the same time.
+    /// Signups beyond it wait their turn, so a spike cannot trip the
+    /// provider's rate limits.
+    pub max_concurrent_confirmation_sends: ConcurrencyLimit,
+    /// Prepended to every subject, e.g. `[STAGING]`, so emails sent from
+    /// other environments cannot be mistaken for production ones.
+    #[serde(default)]
+    pub subject_prefix: Option<String>,
+    /// Attempts made again within a send after a timeout or a 5xx, before
+    /// the failure is handed back to the delivery queue.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_retries: u32,
+    /// The wait before the first of those retries, doubled (with jitter)
+    /// for each one after it.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub base_delay_milliseconds: u64,
+}
+
+/// The services emails can be handed to.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailProviderKind {
+    #[default]
+    Postmark,
+    Smtp,
+}
+
+/// SMTP server settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<SecretString>,
+    pub tls: SmtpTls,
+}
+
+/// How the connection to the SMTP server is secured.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// Plain text, e.g. for a relay on the same host.
+    None,
+    /// Upgrade a plain connection, usually on port 587.
+    Starttls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+}
+
+/// Retry backoff settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct BackoffSettings {
+    pub strategy: BackoffStrategy,
+    /// The delay before the first retry, doubled for each one after it.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub base_milliseconds: u64,
+    /// The longest delay between two attempts.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub cap_milliseconds: u64,
+}
+
+impl BackoffSettings {
+    pub fn backoff(&self) -> Backoff {
+        Backoff::new(
+            self.strategy,
+            Duration::from_millis(self.base_milliseconds),
+            Duration::from_millis(self.cap_milliseconds),
+        )
+    }
+}
+
+/// TLS protocol versions that outbound connections may be pinned to.
+/// Anything older than TLS 1.2 is refused when the configuration is loaded.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum TlsVersion {
+    #[default]
+    Tls1_2,
+    Tls1_3,
+}
+
+impl TlsVersion {
+    pub fn as_reqwest(&self) -> reqwest::tls::Version {
+        match self {
+            TlsVersion::Tls1_2 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls1_3 => reqwest::tls::Version::TLS_1_3,
+        }
+    }
+
+    pub fn as_lettre(&self) -> lettre::transport::smtp::client::TlsVersion {
+        match self {
+            TlsVersion::Tls1_2 => {
+                lettre::transport::smtp::client::TlsVersion::Tlsv12
+            }
+            TlsVersion::Tls1_3 => {
+                lettre::transport::smtp::client::TlsVersion::Tlsv13
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for TlsVersion {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.trim().to_lowercase().trim_start_matches("tls").trim() {
+            "1.2" => Ok(Self::Tls1_2),
+            "1.3" => Ok(Self::Tls1_3),
+            "1.0" | "1.1" => Err(format!(
+                "TLS {} is not allowed. Use '1.2' or '1.3'.",
+                s.trim()
+            )),
+            _ => Err(format!(
+                "{} is not a supported TLS version. Use either '1.2' or '1.3'.",
+                s
+            )),
+        }
+    }
+}
+
+/// Circuit breaker settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct CircuitBreakerSettings {
+    /// Consecutive failures before the breaker opens.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub failure_threshold: u32,
+    /// How long the breaker stays open before probing for recovery.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub cooldown_milliseconds: u64,
+}
+
+impl CircuitBreakerSettings {
+    pub fn breaker(&self) -> CircuitBreaker {
+        CircuitBreaker::new(
+            self.failure_threshold,
+            Duration::from_millis(self.cooldown_milliseconds),
+        )
+    }
 }
 
 impl EmailClientSettings {
@@ -94,23 +426,919 @@ impl EmailClientSettings {
         SubscriberEmail::parse(self.sender_email.clone())
     }
 
+    pub fn reply_to(&self) -> Result<Option<SubscriberEmail>, String> {
+        self.reply_to
+            .clone()
+            .map(SubscriberEmail::parse)
+            .transpose()
+    }
+
     pub fn timeout(&self) -> Duration {
         Duration::from_millis(self.timeout_milliseconds)
     }
 
-    pub fn client(self) -> EmailClient {
-        let sender_email =
-            self.sender().expect("Invalid sender email address.");
-        let timeout = self.timeout();
+    /// Check the settings are safe to run with in an environment.
+    /// The SMTP provider needs its server configured. Production must talk
+    /// to the provider over TLS, since every request carries credentials,
+    /// and must not capture emails instead of sending them. Local runs may
+    /// use plain connections to reach a mock server.
+    /// # Arguments
+    /// * `environment` - The environment the application runs in.
+    /// # Returns
+    /// A Result describing the first problem found, if any.
+    pub fn validate_for(
+        &self,
+        environment: &Environment,
+    ) -> Result<(), String> {
+        if self.provider == EmailProviderKind::Smtp && self.smtp.is_none() {
+            return Err(
+                "The SMTP email provider needs an `smtp` section.".into()
+            );
+        }
+        if let Environment::Local = environment {
+            return Ok(());
+        }
+        if self.test_mode {
+            return Err(
+                "The email client test mode cannot be enabled in production."
+                    .into(),
+            );
+        }
+        if let Some(smtp) = &self.smtp
+            && self.provider == EmailProviderKind::Smtp
+        {
+            if smtp.tls == SmtpTls::None {
+                return Err(
+                    "The SMTP connection must use TLS in production.".into()
+                );
+            }
+            return Ok(());
+        }
+        let base_url = reqwest::Url::parse(&self.base_url).map_err(|e| {
+            format!(
+                "{} is not a valid email client base URL: {}",
+                self.base_url, e
+            )
+        })?;
+        if base_url.scheme() != "https" {
+            return Err(format!(
+                "The email client base URL must use https in production, got {}.",
+                self.base_url
+            ));
+        }
+        Ok(())
+    }
+
+    /// Build the Postmark client, whatever the configured provider.
+    pub fn postmark_client(&self) -> PostmarkClient {
         let base_url = self
             .base_url
             .parse()
             .expect("Invalid email client base URL");
-        EmailClient::new(
+        PostmarkClient::new(
             base_url,
+            self.authorization_token.clone(),
+            self.timeout(),
+            self.min_tls_version,
+        )
+    }
+
+    /// Build the configured provider.
+    pub fn provider(&self) -> Box<dyn EmailProvider> {
+        match self.provider {
+            EmailProviderKind::Postmark => Box::new(self.postmark_client()),
+            EmailProviderKind::Smtp => {
+                let smtp = self
+                    .smtp
+                    .as_ref()
+                    .expect("The SMTP email provider is not configured.");
+                Box::new(
+                    SmtpClient::new(smtp, self.timeout(), self.min_tls_version)
+                        .expect("Invalid SMTP server settings."),
+                )
+            }
+        }
+    }
+
+    pub fn client(self) -> EmailClient {
+        let sender_email =
+            self.sender().expect("Invalid sender email address.");
+        let reply_to =
+            self.reply_to().expect("Invalid reply-to email address.");
+        EmailClient::new(
+            self.provider(),
             sender_email,
-            self.authorization_token,
-            timeout,
+            self.sender_name,
+            reply_to,
+            self.circuit_breaker.breaker(),
+            DailySendCap::new(self.daily_send_cap),
+            self.test_mode,
+            self.retry_backoff.backoff(),
+            self.subject_prefix,
+            self.max_retries,
+            Duration::from_millis(self.base_delay_milliseconds),
+        )
+    }
+}
+
+/// An upper bound on tasks running at the same time.
+/// Zero would leave every task waiting forever, so it is refused when the
+/// configuration is loaded.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(try_from = "RawConcurrencyLimit")]
+pub struct ConcurrencyLimit(usize);
+
+impl ConcurrencyLimit {
+    pub fn get(&self) -> usize {
+        self.0
+    }
+}
+
+impl TryFrom<usize> for ConcurrencyLimit {
+    type Error = String;
+
+    fn try_from(limit: usize) -> Result<Self, Self::Error> {
+        if limit == 0 {
+            return Err(
+                "A concurrency limit of 0 would block every task. Use 1 or more."
+                    .to_string(),
+            );
+        }
+        Ok(Self(limit))
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(transparent)]
+struct RawConcurrencyLimit(
+    #[serde(deserialize_with = "deserialize_number_from_string")] usize,
+);
+
+impl TryFrom<RawConcurrencyLimit> for ConcurrencyLimit {
+    type Error = String;
+
+    fn try_from(raw: RawConcurrencyLimit) -> Result<Self, Self::Error> {
+        Self::try_from(raw.0)
+    }
+}
+
+/// Newsletter publishing settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct NewsletterSettings {
+    /// Upper bound on publish transactions held open at the same time.
+    pub max_concurrent_publish_transactions: ConcurrencyLimit,
+    /// Longest accepted issue title, in characters.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_title_length: usize,
+    /// Warn before publishing content identical to a recent issue.
+    /// Default of the `duplicate_content_detection` feature flag.
+    pub detect_duplicate_content: bool,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub duplicate_content_window_hours: u32,
+    /// Delivery attempts per subscriber before giving up on an email.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_delivery_retries: i16,
+    /// Set the status of confirmed subscribers whose stored address turns
+    /// out to be invalid at send time to `invalid`, so later issues skip
+    /// them and admins can find them to clean up.
+    pub flag_invalid_subscribers: bool,
+    /// Use the first line of the text body as the inbox preview text of
+    /// issues published without a preheader.
+    pub derive_preheader: bool,
+    pub delivery_alarm: DeliveryAlarmSettings,
+    pub completion_summary: CompletionSummarySettings,
+    /// Where the unsubscribe link in the footer of each email leads.
+    /// The `List-Unsubscribe` header is always one-click.
+    #[serde(default)]
+    pub unsubscribe_link: UnsubscribeLink,
+    /// Where replies to newsletter issues go, instead of the email client's
+    /// `reply_to`.
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// What markup survives in the HTML body of published issues.
+    #[serde(default)]
+    pub html_sanitizer: HtmlSanitizerSettings,
+    /// What publishing does with an issue linking to plain `http` URLs.
+    #[serde(default)]
+    pub insecure_links: InsecureLinkPolicy,
+    #[serde(default)]
+    pub send_time_optimization: SendTimeOptimizationSettings,
+}
+
+/// The allowlist applied to newsletter HTML before it is stored.
+/// Anything not listed is dropped, keeping its text content; `script` and
+/// `style` elements are dropped together with their content unless allowed.
+/// Each list replaces the default one when set, so a deployment tightening
+/// or extending the policy has to spell out the full list.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct HtmlSanitizerSettings {
+    pub tags: HashSet<String>,
+    /// Attributes allowed on every tag.
+    pub generic_attributes: HashSet<String>,
+    /// Attributes allowed on specific tags only.
+    pub tag_attributes: HashMap<String, HashSet<String>>,
+    /// Schemes allowed in links and image sources.
+    pub url_schemes: HashSet<String>,
+}
+
+impl Default for HtmlSanitizerSettings {
+    /// Common email markup: layout tables, inline styles and the
+    /// presentational attributes email clients still rely on.
+    fn default() -> Self {
+        fn set(items: &[&str]) -> HashSet<String> {
+            items.iter().map(|item| item.to_string()).collect()
+        }
+        Self {
+            tags: set(&[
+                "a",
+                "abbr",
+                "b",
+                "blockquote",
+                "br",
+                "caption",
+                "center",
+                "code",
+                "col",
+                "colgroup",
+                "div",
+                "em",
+                "font",
+                "h1",
+                "h2",
+                "h3",
+                "h4",
+                "h5",
+                "h6",
+                "hr",
+                "i",
+                "img",
+                "li",
+                "ol",
+                "p",
+                "pre",
+                "s",
+                "small",
+                "span",
+                "strong",
+                "sub",
+                "sup",
+                "table",
+                "tbody",
+                "td",
+                "tfoot",
+                "th",
+                "thead",
+                "tr",
+                "u",
+                "ul",
+            ]),
+            generic_attributes: set(&[
+                "align", "bgcolor", "class", "dir", "height", "lang", "style",
+                "title", "valign", "width",
+            ]),
+            tag_attributes: HashMap::from([
+                ("a".into(), set(&["href", "name", "target"])),
+                ("img".into(), set(&["alt", "border", "src"])),
+                (
+                    "table".into(),
+                    set(&["border", "cellpadding", "cellspacing"]),
+                ),
+                ("td".into(), set(&["colspan", "rowspan"])),
+                ("th".into(), set(&["colspan", "rowspan"])),
+                ("col".into(), set(&["span"])),
+                ("colgroup".into(), set(&["span"])),
+                ("font".into(), set(&["color", "face", "size"])),
+            ]),
+            url_schemes: set(&["http", "https", "mailto"]),
+        }
+    }
+}
+
+/// What following the unsubscribe link in an email does.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsubscribeLink {
+    /// Unsubscribe immediately.
+    #[default]
+    OneClick,
+    /// Show a page asking the subscriber to confirm first.
+    ConfirmPage,
+}
+
+/// How publishing treats `http://` links in the HTML body of an issue.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InsecureLinkPolicy {
+    /// Refuse to publish the issue.
+    Reject,
+    /// Publish the issue, but tell the author which links are insecure.
+    Warn,
+    /// Do not check links.
+    #[default]
+    Ignore,
+}
+
+/// Settings for delivering each issue near the hour its recipient usually
+/// opens emails in.
+#[derive(serde::Deserialize, Clone)]
+pub struct SendTimeOptimizationSettings {
+    pub enabled: bool,
+    /// The longest a delivery may be held back; subscribers whose hour is
+    /// further away get the issue right away.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_delay_hours: u32,
+}
+
+impl Default for SendTimeOptimizationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_delay_hours: 12,
+        }
+    }
+}
+
+/// Bounce/complaint rate alarm settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct DeliveryAlarmSettings {
+    /// Fraction of sent emails that may bounce before the alarm fires.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub bounce_rate_threshold: f64,
+    /// Fraction of sent emails that may be reported as spam.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub complaint_rate_threshold: f64,
+    /// Emails to send before the rates are considered meaningful.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_deliveries: i32,
+    /// Pause the issue's remaining deliveries when the alarm fires.
+    pub auto_pause: bool,
+}
+
+/// Settings for the summary emailed once an issue has been delivered.
+#[derive(serde::Deserialize, Clone)]
+pub struct CompletionSummarySettings {
+    pub enabled: bool,
+    /// Where to send the summary, e.g. the newsletter author's inbox.
+    pub recipient: String,
+}
+
+/// Onboarding sequence settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct OnboardingSettings {
+    /// The emails sent after a subscriber confirms, in any order.
+    #[serde(default)]
+    pub steps: Vec<OnboardingStep>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_delivery_retries: i16,
+}
+
+impl OnboardingSettings {
+    pub fn step(&self, key: &str) -> Option<&OnboardingStep> {
+        self.steps.iter().find(|step| step.key == key)
+    }
+}
+
+/// A single email of the onboarding sequence.
+/// The subject and bodies support the same `{{name}}`/`{{email}}` tokens as
+/// newsletter issues; the plain text body is derived from the HTML one when
+/// left out.
+#[derive(serde::Deserialize, Clone)]
+pub struct OnboardingStep {
+    /// Identifies the step; a subscriber receives each key at most once per
+    /// run of the sequence.
+    pub key: String,
+    /// How long after confirmation the email goes out.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub delay_hours: i32,
+    pub subject: String,
+    pub html_content: String,
+    #[serde(default)]
+    pub text_content: String,
+}
+
+/// Subscriber listing settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriberListingSettings {
+    /// The sort applied when the request does not ask for one.
+    pub default_sort: SubscriberSortField,
+    pub default_order: SortOrder,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub page_size: i64,
+    /// Most subscribers the JSON export returns before refusing with a 413.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub export_max_rows: i64,
+}
+
+/// Subscriber engagement settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct EngagementSettings {
+    /// Days without an open or click before a subscriber counts as
+    /// inactive.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub inactive_after_days: u32,
+}
+
+/// Subscriber cap settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriberLimitSettings {
+    /// The plan's subscriber cap; unlimited when absent.
+    #[serde(default)]
+    pub max_subscribers: Option<i64>,
+    pub counted: CountedSubscribers,
+}
+
+/// Settings for the admin listings of events and audit records.
+#[derive(serde::Deserialize, Clone)]
+pub struct EventListingSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub default_page_size: i64,
+    /// Larger pages are rejected rather than truncated.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_page_size: i64,
+}
+
+impl EventListingSettings {
+    /// Resolve the page size asked for by a request.
+    /// # Arguments
+    /// * `limit` - The requested page size, if any.
+    /// # Returns
+    /// A Result containing the page size or a user-facing message.
+    pub fn page_size(&self, limit: Option<i64>) -> Result<i64, String> {
+        match limit {
+            None => Ok(self.default_page_size),
+            Some(limit) if (1..=self.max_page_size).contains(&limit) => {
+                Ok(limit)
+            }
+            Some(limit) => Err(format!(
+                "{} is not a valid page size. Ask for 1 to {} records.",
+                limit, self.max_page_size
+            )),
+        }
+    }
+}
+
+/// Signup velocity check settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct SignupVelocitySettings {
+    /// Signups accepted from a single IP address within the window.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_signups_per_ip: i64,
+    /// Signups accepted for sequential addresses (`bot1@`, `bot2@`, ...).
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_signups_per_email_pattern: i64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub window_seconds: u32,
+    /// Take the client IP from `Forwarded`/`X-Forwarded-For`.
+    /// Only enable this behind a proxy that overwrites those headers.
+    pub trust_forwarded_for: bool,
+}
+
+/// Subscribe form honeypot settings structure.
+/// The form carries a field hidden from humans; bots that fill it in are
+/// told they subscribed but are dropped.
+#[derive(serde::Deserialize, Clone)]
+pub struct HoneypotSettings {
+    pub enabled: bool,
+    /// The name of the hidden form field.
+    pub field_name: String,
+}
+
+impl Default for HoneypotSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            field_name: "website".into(),
+        }
+    }
+}
+
+/// Idempotency settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct IdempotencySettings {
+    /// Whether to refuse or process requests when the store is down.
+    pub failure_mode: FailureMode,
+    /// Which keys clients may send.
+    #[serde(default)]
+    pub key_format: IdempotencyKeyFormat,
+    /// How long after its first use a key may be repeated; later repeats
+    /// are refused instead of replaying the saved response. 0 means no
+    /// limit.
+    #[serde(default, deserialize_with = "deserialize_number_from_string")]
+    pub replay_window_seconds: u64,
+}
+
+impl IdempotencySettings {
+    pub fn replay_window(&self) -> Option<Duration> {
+        (self.replay_window_seconds > 0)
+            .then(|| Duration::from_secs(self.replay_window_seconds))
+    }
+}
+
+/// Settings for webhooks the email provider sends us (bounces, complaints).
+#[derive(serde::Deserialize, Clone)]
+pub struct InboundWebhookSettings {
+    /// Expected in the `X-Webhook-Token` header of every delivery.
+    pub token: SecretString,
+    /// Acknowledge events whose id was already processed without
+    /// processing them again.
+    pub deduplicate: bool,
+}
+
+/// Prometheus metrics endpoint settings structure.
+/// Enabling the endpoint without a scrape token is refused when the
+/// configuration is loaded.
+#[derive(serde::Deserialize, Clone, Default)]
+#[serde(try_from = "RawMetricsSettings")]
+pub struct MetricsSettings {
+    /// Serve `/metrics`. Off by default, since the counters describe
+    /// delivery volume and every scrape aggregates the delivery queue.
+    pub enabled: bool,
+    /// Expected as `Authorization: Bearer <token>` on every scrape.
+    pub scrape_token: Option<SecretString>,
+}
+
+#[derive(serde::Deserialize)]
+struct RawMetricsSettings {
+    enabled: bool,
+    scrape_token: Option<SecretString>,
+}
+
+impl TryFrom<RawMetricsSettings> for MetricsSettings {
+    type Error = String;
+
+    fn try_from(raw: RawMetricsSettings) -> Result<Self, Self::Error> {
+        let has_token = raw
+            .scrape_token
+            .as_ref()
+            .is_some_and(|t| !t.expose_secret().is_empty());
+        if raw.enabled && !has_token {
+            return Err(
+                "The metrics endpoint needs a `scrape_token` when enabled."
+                    .into(),
+            );
+        }
+        Ok(Self {
+            enabled: raw.enabled,
+            scrape_token: raw.scrape_token,
+        })
+    }
+}
+
+/// Login session settings structure.
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct SessionSettings {
+    /// Sessions a user may hold at once; logging in beyond it evicts the
+    /// oldest one. 0 means no limit.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_per_user: u32,
+}
+
+/// Failed login lockout settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct LoginThrottleSettings {
+    /// Failed logins for one username within the window before it is
+    /// locked out.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_failures_per_username: i64,
+    /// Failed logins from one IP address within the window before it is
+    /// locked out.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_failures_per_ip: i64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub window_seconds: u32,
+    /// Take the client IP from `Forwarded`/`X-Forwarded-For`.
+    /// Only enable this behind a proxy that overwrites those headers.
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for LoginThrottleSettings {
+    fn default() -> Self {
+        Self {
+            max_failures_per_username: 5,
+            max_failures_per_ip: 20,
+            window_seconds: 900,
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+/// Password reset settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct PasswordResetSettings {
+    /// How long an emailed reset link can be used.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub token_ttl_minutes: u32,
+}
+
+impl Default for PasswordResetSettings {
+    fn default() -> Self {
+        Self {
+            token_ttl_minutes: 60,
+        }
+    }
+}
+
+/// Subscriber email address change settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct EmailChangeSettings {
+    /// How long the link confirming a new address can be used.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub token_ttl_minutes: u32,
+}
+
+impl EmailChangeSettings {
+    /// How long the link confirming a new address can be used.
+    pub fn token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.token_ttl_minutes.into())
+    }
+}
+
+impl Default for EmailChangeSettings {
+    fn default() -> Self {
+        Self {
+            token_ttl_minutes: 24 * 60,
+        }
+    }
+}
+
+/// API token settings structure.
+/// Tokens are signed with the application's HMAC secret.
+#[derive(serde::Deserialize, Clone)]
+pub struct ApiTokenSettings {
+    /// The `iss` claim tokens are issued with and must carry.
+    pub issuer: String,
+    /// How long an issued token can be used.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub ttl_minutes: u32,
+}
+
+impl ApiTokenSettings {
+    /// How long an issued token can be used.
+    pub fn ttl(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.ttl_minutes.into())
+    }
+}
+
+impl Default for ApiTokenSettings {
+    fn default() -> Self {
+        Self {
+            issuer: "melierx".into(),
+            ttl_minutes: 15,
+        }
+    }
+}
+
+/// Confirmation email resend settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct ConfirmationResendSettings {
+    /// How long a pending subscriber waits between resent confirmation
+    /// emails.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub cooldown_seconds: u32,
+}
+
+impl Default for ConfirmationResendSettings {
+    fn default() -> Self {
+        Self {
+            cooldown_seconds: 300,
+        }
+    }
+}
+
+/// Stale pending subscriber sweeper settings structure.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct PendingSweepSettings {
+    /// How often the sweeper runs.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub interval_seconds: u64,
+    /// How long a subscriber may stay unconfirmed, counted from signing up
+    /// or from their last resent confirmation email.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub ttl_hours: u32,
+    /// The Postgres advisory lock key instances contend for to sweep, when
+    /// `scheduler.leader_election` is on.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lock_key: i64,
+}
+
+impl Default for PendingSweepSettings {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 3600,
+            ttl_hours: 24 * 7,
+            lock_key: 7402,
+        }
+    }
+}
+
+impl PendingSweepSettings {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_seconds)
+    }
+}
+
+/// User email verification settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct EmailVerificationSettings {
+    /// Refuse logins to accounts whose email address is not verified.
+    /// Accounts without an email address cannot verify one, so they cannot
+    /// log in either while this is on.
+    pub required: bool,
+    /// How long an emailed verification link can be used.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub token_ttl_minutes: u32,
+}
+
+impl Default for EmailVerificationSettings {
+    fn default() -> Self {
+        Self {
+            required: false,
+            token_ttl_minutes: 24 * 60,
+        }
+    }
+}
+
+/// Two-factor authentication settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct TotpSettings {
+    /// The name authenticator apps show next to the account.
+    pub issuer: String,
+    /// Encrypts the stored TOTP secrets. Changing it makes every enrolled
+    /// secret unreadable, locking their users out.
+    pub encryption_key: SecretString,
+}
+
+/// How subscriber addresses are stored.
+/// With hashed storage `subscriptions.email` holds a keyed hash of the
+/// address, which the unique index and every lookup by address use, and the
+/// address itself is only kept encrypted, to be decrypted when sending.
+/// Event logs and the delivery queues then carry the hash, and the
+/// subscriber listing sorts by it. Webhooks and signup notifications are
+/// still sent the decrypted address. Switching an existing list between
+/// modes is not supported: stored rows are not rewritten.
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriberEmailSettings {
+    #[serde(default)]
+    pub storage: EmailStorage,
+    /// Keys both the hash and the encryption. Changing it makes every stored
+    /// address unreadable and unmatchable.
+    pub encryption_key: SecretString,
+}
+
+/// Where subscriber addresses are kept.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailStorage {
+    /// Store addresses as they are.
+    #[default]
+    Plaintext,
+    /// Store a keyed hash, plus an encrypted copy.
+    Hashed,
+}
+
+/// Scheduled job settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct SchedulerSettings {
+    /// Only let the instance holding `lock_key` run scheduled jobs.
+    /// Disable it only when a single instance is deployed.
+    pub leader_election: bool,
+    /// The Postgres advisory lock key the instances contend for.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lock_key: i64,
+}
+
+/// Delivery worker loop settings structure.
+/// Out-of-range values are refused when the configuration is loaded.
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "RawWorkerSettings")]
+pub struct WorkerSettings {
+    /// How long an idle worker waits before polling the queues again.
+    pub poll_interval: Duration,
+    /// Tasks taken on per pass before onboarding emails and scheduled
+    /// issues get a turn.
+    pub batch_size: usize,
+    /// Calls to the email provider a pass makes at the same time, each
+    /// sending its share of the batch.
+    pub concurrency: usize,
+}
+
+impl WorkerSettings {
+    const POLL_INTERVAL_MILLISECONDS: std::ops::RangeInclusive<u64> =
+        100..=60_000;
+    const BATCH_SIZE: std::ops::RangeInclusive<usize> = 1..=1000;
+    const CONCURRENCY: std::ops::RangeInclusive<usize> = 1..=64;
+}
+
+impl Default for WorkerSettings {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(10),
+            batch_size: 50,
+            concurrency: 1,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct RawWorkerSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    poll_interval_milliseconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    batch_size: usize,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    concurrency: usize,
+}
+
+impl TryFrom<RawWorkerSettings> for WorkerSettings {
+    type Error = String;
+
+    fn try_from(raw: RawWorkerSettings) -> Result<Self, Self::Error> {
+        if !Self::POLL_INTERVAL_MILLISECONDS
+            .contains(&raw.poll_interval_milliseconds)
+        {
+            return Err(format!(
+                "A worker poll interval of {}ms is out of range. \
+                Use {} to {}ms.",
+                raw.poll_interval_milliseconds,
+                Self::POLL_INTERVAL_MILLISECONDS.start(),
+                Self::POLL_INTERVAL_MILLISECONDS.end()
+            ));
+        }
+        if !Self::BATCH_SIZE.contains(&raw.batch_size) {
+            return Err(format!(
+                "A worker batch size of {} is out of range. Use {} to {}.",
+                raw.batch_size,
+                Self::BATCH_SIZE.start(),
+                Self::BATCH_SIZE.end()
+            ));
+        }
+        if !Self::CONCURRENCY.contains(&raw.concurrency) {
+            return Err(format!(
+                "A worker concurrency of {} is out of range. Use {} to {}.",
+                raw.concurrency,
+                Self::CONCURRENCY.start(),
+                Self::CONCURRENCY.end()
+            ));
+        }
+        if raw.concurrency > raw.batch_size {
+            return Err(format!(
+                "A worker concurrency of {} exceeds the batch size of {}.",
+                raw.concurrency, raw.batch_size
+            ));
+        }
+        Ok(Self {
+            poll_interval: Duration::from_millis(
+                raw.poll_interval_milliseconds,
+            ),
+            batch_size: raw.batch_size,
+            concurrency: raw.concurrency,
+        })
+    }
+}
+
+/// Outbound webhook settings structure.
+#[derive(serde::Deserialize, Clone)]
+pub struct WebhookSettings {
+    /// Where to POST events; webhooks are disabled when absent.
+    #[serde(default)]
+    pub target_url: Option<String>,
+    pub signing_secret: SecretString,
+    /// The lifecycle events to deliver.
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    /// Where to ping (e.g. a Slack or Discord webhook) on new signups.
+    #[serde(default)]
+    pub signup_notification_url: Option<String>,
+    pub signup_notification_trigger: SignupNotificationTrigger,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_retries: i16,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_milliseconds: u64,
+    /// How long failed deliveries wait to be retried.
+    pub retry_backoff: BackoffSettings,
+}
+
+impl WebhookSettings {
+    /// Whether the given event should be delivered at all.
+    pub fn is_enabled_for(&self, event: WebhookEvent) -> bool {
+        self.target_url.is_some() && self.events.contains(&event)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+
+    pub fn client(&self) -> WebhookClient {
+        let target_url = self
+            .target_url
+            .as_ref()
+            .map(|u| u.parse().expect("Invalid webhook target URL"));
+        WebhookClient::new(
+            target_url,
+            self.signing_secret.clone(),
+            self.timeout(),
+            self.retry_backoff.backoff(),
         )
     }
 }
@@ -121,6 +1349,43 @@ pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
+    pub newsletter: NewsletterSettings,
+    pub onboarding: OnboardingSettings,
+    pub subscriber_listing: SubscriberListingSettings,
+    pub engagement: EngagementSettings,
+    pub event_listing: EventListingSettings,
+    pub subscriber_limit: SubscriberLimitSettings,
+    pub signup_velocity: SignupVelocitySettings,
+    #[serde(default)]
+    pub honeypot: HoneypotSettings,
+    pub idempotency: IdempotencySettings,
+    pub scheduler: SchedulerSettings,
+    #[serde(default)]
+    pub worker: WorkerSettings,
+    #[serde(default)]
+    pub sessions: SessionSettings,
+    #[serde(default)]
+    pub login_throttle: LoginThrottleSettings,
+    #[serde(default)]
+    pub password_reset: PasswordResetSettings,
+    #[serde(default)]
+    pub email_change: EmailChangeSettings,
+    #[serde(default)]
+    pub api_tokens: ApiTokenSettings,
+    #[serde(default)]
+    pub clock_skew: ClockSkewTolerance,
+    #[serde(default)]
+    pub email_verification: EmailVerificationSettings,
+    #[serde(default)]
+    pub confirmation_resend: ConfirmationResendSettings,
+    #[serde(default)]
+    pub pending_sweep: PendingSweepSettings,
+    pub totp: TotpSettings,
+    pub subscriber_emails: SubscriberEmailSettings,
+    pub webhooks: WebhookSettings,
+    pub inbound_webhooks: InboundWebhookSettings,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
     pub redis_uri: SecretString,
 }
 
@@ -151,10 +1416,331 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
             )
             .required(true),
         )
-        .add_source(config::Environment::with_prefix("APP").separator("__"))
-        // Add in settings from environment variables (with prefix APP and '__' as separator)
-        // E.g., `APP_DATABASE__USERNAME` would set `database.username`
+        // Add in settings from environment variables
+        .add_source(environment_variables())
         .build()?;
 
-    settings.try_deserialize::<Settings>()
+    let settings = settings.try_deserialize::<Settings>()?;
+    settings
+        .email_client
+        .validate_for(&environment)
+        .map_err(config::ConfigError::Message)?;
+    Ok(settings)
+}
+
+/// Environment variable source with prefix APP and '__' as separator.
+/// E.g., `APP_DATABASE__USERNAME` would set `database.username`
+fn environment_variables() -> config::Environment {
+    config::Environment::with_prefix("APP")
+        .prefix_separator("_")
+        .separator("__")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use secrecy::ExposeSecret;
+
+    use super::{
+        CompressionSettings, DatabaseSettings, EmailClientSettings,
+        EmailProviderKind, Environment, MetricsSettings, NewsletterSettings,
+        TlsVersion, WorkerSettings, environment_variables,
+    };
+
+    fn load<T: serde::de::DeserializeOwned>(
+        key: &str,
+        env: &[(&str, &str)],
+    ) -> Result<T, config::ConfigError> {
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        config::Config::builder()
+            .add_source(config::File::with_name("configuration/base"))
+            .add_source(environment_variables().source(Some(env)))
+            .build()
+            .unwrap()
+            .get::<T>(key)
+    }
+
+    fn database_settings(env: &[(&str, &str)]) -> DatabaseSettings {
+        load("database", env).unwrap()
+    }
+
+    #[test]
+    fn maintenance_credentials_default_to_the_local_superuser() {
+        let maintenance = database_settings(&[]).maintenance();
+
+        assert_eq!(maintenance.database_name, "postgres");
+        assert_eq!(maintenance.username, "postgres");
+        assert_eq!(maintenance.password.expose_secret(), "password");
+    }
+
+    #[test]
+    fn maintenance_credentials_can_be_overridden_from_the_environment() {
+        let settings = database_settings(&[
+            ("APP_DATABASE__MAINTENANCE__USERNAME", "ci"),
+            ("APP_DATABASE__MAINTENANCE__PASSWORD", "ci-secret"),
+        ]);
+        let maintenance = settings.maintenance();
+
+        assert_eq!(maintenance.username, "ci");
+        assert_eq!(maintenance.password.expose_secret(), "ci-secret");
+        assert_eq!(maintenance.database_name, "postgres");
+        // Only the credentials change, not the server
+        assert_eq!(maintenance.host, settings.host);
+        assert_eq!(maintenance.port, settings.port);
+    }
+
+    #[test]
+    fn outbound_email_requires_tls_1_2_by_default() {
+        let settings: EmailClientSettings = load("email_client", &[]).unwrap();
+
+        assert_eq!(settings.min_tls_version, TlsVersion::Tls1_2);
+    }
+
+    #[test]
+    fn the_minimum_tls_version_can_be_raised_to_1_3() {
+        let settings: EmailClientSettings = load(
+            "email_client",
+            &[("APP_EMAIL_CLIENT__MIN_TLS_VERSION", "1.3")],
+        )
+        .unwrap();
+
+        assert_eq!(settings.min_tls_version, TlsVersion::Tls1_3);
+        // The client can be built with the raised minimum
+        settings.client();
+    }
+
+    #[test]
+    fn tls_versions_older_than_1_2_are_rejected() {
+        for version in ["1.0", "1.1", "TLS1.1"] {
+            let settings = load::<EmailClientSettings>(
+                "email_client",
+                &[("APP_EMAIL_CLIENT__MIN_TLS_VERSION", version)],
+            );
+
+            assert!(settings.is_err(), "TLS {version} was accepted");
+        }
+    }
+
+    #[test]
+    fn a_plain_http_email_base_url_is_rejected_in_production() {
+        let settings: EmailClientSettings = load(
+            "email_client",
+            &[("APP_EMAIL_CLIENT__BASE_URL", "http://api.postmarkapp.com")],
+        )
+        .unwrap();
+
+        assert!(settings.validate_for(&Environment::Production).is_err());
+    }
+
+    #[test]
+    fn a_plain_http_email_base_url_is_allowed_locally() {
+        let settings: EmailClientSettings = load(
+            "email_client",
+            &[("APP_EMAIL_CLIENT__BASE_URL", "http://127.0.0.1:8025")],
+        )
+        .unwrap();
+
+        assert!(settings.validate_for(&Environment::Local).is_ok());
+    }
+
+    #[test]
+    fn an_https_email_base_url_is_allowed_in_production() {
+        let settings: EmailClientSettings = load(
+            "email_client",
+            &[("APP_EMAIL_CLIENT__BASE_URL", "https://api.postmarkapp.com")],
+        )
+        .unwrap();
+
+        assert!(settings.validate_for(&Environment::Production).is_ok());
+    }
+
+    #[test]
+    fn the_email_test_mode_is_rejected_in_production() {
+        let settings: EmailClientSettings = load(
+            "email_client",
+            &[
+                ("APP_EMAIL_CLIENT__BASE_URL", "https://api.postmarkapp.com"),
+                ("APP_EMAIL_CLIENT__TEST_MODE", "true"),
+            ],
+        )
+        .unwrap();
+
+        assert!(settings.validate_for(&Environment::Production).is_err());
+        assert!(settings.validate_for(&Environment::Local).is_ok());
+    }
+
+    #[test]
+    fn postmark_is_the_default_email_provider() {
+        let settings: EmailClientSettings = load("email_client", &[]).unwrap();
+
+        assert_eq!(settings.provider, EmailProviderKind::Postmark);
+    }
+
+    #[test]
+    fn the_smtp_provider_needs_a_server() {
+        let settings: EmailClientSettings =
+            load("email_client", &[("APP_EMAIL_CLIENT__PROVIDER", "smtp")])
+                .unwrap();
+
+        assert!(settings.validate_for(&Environment::Local).is_err());
+    }
+
+    #[test]
+    fn a_plain_smtp_connection_is_only_allowed_locally() {
+        let settings: EmailClientSettings = load(
+            "email_client",
+            &[
+                ("APP_EMAIL_CLIENT__PROVIDER", "smtp"),
+                ("APP_EMAIL_CLIENT__SMTP__HOST", "127.0.0.1"),
+                ("APP_EMAIL_CLIENT__SMTP__PORT", "1025"),
+                ("APP_EMAIL_CLIENT__SMTP__TLS", "none"),
+            ],
+        )
+        .unwrap();
+
+        assert!(settings.validate_for(&Environment::Local).is_ok());
+        assert!(settings.validate_for(&Environment::Production).is_err());
+    }
+
+    #[test]
+    fn an_smtp_connection_over_tls_is_allowed_in_production() {
+        let settings: EmailClientSettings = load(
+            "email_client",
+            &[
+                ("APP_EMAIL_CLIENT__PROVIDER", "smtp"),
+                ("APP_EMAIL_CLIENT__SMTP__HOST", "smtp.melierx.com"),
+                ("APP_EMAIL_CLIENT__SMTP__PORT", "465"),
+                ("APP_EMAIL_CLIENT__SMTP__TLS", "tls"),
+            ],
+        )
+        .unwrap();
+
+        assert!(settings.validate_for(&Environment::Production).is_ok());
+    }
+
+    #[test]
+    fn compression_matches_the_media_type_ignoring_parameters() {
+        let settings: CompressionSettings =
+            load("application.compression", &[]).unwrap();
+
+        assert!(settings.compresses("application/json"));
+        assert!(settings.compresses("text/html; charset=utf-8"));
+        assert!(settings.compresses("Text/HTML"));
+        assert!(!settings.compresses("image/png"));
+        assert!(!settings.compresses("application/gzip"));
+    }
+
+    #[test]
+    fn the_worker_settings_in_base_are_the_defaults() {
+        let settings: WorkerSettings = load("worker", &[]).unwrap();
+
+        assert_eq!(settings, WorkerSettings::default());
+    }
+
+    #[test]
+    fn valid_worker_settings_are_applied() {
+        let settings: WorkerSettings = load(
+            "worker",
+            &[
+                ("APP_WORKER__POLL_INTERVAL_MILLISECONDS", "100"),
+                ("APP_WORKER__BATCH_SIZE", "200"),
+                ("APP_WORKER__CONCURRENCY", "8"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(settings.poll_interval, Duration::from_millis(100));
+        assert_eq!(settings.batch_size, 200);
+        assert_eq!(settings.concurrency, 8);
+    }
+
+    #[test]
+    fn out_of_range_worker_settings_are_rejected() {
+        let cases = [
+            ("APP_WORKER__POLL_INTERVAL_MILLISECONDS", "99"),
+            ("APP_WORKER__POLL_INTERVAL_MILLISECONDS", "60001"),
+            ("APP_WORKER__BATCH_SIZE", "0"),
+            ("APP_WORKER__BATCH_SIZE", "1001"),
+            ("APP_WORKER__CONCURRENCY", "0"),
+            ("APP_WORKER__CONCURRENCY", "65"),
+        ];
+        for (key, value) in cases {
+            let settings = load::<WorkerSettings>("worker", &[(key, value)]);
+
+            assert!(settings.is_err(), "{key}={value} was accepted");
+        }
+    }
+
+    #[test]
+    fn the_worker_concurrency_cannot_exceed_the_batch_size() {
+        let settings = load::<WorkerSettings>(
+            "worker",
+            &[
+                ("APP_WORKER__BATCH_SIZE", "4"),
+                ("APP_WORKER__CONCURRENCY", "5"),
+            ],
+        );
+
+        assert!(settings.is_err());
+    }
+
+    #[test]
+    fn concurrent_publish_transactions_can_be_limited() {
+        let settings: NewsletterSettings = load(
+            "newsletter",
+            &[("APP_NEWSLETTER__MAX_CONCURRENT_PUBLISH_TRANSACTIONS", "3")],
+        )
+        .unwrap();
+
+        assert_eq!(settings.max_concurrent_publish_transactions.get(), 3);
+    }
+
+    #[test]
+    fn a_zero_publish_transaction_limit_is_rejected() {
+        let settings = load::<NewsletterSettings>(
+            "newsletter",
+            &[("APP_NEWSLETTER__MAX_CONCURRENT_PUBLISH_TRANSACTIONS", "0")],
+        );
+
+        assert!(settings.is_err());
+    }
+
+    #[test]
+    fn a_zero_confirmation_send_limit_is_rejected() {
+        let settings = load::<EmailClientSettings>(
+            "email_client",
+            &[("APP_EMAIL_CLIENT__MAX_CONCURRENT_CONFIRMATION_SENDS", "0")],
+        );
+
+        assert!(settings.is_err());
+    }
+
+    #[test]
+    fn the_metrics_endpoint_cannot_be_enabled_without_a_scrape_token() {
+        let settings = load::<MetricsSettings>(
+            "metrics",
+            &[("APP_METRICS__ENABLED", "true")],
+        );
+
+        assert!(settings.is_err());
+    }
+
+    #[test]
+    fn the_metrics_endpoint_can_be_enabled_with_a_scrape_token() {
+        let settings: MetricsSettings = load(
+            "metrics",
+            &[
+                ("APP_METRICS__ENABLED", "true"),
+                ("APP_METRICS__SCRAPE_TOKEN", "scrape-token"),
+            ],
+        )
+        .unwrap();
+
+        assert!(settings.enabled);
+    }
 }
@@ -0,0 +1,121 @@
+use std::future::Future;
+
+use sqlx::pool::PoolConnection;
+use sqlx::{PgPool, Postgres};
+
+use crate::backoff::{Backoff, BackoffStrategy};
+use crate::configuration::ConnectionPoolSettings;
+
+/// Acquire a connection for read-only queries, trying again with backoff if
+/// the pool times out, so that a momentary spike in load does not fail the
+/// request.
+/// Only use it for reads: a write that timed out is better reported than
+/// delayed further.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `settings` - How often and how soon to retry.
+/// # Returns
+/// A Result containing the connection, or the last error.
+pub async fn acquire_for_read(
+    pool: &PgPool,
+    settings: &ConnectionPoolSettings,
+) -> Result<PoolConnection<Postgres>, sqlx::Error> {
+    retry_acquire(settings, || pool.acquire()).await
+}
+
+async fn retry_acquire<T, F, Fut>(
+    settings: &ConnectionPoolSettings,
+    mut acquire: F,
+) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    let base = settings.read_acquire_retry_delay();
+    let backoff = Backoff::new(
+        BackoffStrategy::EqualJitter,
+        base,
+        base.saturating_mul(8),
+    );
+    let mut n_retries = 0;
+    loop {
+        match acquire().await {
+            Err(sqlx::Error::PoolTimedOut)
+                if n_retries < settings.read_acquire_retries =>
+            {
+                tracing::warn!(
+                    n_retries,
+                    "Timed out acquiring a database connection. Retrying."
+                );
+                actix_web::rt::time::sleep(backoff.delay(n_retries)).await;
+                n_retries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::retry_acquire;
+    use crate::configuration::ConnectionPoolSettings;
+
+    fn settings(read_acquire_retries: i16) -> ConnectionPoolSettings {
+        ConnectionPoolSettings {
+            read_acquire_retries,
+            read_acquire_retry_delay_milliseconds: 1,
+            ..ConnectionPoolSettings::default()
+        }
+    }
+
+    /// Time out `failures` times, then succeed.
+    fn flaky(
+        failures: u32,
+        attempts: &Cell<u32>,
+    ) -> impl FnMut() -> std::future::Ready<Result<(), sqlx::Error>> {
+        move || {
+            attempts.set(attempts.get() + 1);
+            std::future::ready(if attempts.get() <= failures {
+                Err(sqlx::Error::PoolTimedOut)
+            } else {
+                Ok(())
+            })
+        }
+    }
+
+    #[actix_web::test]
+    async fn a_transient_timeout_is_retried() {
+        let attempts = Cell::new(0);
+
+        let result = retry_acquire(&settings(2), flaky(1, &attempts)).await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.get(), 2);
+    }
+
+    #[actix_web::test]
+    async fn retries_stop_at_the_configured_limit() {
+        let attempts = Cell::new(0);
+
+        let result = retry_acquire(&settings(2), flaky(5, &attempts)).await;
+
+        assert!(matches!(result, Err(sqlx::Error::PoolTimedOut)));
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[actix_web::test]
+    async fn other_errors_are_not_retried() {
+        let attempts = Cell::new(0);
+
+        let result = retry_acquire(&settings(2), || {
+            attempts.set(attempts.get() + 1);
+            std::future::ready(Err::<(), _>(sqlx::Error::PoolClosed))
+        })
+        .await;
+
+        assert!(matches!(result, Err(sqlx::Error::PoolClosed)));
+        assert_eq!(attempts.get(), 1);
+    }
+}
@@ -1,7 +1,13 @@
 mod new_subscriber;
+mod newsletter_title;
 mod subscriber_email;
 mod subscriber_name;
+mod subscriber_status;
 
 pub use new_subscriber::NewSubscriber;
+pub use newsletter_title::NewsletterTitle;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscriber_status::{
+    InvalidTransition, StatusEvent, SubscriberStatus, transition_status,
+};
@@ -0,0 +1,84 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// NewsletterTitle newtype with validation.
+/// The title becomes the subject of every delivered email, so line breaks
+/// (which could inject extra headers) are folded into spaces and any other
+/// control character is rejected.
+#[derive(Debug)]
+pub struct NewsletterTitle(String);
+
+impl NewsletterTitle {
+    pub fn parse(s: String, max_length: usize) -> Result<Self, String> {
+        let title = s
+            .split(['\r', '\n'])
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if title.is_empty() {
+            return Err("The newsletter title cannot be empty.".into());
+        }
+        if title.graphemes(true).count() > max_length {
+            return Err(format!(
+                "The newsletter title cannot be longer than {} characters.",
+                max_length
+            ));
+        }
+        if title.chars().any(char::is_control) {
+            return Err(
+                "The newsletter title cannot contain control characters."
+                    .into(),
+            );
+        }
+        Ok(Self(title))
+    }
+}
+
+impl AsRef<str> for NewsletterTitle {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NewsletterTitle;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn a_title_at_the_maximum_length_is_valid() {
+        assert_ok!(NewsletterTitle::parse("a".repeat(200), 200));
+    }
+
+    #[test]
+    fn a_title_over_the_maximum_length_is_rejected() {
+        assert_err!(NewsletterTitle::parse("a".repeat(201), 200));
+    }
+
+    #[test]
+    fn whitespace_only_title_is_rejected() {
+        assert_err!(NewsletterTitle::parse(" \r\n ".into(), 200));
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_trimmed() {
+        let title = NewsletterTitle::parse("  Weekly digest ".into(), 200);
+        assert_eq!(title.unwrap().as_ref(), "Weekly digest");
+    }
+
+    #[test]
+    fn line_breaks_are_folded_into_spaces() {
+        let title = NewsletterTitle::parse(
+            "Hi\r\nBcc: everyone@example.com".into(),
+            200,
+        );
+        assert_eq!(title.unwrap().as_ref(), "Hi Bcc: everyone@example.com");
+    }
+
+    #[test]
+    fn titles_containing_control_characters_are_rejected() {
+        for c in ['\0', '\t', '\u{7f}', '\u{1b}'] {
+            assert_err!(NewsletterTitle::parse(format!("Hi{c}there"), 200));
+        }
+    }
+}
@@ -14,6 +14,18 @@ impl SubscriberEmail {
             Err(format!("'{}' is not a valid subscriber email.", s))
         }
     }
+
+    /// The address with `+tags` and trailing digits dropped from the local
+    /// part, so that `bot1@x.com`, `bot2@x.com` and `Bot3+a@x.com` share one
+    /// pattern.
+    pub fn signup_pattern(&self) -> String {
+        let address = self.0.to_lowercase();
+        let (local, domain) =
+            address.rsplit_once('@').unwrap_or(("", &address));
+        let local = local.split('+').next().unwrap_or_default();
+        let stem = local.trim_end_matches(|c: char| c.is_ascii_digit());
+        format!("{stem}@{domain}")
+    }
 }
 
 impl AsRef<str> for SubscriberEmail {
@@ -73,4 +85,25 @@ mod tests {
     ) -> bool {
         SubscriberEmail::parse(valid_email.0).is_ok()
     }
+
+    #[test]
+    fn sequential_addresses_share_a_signup_pattern() {
+        let patterns: Vec<String> = [
+            "bot1@example.com",
+            "bot22@example.com",
+            "Bot3+x@Example.com",
+        ]
+        .into_iter()
+        .map(|e| SubscriberEmail::parse(e.into()).unwrap())
+        .map(|e| e.signup_pattern())
+        .collect();
+        assert!(patterns.iter().all(|p| p == "bot@example.com"));
+    }
+
+    #[test]
+    fn different_people_have_different_signup_patterns() {
+        let a = SubscriberEmail::parse("ursula@example.com".into()).unwrap();
+        let b = SubscriberEmail::parse("le.guin@example.com".into()).unwrap();
+        assert_ne!(a.signup_pattern(), b.signup_pattern());
+    }
 }
@@ -0,0 +1,161 @@
+/// Where a subscriber stands with the mailing list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriberStatus {
+    PendingConfirmation,
+    Confirmed,
+    Unsubscribed,
+    Invalid,
+}
+
+impl SubscriberStatus {
+    /// The value stored in the `status` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PendingConfirmation => "pending_confirmation",
+            Self::Confirmed => "confirmed",
+            Self::Unsubscribed => "unsubscribed",
+            Self::Invalid => "invalid",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "pending_confirmation" => Ok(Self::PendingConfirmation),
+            "confirmed" => Ok(Self::Confirmed),
+            "unsubscribed" => Ok(Self::Unsubscribed),
+            "invalid" => Ok(Self::Invalid),
+            other => Err(format!("{other} is not a subscriber status.")),
+        }
+    }
+}
+
+impl std::fmt::Display for SubscriberStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Something that happens to a subscriber and may change their status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusEvent {
+    /// The subscriber followed their confirmation link.
+    Confirm,
+    /// The subscriber opted out, or complained about an email.
+    Unsubscribe,
+    /// Sending to the stored address failed because it is malformed.
+    FlagInvalid,
+}
+
+impl std::fmt::Display for StatusEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Confirm => "confirm",
+            Self::Unsubscribe => "unsubscribe",
+            Self::FlagInvalid => "flag_invalid",
+        })
+    }
+}
+
+/// An event that is not allowed in the subscriber's current status.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("A subscriber who is {from} cannot go through `{event}`.")]
+pub struct InvalidTransition {
+    pub from: SubscriberStatus,
+    pub event: StatusEvent,
+}
+
+/// Work out the status a subscriber ends up in after an event.
+/// Opting out is always allowed, while consent is never restored behind the
+/// subscriber's back: once unsubscribed or invalid, only a new signup brings
+/// them back. Repeating an event is a no-op rather than an error, so callers
+/// can compare the result with `current` to tell whether anything changed.
+/// # Arguments
+/// * `current` - The subscriber's status.
+/// * `event` - What happened.
+/// # Returns
+/// A Result containing the new status, or the rejected transition.
+pub fn transition_status(
+    current: SubscriberStatus,
+    event: StatusEvent,
+) -> Result<SubscriberStatus, InvalidTransition> {
+    use StatusEvent::*;
+    use SubscriberStatus::*;
+
+    match (current, event) {
+        (PendingConfirmation | Confirmed, Confirm) => Ok(Confirmed),
+        (_, Unsubscribe) => Ok(Unsubscribed),
+        (Confirmed | Invalid, FlagInvalid) => Ok(Invalid),
+        (from, event) => Err(InvalidTransition { from, event }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatusEvent::*;
+    use super::SubscriberStatus::*;
+    use super::{
+        InvalidTransition, StatusEvent, SubscriberStatus, transition_status,
+    };
+
+    const STATUSES: [SubscriberStatus; 4] =
+        [PendingConfirmation, Confirmed, Unsubscribed, Invalid];
+    const EVENTS: [StatusEvent; 3] = [Confirm, Unsubscribe, FlagInvalid];
+
+    #[test]
+    fn every_transition_follows_the_table() {
+        let legal = [
+            (PendingConfirmation, Confirm, Confirmed),
+            (Confirmed, Confirm, Confirmed),
+            (PendingConfirmation, Unsubscribe, Unsubscribed),
+            (Confirmed, Unsubscribe, Unsubscribed),
+            (Unsubscribed, Unsubscribe, Unsubscribed),
+            (Invalid, Unsubscribe, Unsubscribed),
+            (Confirmed, FlagInvalid, Invalid),
+            (Invalid, FlagInvalid, Invalid),
+        ];
+        for from in STATUSES {
+            for event in EVENTS {
+                let expected = legal
+                    .iter()
+                    .find(|(f, e, _)| *f == from && *e == event)
+                    .map(|(_, _, to)| *to)
+                    .ok_or(InvalidTransition { from, event });
+                assert_eq!(
+                    transition_status(from, event),
+                    expected,
+                    "{from} + {event}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn an_unsubscribed_subscriber_cannot_be_confirmed() {
+        assert_eq!(
+            transition_status(Unsubscribed, Confirm),
+            Err(InvalidTransition {
+                from: Unsubscribed,
+                event: Confirm
+            })
+        );
+    }
+
+    #[test]
+    fn an_invalid_subscriber_cannot_be_confirmed() {
+        assert!(transition_status(Invalid, Confirm).is_err());
+    }
+
+    #[test]
+    fn only_delivered_to_subscribers_can_be_flagged_invalid() {
+        assert!(transition_status(PendingConfirmation, FlagInvalid).is_err());
+        assert!(transition_status(Unsubscribed, FlagInvalid).is_err());
+    }
+
+    #[test]
+    fn every_status_round_trips_through_its_column_value() {
+        for status in STATUSES {
+            assert_eq!(SubscriberStatus::parse(status.as_str()), Ok(status));
+        }
+        assert!(SubscriberStatus::parse("bounced").is_err());
+    }
+}
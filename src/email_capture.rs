@@ -0,0 +1,49 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+
+/// Process-wide store of the emails sent in test mode.
+/// The delivery worker and the API share a process, so `/dev/emails` shows
+/// newsletters as well as confirmation emails.
+pub static CAPTURED_EMAILS: EmailCapture = EmailCapture::new();
+
+/// How many emails are kept; older ones are dropped first.
+const MAX_CAPTURED_EMAILS: usize = 100;
+
+/// An email that was captured instead of being handed to the provider.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CapturedEmail {
+    pub to: String,
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+    pub headers: Vec<(String, String)>,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// An in-memory stand-in for the email provider, for local development.
+pub struct EmailCapture {
+    emails: Mutex<VecDeque<CapturedEmail>>,
+}
+
+impl EmailCapture {
+    const fn new() -> Self {
+        Self {
+            emails: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn record(&self, email: CapturedEmail) {
+        let mut emails = self.emails.lock().unwrap();
+        if emails.len() == MAX_CAPTURED_EMAILS {
+            emails.pop_front();
+        }
+        emails.push_back(email);
+    }
+
+    /// The captured emails, most recent first.
+    pub fn list(&self) -> Vec<CapturedEmail> {
+        self.emails.lock().unwrap().iter().rev().cloned().collect()
+    }
+}
@@ -0,0 +1,1346 @@
+mod postmark;
+mod smtp;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::rt::time::sleep;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use uuid::Uuid;
+
+use crate::backoff::{Backoff, BackoffStrategy};
+use crate::circuit_breaker::CircuitBreaker;
+use crate::domain::SubscriberEmail;
+use crate::email_capture::{CAPTURED_EMAILS, CapturedEmail};
+use crate::send_cap::DailySendCap;
+
+pub use postmark::PostmarkClient;
+pub use smtp::SmtpClient;
+
+/// The longest wait between two attempts of the same send.
+const MAX_SEND_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// The most emails sent in one batch call.
+const MAX_BATCH_SIZE: usize = 500;
+
+/// Error type for email delivery failures.
+#[derive(thiserror::Error, Debug)]
+pub enum EmailError {
+    #[error("{0:?} cannot be used in an email header.")]
+    InvalidHeaderValue(String),
+    #[error("The email could not be built.")]
+    InvalidMessage(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+    #[error(transparent)]
+    SmtpError(#[from] lettre::transport::smtp::Error),
+    #[error("The email provider is failing - not sending until it recovers.")]
+    CircuitOpen,
+    #[error(
+        "The daily email send cap was reached - not sending until tomorrow."
+    )]
+    DailySendCapReached,
+    #[error("The email provider rejected the email: {0}")]
+    Rejected(String),
+    #[error("The batch the email was sent in failed: {0}")]
+    BatchFailed(Arc<EmailError>),
+}
+
+impl EmailError {
+    /// Whether a failed send may succeed if made again right away.
+    fn is_transient(&self) -> bool {
+        match self {
+            Self::RequestError(e) => {
+                e.is_timeout()
+                    || e.status().is_some_and(|s| s.is_server_error())
+            }
+            Self::SmtpError(e) => e.is_timeout() || e.is_transient(),
+            _ => false,
+        }
+    }
+
+    /// Whether the failure is down to the provider rather than the email,
+    /// e.g. a 5xx as opposed to a 4xx.
+    fn is_provider_failure(&self) -> bool {
+        match self {
+            Self::RequestError(e) => {
+                e.status().is_none_or(|s| s.is_server_error())
+            }
+            Self::SmtpError(e) => !e.is_permanent(),
+            _ => false,
+        }
+    }
+}
+
+/// The outcome of each email of a batch, or an error if the call as a
+/// whole failed.
+pub type BatchResult =
+    Result<Vec<Result<SendEmailResponse, EmailError>>, EmailError>;
+
+/// A service emails are handed to for delivery, e.g. Postmark or an SMTP
+/// server.
+/// Providers make a single attempt per call; `EmailClient` takes care of
+/// retries, the circuit breaker and the daily send cap.
+pub trait EmailProvider: Send + Sync {
+    /// Hand one email over.
+    fn send<'a>(
+        &'a self,
+        email: &'a SendEmailRequest<'a>,
+    ) -> BoxFuture<'a, Result<SendEmailResponse, EmailError>>;
+
+    /// Hand many emails over.
+    /// Sends them one at a time unless the provider has a batch API.
+    /// # Returns
+    /// The outcome of each email, in order, or an error if the call as a
+    /// whole failed.
+    fn send_batch<'a>(
+        &'a self,
+        emails: &'a [SendEmailRequest<'a>],
+    ) -> BoxFuture<'a, BatchResult> {
+        Box::pin(async move {
+            let mut outcomes = Vec::with_capacity(emails.len());
+            for email in emails {
+                outcomes.push(self.send(email).await);
+            }
+            Ok(outcomes)
+        })
+    }
+}
+
+/// What to do at startup when the sender is not verified with the provider.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SenderVerification {
+    /// Do not check the sender.
+    Skip,
+    /// Log a warning and start anyway.
+    Warn,
+    /// Refuse to start.
+    Fail,
+}
+
+/// Email client structure.
+/// Applies the sending policies (subject prefix, test mode, daily send
+/// cap, circuit breaker, retries) on top of an `EmailProvider`.
+pub struct EmailClient {
+    provider: Box<dyn EmailProvider>,
+    sender: SubscriberEmail,
+    /// Shown next to the sender address, e.g. `Melierx Newsletter`.
+    sender_name: Option<String>,
+    /// Where replies go unless a send says otherwise.
+    reply_to: Option<SubscriberEmail>,
+    circuit_breaker: CircuitBreaker,
+    send_cap: DailySendCap,
+    /// Capture emails in `CAPTURED_EMAILS` instead of sending them.
+    test_mode: bool,
+    retry_backoff: Backoff,
+    /// Prepended to every subject, e.g. `[STAGING]`.
+    subject_prefix: Option<String>,
+    /// Attempts made again within a send after a transient failure.
+    max_retries: u32,
+    /// The wait before the first of those, doubled for each one after it.
+    base_delay: Duration,
+}
+
+impl EmailClient {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        provider: Box<dyn EmailProvider>,
+        sender: SubscriberEmail,
+        sender_name: Option<String>,
+        reply_to: Option<SubscriberEmail>,
+        circuit_breaker: CircuitBreaker,
+        send_cap: DailySendCap,
+        test_mode: bool,
+        retry_backoff: Backoff,
+        subject_prefix: Option<String>,
+        max_retries: u32,
+        base_delay: Duration,
+    ) -> Self {
+        Self {
+            provider,
+            sender,
+            sender_name: sender_name
+                .map(|name| name.trim().to_owned())
+                .filter(|name| !name.is_empty()),
+            reply_to,
+            circuit_breaker,
+            send_cap,
+            test_mode,
+            retry_backoff,
+            subject_prefix: subject_prefix
+                .map(|prefix| prefix.trim().to_owned())
+                .filter(|prefix| !prefix.is_empty()),
+            max_retries,
+            base_delay,
+        }
+    }
+
+    /// How long a failed send waits before it is retried.
+    /// # Arguments
+    /// * `n_retries` - How many times the send was retried already.
+    pub fn retry_delay(&self, n_retries: i16) -> Duration {
+        self.retry_backoff.delay(n_retries)
+    }
+
+    /// Whether emails are captured rather than sent.
+    pub fn is_test_mode(&self) -> bool {
+        self.test_mode
+    }
+
+    /// How long sends fail fast for once the provider is deemed down.
+    pub fn circuit_breaker_cooldown(&self) -> Duration {
+        self.circuit_breaker.cooldown()
+    }
+
+    /// How long sends are refused for once the daily cap is reached.
+    pub fn send_cap_resets_in(&self) -> Duration {
+        self.send_cap.resets_in()
+    }
+
+    /// Send an email.
+    /// # Arguments
+    /// * `recipient` - The recipient address.
+    /// * `subject` - The subject line.
+    /// * `html_content` - The HTML body.
+    /// * `text_content` - The plain text body.
+    /// * `reply_to` - Where replies go, overriding the configured default.
+    /// # Returns
+    /// A Result containing what the provider reported back, or an
+    /// EmailError.
+    pub async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        reply_to: Option<&str>,
+    ) -> Result<SendEmailResponse, EmailError> {
+        self.send_email_with_headers(
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            &[],
+            reply_to,
+        )
+        .await
+    }
+
+    /// Send an email carrying extra headers, e.g. `List-Unsubscribe`.
+    /// Header values containing control characters are rejected.
+    /// The subject gets the configured prefix, if any.
+    /// Transient failures (timeouts, 5xx responses) are retried up to
+    /// `max_retries` times, with jittered exponential backoff from
+    /// `base_delay`, so a blip at the provider does not fail the send;
+    /// rejections of the email itself (4xx responses) are never retried.
+    pub async fn send_email_with_headers(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[(&str, &str)],
+        reply_to: Option<&str>,
+    ) -> Result<SendEmailResponse, EmailError> {
+        let request = self.prepare(&OutgoingEmail {
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            headers,
+            reply_to,
+            metadata: &[],
+        })?;
+        if self.test_mode {
+            capture(&request);
+            return Ok(SendEmailResponse::accepted_now(
+                Uuid::new_v4().to_string(),
+            ));
+        }
+        let response = self
+            .with_retries(async || {
+                // Every attempt counts, so a send loop is stopped even while
+                // the provider is rejecting it.
+                if !self.send_cap.try_acquire() {
+                    return Err(EmailError::DailySendCapReached);
+                }
+                self.through_breaker(self.provider.send(&request)).await
+            })
+            .await?;
+        tracing::info!(
+            message_id = response.message_id.as_deref(),
+            "The email provider accepted the email."
+        );
+        Ok(response)
+    }
+
+    /// Send many emails in batches of at most `MAX_BATCH_SIZE`, the most
+    /// Postmark accepts in one call.
+    /// Each email is prepared as by `send_email_with_headers` and counts
+    /// against the daily send cap once; a failed call is retried like a
+    /// single send. The provider accepts or rejects every email on its own,
+    /// so outcomes are reported per email and only the failed ones need to
+    /// be sent again.
+    /// # Arguments
+    /// * `messages` - The emails to send.
+    /// # Returns
+    /// The provider's response for each email, or why it was not sent, in
+    /// the order the emails were given.
+    pub async fn send_email_batch(
+        &self,
+        messages: &[OutgoingEmail<'_>],
+    ) -> Vec<Result<SendEmailResponse, EmailError>> {
+        let mut outcomes = Vec::with_capacity(messages.len());
+        for chunk in messages.chunks(MAX_BATCH_SIZE) {
+            outcomes.extend(self.send_batch_chunk(chunk).await);
+        }
+        outcomes
+    }
+
+    async fn send_batch_chunk(
+        &self,
+        messages: &[OutgoingEmail<'_>],
+    ) -> Vec<Result<SendEmailResponse, EmailError>> {
+        let mut outcomes = Vec::with_capacity(messages.len());
+        // Where each email of the batch sits in `outcomes`.
+        let mut positions = Vec::new();
+        let mut batch = Vec::new();
+        for message in messages {
+            match self.prepare(message) {
+                Ok(request) if self.test_mode => {
+                    capture(&request);
+                    outcomes.push(Ok(SendEmailResponse::accepted_now(
+                        Uuid::new_v4().to_string(),
+                    )));
+                }
+                Ok(_) if !self.send_cap.try_acquire() => {
+                    outcomes.push(Err(EmailError::DailySendCapReached));
+                }
+                Ok(request) => {
+                    positions.push(outcomes.len());
+                    batch.push(request);
+                    // Overwritten with the provider's verdict below.
+                    outcomes.push(Err(EmailError::Rejected(
+                        "No result was returned for the email.".into(),
+                    )));
+                }
+                Err(e) => outcomes.push(Err(e)),
+            }
+        }
+        if batch.is_empty() {
+            return outcomes;
+        }
+        match self
+            .with_retries(async || {
+                self.through_breaker(self.provider.send_batch(&batch)).await
+            })
+            .await
+        {
+            Ok(results) => {
+                for (position, result) in positions.into_iter().zip(results) {
+                    outcomes[position] = result;
+                }
+            }
+            Err(e) => {
+                let e = Arc::new(e);
+                for position in positions {
+                    outcomes[position] = Err(match e.as_ref() {
+                        EmailError::CircuitOpen => EmailError::CircuitOpen,
+                        _ => EmailError::BatchFailed(e.clone()),
+                    });
+                }
+            }
+        }
+        outcomes
+    }
+
+    /// Build the request for an email, applying the subject prefix and the
+    /// default reply address.
+    fn prepare<'a>(
+        &self,
+        email: &OutgoingEmail<'a>,
+    ) -> Result<SendEmailRequest<'a>, EmailError> {
+        let headers = email
+            .headers
+            .iter()
+            .map(|&(name, value)| {
+                if value.chars().any(char::is_control) {
+                    return Err(EmailError::InvalidHeaderValue(value.into()));
+                }
+                Ok(EmailHeader { name, value })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let subject = match &self.subject_prefix {
+            Some(prefix) => format!("{} {}", prefix, email.subject),
+            None => email.subject.to_owned(),
+        };
+        let reply_to = email
+            .reply_to
+            .or(self.reply_to.as_ref().map(AsRef::as_ref))
+            .map(|address| format_mailbox(None, address))
+            .transpose()?;
+        Ok(SendEmailRequest {
+            from: format_mailbox(
+                self.sender_name.as_deref(),
+                self.sender.as_ref(),
+            )?,
+            to: format_mailbox(None, email.recipient.as_ref())?,
+            subject,
+            html_body: email.html_content,
+            text_body: email.text_content,
+            reply_to,
+            headers,
+            metadata: email.metadata.iter().copied().collect(),
+        })
+    }
+
+    /// Make an attempt, retrying transient failures with backoff.
+    async fn with_retries<T>(
+        &self,
+        attempt: impl AsyncFn() -> Result<T, EmailError>,
+    ) -> Result<T, EmailError> {
+        let backoff = Backoff::new(
+            BackoffStrategy::EqualJitter,
+            self.base_delay,
+            MAX_SEND_RETRY_DELAY,
+        );
+        let mut n_retries = 0;
+        loop {
+            match attempt().await {
+                Err(e) if n_retries < self.max_retries && e.is_transient() => {
+                    let delay = backoff.delay(n_retries as i16);
+                    tracing::warn!(
+                        error.message = %e,
+                        n_retries,
+                        "Failed to send an email. Retrying in {:?}.",
+                        delay,
+                    );
+                    sleep(delay).await;
+                    n_retries += 1;
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+
+    /// Make a single call to the provider, unless the circuit breaker is
+    /// open, and record how it went.
+    async fn through_breaker<T>(
+        &self,
+        call: BoxFuture<'_, Result<T, EmailError>>,
+    ) -> Result<T, EmailError> {
+        if !self.circuit_breaker.try_acquire() {
+            return Err(EmailError::CircuitOpen);
+        }
+        let outcome = call.await;
+        // A rejected email is about this request, not the provider's
+        // health.
+        match &outcome {
+            Err(e) if e.is_provider_failure() => {
+                self.circuit_breaker.record_failure()
+            }
+            _ => self.circuit_breaker.record_success(),
+        }
+        outcome
+    }
+}
+
+/// Record an email in `CAPTURED_EMAILS` instead of sending it.
+fn capture(email: &SendEmailRequest<'_>) {
+    CAPTURED_EMAILS.record(CapturedEmail {
+        to: email.to.clone(),
+        subject: email.subject.clone(),
+        html_content: email.html_body.into(),
+        text_content: email.text_body.into(),
+        headers: email
+            .headers
+            .iter()
+            .map(|h| (h.name.into(), h.value.into()))
+            .collect(),
+        sent_at: Utc::now(),
+    });
+}
+
+/// Format an address (and optional display name) for an address header.
+/// Control characters such as CR/LF are rejected outright: they would let a
+/// crafted name or address smuggle extra headers into the message. A name
+/// with characters that are special in RFC 5322 (e.g. a comma, which would
+/// otherwise split the header into two addresses) is sent as a quoted
+/// string.
+/// # Arguments
+/// * `name` - The optional display name.
+/// * `address` - The email address.
+/// # Returns
+/// A Result containing the header value or an EmailError.
+pub fn format_mailbox(
+    name: Option<&str>,
+    address: &str,
+) -> Result<String, EmailError> {
+    for component in name.into_iter().chain([address]) {
+        if component.chars().any(char::is_control) {
+            return Err(EmailError::InvalidHeaderValue(component.to_owned()));
+        }
+    }
+    match name {
+        Some(name) if name.contains(is_special) => {
+            let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+            Ok(format!("\"{escaped}\" <{address}>"))
+        }
+        Some(name) => Ok(format!("{name} <{address}>")),
+        None => Ok(address.to_owned()),
+    }
+}
+
+/// Whether a character needs quoting in a display name (RFC 5322 `specials`).
+fn is_special(c: char) -> bool {
+    matches!(
+        c,
+        '(' | ')'
+            | '<'
+            | '>'
+            | '['
+            | ']'
+            | ':'
+            | ';'
+            | '@'
+            | '\\'
+            | ','
+            | '.'
+            | '"'
+    )
+}
+
+/// An email to send as part of a batch.
+pub struct OutgoingEmail<'a> {
+    pub recipient: &'a SubscriberEmail,
+    pub subject: &'a str,
+    pub html_content: &'a str,
+    pub text_content: &'a str,
+    pub headers: &'a [(&'a str, &'a str)],
+    /// Where replies go, overriding the configured default.
+    pub reply_to: Option<&'a str>,
+    /// Key-value pairs the provider echoes back in its bounce and
+    /// complaint webhooks.
+    pub metadata: &'a [(&'a str, &'a str)],
+}
+
+/// An email ready to be handed to a provider, serialized as Postmark's
+/// request body.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SendEmailRequest<'a> {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub html_body: &'a str,
+    pub text_body: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<EmailHeader<'a>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<&'a str, &'a str>,
+}
+
+/// What the provider reports back about an accepted email, deserialized
+/// from Postmark's response body.
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct SendEmailResponse {
+    /// The provider's ID for the email, to look it up in its dashboard.
+    /// `None` if the email was accepted but the response could not be read.
+    #[serde(rename = "MessageID")]
+    pub message_id: Option<String>,
+    pub submitted_at: DateTime<Utc>,
+    /// 0 for an accepted email.
+    pub error_code: i64,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl SendEmailResponse {
+    /// A response for an email accepted without a provider reporting on
+    /// it, e.g. when captured or relayed over SMTP.
+    fn accepted_now(message_id: String) -> Self {
+        Self {
+            message_id: Some(message_id),
+            ..Self::unreadable()
+        }
+    }
+
+    /// A response for an email the provider accepted with a response body
+    /// that could not be read.
+    fn unreadable() -> Self {
+        Self {
+            message_id: None,
+            submitted_at: Utc::now(),
+            error_code: 0,
+            message: String::new(),
+        }
+    }
+
+    /// Parse the body of a successful send.
+    /// The status already says the email was accepted, so a body that
+    /// cannot be read is logged rather than failing the send, which would
+    /// have it sent again. A non-zero error code is a rejection.
+    fn parse(body: &[u8]) -> Result<Self, EmailError> {
+        match serde_json::from_slice::<Self>(body) {
+            Ok(response) if response.error_code != 0 => {
+                Err(EmailError::Rejected(format!(
+                    "{} (error code {})",
+                    response.message, response.error_code
+                )))
+            }
+            Ok(response) => Ok(response),
+            Err(e) => {
+                tracing::warn!(
+                    error.message = %e,
+                    "The email provider accepted the email, \
+                    but its response could not be read."
+                );
+                Ok(Self::unreadable())
+            }
+        }
+    }
+}
+
+/// A custom header of an outgoing email.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailHeader<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use reqwest::Url;
+    use secrecy::SecretString;
+    use uuid::Uuid;
+    use wiremock::matchers::{
+        any, body_partial_json, header, header_exists, method, path,
+    };
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    use crate::backoff::{Backoff, BackoffStrategy};
+    use crate::circuit_breaker::CircuitBreaker;
+    use crate::configuration::TlsVersion;
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{
+        EmailClient, EmailError, OutgoingEmail, PostmarkClient, format_mailbox,
+    };
+    use crate::send_cap::DailySendCap;
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> =
+                serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    /// Matches a batch call with the given number of well-formed emails.
+    struct BatchBodyMatcher(usize);
+
+    impl wiremock::Match for BatchBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let Ok(serde_json::Value::Array(emails)) =
+                serde_json::from_slice(&request.body)
+            else {
+                return false;
+            };
+            emails.len() == self.0
+                && emails.iter().all(|email| {
+                    ["From", "To", "Subject", "HtmlBody", "TextBody"]
+                        .iter()
+                        .all(|field| email.get(field).is_some())
+                })
+        }
+    }
+
+    /// Accepts every email of a batch call.
+    struct AcceptBatch;
+
+    impl wiremock::Respond for AcceptBatch {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let emails: Vec<serde_json::Value> =
+                serde_json::from_slice(&request.body).unwrap();
+            let results: Vec<_> = emails
+                .iter()
+                .enumerate()
+                .map(|(i, email)| {
+                    serde_json::json!({
+                        "ErrorCode": 0,
+                        "Message": "OK",
+                        "MessageID": format!("batch-{i}"),
+                        "SubmittedAt": "2026-10-15T09:30:00.1234567-04:00",
+                        "To": email["To"],
+                    })
+                })
+                .collect();
+            ResponseTemplate::new(200).set_body_json(results)
+        }
+    }
+
+    /// Postmark's response to an accepted email.
+    fn accepted(message_id: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "To": "receiver@example.com",
+            "SubmittedAt": "2026-10-15T09:30:00.1234567-04:00",
+            "MessageID": message_id,
+            "ErrorCode": 0,
+            "Message": "OK"
+        }))
+    }
+
+    /// Generate a random email subject
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    /// Generate a random email content
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    /// Generate a random subscriber email
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    /// Get a test instance of EmailClient
+    fn email_client(base_url: Url) -> EmailClient {
+        email_client_with_cap(base_url, DailySendCap::new(1_000))
+    }
+
+    /// Get a test instance of EmailClient with the given daily send cap
+    fn email_client_with_cap(base_url: Url, cap: DailySendCap) -> EmailClient {
+        email_client_with(base_url, cap, 0, None)
+    }
+
+    /// Get a test instance of EmailClient that retries failed sends
+    fn email_client_with_retries(
+        base_url: Url,
+        max_retries: u32,
+    ) -> EmailClient {
+        email_client_with(base_url, DailySendCap::new(1_000), max_retries, None)
+    }
+
+    fn email_client_with(
+        base_url: Url,
+        cap: DailySendCap,
+        max_retries: u32,
+        reply_to: Option<SubscriberEmail>,
+    ) -> EmailClient {
+        let authorization_token =
+            SecretString::new(Faker.fake::<String>().into_boxed_str());
+        let provider = PostmarkClient::new(
+            base_url,
+            authorization_token,
+            std::time::Duration::from_millis(200),
+            TlsVersion::default(),
+        );
+        EmailClient::new(
+            Box::new(provider),
+            email(),
+            None,
+            reply_to,
+            CircuitBreaker::new(3, std::time::Duration::from_secs(60)),
+            cap,
+            false,
+            Backoff::new(
+                BackoffStrategy::Fixed,
+                std::time::Duration::from_secs(1),
+                std::time::Duration::from_secs(60),
+            ),
+            None,
+            max_retries,
+            std::time::Duration::from_millis(1),
+        )
+    }
+
+    #[actix_web::test]
+    async fn send_email_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(accepted(&Uuid::new_v4().to_string()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _ = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+    }
+
+    #[actix_web::test]
+    async fn send_email_succeeds_if_server_returns_200() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+
+        Mock::given(any())
+            .respond_with(accepted("b7bc2f4a-e38e-4336-af7d-e6c392c2f817"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        let response = assert_ok!(outcome);
+        assert_eq!(
+            response.message_id.as_deref(),
+            Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+        );
+        assert_eq!(response.error_code, 0);
+        assert_eq!(
+            response.submitted_at.to_rfc3339(),
+            "2026-10-15T13:30:00.123456700+00:00"
+        );
+    }
+
+    #[actix_web::test]
+    async fn an_unreadable_success_body_does_not_fail_the_send() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_string("OK"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        let response = assert_ok!(outcome);
+        assert_eq!(response.message_id, None);
+    }
+
+    #[actix_web::test]
+    async fn a_non_zero_error_code_is_a_rejection() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "SubmittedAt": "2026-10-15T09:30:00.1234567-04:00",
+                    "MessageID": null,
+                    "ErrorCode": 406,
+                    "Message": "You tried to send to an inactive recipient."
+                }),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        assert!(matches!(outcome, Err(EmailError::Rejected(_))));
+    }
+
+    #[actix_web::test]
+    async fn send_email_fails_if_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client_with_retries(base_url, 2);
+
+        // The first attempt and both retries
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        assert_err!(outcome);
+    }
+
+    #[actix_web::test]
+    async fn send_email_succeeds_if_a_retry_gets_a_200_after_a_500() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client_with_retries(base_url, 2);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(any())
+            .respond_with(accepted(&Uuid::new_v4().to_string()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        assert_ok!(outcome);
+    }
+
+    #[actix_web::test]
+    async fn client_errors_are_not_retried() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client_with_retries(base_url, 2);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        assert_err!(outcome);
+    }
+
+    #[actix_web::test]
+    async fn send_email_batch_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+        let recipients = [email(), email()];
+        let (subject, content) = (subject(), content());
+        let messages: Vec<_> = recipients
+            .iter()
+            .map(|recipient| OutgoingEmail {
+                recipient,
+                subject: &subject,
+                html_content: &content,
+                text_content: &content,
+                headers: &[],
+                reply_to: None,
+                metadata: &[],
+            })
+            .collect();
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email/batch"))
+            .and(method("POST"))
+            .and(BatchBodyMatcher(2))
+            .respond_with(AcceptBatch)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcomes = email_client.send_email_batch(&messages).await;
+
+        let message_ids: Vec<_> = outcomes
+            .into_iter()
+            .map(|outcome| outcome.unwrap().message_id)
+            .collect();
+        assert_eq!(
+            message_ids,
+            [Some("batch-0".to_owned()), Some("batch-1".to_owned())]
+        );
+    }
+
+    #[actix_web::test]
+    async fn send_email_batch_splits_501_emails_into_two_calls() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+        let recipients: Vec<_> = (0..501).map(|_| email()).collect();
+        let (subject, content) = (subject(), content());
+        let messages: Vec<_> = recipients
+            .iter()
+            .map(|recipient| OutgoingEmail {
+                recipient,
+                subject: &subject,
+                html_content: &content,
+                text_content: &content,
+                headers: &[],
+                reply_to: None,
+                metadata: &[],
+            })
+            .collect();
+
+        Mock::given(path("/email/batch"))
+            .and(BatchBodyMatcher(500))
+            .respond_with(AcceptBatch)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/email/batch"))
+            .and(BatchBodyMatcher(1))
+            .respond_with(AcceptBatch)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcomes = email_client.send_email_batch(&messages).await;
+
+        assert_eq!(outcomes.len(), 501);
+        assert!(outcomes.iter().all(Result::is_ok));
+    }
+
+    #[actix_web::test]
+    async fn emails_rejected_in_a_batch_fail_on_their_own() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+        let recipients = [email(), email()];
+        let (subject, content) = (subject(), content());
+        let messages: Vec<_> = recipients
+            .iter()
+            .map(|recipient| OutgoingEmail {
+                recipient,
+                subject: &subject,
+                html_content: &content,
+                text_content: &content,
+                headers: &[],
+                reply_to: None,
+                metadata: &[],
+            })
+            .collect();
+
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!([
+                    { "ErrorCode": 0, "Message": "OK" },
+                    { "ErrorCode": 406, "Message": "Inactive recipient" },
+                ]),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let outcomes = email_client.send_email_batch(&messages).await;
+
+        assert_ok!(&outcomes[0]);
+        assert!(matches!(outcomes[1], Err(EmailError::Rejected(_))));
+    }
+
+    #[actix_web::test]
+    async fn send_email_times_out_if_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+
+        let response = ResponseTemplate::new(200)
+            .set_delay(std::time::Duration::from_secs(180));
+
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        assert_err!(outcome);
+    }
+
+    #[actix_web::test]
+    async fn send_email_fails_fast_once_the_circuit_breaker_opens() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+
+        // The breaker opens after three failures; nothing else gets through
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        for _ in 0..3 {
+            let outcome = email_client
+                .send_email(&email(), &subject(), &content(), &content(), None)
+                .await;
+            assert!(matches!(outcome, Err(EmailError::RequestError(_))));
+        }
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        assert!(matches!(outcome, Err(EmailError::CircuitOpen)));
+    }
+
+    #[actix_web::test]
+    async fn client_errors_do_not_open_the_circuit_breaker() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422))
+            .expect(4)
+            .mount(&mock_server)
+            .await;
+
+        for _ in 0..4 {
+            let outcome = email_client
+                .send_email(&email(), &subject(), &content(), &content(), None)
+                .await;
+            assert!(matches!(outcome, Err(EmailError::RequestError(_))));
+        }
+    }
+
+    #[test]
+    fn mailbox_without_a_name_is_the_bare_address() {
+        let mailbox = format_mailbox(None, "news@melierx.com");
+        assert_eq!(assert_ok!(mailbox), "news@melierx.com");
+    }
+
+    #[test]
+    fn a_plain_name_is_sent_as_is() {
+        let mailbox =
+            format_mailbox(Some("Melierx Newsletter"), "news@melierx.com");
+        assert_eq!(
+            assert_ok!(mailbox),
+            "Melierx Newsletter <news@melierx.com>"
+        );
+    }
+
+    #[test]
+    fn names_with_special_characters_are_quoted() {
+        let mailbox = format_mailbox(Some("Melierx, Inc."), "news@melierx.com");
+        assert_eq!(assert_ok!(mailbox), "\"Melierx, Inc.\" <news@melierx.com>");
+    }
+
+    #[test]
+    fn quotes_and_backslashes_in_a_quoted_name_are_escaped() {
+        let mailbox =
+            format_mailbox(Some(r#"The "Best" \ News"#), "news@melierx.com");
+        assert_eq!(
+            assert_ok!(mailbox),
+            r#""The \"Best\" \\ News" <news@melierx.com>"#
+        );
+    }
+
+    #[test]
+    fn names_containing_line_breaks_are_rejected() {
+        for name in ["Melierx\r\nBcc: victim@example.com", "Melierx\nX: y"] {
+            let outcome = format_mailbox(Some(name), "news@melierx.com");
+            assert!(matches!(outcome, Err(EmailError::InvalidHeaderValue(_))));
+        }
+    }
+
+    #[test]
+    fn addresses_containing_control_characters_are_rejected() {
+        let outcome = format_mailbox(None, "news@melierx.com\r\nBcc: x@y.com");
+        assert_err!(outcome);
+    }
+
+    #[actix_web::test]
+    async fn extra_headers_are_sent_with_the_email() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+
+        Mock::given(body_partial_json(serde_json::json!({
+            "Headers": [{
+                "Name": "List-Unsubscribe",
+                "Value": "<https://melierx.com/unsubscribe>"
+            }]
+        })))
+        .respond_with(accepted(&Uuid::new_v4().to_string()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+        let outcome = email_client
+            .send_email_with_headers(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                &[("List-Unsubscribe", "<https://melierx.com/unsubscribe>")],
+                None,
+            )
+            .await;
+
+        assert_ok!(outcome);
+    }
+
+    /// Matches a send whose `ReplyTo` is the given address.
+    struct ReplyToMatcher(String);
+
+    impl wiremock::Match for ReplyToMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            SendEmailBodyMatcher.matches(request)
+                && serde_json::from_slice::<serde_json::Value>(&request.body)
+                    .is_ok_and(|body| body["ReplyTo"] == self.0.as_str())
+        }
+    }
+
+    #[actix_web::test]
+    async fn the_configured_reply_address_is_sent_with_the_email() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let reply_to = email();
+        let expected = reply_to.as_ref().to_owned();
+        let email_client = email_client_with(
+            base_url,
+            DailySendCap::new(1_000),
+            0,
+            Some(reply_to),
+        );
+
+        Mock::given(ReplyToMatcher(expected))
+            .respond_with(accepted(&Uuid::new_v4().to_string()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        assert_ok!(outcome);
+    }
+
+    #[actix_web::test]
+    async fn a_reply_address_given_to_a_send_overrides_the_configured_one() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client_with(
+            base_url,
+            DailySendCap::new(1_000),
+            0,
+            Some(email()),
+        );
+
+        Mock::given(ReplyToMatcher("editor@melierx.com".into()))
+            .respond_with(accepted(&Uuid::new_v4().to_string()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                Some("editor@melierx.com"),
+            )
+            .await;
+
+        assert_ok!(outcome);
+    }
+
+    #[actix_web::test]
+    async fn no_reply_address_is_sent_unless_one_is_configured() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+
+        Mock::given(SendEmailBodyMatcher)
+            .respond_with(|request: &Request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).unwrap();
+                if body.get("ReplyTo").is_none() {
+                    accepted(&Uuid::new_v4().to_string())
+                } else {
+                    ResponseTemplate::new(400)
+                }
+            })
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        assert_ok!(outcome);
+    }
+
+    #[actix_web::test]
+    async fn header_values_containing_control_characters_are_rejected() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client = email_client(base_url);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email_with_headers(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                &[("List-Unsubscribe", "<https://x>\r\nBcc: x@y.com")],
+                None,
+            )
+            .await;
+
+        assert!(matches!(outcome, Err(EmailError::InvalidHeaderValue(_))));
+    }
+
+    #[actix_web::test]
+    async fn sends_beyond_the_daily_cap_are_refused() {
+        let mock_server = MockServer::start().await;
+        let base_url = reqwest::Url::parse(&mock_server.uri()).unwrap();
+        let email_client =
+            email_client_with_cap(base_url, DailySendCap::new(2));
+
+        Mock::given(any())
+            .respond_with(accepted(&Uuid::new_v4().to_string()))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        for _ in 0..2 {
+            let outcome = email_client
+                .send_email(&email(), &subject(), &content(), &content(), None)
+                .await;
+            assert_ok!(outcome);
+        }
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), None)
+            .await;
+
+        assert!(matches!(outcome, Err(EmailError::DailySendCapReached)));
+    }
+}
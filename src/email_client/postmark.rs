@@ -0,0 +1,232 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, SecretString};
+
+use crate::configuration::TlsVersion;
+use crate::email_client::{
+    BatchResult, EmailError, EmailProvider, SendEmailRequest, SendEmailResponse,
+};
+
+/// Sends emails through Postmark's JSON API.
+pub struct PostmarkClient {
+    http_client: Client,
+    base_url: Url,
+    authorization_token: SecretString,
+}
+
+impl PostmarkClient {
+    pub fn new(
+        base_url: Url,
+        authorization_token: SecretString,
+        timeout: Duration,
+        min_tls_version: TlsVersion,
+    ) -> Self {
+        let http_client = Client::builder()
+            .timeout(timeout)
+            .min_tls_version(min_tls_version.as_reqwest())
+            .build()
+            .unwrap();
+        Self {
+            http_client,
+            base_url,
+            authorization_token,
+        }
+    }
+
+    async fn post(
+        &self,
+        path: &str,
+        body: &impl serde::Serialize,
+    ) -> Result<reqwest::Response, EmailError> {
+        let url = self.base_url.join(path).unwrap();
+        let response = self
+            .http_client
+            .post(url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response)
+    }
+
+    /// Check that the sender address has a confirmed sender signature with
+    /// Postmark.
+    /// Listing sender signatures needs the account token rather than the
+    /// server token used for sending.
+    /// # Arguments
+    /// * `sender` - The sender address.
+    /// * `account_token` - The provider account API token.
+    /// # Returns
+    /// A Result containing whether the sender is verified, or an EmailError.
+    pub async fn is_sender_verified(
+        &self,
+        sender: &str,
+        account_token: &SecretString,
+    ) -> Result<bool, EmailError> {
+        let url = self.base_url.join("/senders").unwrap();
+        let response: SenderSignaturesResponse = self
+            .http_client
+            .get(url)
+            .query(&[("count", "500"), ("offset", "0")])
+            .header("X-Postmark-Account-Token", account_token.expose_secret())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.sender_signatures.iter().any(|signature| {
+            signature.confirmed
+                && signature.email_address.eq_ignore_ascii_case(sender)
+        }))
+    }
+}
+
+impl EmailProvider for PostmarkClient {
+    fn send<'a>(
+        &'a self,
+        email: &'a SendEmailRequest<'a>,
+    ) -> BoxFuture<'a, Result<SendEmailResponse, EmailError>> {
+        Box::pin(async move {
+            let body = self.post("/email", email).await?.bytes().await?;
+            SendEmailResponse::parse(&body)
+        })
+    }
+
+    /// Postmark accepts or rejects every email of a batch on its own and
+    /// reports on each, in order.
+    fn send_batch<'a>(
+        &'a self,
+        emails: &'a [SendEmailRequest<'a>],
+    ) -> BoxFuture<'a, BatchResult> {
+        Box::pin(async move {
+            let results: Vec<BatchSendResult> =
+                self.post("/email/batch", &emails).await?.json().await?;
+            let mut results = results.into_iter();
+            Ok(emails
+                .iter()
+                .map(|_| match results.next() {
+                    Some(result) if result.error_code == 0 => {
+                        Ok(SendEmailResponse {
+                            message_id: result.message_id,
+                            submitted_at: result
+                                .submitted_at
+                                .unwrap_or_else(Utc::now),
+                            error_code: 0,
+                            message: result.message,
+                        })
+                    }
+                    Some(result) => Err(EmailError::Rejected(format!(
+                        "{} (error code {})",
+                        result.message, result.error_code
+                    ))),
+                    None => Err(EmailError::Rejected(
+                        "No result was returned for the email.".into(),
+                    )),
+                })
+                .collect())
+        })
+    }
+}
+
+/// The provider's verdict on one email of a batch.
+/// Rejected emails come back without an ID or submission time.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BatchSendResult {
+    #[serde(rename = "MessageID")]
+    message_id: Option<String>,
+    submitted_at: Option<DateTime<Utc>>,
+    error_code: i64,
+    message: String,
+}
+
+/// Response body of the sender signatures listing.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SenderSignaturesResponse {
+    sender_signatures: Vec<SenderSignature>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SenderSignature {
+    email_address: String,
+    confirmed: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+    use secrecy::SecretString;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::configuration::TlsVersion;
+    use crate::email_client::PostmarkClient;
+
+    fn sender_signatures(
+        email_address: &str,
+        confirmed: bool,
+    ) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "TotalCount": 1,
+            "SenderSignatures": [{
+                "Domain": "melierx.com",
+                "EmailAddress": email_address,
+                "Name": "Melierx",
+                "Confirmed": confirmed,
+                "ID": 36735
+            }]
+        }))
+    }
+
+    async fn is_sender_verified(response: ResponseTemplate) -> bool {
+        let mock_server = MockServer::start().await;
+        let client = PostmarkClient::new(
+            Url::parse(&mock_server.uri()).unwrap(),
+            SecretString::from("server-token"),
+            std::time::Duration::from_millis(200),
+            TlsVersion::default(),
+        );
+        Mock::given(path("/senders"))
+            .and(method("GET"))
+            .and(header("X-Postmark-Account-Token", "account-token"))
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        client
+            .is_sender_verified(
+                "news@melierx.com",
+                &SecretString::from("account-token"),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn a_confirmed_sender_signature_verifies_the_sender() {
+        let response = sender_signatures("News@Melierx.com", true);
+        assert!(is_sender_verified(response).await);
+    }
+
+    #[actix_web::test]
+    async fn an_unconfirmed_sender_signature_does_not_verify_the_sender() {
+        let response = sender_signatures("news@melierx.com", false);
+        assert!(!is_sender_verified(response).await);
+    }
+
+    #[actix_web::test]
+    async fn a_signature_for_another_address_does_not_verify_the_sender() {
+        let response = sender_signatures("billing@melierx.com", true);
+        assert!(!is_sender_verified(response).await);
+    }
+}
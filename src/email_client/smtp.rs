@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+
+use crate::configuration::{SmtpSettings, SmtpTls, TlsVersion};
+use crate::email_client::{
+    EmailError, EmailProvider, SendEmailRequest, SendEmailResponse,
+};
+
+/// Sends emails to an SMTP server, for self-hosted setups.
+pub struct SmtpClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpClient {
+    /// Build a client for the configured server.
+    /// No connection is made until the first email is sent.
+    /// # Arguments
+    /// * `settings` - The SMTP server settings.
+    /// * `timeout` - How long each exchange with the server may take.
+    /// * `min_tls_version` - The oldest TLS version the server may
+    ///   negotiate, unless TLS is off.
+    /// # Returns
+    /// A Result containing the client, or an error if TLS cannot be set up
+    /// for the host.
+    pub fn new(
+        settings: &SmtpSettings,
+        timeout: Duration,
+        min_tls_version: TlsVersion,
+    ) -> Result<Self, lettre::transport::smtp::Error> {
+        let tls_parameters = || {
+            TlsParameters::builder(settings.host.clone())
+                .set_min_tls_version(min_tls_version.as_lettre())
+                .build_rustls()
+        };
+        let tls = match settings.tls {
+            SmtpTls::Tls => Tls::Wrapper(tls_parameters()?),
+            SmtpTls::Starttls => Tls::Required(tls_parameters()?),
+            SmtpTls::None => Tls::None,
+        };
+        let mut builder =
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &settings.host,
+            )
+            .tls(tls)
+            .port(settings.port)
+            .timeout(Some(timeout));
+        if let Some(username) = &settings.username {
+            let password = settings
+                .password
+                .as_ref()
+                .map(|p| p.expose_secret().to_owned())
+                .unwrap_or_default();
+            builder = builder
+                .credentials(Credentials::new(username.clone(), password));
+        }
+        Ok(Self {
+            transport: builder.build(),
+        })
+    }
+}
+
+impl EmailProvider for SmtpClient {
+    fn send<'a>(
+        &'a self,
+        email: &'a SendEmailRequest<'a>,
+    ) -> BoxFuture<'a, Result<SendEmailResponse, EmailError>> {
+        Box::pin(async move {
+            // SMTP servers do not hand an ID back, so the Message-ID header
+            // we set is the one to look the email up by.
+            let message_id = format!("<{}@melierx>", Uuid::new_v4());
+            let message = build_message(email, &message_id)?;
+            self.transport.send(message).await?;
+            Ok(SendEmailResponse::accepted_now(message_id))
+        })
+    }
+}
+
+/// Turn a request into a MIME message with plain text and HTML parts.
+fn build_message(
+    email: &SendEmailRequest<'_>,
+    message_id: &str,
+) -> Result<Message, EmailError> {
+    let from: Mailbox = email
+        .from
+        .parse()
+        .map_err(|e| EmailError::InvalidMessage(Box::new(e)))?;
+    let to: Mailbox = email
+        .to
+        .parse()
+        .map_err(|e| EmailError::InvalidMessage(Box::new(e)))?;
+    let mut builder = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(email.subject.as_str())
+        .message_id(Some(message_id.to_owned()));
+    if let Some(reply_to) = &email.reply_to {
+        let reply_to: Mailbox = reply_to
+            .parse()
+            .map_err(|e| EmailError::InvalidMessage(Box::new(e)))?;
+        builder = builder.reply_to(reply_to);
+    }
+    for header in &email.headers {
+        let name = HeaderName::new_from_ascii(header.name.to_owned())
+            .map_err(|e| EmailError::InvalidMessage(Box::new(e)))?;
+        builder =
+            builder.raw_header(HeaderValue::new(name, header.value.to_owned()));
+    }
+    builder
+        .multipart(MultiPart::alternative_plain_html(
+            email.text_body.to_owned(),
+            email.html_body.to_owned(),
+        ))
+        .map_err(|e| EmailError::InvalidMessage(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use claim::{assert_err, assert_ok};
+
+    use super::{SmtpClient, build_message};
+    use crate::configuration::{SmtpSettings, SmtpTls, TlsVersion};
+    use crate::email_client::{EmailHeader, SendEmailRequest};
+
+    fn request<'a>(headers: Vec<EmailHeader<'a>>) -> SendEmailRequest<'a> {
+        SendEmailRequest {
+            from: "news@melierx.com".into(),
+            to: "ursula@example.com".into(),
+            subject: "Issue #1".into(),
+            html_body: "<p>Hello</p>",
+            text_body: "Hello",
+            reply_to: Some("editor@melierx.com".into()),
+            headers,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn the_message_carries_both_bodies_and_the_extra_headers() {
+        let email = request(vec![EmailHeader {
+            name: "List-Unsubscribe",
+            value: "<https://melierx.com/unsubscribe>",
+        }]);
+
+        let message = build_message(&email, "<1@melierx>").unwrap();
+        let formatted = String::from_utf8(message.formatted()).unwrap();
+
+        assert!(formatted.contains("From: news@melierx.com"));
+        assert!(formatted.contains("To: ursula@example.com"));
+        assert!(formatted.contains("Subject: Issue #1"));
+        assert!(formatted.contains("Reply-To: editor@melierx.com"));
+        assert!(formatted.contains("Message-ID: <1@melierx>"));
+        assert!(
+            formatted.contains(
+                "List-Unsubscribe: <https://melierx.com/unsubscribe>"
+            )
+        );
+        assert!(formatted.contains("Content-Type: text/plain"));
+        assert!(formatted.contains("Content-Type: text/html"));
+    }
+
+    #[test]
+    fn an_invalid_header_name_is_rejected() {
+        let email = request(vec![EmailHeader {
+            name: "Not A Header",
+            value: "value",
+        }]);
+
+        assert_err!(build_message(&email, "<1@melierx>"));
+    }
+
+    #[actix_web::test]
+    async fn every_tls_mode_can_be_pinned_to_tls_1_3() {
+        for tls in [SmtpTls::Tls, SmtpTls::Starttls, SmtpTls::None] {
+            let settings = SmtpSettings {
+                host: "smtp.example.com".into(),
+                port: 465,
+                username: None,
+                password: None,
+                tls,
+            };
+
+            assert_ok!(SmtpClient::new(
+                &settings,
+                Duration::from_secs(1),
+                TlsVersion::Tls1_3
+            ));
+        }
+    }
+}
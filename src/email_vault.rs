@@ -0,0 +1,203 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use hmac::{Hmac, Mac};
+use secrecy::ExposeSecret;
+use sha2::Sha256;
+
+use crate::configuration::{EmailStorage, SubscriberEmailSettings};
+
+const NONCE_LENGTH: usize = 12;
+
+/// Prefix of the lookup keys stored in place of hashed addresses.
+const LOOKUP_KEY_PREFIX: &str = "hmac-sha256:";
+
+/// Turns subscriber addresses into what is stored in `subscriptions.email`,
+/// and back.
+/// With plaintext storage the address is stored as is. With hashed storage
+/// the column holds a keyed hash of the address, which the unique index,
+/// joins and lookups by address all work on, while `encrypted_email` holds
+/// the address itself for when an email has to be sent.
+#[derive(Clone)]
+pub struct EmailVault {
+    storage: EmailStorage,
+    lookup_key: [u8; 32],
+    cipher: Aes256Gcm,
+}
+
+impl EmailVault {
+    pub fn new(settings: &SubscriberEmailSettings) -> Self {
+        let secret = settings.encryption_key.expose_secret().as_bytes();
+        let subkey = |label: &[u8]| -> [u8; 32] {
+            let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret)
+                .expect("HMAC accepts keys of any length");
+            mac.update(label);
+            mac.finalize().into_bytes().into()
+        };
+        let encryption_key = subkey(b"subscriber-email-encryption");
+        Self {
+            storage: settings.storage,
+            lookup_key: subkey(b"subscriber-email-lookup"),
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(
+                &encryption_key,
+            )),
+        }
+    }
+
+    /// The value stored in, and matched against, `subscriptions.email` and
+    /// the tables that copy it.
+    /// Hashed addresses are lowercased first, so lookups ignore case.
+    /// # Arguments
+    /// * `email` - The subscriber's address.
+    /// # Returns
+    /// The address itself, or its keyed hash.
+    pub fn lookup_key(&self, email: &str) -> String {
+        match self.storage {
+            EmailStorage::Plaintext => email.to_owned(),
+            EmailStorage::Hashed => {
+                let mut mac =
+                    <Hmac<Sha256> as Mac>::new_from_slice(&self.lookup_key)
+                        .expect("HMAC accepts keys of any length");
+                mac.update(email.trim().to_lowercase().as_bytes());
+                format!(
+                    "{LOOKUP_KEY_PREFIX}{}",
+                    hex::encode(mac.finalize().into_bytes())
+                )
+            }
+        }
+    }
+
+    /// Encrypt an address for `encrypted_email`; the nonce is stored in
+    /// front of the ciphertext.
+    /// # Arguments
+    /// * `email` - The subscriber's address.
+    /// # Returns
+    /// A Result containing the encrypted address, or None with plaintext
+    /// storage.
+    pub fn seal(&self, email: &str) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        if self.storage == EmailStorage::Plaintext {
+            return Ok(None);
+        }
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, email.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt the address."))?;
+        Ok(Some([nonce.as_slice(), &ciphertext].concat()))
+    }
+
+    /// Recover a subscriber's address from their stored row.
+    /// # Arguments
+    /// * `stored` - The value of `subscriptions.email`.
+    /// * `encrypted` - The value of `subscriptions.encrypted_email`.
+    /// # Returns
+    /// A Result containing the address.
+    pub fn reveal(
+        &self,
+        stored: &str,
+        encrypted: Option<&[u8]>,
+    ) -> Result<String, anyhow::Error> {
+        let Some(encrypted) = encrypted else {
+            return Ok(stored.to_owned());
+        };
+        if encrypted.len() < NONCE_LENGTH {
+            anyhow::bail!("The stored address is truncated.");
+        }
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_LENGTH);
+        let address = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Failed to decrypt the stored address. \
+                    Was the encryption key changed?"
+                )
+            })?;
+        Ok(String::from_utf8(address)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::SecretString;
+
+    use super::EmailVault;
+    use crate::configuration::{EmailStorage, SubscriberEmailSettings};
+
+    fn vault(storage: EmailStorage, encryption_key: &str) -> EmailVault {
+        EmailVault::new(&SubscriberEmailSettings {
+            storage,
+            encryption_key: SecretString::from(encryption_key),
+        })
+    }
+
+    #[test]
+    fn plaintext_storage_keeps_the_address() {
+        let vault = vault(EmailStorage::Plaintext, "key");
+
+        assert_eq!(
+            vault.lookup_key("Ursula@Example.com"),
+            "Ursula@Example.com"
+        );
+        assert_eq!(vault.seal("ursula@example.com").unwrap(), None);
+        assert_eq!(
+            vault.reveal("ursula@example.com", None).unwrap(),
+            "ursula@example.com"
+        );
+    }
+
+    #[test]
+    fn hashed_lookup_keys_do_not_contain_the_address() {
+        let vault = vault(EmailStorage::Hashed, "key");
+
+        let key = vault.lookup_key("ursula@example.com");
+
+        assert!(key.starts_with("hmac-sha256:"));
+        assert!(!key.contains("ursula"));
+    }
+
+    #[test]
+    fn hashed_lookup_keys_ignore_case() {
+        let vault = vault(EmailStorage::Hashed, "key");
+
+        assert_eq!(
+            vault.lookup_key("Ursula@Example.com"),
+            vault.lookup_key("ursula@example.com")
+        );
+    }
+
+    #[test]
+    fn hashed_lookup_keys_depend_on_the_key() {
+        assert_ne!(
+            vault(EmailStorage::Hashed, "key").lookup_key("ursula@example.com"),
+            vault(EmailStorage::Hashed, "other")
+                .lookup_key("ursula@example.com")
+        );
+    }
+
+    #[test]
+    fn a_sealed_address_round_trips() {
+        let vault = vault(EmailStorage::Hashed, "key");
+        let key = vault.lookup_key("Ursula@Example.com");
+
+        let sealed = vault.seal("Ursula@Example.com").unwrap().unwrap();
+
+        assert_eq!(
+            vault.reveal(&key, Some(&sealed)).unwrap(),
+            "Ursula@Example.com"
+        );
+    }
+
+    #[test]
+    fn a_sealed_address_cannot_be_revealed_with_another_key() {
+        let sealed = vault(EmailStorage::Hashed, "key")
+            .seal("ursula@example.com")
+            .unwrap()
+            .unwrap();
+
+        assert!(
+            vault(EmailStorage::Hashed, "other")
+                .reveal("", Some(&sealed))
+                .is_err()
+        );
+    }
+}
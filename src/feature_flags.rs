@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::configuration::Settings;
+
+/// Behaviour that can be switched on and off at runtime.
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Warn before publishing content identical to a recent issue.
+    DuplicateContentDetection,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 1] = [Feature::DuplicateContentDetection];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Feature::DuplicateContentDetection => "duplicate_content_detection",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.as_str() == s)
+    }
+}
+
+/// The state of a single flag.
+pub struct FlagState {
+    pub feature: Feature,
+    pub default: bool,
+    pub overridden: Option<bool>,
+}
+
+impl FlagState {
+    pub fn enabled(&self) -> bool {
+        self.overridden.unwrap_or(self.default)
+    }
+}
+
+/// Feature flags with defaults from configuration and overrides persisted in
+/// the `feature_flags` table.
+/// Overrides are loaded at startup and updated in place when toggled, so
+/// changes take effect immediately for every handler sharing this instance.
+pub struct FeatureFlags {
+    defaults: HashMap<Feature, bool>,
+    overrides: RwLock<HashMap<Feature, bool>>,
+}
+
+impl FeatureFlags {
+    /// Build the flags from their configured defaults.
+    /// # Arguments
+    /// * `configuration` - The application settings.
+    pub fn new(configuration: &Settings) -> Self {
+        let defaults = HashMap::from([(
+            Feature::DuplicateContentDetection,
+            configuration.newsletter.detect_duplicate_content,
+        )]);
+        Self {
+            defaults,
+            overrides: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Replace the in-memory overrides with the persisted ones.
+    /// # Arguments
+    /// * `pool` - A reference to the PostgreSQL connection pool.
+    /// # Returns
+    /// A Result indicating success or a sqlx::Error.
+    #[tracing::instrument(skip_all)]
+    pub async fn load_overrides(
+        &self,
+        pool: &PgPool,
+    ) -> Result<(), sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT feature, enabled
+            FROM feature_flags
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+        // Rows for features this build no longer knows about are ignored.
+        let overrides = rows
+            .into_iter()
+            .filter_map(|r| Feature::parse(&r.feature).map(|f| (f, r.enabled)))
+            .collect();
+        *self.overrides.write().unwrap() = overrides;
+        Ok(())
+    }
+
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        let overridden = self.overrides.read().unwrap().get(&feature).copied();
+        overridden.unwrap_or(self.defaults[&feature])
+    }
+
+    /// Every flag with its default and current override.
+    pub fn states(&self) -> Vec<FlagState> {
+        let overrides = self.overrides.read().unwrap();
+        Feature::ALL
+            .into_iter()
+            .map(|feature| FlagState {
+                feature,
+                default: self.defaults[&feature],
+                overridden: overrides.get(&feature).copied(),
+            })
+            .collect()
+    }
+
+    /// Persist an override, record who made it and apply it.
+    /// # Arguments
+    /// * `pool` - A reference to the PostgreSQL connection pool.
+    /// * `feature` - The flag to change.
+    /// * `enabled` - The new value.
+    /// * `user_id` - The admin making the change.
+    /// # Returns
+    /// A Result indicating success or a sqlx::Error.
+    #[tracing::instrument(skip(self, pool))]
+    pub async fn set(
+        &self,
+        pool: &PgPool,
+        feature: Feature,
+        enabled: bool,
+        user_id: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        let mut transaction = pool.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO feature_flags (feature, enabled, updated_at)
+            VALUES ($1, $2, now())
+            ON CONFLICT (feature) DO UPDATE
+            SET enabled = EXCLUDED.enabled, updated_at = EXCLUDED.updated_at
+            "#,
+            feature.as_str(),
+            enabled
+        )
+        .execute(transaction.as_mut())
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO feature_flag_audit (
+                id, feature, enabled, changed_by, changed_at
+            )
+            VALUES ($1, $2, $3, $4, now())
+            "#,
+            Uuid::new_v4(),
+            feature.as_str(),
+            enabled,
+            user_id
+        )
+        .execute(transaction.as_mut())
+        .await?;
+        transaction.commit().await?;
+
+        self.overrides.write().unwrap().insert(feature, enabled);
+        tracing::info!("Feature flag changed.");
+        Ok(())
+    }
+}
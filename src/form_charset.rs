@@ -0,0 +1,100 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::{ErrorBadRequest, ErrorUnsupportedMediaType};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::middleware::Next;
+use actix_web::web::Bytes;
+
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+/// Reject URL-encoded form submissions that are not UTF-8.
+/// A charset other than UTF-8 on the Content-Type is rejected with a 415,
+/// and a body whose decoded fields are not valid UTF-8 with a 400, so that
+/// mangled names and emails never reach the handlers.
+/// # Arguments
+/// * `req` - The incoming request.
+/// * `next` - The next service in the chain.
+/// # Returns
+/// A Result containing the downstream response or the rejection.
+pub async fn require_utf8_forms(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(charset) = form_charset(&req) else {
+        return next.call(req).await;
+    };
+    if !is_utf8_charset(charset.as_deref()) {
+        return Err(ErrorUnsupportedMediaType(
+            "Form submissions must be encoded as UTF-8.",
+        ));
+    }
+
+    let body = req.extract::<Bytes>().await?;
+    if !fields_are_utf8(&body) {
+        return Err(ErrorBadRequest("The form data is not valid UTF-8."));
+    }
+    req.set_payload(Payload::from(body));
+    next.call(req).await
+}
+
+/// Return the charset declared on a form submission.
+/// # Returns
+/// None when the request is not a URL-encoded form, Some(None) when it is
+/// but no charset was declared.
+fn form_charset(req: &ServiceRequest) -> Option<Option<String>> {
+    let content_type = req.headers().get(CONTENT_TYPE)?.to_str().ok()?;
+    let mut parts = content_type.split(';').map(str::trim);
+    if !parts.next()?.eq_ignore_ascii_case(FORM_CONTENT_TYPE) {
+        return None;
+    }
+    let charset = parts.find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"').to_owned())
+    });
+    Some(charset)
+}
+
+fn is_utf8_charset(charset: Option<&str>) -> bool {
+    match charset {
+        None => true,
+        Some(charset) => {
+            charset.eq_ignore_ascii_case("utf-8")
+                || charset.eq_ignore_ascii_case("utf8")
+        }
+    }
+}
+
+fn fields_are_utf8(body: &[u8]) -> bool {
+    body.split(|b| *b == b'&' || *b == b'=').all(|field| {
+        std::str::from_utf8(&urlencoding::decode_binary(field)).is_ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fields_are_utf8, is_utf8_charset};
+
+    #[test]
+    fn a_missing_charset_defaults_to_utf8() {
+        assert!(is_utf8_charset(None));
+    }
+
+    #[test]
+    fn utf8_charset_names_are_case_insensitive() {
+        assert!(is_utf8_charset(Some("UTF-8")));
+        assert!(is_utf8_charset(Some("utf8")));
+        assert!(!is_utf8_charset(Some("ISO-8859-1")));
+    }
+
+    #[test]
+    fn percent_encoded_utf8_is_accepted() {
+        assert!(fields_are_utf8(b"name=Jos%C3%A9&email=jose%40gmail.com"));
+    }
+
+    #[test]
+    fn percent_encoded_latin1_is_rejected() {
+        assert!(!fields_are_utf8(b"name=Jos%E9&email=jose%40gmail.com"));
+    }
+}
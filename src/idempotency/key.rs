@@ -1,3 +1,5 @@
+use uuid::Uuid;
+
 /// A unique identifier for ensuring idempotent operations.
 #[derive(Debug)]
 pub struct IdempotencyKey(String);
@@ -20,6 +22,30 @@ impl TryFrom<String> for IdempotencyKey {
     }
 }
 
+impl IdempotencyKey {
+    /// Parse a key, also checking it against the configured format.
+    /// # Arguments
+    /// * `s` - The key sent by the client.
+    /// * `format` - Which keys are accepted.
+    /// # Returns
+    /// A Result containing the key, or an error explaining the rejection.
+    pub fn parse(
+        s: String,
+        format: IdempotencyKeyFormat,
+    ) -> Result<Self, anyhow::Error> {
+        let key = Self::try_from(s)?;
+        if format == IdempotencyKeyFormat::Uuid
+            && Uuid::try_parse(&key.0).is_err()
+        {
+            anyhow::bail!(
+                "The idempotency key must be a UUID, such as {}.",
+                Uuid::new_v4()
+            );
+        }
+        Ok(key)
+    }
+}
+
 impl From<IdempotencyKey> for String {
     fn from(k: IdempotencyKey) -> Self {
         k.0
@@ -31,3 +57,34 @@ impl AsRef<str> for IdempotencyKey {
         &self.0
     }
 }
+
+/// The endpoint an idempotency key was sent to.
+/// Keys are only unique within a scope, so the same string sent to two
+/// endpoints does not replay one's saved response on the other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdempotencyScope {
+    PublishNewsletter,
+    ApiPublishNewsletter,
+    AddSubscriber,
+}
+
+impl IdempotencyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PublishNewsletter => "publish_newsletter",
+            Self::ApiPublishNewsletter => "api_publish_newsletter",
+            Self::AddSubscriber => "add_subscriber",
+        }
+    }
+}
+
+/// Which idempotency keys clients may send.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdempotencyKeyFormat {
+    /// Any non-empty key of up to 50 characters.
+    #[default]
+    Any,
+    /// Only UUIDs, which clients cannot easily reuse by accident.
+    Uuid,
+}
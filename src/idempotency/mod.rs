@@ -1,6 +1,8 @@
 mod key;
 mod persistence;
 
-pub use key::IdempotencyKey;
-pub use persistence::{NextAction, try_processing};
+pub use key::{IdempotencyKey, IdempotencyKeyFormat, IdempotencyScope};
+pub use persistence::IN_FLIGHT_MESSAGE;
+pub use persistence::{EXPIRED_KEY_MESSAGE, FailureMode, NextAction};
 pub use persistence::{get_saved_response, save_response};
+pub use persistence::{is_store_unavailable, try_processing};
@@ -1,16 +1,71 @@
+use std::time::Duration;
+
 use actix_web::HttpResponse;
 use actix_web::body::to_bytes;
 use actix_web::http::StatusCode;
 use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
-use super::IdempotencyKey;
+use super::{IdempotencyKey, IdempotencyScope};
+use crate::metrics::{IDEMPOTENCY_METRICS, IdempotencyOutcome};
+
+/// Told to clients repeating a key past the replay window.
+pub const EXPIRED_KEY_MESSAGE: &str = "This idempotency key was first used \
+    too long ago to be repeated - send the request again with a new key.";
+
+/// Told to clients repeating a key whose first request has no saved
+/// response yet.
+pub const IN_FLIGHT_MESSAGE: &str = "A request with this idempotency key is \
+    still being processed - retry it shortly.";
 
 /// The next action to take based on idempotency key lookup.
 #[allow(clippy::large_enum_variant)]
 pub enum NextAction {
     StartProcessing(Transaction<'static, Postgres>),
     ReturnSavedResponse(HttpResponse),
+    /// The key was first used longer ago than the replay window allows.
+    RejectExpiredKey,
+    /// The key was claimed by a request whose response is not saved yet.
+    RejectInFlight,
+}
+
+/// What to do when the idempotency store cannot be reached.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureMode {
+    /// Refuse the request.
+    FailClosed,
+    /// Process the request without duplicate-submission protection, as long
+    /// as the store is unreachable rather than failing in some other way.
+    FailOpen,
+}
+
+/// Whether `try_processing` failed because the database could not be
+/// reached, the only failure `FailureMode::FailOpen` lets a request through
+/// on. Anything else, e.g. a missing table, is a bug that protection should
+/// not be dropped for.
+/// # Arguments
+/// * `e` - The error returned by `try_processing`.
+/// # Returns
+/// true if the error is a connectivity error.
+pub fn is_store_unavailable(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<sqlx::Error>(),
+        Some(
+            sqlx::Error::PoolTimedOut
+                | sqlx::Error::PoolClosed
+                | sqlx::Error::Io(_)
+                | sqlx::Error::Tls(_)
+        )
+    )
+}
+
+/// Whether the statement lost a race with a concurrent transaction
+/// (SQLSTATE 40001).
+fn is_serialization_failure(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "40001")
 }
 
 #[derive(Debug, sqlx::Type)]
@@ -23,6 +78,7 @@ struct HeaderPairRecord {
 pub async fn get_saved_response(
     pool: &PgPool,
     idempotency_key: &IdempotencyKey,
+    scope: IdempotencyScope,
     user_id: Uuid,
 ) -> Result<Option<HttpResponse>, anyhow::Error> {
     let saved_response = sqlx::query!(
@@ -34,10 +90,12 @@ pub async fn get_saved_response(
         FROM idempotency
         WHERE 
             idempotency_key = $1 AND 
-            user_id = $2
+            user_id = $2 AND
+            scope = $3
         "#,
         idempotency_key.as_ref(),
-        user_id
+        user_id,
+        scope.as_str()
     )
     .fetch_optional(pool)
     .await?;
@@ -58,6 +116,7 @@ pub async fn get_saved_response(
 pub async fn save_response(
     mut transaction: Transaction<'_, Postgres>,
     idempotency_key: &IdempotencyKey,
+    scope: IdempotencyScope,
     user_id: Uuid,
     http_response: HttpResponse,
 ) -> Result<HttpResponse, anyhow::Error> {
@@ -85,13 +144,15 @@ pub async fn save_response(
             response_body = $5
         WHERE 
             user_id = $1 AND 
-            idempotency_key = $2;
+            idempotency_key = $2 AND
+            scope = $6;
         "#,
         user_id,
         idempotency_key.as_ref(),
         status_code,
         header,
         body.as_ref(),
+        scope.as_str(),
     )
     .execute(transaction.as_mut())
     .await?;
@@ -101,42 +162,123 @@ pub async fn save_response(
     Ok(http_response)
 }
 
+/// Claim an idempotency key, or find out what to do with a repeated one.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `idempotency_key` - The key sent by the client.
+/// * `scope` - The endpoint the key was sent to.
+/// * `user_id` - The ID of the authenticated user.
+/// * `replay_window` - How long after its first use a key may be repeated,
+///   or `None` for no limit.
+/// # Returns
+/// A Result containing the next action to take.
 pub async fn try_processing(
     pool: &PgPool,
     idempotency_key: &IdempotencyKey,
+    scope: IdempotencyScope,
     user_id: Uuid,
+    replay_window: Option<Duration>,
 ) -> Result<NextAction, anyhow::Error> {
     let mut transaction = pool.begin().await?;
     sqlx::query!("SET TRANSACTION ISOLATION LEVEL repeatable read")
         .execute(transaction.as_mut())
         .await?;
 
-    let n_inserted_rows = sqlx::query!(
+    // A concurrent request claiming the same key makes this insert wait
+    // for it, then fail to serialize once it commits: the key is taken.
+    let n_inserted_rows = match sqlx::query!(
         r#"
         INSERT INTO idempotency (
             user_id, 
             idempotency_key, 
+            scope,
             created_at
-        ) VALUES ($1, $2, now())
+        ) VALUES ($1, $2, $3, now())
         ON CONFLICT DO NOTHING
         "#,
         user_id,
         idempotency_key.as_ref(),
+        scope.as_str(),
     )
     .execute(transaction.as_mut())
-    .await?
-    .rows_affected();
+    .await
+    {
+        Ok(result) => result.rows_affected(),
+        Err(e) if is_serialization_failure(&e) => 0,
+        Err(e) => return Err(e.into()),
+    };
 
     if n_inserted_rows > 0 {
+        IDEMPOTENCY_METRICS.record(IdempotencyOutcome::Started);
         Ok(NextAction::StartProcessing(transaction))
     } else {
-        let saved_response = get_saved_response(pool, idempotency_key, user_id)
+        if let Some(replay_window) = replay_window
+            && is_key_expired(
+                pool,
+                idempotency_key,
+                scope,
+                user_id,
+                replay_window,
+            )
             .await?
-            .ok_or_else(|| {
-                anyhow::anyhow!(
-                    "We expected a saved response, we didn't find it."
-                )
-            })?;
+        {
+            return Ok(NextAction::RejectExpiredKey);
+        }
+        let Some(saved_response) =
+            get_saved_response(pool, idempotency_key, scope, user_id).await?
+        else {
+            return Ok(NextAction::RejectInFlight);
+        };
+        IDEMPOTENCY_METRICS.record(IdempotencyOutcome::Replayed);
         Ok(NextAction::ReturnSavedResponse(saved_response))
     }
 }
+
+/// Whether the key was first used longer ago than the replay window.
+async fn is_key_expired(
+    pool: &PgPool,
+    idempotency_key: &IdempotencyKey,
+    scope: IdempotencyScope,
+    user_id: Uuid,
+    replay_window: Duration,
+) -> Result<bool, sqlx::Error> {
+    let expired = sqlx::query_scalar!(
+        r#"
+        SELECT created_at < now() - make_interval(secs => $4) AS "expired!"
+        FROM idempotency
+        WHERE
+            idempotency_key = $1 AND
+            user_id = $2 AND
+            scope = $3
+        "#,
+        idempotency_key.as_ref(),
+        user_id,
+        scope.as_str(),
+        replay_window.as_secs_f64()
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(expired.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_store_unavailable;
+
+    #[test]
+    fn connectivity_errors_make_the_store_unavailable() {
+        for e in [
+            sqlx::Error::PoolTimedOut,
+            sqlx::Error::PoolClosed,
+            sqlx::Error::Io(std::io::ErrorKind::ConnectionReset.into()),
+        ] {
+            assert!(is_store_unavailable(&e.into()));
+        }
+    }
+
+    #[test]
+    fn other_errors_do_not_make_the_store_unavailable() {
+        assert!(!is_store_unavailable(&sqlx::Error::RowNotFound.into()));
+        assert!(!is_store_unavailable(&anyhow::anyhow!("Invalid status")));
+    }
+}
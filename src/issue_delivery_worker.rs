@@ -1,69 +1,291 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
 use std::time::Duration;
 
+use anyhow::Context;
+use chrono::Utc;
+use futures::future::join_all;
 use sqlx::{PgPool, Postgres, Transaction};
-use tracing::{Span, field::display};
+use tracing::{Instrument, Span, field::display};
 use uuid::Uuid;
 
-use crate::domain::SubscriberEmail;
-use crate::email_client::EmailClient;
+use crate::configuration::{
+    CompletionSummarySettings, DeliveryAlarmSettings, NewsletterSettings,
+    OnboardingSettings, SchedulerSettings, SendTimeOptimizationSettings,
+    UnsubscribeLink, WebhookSettings, WorkerSettings,
+};
+use crate::domain::{StatusEvent, SubscriberEmail, transition_status};
+use crate::email_client::{
+    EmailClient, EmailError, OutgoingEmail, SendEmailResponse,
+};
+use crate::email_vault::EmailVault;
+use crate::metrics::{DELIVERY_METRICS, DeliveryOutcome};
+use crate::newsletter::{
+    NewsletterIssue, Recipient, RenderOptions, RenderedEmail,
+    render_for_recipient,
+};
+use crate::onboarding::try_execute_onboarding_task;
+use crate::routes::{lock_subscriber_status_by_email, set_subscriber_status};
+use crate::scheduler::LeaderElection;
+use crate::send_time::optimize_send_times;
+use crate::subscriber_token::{SubscriberAction, SubscriberLinks};
+use crate::webhooks::{WebhookEvent, enqueue_webhook};
 use crate::{configuration::Settings, startup::get_connection_pool};
 
 type PgTransaction = Transaction<'static, Postgres>;
 
-struct NewsletterIssue {
-    title: String,
-    text_content: String,
-    html_content: String,
-}
-
+#[derive(Debug, PartialEq, Eq)]
 pub enum ExecutionOutcome {
     TaskCompleted,
+    TaskDeferred,
     EmptyQueue,
 }
 
-#[tracing::instrument(
-    skip_all,
-    fields(
-        issue_id = tracing::field::Empty,
-        subscriber_email = tracing::field::Empty,
-    ),
-    err
-)]
-pub async fn try_execute_task(
+struct DeliveryTask {
+    issue_id: Uuid,
+    subscriber_email: String,
+    n_retries: i16,
+    correlation_id: Option<Uuid>,
+}
+
+impl DeliveryTask {
+    /// The span the work on this delivery is logged under.
+    fn span(&self) -> Span {
+        let span = tracing::info_span!(
+            "Delivering an issue",
+            issue_id = %self.issue_id,
+            subscriber_email = %self.subscriber_email,
+            correlation_id = tracing::field::Empty,
+        );
+        if let Some(correlation_id) = self.correlation_id {
+            span.record("correlation_id", display(correlation_id));
+        }
+        span
+    }
+}
+
+/// A delivery rendered for its recipient, waiting for the batch to go out.
+struct PreparedDelivery {
+    task: DeliveryTask,
+    email: SubscriberEmail,
+    rendered: RenderedEmail,
+    /// The value of the `List-Unsubscribe` header.
+    list_unsubscribe: String,
+}
+
+/// What preparing a delivery led to.
+enum Preparation {
+    Ready(PreparedDelivery),
+    /// Put off without an attempt, e.g. for a snoozed subscriber.
+    Deferred,
+    /// Dropped without an attempt, as the subscriber is no longer confirmed.
+    Dropped(Uuid),
+    /// Settled without reaching the provider.
+    Settled(Uuid, DeliveryOutcome),
+}
+
+/// Deliver up to `max_tasks` pending newsletter emails with a single call
+/// to the email provider.
+/// Failed deliveries are rescheduled after the email client's retry backoff
+/// and moved to the dead-letter table once `max_delivery_retries` attempts
+/// have failed.
+/// Deliveries to snoozed subscribers are deferred until the snooze ends.
+/// Every attempt is logged to `delivery_attempts` with its outcome and the
+/// provider's message ID or the error.
+/// After the batch the bounce/complaint rates of the issues it touched are
+/// checked against the configured alarm thresholds.
+/// Each email is rendered for its recipient by `render_for_recipient`; every
+/// email carries an unsubscribe link tagged with the issue it belongs to, so
+/// opt-outs can be attributed to the issue that drove them. The footer link
+/// follows `unsubscribe_link`, while the `List-Unsubscribe` header always
+/// unsubscribes in one click, with `List-Unsubscribe-Post` advertising
+/// RFC 8058 one-click POSTs.
+///
+/// Sends are resumable: the queue rows are locked, the batch sent and the
+/// rows deleted in a single transaction, so the queue always holds exactly
+/// the recipients still owed the issue. A worker that stops mid-send
+/// (crash, deploy) has its transaction rolled back and the next worker picks
+/// the deliveries up again, while recipients whose delivery was committed
+/// are never emailed again. The only duplicates possible are the emails of
+/// the batch in flight when the worker stopped, if the provider had already
+/// accepted them.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `email_client` - The client used to send emails.
+/// * `links` - Builds the unsubscribe links.
+/// * `settings` - The newsletter delivery settings.
+/// * `webhooks` - The outbound webhook settings.
+/// * `vault` - Recovers subscribers' addresses.
+/// * `max_tasks` - The most deliveries to take on.
+/// # Returns
+/// A Result containing how many deliveries were processed; none if the
+/// queue was empty.
+#[tracing::instrument(skip_all, err)]
+pub async fn try_execute_tasks(
     pool: &PgPool,
     email_client: &EmailClient,
-) -> Result<ExecutionOutcome, anyhow::Error> {
-    let task = dequeue_task(pool).await?;
-    if task.is_none() {
-        return Ok(ExecutionOutcome::EmptyQueue);
-    }
-
-    let (transaction, issue_id, email) = task.unwrap();
-
-    Span::current()
-        .record("issue_id", &display(issue_id))
-        .record("subscriber_email", &display(&email));
-
-    match SubscriberEmail::parse(email.clone()) {
-        Ok(email) => {
-            let issue = get_issue(pool, issue_id).await?;
-            if let Err(e) = email_client
-                .send_email(
-                    &email,
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
-                )
-                .await
-            {
-                tracing::error!(
-                    error.cause_chain = ?e,
-                    error.message = %e,
-                    "Failed to deliver issue to a confirmed subscriber. \
-                    Skipping.",
-                );
+    links: &SubscriberLinks,
+    settings: &NewsletterSettings,
+    webhooks: &WebhookSettings,
+    vault: &EmailVault,
+    max_tasks: usize,
+) -> Result<DrainSummary, anyhow::Error> {
+    let (mut transaction, tasks) = dequeue_tasks(pool, max_tasks).await?;
+    let mut summary = DrainSummary::default();
+    let mut outcomes = Vec::new();
+    let mut dropped = Vec::new();
+    let mut issues = HashMap::new();
+    let mut deliveries = Vec::new();
+    for task in tasks {
+        let span = task.span();
+        match prepare_delivery(
+            &mut transaction,
+            pool,
+            links,
+            settings,
+            vault,
+            &mut issues,
+            task,
+        )
+        .instrument(span)
+        .await?
+        {
+            Preparation::Ready(delivery) => deliveries.push(delivery),
+            Preparation::Deferred => summary.deferred += 1,
+            Preparation::Dropped(issue_id) => dropped.push(issue_id),
+            Preparation::Settled(issue_id, outcome) => {
+                outcomes.push((issue_id, outcome))
             }
         }
+    }
+
+    let headers: Vec<_> = deliveries
+        .iter()
+        .map(|delivery| list_unsubscribe_headers(&delivery.list_unsubscribe))
+        .collect();
+    // Echoed back by the provider's bounce and complaint webhooks, so the
+    // events can be attributed to the issue.
+    let issue_ids: Vec<_> = deliveries
+        .iter()
+        .map(|delivery| delivery.task.issue_id.to_string())
+        .collect();
+    let metadata: Vec<_> = issue_ids
+        .iter()
+        .map(|issue_id| [("issue_id", issue_id.as_str())])
+        .collect();
+    let messages: Vec<_> = deliveries
+        .iter()
+        .zip(&headers)
+        .zip(&metadata)
+        .map(|((delivery, headers), metadata)| OutgoingEmail {
+            recipient: &delivery.email,
+            subject: &delivery.rendered.subject,
+            html_content: &delivery.rendered.html_content,
+            text_content: &delivery.rendered.text_content,
+            headers,
+            reply_to: settings.reply_to.as_deref(),
+            metadata,
+        })
+        .collect();
+    let responses = email_client.send_email_batch(&messages).await;
+    for (delivery, response) in deliveries.iter().zip(responses) {
+        let task = &delivery.task;
+        match settle_delivery(
+            &mut transaction,
+            email_client,
+            settings,
+            task,
+            response,
+        )
+        .instrument(task.span())
+        .await?
+        {
+            Some(outcome) => outcomes.push((task.issue_id, outcome)),
+            None => summary.deferred += 1,
+        }
+    }
+
+    let attempted: BTreeSet<_> =
+        outcomes.iter().map(|&(issue_id, _)| issue_id).collect();
+    for &issue_id in &attempted {
+        check_delivery_alarm(
+            &mut transaction,
+            issue_id,
+            &settings.delivery_alarm,
+            webhooks,
+        )
+        .await?;
+    }
+    transaction.commit().await?;
+    for &(_, outcome) in &outcomes {
+        DELIVERY_METRICS.record(outcome);
+    }
+    summary.completed = (outcomes.len() + dropped.len()) as u64;
+    if !settings.completion_summary.enabled {
+        return Ok(summary);
+    }
+    let settled: BTreeSet<_> = outcomes
+        .iter()
+        .filter(|&&(_, outcome)| outcome != DeliveryOutcome::Retrying)
+        .map(|&(issue_id, _)| issue_id)
+        .chain(dropped)
+        .collect();
+    for issue_id in settled {
+        if let Err(e) = send_completion_summary(
+            pool,
+            email_client,
+            issue_id,
+            &settings.completion_summary,
+        )
+        .await
+        {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send the completion summary of an issue.",
+            );
+        }
+    }
+    Ok(summary)
+}
+
+/// Get a delivery ready to go out in the batch.
+/// Deliveries to subscribers who are no longer confirmed are dropped, to
+/// snoozed subscribers deferred and to invalid addresses dead-lettered,
+/// all without reaching the provider.
+async fn prepare_delivery(
+    transaction: &mut PgTransaction,
+    pool: &PgPool,
+    links: &SubscriberLinks,
+    settings: &NewsletterSettings,
+    vault: &EmailVault,
+    issues: &mut HashMap<Uuid, NewsletterIssue>,
+    task: DeliveryTask,
+) -> Result<Preparation, anyhow::Error> {
+    // The subscriber may have unsubscribed since the issue was enqueued,
+    // e.g. while snoozed or held back for their best send time.
+    let Some(subscriber) = get_subscriber(pool, &task.subscriber_email).await?
+    else {
+        tracing::info!(
+            "The subscriber is no longer confirmed. Dropping the delivery."
+        );
+        delete_task(transaction, &task).await?;
+        return Ok(Preparation::Dropped(task.issue_id));
+    };
+    if let Some(snoozed_for) =
+        remaining_snooze(transaction, &task.subscriber_email).await?
+    {
+        tracing::info!("The subscriber is snoozed. Deferring delivery.");
+        defer_task(transaction, &task, snoozed_for).await?;
+        return Ok(Preparation::Deferred);
+    }
+
+    let address = vault.reveal(
+        &task.subscriber_email,
+        subscriber.encrypted_email.as_deref(),
+    )?;
+    let email = match SubscriberEmail::parse(address) {
+        Ok(email) => email,
         Err(e) => {
             tracing::error!(
                 error.cause_chain = ?e,
@@ -71,52 +293,632 @@ pub async fn try_execute_task(
                 "Skipping a confirmed subscriber. \
                 Their stored contact details are invalid.",
             );
+            // Retrying cannot fix an invalid address.
+            dead_letter_task(transaction, &task, &e).await?;
+            if settings.flag_invalid_subscribers {
+                flag_invalid_subscriber(transaction, &task).await?;
+            }
+            let outcome = DeliveryOutcome::DeadLettered;
+            finish_task(transaction, &task, outcome, None, Some(&e)).await?;
+            return Ok(Preparation::Settled(task.issue_id, outcome));
+        }
+    };
+    let issue = match issues.entry(task.issue_id) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            entry.insert(get_issue(pool, task.issue_id).await?)
+        }
+    };
+    let rendered = render_for_recipient(
+        issue,
+        &Recipient {
+            name: &subscriber.name,
+            email: email.as_ref(),
+        },
+        &RenderOptions {
+            unsubscribe_link: Some(unsubscribe_link(
+                links,
+                settings.unsubscribe_link,
+                subscriber.id,
+                task.issue_id,
+            )),
+            preferences_link: Some(preferences_link(links, subscriber.id)),
+            derive_preheader: settings.derive_preheader,
+        },
+    );
+    let list_unsubscribe = format!(
+        "<{}>",
+        unsubscribe_link(
+            links,
+            UnsubscribeLink::OneClick,
+            subscriber.id,
+            task.issue_id,
+        )
+    );
+    Ok(Preparation::Ready(PreparedDelivery {
+        task,
+        email,
+        rendered,
+        list_unsubscribe,
+    }))
+}
+
+/// RFC 8058: the `-Post` header tells mail clients the link unsubscribes
+/// with a POST, without opening a page.
+fn list_unsubscribe_headers(list_unsubscribe: &str) -> Vec<(&str, &str)> {
+    vec![
+        ("List-Unsubscribe", list_unsubscribe),
+        ("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+    ]
+}
+
+/// Delete, reschedule or dead-letter a delivery of the batch according to
+/// what the provider made of its email.
+/// # Returns
+/// A Result containing the outcome of the attempt, or `None` if the
+/// delivery was deferred without spending an attempt.
+async fn settle_delivery(
+    transaction: &mut PgTransaction,
+    email_client: &EmailClient,
+    settings: &NewsletterSettings,
+    task: &DeliveryTask,
+    response: Result<SendEmailResponse, EmailError>,
+) -> Result<Option<DeliveryOutcome>, anyhow::Error> {
+    let (outcome, message_id, error) = match response {
+        Ok(response) => {
+            record_delivery(transaction, task, response.message_id.as_deref())
+                .await?;
+            delete_task(transaction, task).await?;
+            (DeliveryOutcome::Delivered, response.message_id, None)
+        }
+        Err(EmailError::CircuitOpen) => {
+            // Not the subscriber's fault: wait for the provider without
+            // spending the retry budget.
+            tracing::warn!(
+                "The email provider circuit breaker is open. \
+                Deferring delivery."
+            );
+            defer_task(
+                transaction,
+                task,
+                email_client.circuit_breaker_cooldown(),
+            )
+            .await?;
+            return Ok(None);
+        }
+        Err(EmailError::DailySendCapReached) => {
+            // Hold the delivery until the cap resets.
+            defer_task(transaction, task, email_client.send_cap_resets_in())
+                .await?;
+            return Ok(None);
+        }
+        Err(e) if task.n_retries + 1 >= settings.max_delivery_retries => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to deliver issue to a confirmed subscriber. \
+                Giving up.",
+            );
+            let error = e.to_string();
+            dead_letter_task(transaction, task, &error).await?;
+            (DeliveryOutcome::DeadLettered, None, Some(error))
+        }
+        Err(e) => {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to deliver issue to a confirmed subscriber. \
+                Retrying later.",
+            );
+            reschedule_task(
+                transaction,
+                task,
+                email_client.retry_delay(task.n_retries),
+            )
+            .await?;
+            (DeliveryOutcome::Retrying, None, Some(e.to_string()))
+        }
+    };
+    finish_task(
+        transaction,
+        task,
+        outcome,
+        message_id.as_deref(),
+        error.as_deref(),
+    )
+    .await?;
+    Ok(Some(outcome))
+}
+
+/// Log an attempt to the recipient's delivery history and the issue's
+/// counters.
+async fn finish_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    outcome: DeliveryOutcome,
+    message_id: Option<&str>,
+    error: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    record_attempt(transaction, task, outcome, message_id, error).await?;
+    record_outcome(transaction, task.issue_id, outcome).await?;
+    Ok(())
+}
+
+/// What draining the delivery queue got through.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DrainSummary {
+    /// Deliveries that were sent, rescheduled, dead-lettered or dropped.
+    pub completed: u64,
+    /// Deliveries put off, e.g. for a snoozed subscriber.
+    pub deferred: u64,
+}
+
+impl DrainSummary {
+    /// The outcome of the drain as a whole.
+    pub fn outcome(&self) -> ExecutionOutcome {
+        if self.completed == 0 && self.deferred == 0 {
+            ExecutionOutcome::EmptyQueue
+        } else {
+            ExecutionOutcome::TaskCompleted
         }
     }
-    delete_task(transaction, issue_id, &email).await?;
-    Ok(ExecutionOutcome::TaskCompleted)
+
+    /// How many deliveries were taken on.
+    fn processed(&self) -> u64 {
+        self.completed + self.deferred
+    }
 }
 
+/// Deliveries a drain sends per call to the email provider.
+const DRAIN_BATCH_SIZE: usize = 50;
+
+/// Run `try_execute_tasks` until no delivery is due, without waiting for
+/// the worker loop.
+/// Deliveries that are rescheduled or deferred are not due any more, so the
+/// drain ends once every delivery due when it started was attempted once.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `email_client` - The client used to send emails.
+/// * `links` - Builds the unsubscribe links.
+/// * `settings` - The newsletter delivery settings.
+/// * `webhooks` - The outbound webhook settings.
+/// * `vault` - Recovers subscribers' addresses.
+/// # Returns
+/// A Result containing how many deliveries were processed.
 #[tracing::instrument(skip_all)]
-async fn dequeue_task(
+pub async fn drain_delivery_queue(
     pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+    email_client: &EmailClient,
+    links: &SubscriberLinks,
+    settings: &NewsletterSettings,
+    webhooks: &WebhookSettings,
+    vault: &EmailVault,
+) -> Result<DrainSummary, anyhow::Error> {
+    let mut summary = DrainSummary::default();
+    loop {
+        let batch = try_execute_tasks(
+            pool,
+            email_client,
+            links,
+            settings,
+            webhooks,
+            vault,
+            DRAIN_BATCH_SIZE,
+        )
+        .await?;
+        if batch.outcome() == ExecutionOutcome::EmptyQueue {
+            return Ok(summary);
+        }
+        summary.completed += batch.completed;
+        summary.deferred += batch.deferred;
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+async fn dequeue_tasks(
+    pool: &PgPool,
+    max_tasks: usize,
+) -> Result<(PgTransaction, Vec<DeliveryTask>), anyhow::Error> {
     let mut transaction = pool.begin().await?;
-    let r = sqlx::query!(
+    let tasks = sqlx::query_as!(
+        DeliveryTask,
         r#"
-        SELECT issue_id, subscriber_email
-        FROM issue_delivery_queue
-        FOR UPDATE 
+        SELECT issue_id, subscriber_email, n_retries, correlation_id
+        FROM issue_delivery_queue q
+        WHERE
+            execute_after <= now() AND
+            NOT EXISTS (
+                SELECT 1
+                FROM issues
+                WHERE issues.issue_id = q.issue_id AND status = 'paused'
+            )
+        FOR UPDATE
         SKIP LOCKED
-        LIMIT 1
-        "#
+        LIMIT $1
+        "#,
+        i64::try_from(max_tasks).unwrap_or(i64::MAX)
     )
-    .fetch_optional(transaction.as_mut())
+    .fetch_all(transaction.as_mut())
     .await?;
 
-    if let Some(r) = r {
-        Ok(Some((transaction, r.issue_id, r.subscriber_email)))
-    } else {
-        Ok(None)
-    }
+    Ok((transaction, tasks))
+}
+
+/// Keep the provider's message ID of a delivered email, so operators can
+/// look the send up with the provider.
+#[tracing::instrument(skip_all)]
+async fn record_delivery(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    message_id: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_deliveries (issue_id, subscriber_email, message_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (issue_id, subscriber_email) DO UPDATE
+        SET message_id = EXCLUDED.message_id, delivered_at = now()
+        "#,
+        task.issue_id,
+        task.subscriber_email,
+        message_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
 }
 
 #[tracing::instrument(skip_all)]
 async fn delete_task(
-    mut transaction: PgTransaction,
-    issue_id: Uuid,
-    subscriber_email: &str,
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
 ) -> Result<(), anyhow::Error> {
     sqlx::query!(
         r#"
         DELETE FROM issue_delivery_queue
         WHERE issue_id = $1 AND subscriber_email = $2
         "#,
-        issue_id,
+        task.issue_id,
+        task.subscriber_email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn reschedule_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    delay: Duration,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET
+            n_retries = n_retries + 1,
+            execute_after = now() + make_interval(secs => $3)
+        WHERE issue_id = $1 AND subscriber_email = $2
+        "#,
+        task.issue_id,
+        task.subscriber_email,
+        delay.as_secs_f64()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn defer_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    delay: Duration,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET execute_after = now() + make_interval(secs => $3)
+        WHERE issue_id = $1 AND subscriber_email = $2
+        "#,
+        task.issue_id,
+        task.subscriber_email,
+        delay.as_secs_f64()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// How long the subscriber has asked not to receive emails for, if at all.
+#[tracing::instrument(skip_all)]
+async fn remaining_snooze(
+    transaction: &mut PgTransaction,
+    subscriber_email: &str,
+) -> Result<Option<Duration>, anyhow::Error> {
+    let snoozed_until = sqlx::query_scalar!(
+        r#"
+        SELECT snoozed_until AS "snoozed_until!"
+        FROM subscriptions
+        WHERE email = $1 AND snoozed_until > now()
+        "#,
         subscriber_email
     )
+    .fetch_optional(transaction.as_mut())
+    .await?;
+    Ok(snoozed_until.and_then(|t| (t - Utc::now()).to_std().ok()))
+}
+
+#[tracing::instrument(skip_all)]
+async fn dead_letter_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    last_error: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_dead_letter (
+            issue_id, subscriber_email, n_retries, last_error, failed_at
+        )
+        VALUES ($1, $2, $3, $4, now())
+        "#,
+        task.issue_id,
+        task.subscriber_email,
+        task.n_retries + 1,
+        last_error
+    )
     .execute(transaction.as_mut())
     .await?;
+    delete_task(transaction, task).await
+}
+
+/// Take a subscriber with an invalid stored address off the mailing list.
+/// Subscribers who left the list in the meantime keep their status.
+#[tracing::instrument(skip_all)]
+async fn flag_invalid_subscriber(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+) -> Result<(), anyhow::Error> {
+    let Some((subscriber_id, current)) =
+        lock_subscriber_status_by_email(transaction, &task.subscriber_email)
+            .await?
+    else {
+        return Ok(());
+    };
+    match transition_status(current, StatusEvent::FlagInvalid) {
+        Ok(status) if status != current => {
+            set_subscriber_status(transaction, subscriber_id, status).await?;
+        }
+        Ok(_) => {}
+        Err(e) => tracing::info!(
+            error.message = %e,
+            "Not flagging the subscriber as invalid."
+        ),
+    }
+    Ok(())
+}
+
+/// Log a delivery attempt to the recipient's delivery history.
+/// # Arguments
+/// * `transaction` - The transaction holding the delivery task.
+/// * `task` - The delivery that was attempted.
+/// * `outcome` - What happened to the attempt.
+/// * `message_id` - The provider's message ID, if the email was accepted.
+/// * `error` - Why the attempt failed, if it did.
+/// # Returns
+/// A Result indicating success or a sqlx::Error.
+#[tracing::instrument(skip(transaction, task, error))]
+async fn record_attempt(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    outcome: DeliveryOutcome,
+    message_id: Option<&str>,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO delivery_attempts (
+            issue_id, subscriber_email, attempt, outcome, message_id, error
+        )
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        task.issue_id,
+        task.subscriber_email,
+        task.n_retries + 1,
+        outcome.as_str(),
+        message_id,
+        error
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// Bump the per-issue delivery counters.
+/// # Arguments
+/// * `transaction` - The transaction holding the delivery task.
+/// * `issue_id` - The UUID of the newsletter issue.
+/// * `outcome` - What happened to the attempt.
+/// # Returns
+/// A Result indicating success or a sqlx::Error.
+#[tracing::instrument(skip(transaction))]
+async fn record_outcome(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    outcome: DeliveryOutcome,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE issues
+        SET
+            delivery_attempts = delivery_attempts + 1,
+            delivery_retries = delivery_retries + $2,
+            delivery_successes = delivery_successes + $3,
+            delivery_dead_letters = delivery_dead_letters + $4
+        WHERE issue_id = $1
+        "#,
+        issue_id,
+        i32::from(outcome == DeliveryOutcome::Retrying),
+        i32::from(outcome == DeliveryOutcome::Delivered),
+        i32::from(outcome == DeliveryOutcome::DeadLettered)
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// Raise the bounce/complaint alarm for an issue once its rates cross the
+/// configured thresholds, pausing its remaining deliveries if so configured.
+/// The alarm fires at most once per issue.
+/// # Arguments
+/// * `transaction` - The transaction holding the delivery task.
+/// * `issue_id` - The UUID of the newsletter issue.
+/// * `settings` - The alarm thresholds.
+/// * `webhooks` - The outbound webhook settings.
+/// # Returns
+/// A Result indicating success or an anyhow::Error.
+#[tracing::instrument(skip(transaction, settings, webhooks))]
+async fn check_delivery_alarm(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    settings: &DeliveryAlarmSettings,
+    webhooks: &WebhookSettings,
+) -> Result<(), anyhow::Error> {
+    let alarm = sqlx::query!(
+        r#"
+        WITH reported AS (
+            SELECT
+                COUNT(*) FILTER (WHERE event_type = 'bounce') AS bounces,
+                COUNT(*) FILTER (WHERE event_type = 'complaint') AS complaints
+            FROM email_events
+            WHERE issue_id = $1
+        )
+        UPDATE issues
+        SET
+            alarm_raised_at = now(),
+            status = CASE
+                WHEN $5 AND status = 'published' THEN 'paused'
+                ELSE status
+            END
+        FROM reported
+        WHERE
+            issue_id = $1 AND
+            alarm_raised_at IS NULL AND
+            delivery_successes > 0 AND
+            delivery_successes >= $2 AND
+            (
+                reported.bounces::float8 / delivery_successes > $3 OR
+                reported.complaints::float8 / delivery_successes > $4
+            )
+        RETURNING
+            status,
+            reported.bounces::float8 / delivery_successes AS "bounce_rate!",
+            reported.complaints::float8 / delivery_successes
+                AS "complaint_rate!"
+        "#,
+        issue_id,
+        settings.min_deliveries,
+        settings.bounce_rate_threshold,
+        settings.complaint_rate_threshold,
+        settings.auto_pause
+    )
+    .fetch_optional(transaction.as_mut())
+    .await
+    .context("Failed to evaluate the delivery alarm")?;
+
+    let Some(alarm) = alarm else {
+        return Ok(());
+    };
+    let paused = alarm.status == "paused";
+    tracing::error!(
+        %issue_id,
+        bounce_rate = alarm.bounce_rate,
+        complaint_rate = alarm.complaint_rate,
+        paused,
+        "The bounce/complaint rate of a newsletter issue is too high."
+    );
+    enqueue_webhook(
+        transaction,
+        webhooks,
+        WebhookEvent::IssueDeliveryAlarm,
+        serde_json::json!({
+            "issue_id": issue_id,
+            "bounce_rate": alarm.bounce_rate,
+            "complaint_rate": alarm.complaint_rate,
+            "paused": paused,
+        }),
+    )
+    .await
+    .context("Failed to enqueue the `issue.delivery_alarm` webhook")?;
+    Ok(())
+}
+
+/// Email the author a summary once the last delivery of an issue is done.
+/// The summary is sent at most once per issue; it is a no-op while
+/// deliveries of the issue are still queued.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `email_client` - The client used to send emails.
+/// * `issue_id` - The UUID of the newsletter issue.
+/// * `settings` - Where to send the summary.
+/// # Returns
+/// A Result indicating success or an anyhow::Error.
+#[tracing::instrument(skip(pool, email_client, settings))]
+async fn send_completion_summary(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    issue_id: Uuid,
+    settings: &CompletionSummarySettings,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    // Bounces count both deliveries the provider refused for good and
+    // bounces it has reported back so far.
+    let summary = sqlx::query!(
+        r#"
+        UPDATE issues
+        SET completion_summary_sent_at = now()
+        WHERE
+            issue_id = $1 AND
+            completion_summary_sent_at IS NULL AND
+            NOT EXISTS (
+                SELECT 1 FROM issue_delivery_queue q WHERE q.issue_id = $1
+            )
+        RETURNING
+            title,
+            delivery_successes,
+            delivery_dead_letters + (
+                SELECT COUNT(*)
+                FROM email_events e
+                WHERE e.issue_id = $1 AND e.event_type = 'bounce'
+            )::int4 AS "bounced!"
+        "#,
+        issue_id
+    )
+    .fetch_optional(transaction.as_mut())
+    .await
+    .context("Failed to claim the completion summary")?;
+    let Some(summary) = summary else {
+        return Ok(());
+    };
+
+    let recipient = SubscriberEmail::parse(settings.recipient.clone())
+        .map_err(anyhow::Error::msg)
+        .context("Invalid completion summary recipient")?;
+    let text = format!(
+        "Your newsletter '{}' was sent to {} subscriber(s), {} bounced.",
+        summary.title, summary.delivery_successes, summary.bounced
+    );
+    let html = format!("<p>{}</p>", htmlescape::encode_minimal(&text));
+    email_client
+        .send_email(
+            &recipient,
+            &format!("Delivery summary: {}", summary.title),
+            &html,
+            &text,
+            None,
+        )
+        .await
+        .context("Failed to send the completion summary")?;
     transaction.commit().await?;
     Ok(())
 }
@@ -129,7 +931,7 @@ async fn get_issue(
     let issue = sqlx::query_as!(
         NewsletterIssue,
         r#"
-        SELECT title, text_content, html_content
+        SELECT title, text_content, html_content, preheader
         FROM issues
         WHERE issue_id = $1
         "#,
@@ -140,19 +942,325 @@ async fn get_issue(
     Ok(issue)
 }
 
+struct Subscriber {
+    id: Uuid,
+    name: String,
+    encrypted_email: Option<Vec<u8>>,
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_subscriber(
+    pool: &PgPool,
+    subscriber_email: &str,
+) -> Result<Option<Subscriber>, anyhow::Error> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, name, encrypted_email
+        FROM subscriptions
+        WHERE email = $1 AND status = 'confirmed'
+        "#,
+        subscriber_email
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(subscriber)
+}
+
+fn unsubscribe_link(
+    links: &SubscriberLinks,
+    kind: UnsubscribeLink,
+    subscriber_id: Uuid,
+    issue_id: Uuid,
+) -> String {
+    let path = match kind {
+        UnsubscribeLink::OneClick => "/subscriptions/unsubscribe",
+        UnsubscribeLink::ConfirmPage => "/subscriptions/unsubscribe/confirm",
+    };
+    format!(
+        "{}{}?token={}&issue_id={}",
+        links.base_url(),
+        path,
+        links.token(subscriber_id, SubscriberAction::Unsubscribe),
+        issue_id
+    )
+}
+
+fn preferences_link(links: &SubscriberLinks, subscriber_id: Uuid) -> String {
+    format!(
+        "{}/preferences?token={}",
+        links.base_url(),
+        links.token(subscriber_id, SubscriberAction::ManagePreferences)
+    )
+}
+
+/// Enqueue the deliveries for an issue that is going out now.
+/// With send-time optimization on, each delivery is held back until the hour
+/// its recipient usually opens emails in.
+/// # Arguments
+/// * `transaction` - The database transaction publishing the issue.
+/// * `issue_id` - The UUID of the newsletter issue.
+/// * `title` - The title of the newsletter issue.
+/// * `webhooks` - The outbound webhook settings.
+/// * `send_time` - The send-time optimization settings.
+/// # Returns
+/// A Result containing the number of enqueued deliveries.
+#[tracing::instrument(skip(transaction, title, webhooks, send_time))]
+pub async fn enqueue_issue_delivery(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    title: &str,
+    webhooks: &WebhookSettings,
+    send_time: &SendTimeOptimizationSettings,
+) -> Result<u64, anyhow::Error> {
+    let recipient_count = enqueue_delivery_tasks(transaction, issue_id)
+        .await
+        .context("Failed to enqueue delivery tasks")?;
+    tracing::info!(recipient_count, "Enqueued delivery tasks");
+    if send_time.enabled {
+        let held_back = optimize_send_times(transaction, issue_id, send_time)
+            .await
+            .context("Failed to optimize the delivery send times")?;
+        tracing::info!(held_back, "Held back deliveries until open times");
+    }
+    store_recipient_count(transaction, issue_id, recipient_count)
+        .await
+        .context("Failed to store the recipient count")?;
+    enqueue_webhook(
+        transaction,
+        webhooks,
+        WebhookEvent::IssueSent,
+        serde_json::json!({
+            "issue_id": issue_id,
+            "title": title,
+        }),
+    )
+    .await
+    .context("Failed to enqueue the `issue.sent` webhook")?;
+    Ok(recipient_count)
+}
+
+/// Enqueue a delivery task for every confirmed subscriber.
+/// Tasks inherit the correlation id of the request that published the issue.
+/// # Arguments
+/// * `transaction` - The database transaction.
+/// * `issue_id` - The UUID of the newsletter issue.
+/// # Returns
+/// A Result containing the number of enqueued tasks or a sqlx::Error.
+#[tracing::instrument(skip_all)]
+async fn enqueue_delivery_tasks(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let n_enqueued = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            issue_id, subscriber_email, correlation_id
+        )
+        SELECT $1, email, (SELECT correlation_id FROM issues WHERE issue_id = $1)
+        FROM subscriptions
+        WHERE status = 'confirmed'
+        "#,
+        issue_id
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+    Ok(n_enqueued)
+}
+
+/// Record how many subscribers an issue was sent to.
+/// # Arguments
+/// * `transaction` - The database transaction.
+/// * `issue_id` - The UUID of the newsletter issue.
+/// * `recipient_count` - The number of enqueued delivery tasks.
+/// # Returns
+/// A Result indicating success or a sqlx::Error.
+#[tracing::instrument(skip(transaction))]
+async fn store_recipient_count(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    recipient_count: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE issues
+        SET recipient_count = $2
+        WHERE issue_id = $1
+        "#,
+        issue_id,
+        i32::try_from(recipient_count).unwrap_or(i32::MAX)
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// Publish every scheduled issue whose send time has passed.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `webhooks` - The outbound webhook settings.
+/// * `send_time` - The send-time optimization settings.
+/// # Returns
+/// A Result containing the number of issues released for delivery.
+#[tracing::instrument(skip_all, err)]
+pub async fn release_scheduled_issues(
+    pool: &PgPool,
+    webhooks: &WebhookSettings,
+    send_time: &SendTimeOptimizationSettings,
+) -> Result<u64, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let due_issues = sqlx::query!(
+        r#"
+        UPDATE issues
+        SET status = 'published', published_at = now()
+        WHERE issue_id IN (
+            SELECT issue_id
+            FROM issues
+            WHERE status = 'scheduled' AND scheduled_at <= now()
+            FOR UPDATE
+            SKIP LOCKED
+        )
+        RETURNING issue_id, title
+        "#
+    )
+    .fetch_all(transaction.as_mut())
+    .await?;
+
+    for issue in &due_issues {
+        enqueue_issue_delivery(
+            &mut transaction,
+            issue.issue_id,
+            &issue.title,
+            webhooks,
+            send_time,
+        )
+        .await?;
+    }
+    transaction.commit().await?;
+    Ok(due_issues.len() as u64)
+}
+
+enum BatchOutcome {
+    BatchFull,
+    QueueDrained,
+    Failed,
+}
+
+/// Deliver up to `batch_size` pending emails, split across `concurrency`
+/// calls to the email provider made at the same time.
+/// The batch stops early once the queue is drained or a delivery fails.
+async fn deliver_batch(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    links: &SubscriberLinks,
+    settings: &NewsletterSettings,
+    webhooks: &WebhookSettings,
+    worker: &WorkerSettings,
+    vault: &EmailVault,
+) -> BatchOutcome {
+    let share = worker.batch_size.div_ceil(worker.concurrency);
+    // Tasks are dequeued with SKIP LOCKED, so concurrent calls never pick
+    // up the same email.
+    let outcomes = join_all((0..worker.concurrency).map(|_| {
+        try_execute_tasks(
+            pool,
+            email_client,
+            links,
+            settings,
+            webhooks,
+            vault,
+            share,
+        )
+    }))
+    .await;
+    if outcomes.iter().any(Result::is_err) {
+        return BatchOutcome::Failed;
+    }
+    // A call that found fewer deliveries due than it asked for emptied the
+    // queue.
+    if outcomes.iter().flatten().any(|summary| {
+        usize::try_from(summary.processed()).is_ok_and(|n| n < share)
+    }) {
+        return BatchOutcome::QueueDrained;
+    }
+    BatchOutcome::BatchFull
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn worker_loop(
     pool: PgPool,
     email_client: EmailClient,
+    links: SubscriberLinks,
+    settings: NewsletterSettings,
+    webhooks: WebhookSettings,
+    scheduler: SchedulerSettings,
+    onboarding: OnboardingSettings,
+    worker: WorkerSettings,
+    vault: EmailVault,
 ) -> Result<(), anyhow::Error> {
+    // Deliveries are safe to share between instances; releasing scheduled
+    // issues is left to the elected leader.
+    let mut election = LeaderElection::new(pool.clone(), scheduler);
     loop {
-        match try_execute_task(&pool, &email_client).await {
-            Ok(ExecutionOutcome::EmptyQueue) => {
-                actix_web::rt::time::sleep(Duration::from_secs(10)).await;
+        match deliver_batch(
+            &pool,
+            &email_client,
+            &links,
+            &settings,
+            &webhooks,
+            &worker,
+            &vault,
+        )
+        .await
+        {
+            BatchOutcome::BatchFull => {
+                // A long delivery must not hold up onboarding emails and
+                // scheduled issues until it is over.
+                let _ = try_execute_onboarding_task(
+                    &pool,
+                    &email_client,
+                    &onboarding,
+                    &vault,
+                )
+                .await;
+                if election.is_leader().await {
+                    let _ = release_scheduled_issues(
+                        &pool,
+                        &webhooks,
+                        &settings.send_time_optimization,
+                    )
+                    .await;
+                }
+            }
+            BatchOutcome::QueueDrained => {
+                // Onboarding emails go out when no issue is being delivered.
+                let onboarding_outcome = try_execute_onboarding_task(
+                    &pool,
+                    &email_client,
+                    &onboarding,
+                    &vault,
+                )
+                .await;
+                if let Ok(ExecutionOutcome::EmptyQueue) | Err(_) =
+                    onboarding_outcome
+                {
+                    if !election.is_leader().await {
+                        actix_web::rt::time::sleep(worker.poll_interval).await;
+                    } else if let Ok(0) | Err(_) = release_scheduled_issues(
+                        &pool,
+                        &webhooks,
+                        &settings.send_time_optimization,
+                    )
+                    .await
+                    {
+                        actix_web::rt::time::sleep(worker.poll_interval).await;
+                    }
+                }
             }
-            Err(_) => {
+            BatchOutcome::Failed => {
                 actix_web::rt::time::sleep(Duration::from_secs(1)).await;
             }
-            Ok(ExecutionOutcome::TaskCompleted) => {}
         }
     }
 }
@@ -162,5 +1270,19 @@ pub async fn run_worker_until_stopped(
 ) -> Result<(), anyhow::Error> {
     let connection_pool = get_connection_pool(&configuration.database).await?;
     let email_client = configuration.email_client.client();
-    worker_loop(connection_pool, email_client).await
+    worker_loop(
+        connection_pool,
+        email_client,
+        SubscriberLinks::new(
+            configuration.application.base_url,
+            configuration.application.hmac_secret,
+        ),
+        configuration.newsletter,
+        configuration.webhooks,
+        configuration.scheduler,
+        configuration.onboarding,
+        configuration.worker,
+        EmailVault::new(&configuration.subscriber_emails),
+    )
+    .await
 }
@@ -1,11 +1,30 @@
 pub mod authentication;
+pub mod backoff;
+pub mod circuit_breaker;
+pub mod clock_skew;
+pub mod compression;
 pub mod configuration;
+pub mod database;
 pub mod domain;
+pub mod email_capture;
 pub mod email_client;
+pub mod email_vault;
+pub mod feature_flags;
+pub mod form_charset;
 pub mod idempotency;
 pub mod issue_delivery_worker;
+pub mod metrics;
+pub mod migrations;
+pub mod newsletter;
+pub mod onboarding;
+pub mod pending_sweeper;
 pub mod routes;
+pub mod scheduler;
+pub mod send_cap;
+pub mod send_time;
 pub mod session_state;
 pub mod startup;
+pub mod subscriber_token;
 pub mod telemetry;
 pub mod utils;
+pub mod webhooks;
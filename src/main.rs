@@ -7,23 +7,29 @@ use melierx_backend::configuration::get_configuration;
 use melierx_backend::issue_delivery_worker::run_worker_until_stopped;
 use melierx_backend::startup::Application;
 use melierx_backend::telemetry::{get_subscriber, init_subscriber};
+use melierx_backend::webhooks::run_webhook_worker_until_stopped;
 
 #[actix_web::main]
 async fn main() -> anyhow::Result<()> {
     let subscriber =
         get_subscriber("melierx_backend".into(), "info".into(), io::stdout);
-    init_subscriber(subscriber);
+    // Flushes telemetry when main returns, after the exit has been reported.
+    let _telemetry = init_subscriber(subscriber);
 
     let configuration =
         get_configuration().expect("Failed to read configuration.");
 
     let application = Application::build(configuration.clone()).await?;
     let application_task = rt::spawn(application.run_until_stopped());
-    let worker_task = rt::spawn(run_worker_until_stopped(configuration));
+    let worker_task =
+        rt::spawn(run_worker_until_stopped(configuration.clone()));
+    let webhook_worker_task =
+        rt::spawn(run_webhook_worker_until_stopped(configuration));
 
     futures::select! {
         o = application_task.fuse() => report_exit("API", o),
         o = worker_task.fuse() => report_exit("Background worker", o),
+        o = webhook_worker_task.fuse() => report_exit("Webhook worker", o),
     };
 
     Ok(())
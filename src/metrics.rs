@@ -0,0 +1,204 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Process-wide counters for newsletter deliveries.
+/// The delivery worker and the API share a process, so `/metrics` can read
+/// what the worker records without any coordination.
+pub static DELIVERY_METRICS: DeliveryMetrics = DeliveryMetrics::new();
+
+/// What happened to a single delivery attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    /// The email was accepted by the provider.
+    Delivered,
+    /// The attempt failed and the delivery was rescheduled.
+    Retrying,
+    /// The attempt failed and the retry budget is exhausted.
+    DeadLettered,
+}
+
+impl DeliveryOutcome {
+    /// The value stored in the `outcome` column of `delivery_attempts`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Delivered => "delivered",
+            Self::Retrying => "retrying",
+            Self::DeadLettered => "dead_lettered",
+        }
+    }
+}
+
+/// Cheap, lock-free counters for delivery attempts and their outcomes.
+pub struct DeliveryMetrics {
+    attempts: AtomicU64,
+    retries: AtomicU64,
+    successes: AtomicU64,
+    dead_letters: AtomicU64,
+}
+
+impl DeliveryMetrics {
+    const fn new() -> Self {
+        Self {
+            attempts: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            successes: AtomicU64::new(0),
+            dead_letters: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a delivery attempt and its outcome.
+    /// # Arguments
+    /// * `outcome` - What happened to the attempt.
+    pub fn record(&self, outcome: DeliveryOutcome) {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        let counter = match outcome {
+            DeliveryOutcome::Delivered => &self.successes,
+            DeliveryOutcome::Retrying => &self.retries,
+            DeliveryOutcome::DeadLettered => &self.dead_letters,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render the counters in the Prometheus text exposition format.
+    /// # Arguments
+    /// * `out` - The buffer to append to.
+    pub fn render(&self, out: &mut String) {
+        let counters = [
+            ("attempts", "Delivery attempts made.", &self.attempts),
+            ("retries", "Failed deliveries rescheduled.", &self.retries),
+            ("successes", "Deliveries accepted.", &self.successes),
+            (
+                "dead_letters",
+                "Deliveries abandoned after the retry budget.",
+                &self.dead_letters,
+            ),
+        ];
+        for (name, help, counter) in counters {
+            render_metric(
+                out,
+                &format!("newsletter_delivery_{name}_total"),
+                "counter",
+                help,
+                counter.load(Ordering::Relaxed) as i64,
+            );
+        }
+    }
+}
+
+/// Process-wide counters for idempotent requests.
+pub static IDEMPOTENCY_METRICS: IdempotencyMetrics = IdempotencyMetrics::new();
+
+/// How an idempotency key was resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdempotencyOutcome {
+    /// The key was new and the request was processed.
+    Started,
+    /// The key was seen before and the saved response was returned.
+    Replayed,
+}
+
+/// Counters for idempotency cache misses and hits.
+/// A high replay rate points at clients retrying in a storm.
+pub struct IdempotencyMetrics {
+    started: AtomicU64,
+    replayed: AtomicU64,
+}
+
+impl IdempotencyMetrics {
+    const fn new() -> Self {
+        Self {
+            started: AtomicU64::new(0),
+            replayed: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a resolved idempotency key.
+    /// # Arguments
+    /// * `outcome` - Whether the request was processed or replayed.
+    pub fn record(&self, outcome: IdempotencyOutcome) {
+        let counter = match outcome {
+            IdempotencyOutcome::Started => &self.started,
+            IdempotencyOutcome::Replayed => &self.replayed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render the counters in the Prometheus text exposition format.
+    /// # Arguments
+    /// * `out` - The buffer to append to.
+    pub fn render(&self, out: &mut String) {
+        render_metric(
+            out,
+            "idempotency_requests_started_total",
+            "counter",
+            "Requests processed under a new idempotency key.",
+            self.started.load(Ordering::Relaxed) as i64,
+        );
+        render_metric(
+            out,
+            "idempotency_responses_replayed_total",
+            "counter",
+            "Saved responses returned for a repeated idempotency key.",
+            self.replayed.load(Ordering::Relaxed) as i64,
+        );
+    }
+}
+
+/// Append a single metric in the Prometheus text exposition format.
+/// # Arguments
+/// * `out` - The buffer to append to.
+/// * `name` - The metric name.
+/// * `kind` - The metric type, e.g. `counter` or `gauge`.
+/// * `help` - A one-line description of the metric.
+/// * `value` - The current value.
+pub fn render_metric(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    value: i64,
+) {
+    // Writing to a String cannot fail.
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DeliveryMetrics, DeliveryOutcome, IdempotencyMetrics,
+        IdempotencyOutcome,
+    };
+
+    #[test]
+    fn every_outcome_counts_as_an_attempt() {
+        let metrics = DeliveryMetrics::new();
+        metrics.record(DeliveryOutcome::Retrying);
+        metrics.record(DeliveryOutcome::Delivered);
+
+        let mut rendered = String::new();
+        metrics.render(&mut rendered);
+
+        assert!(rendered.contains("newsletter_delivery_attempts_total 2\n"));
+        assert!(rendered.contains("newsletter_delivery_retries_total 1\n"));
+        assert!(rendered.contains("newsletter_delivery_successes_total 1\n"));
+        assert!(
+            rendered.contains("newsletter_delivery_dead_letters_total 0\n")
+        );
+    }
+
+    #[test]
+    fn idempotency_hits_and_misses_are_counted_apart() {
+        let metrics = IdempotencyMetrics::new();
+        metrics.record(IdempotencyOutcome::Started);
+        metrics.record(IdempotencyOutcome::Replayed);
+        metrics.record(IdempotencyOutcome::Replayed);
+
+        let mut rendered = String::new();
+        metrics.render(&mut rendered);
+
+        assert!(rendered.contains("idempotency_requests_started_total 1\n"));
+        assert!(rendered.contains("idempotency_responses_replayed_total 2\n"));
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use sqlx::migrate::Migrator;
+
+/// The migrations in `migrations/`, embedded at compile time.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// What to do at startup about migrations that were not applied yet.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationMode {
+    /// Leave the schema alone.
+    #[default]
+    Skip,
+    /// Refuse to start while any migration is pending, without applying it.
+    Check,
+    /// Apply pending migrations. Meant for local development.
+    Apply,
+}
+
+/// Bring the database schema in line with the migrations this build embeds,
+/// as far as `mode` allows.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `mode` - Whether to check or apply pending migrations.
+/// # Returns
+/// An error naming the pending migrations in `Check` mode, or if they could
+/// not be applied in `Apply` mode.
+#[tracing::instrument(skip(pool))]
+pub async fn prepare_schema(
+    pool: &PgPool,
+    mode: MigrationMode,
+) -> Result<(), anyhow::Error> {
+    match mode {
+        MigrationMode::Skip => Ok(()),
+        MigrationMode::Check => {
+            let pending = pending_migrations(pool)
+                .await
+                .context("Failed to list the applied migrations")?;
+            if pending.is_empty() {
+                return Ok(());
+            }
+            anyhow::bail!(
+                "The database schema is out of date. Pending migrations: {}. \
+                Apply them before starting the application.",
+                pending.join(", ")
+            )
+        }
+        MigrationMode::Apply => MIGRATOR
+            .run(pool)
+            .await
+            .context("Failed to apply the pending migrations"),
+    }
+}
+
+/// The embedded migrations not applied to the database yet, as
+/// `<version>_<description>`.
+async fn pending_migrations(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    // A database never migrated has no bookkeeping table yet.
+    let is_tracked: bool = sqlx::query_scalar(
+        "SELECT to_regclass('_sqlx_migrations') IS NOT NULL",
+    )
+    .fetch_one(pool)
+    .await?;
+    let applied: Vec<i64> = if is_tracked {
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await?
+    } else {
+        Vec::new()
+    };
+    Ok(MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .filter(|m| !applied.contains(&m.version))
+        .map(|m| format!("{}_{}", m.version, m.description.replace(' ', "_")))
+        .collect())
+}
@@ -0,0 +1,73 @@
+use super::render::attribute;
+
+/// Collect the link targets in an HTML body that use plain `http`.
+/// Targets are returned decoded, in document order, once each.
+/// # Arguments
+/// * `html` - The HTML body, as stored after sanitizing.
+/// # Returns
+/// The `href` values starting with `http://`.
+pub fn insecure_links(html: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let Some(length) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + length];
+        rest = &rest[start + length + 1..];
+        let Some(href) = attribute(tag, "href") else {
+            continue;
+        };
+        let is_insecure = href
+            .trim()
+            .get(..7)
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("http://"));
+        if is_insecure && !links.contains(&href) {
+            links.push(href);
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::insecure_links;
+
+    #[test]
+    fn http_links_are_found() {
+        let html = r#"<p><a href="http://example.com/a">A</a></p>"#;
+
+        assert_eq!(insecure_links(html), vec!["http://example.com/a"]);
+    }
+
+    #[test]
+    fn https_and_mailto_links_are_fine() {
+        let html = r#"<a href="https://example.com">A</a>
+            <a href="mailto:editor@melierx.com">Mail</a>
+            <a href="/archive">Archive</a>"#;
+
+        assert!(insecure_links(html).is_empty());
+    }
+
+    #[test]
+    fn the_scheme_is_matched_case_insensitively() {
+        let html = r#"<a href="HTTP://example.com">A</a>"#;
+
+        assert_eq!(insecure_links(html), vec!["HTTP://example.com"]);
+    }
+
+    #[test]
+    fn each_link_is_reported_once() {
+        let html = r#"<a href="http://example.com?a=1&amp;b=2">A</a>
+            <a href="http://example.com?a=1&amp;b=2">B</a>"#;
+
+        assert_eq!(insecure_links(html), vec!["http://example.com?a=1&b=2"]);
+    }
+
+    #[test]
+    fn link_text_mentioning_http_is_ignored() {
+        let html = "<p>Visit http://example.com</p>";
+
+        assert!(insecure_links(html).is_empty());
+    }
+}
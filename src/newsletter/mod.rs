@@ -0,0 +1,10 @@
+mod links;
+mod render;
+mod sanitize;
+
+pub use links::insecure_links;
+pub use render::{
+    NewsletterIssue, Recipient, RenderOptions, RenderedEmail,
+    render_for_recipient,
+};
+pub use sanitize::sanitize_html;
@@ -0,0 +1,588 @@
+/// A newsletter issue as it is stored, before it is tailored to a recipient.
+pub struct NewsletterIssue {
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    /// The preview text shown after the subject in the inbox.
+    pub preheader: Option<String>,
+}
+
+/// Longest preheader derived from the body, in characters. Inbox previews
+/// are cut off well before this anyway.
+const DERIVED_PREHEADER_MAX_CHARS: usize = 100;
+
+/// The subscriber an issue is rendered for.
+pub struct Recipient<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+}
+
+/// Per-delivery rendering options.
+#[derive(Default)]
+pub struct RenderOptions {
+    /// Appended as a footer to both bodies when present.
+    pub unsubscribe_link: Option<String>,
+    /// The subscriber's preferences page, linked after the unsubscribe
+    /// footer when present.
+    pub preferences_link: Option<String>,
+    /// Use the first line of the text body as the preheader of issues that
+    /// do not set one.
+    pub derive_preheader: bool,
+}
+
+/// An email ready to be handed to the email client.
+#[derive(Debug, PartialEq)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+}
+
+/// Render an issue for a single recipient.
+/// The steps run in a fixed order:
+/// 1. `{{name}}` and `{{email}}` tokens are replaced in the subject and both
+///    bodies (HTML-escaped in the HTML body). Unknown tokens are left as is.
+/// 2. An issue without plain text content gets one derived from its HTML.
+/// 3. The preheader is prepended to the HTML body as hidden preview text.
+///    One set on the issue also becomes the first line of the text body; one
+///    derived from the text body is not repeated there.
+/// 4. The unsubscribe footer, if any, is appended to both bodies, followed
+///    by the preferences link, if any.
+///
+/// This function does no IO, so everything that ends up in a subscriber's
+/// inbox can be tested in isolation.
+/// # Arguments
+/// * `issue` - The stored newsletter issue.
+/// * `recipient` - The subscriber the email is for.
+/// * `options` - Per-delivery rendering options.
+/// # Returns
+/// The rendered subject and bodies.
+pub fn render_for_recipient(
+    issue: &NewsletterIssue,
+    recipient: &Recipient,
+    options: &RenderOptions,
+) -> RenderedEmail {
+    let subject = personalize(&issue.title, recipient, |v| v.to_owned());
+    let mut html_content = personalize(&issue.html_content, recipient, |v| {
+        htmlescape::encode_minimal(v)
+    });
+    let mut text_content = if issue.text_content.trim().is_empty() {
+        text_from_html(&html_content)
+    } else {
+        personalize(&issue.text_content, recipient, |v| v.to_owned())
+    };
+    let preheader = issue
+        .preheader
+        .as_deref()
+        .map(|p| personalize(p.trim(), recipient, |v| v.to_owned()))
+        .filter(|p| !p.is_empty());
+    if let Some(preheader) = &preheader {
+        text_content = format!("{}\n\n{}", preheader, text_content);
+    }
+    let preheader = preheader.or_else(|| {
+        options
+            .derive_preheader
+            .then(|| derive_preheader(&text_content))
+            .flatten()
+    });
+    if let Some(preheader) = preheader {
+        html_content = format!(
+            "<div style=\"display:none;max-height:0;overflow:hidden;\
+            mso-hide:all\">{}</div>{}",
+            htmlescape::encode_minimal(&preheader),
+            html_content
+        );
+    }
+    if let Some(link) = &options.unsubscribe_link {
+        html_content
+            .push_str(&format!("<p><a href=\"{}\">Unsubscribe</a></p>", link));
+        text_content.push_str(&format!("\n\nUnsubscribe: {}", link));
+    }
+    if let Some(link) = &options.preferences_link {
+        html_content.push_str(&format!(
+            "<p><a href=\"{}\">Manage your preferences</a></p>",
+            link
+        ));
+        text_content.push_str(&format!("\nManage your preferences: {}", link));
+    }
+    RenderedEmail {
+        // A subscriber name must not smuggle line breaks into the subject.
+        subject: subject
+            .split(['\r', '\n'])
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" "),
+        html_content,
+        text_content,
+    }
+}
+
+/// Replace personalization tokens in a single pass, so a substituted value
+/// is never expanded again.
+fn personalize(
+    template: &str,
+    recipient: &Recipient,
+    encode: impl Fn(&str) -> String,
+) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + length + 2;
+        output.push_str(&rest[..start]);
+        match rest[start + 2..end - 2].trim() {
+            "name" => output.push_str(&encode(recipient.name)),
+            "email" => output.push_str(&encode(recipient.email)),
+            _ => output.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// The first non-empty line of the text body, shortened on a word boundary.
+fn derive_preheader(text: &str) -> Option<String> {
+    let line = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+    if line.chars().count() <= DERIVED_PREHEADER_MAX_CHARS {
+        return Some(line.to_owned());
+    }
+    let cut: String = line.chars().take(DERIVED_PREHEADER_MAX_CHARS).collect();
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(end) => cut[..end].trim_end(),
+        None => &cut,
+    };
+    Some(format!("{}…", cut))
+}
+
+/// Derive a plain text body from HTML: block elements become line breaks,
+/// list items become dashes, links keep their target, entities are decoded
+/// and scripts and styles are dropped.
+fn text_from_html(html: &str) -> String {
+    let mut text = String::new();
+    // The target of the open link and where its label starts in `text`.
+    let mut link: Option<(String, usize)> = None;
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        push_text(&mut text, &rest[..start]);
+        let Some(length) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[start + 1..start + length];
+        rest = &rest[start + length + 1..];
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "script" | "style" if !closing => {
+                rest = skip_element(rest, &name);
+            }
+            "br" | "tr" => text.push('\n'),
+            "li" if !closing => text.push_str("\n- "),
+            "p" | "div" | "blockquote" | "ul" | "ol" | "table" | "hr"
+            | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => text.push_str("\n\n"),
+            "a" if !closing => {
+                link = attribute(tag, "href").map(|href| (href, text.len()));
+            }
+            "a" => {
+                if let Some((href, label_start)) = link.take()
+                    && text[label_start..].trim() != href
+                {
+                    text.push_str(&format!(" ({})", href));
+                }
+            }
+            _ => {}
+        }
+    }
+    push_text(&mut text, rest);
+    tidy(&text)
+}
+
+/// Append decoded text, collapsing runs of whitespace into a single space.
+fn push_text(text: &mut String, raw: &str) {
+    for c in decode(raw).chars() {
+        if !c.is_whitespace() {
+            text.push(c);
+        } else if !text.is_empty() && !text.ends_with([' ', '\n']) {
+            text.push(' ');
+        }
+    }
+}
+
+/// Skip past the closing tag of a `script` or `style` element.
+fn skip_element<'a>(html: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{}", name);
+    html.to_ascii_lowercase()
+        .find(&closing)
+        .and_then(|start| {
+            let after = &html[start..];
+            after.find('>').map(|end| &after[end + 1..])
+        })
+        .unwrap_or_default()
+}
+
+pub(super) fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lowercase = tag.to_ascii_lowercase();
+    let needle = format!("{}=", name);
+    let (start, _) = lowercase
+        .match_indices(&needle)
+        .find(|(i, _)| tag[..*i].ends_with(char::is_whitespace))?;
+    let value = &tag[start + needle.len()..];
+    let value = match value.chars().next()? {
+        quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+        _ => value.split(char::is_whitespace).next()?,
+    };
+    Some(decode(value))
+}
+
+fn decode(raw: &str) -> String {
+    htmlescape::decode_html(raw).unwrap_or_else(|_| raw.to_owned())
+}
+
+/// Trim every line and keep at most one blank line between paragraphs.
+fn tidy(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() && lines.last().is_none_or(|l| l.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    if lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        NewsletterIssue, Recipient, RenderOptions, render_for_recipient,
+        text_from_html,
+    };
+
+    const RECIPIENT: Recipient = Recipient {
+        name: "Ursula",
+        email: "ursula@example.com",
+    };
+
+    fn issue(title: &str, html: &str, text: &str) -> NewsletterIssue {
+        NewsletterIssue {
+            title: title.into(),
+            html_content: html.into(),
+            text_content: text.into(),
+            preheader: None,
+        }
+    }
+
+    fn with_link() -> RenderOptions {
+        RenderOptions {
+            unsubscribe_link: Some("https://example.com/unsubscribe".into()),
+            ..Default::default()
+        }
+    }
+
+    fn deriving_preheaders() -> RenderOptions {
+        RenderOptions {
+            derive_preheader: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_preheader_is_hidden_preview_text_and_the_first_text_line() {
+        let issue = NewsletterIssue {
+            preheader: Some("Big news, {{name}} & more".into()),
+            ..issue("Title", "<p>Body</p>", "Body")
+        };
+
+        let email =
+            render_for_recipient(&issue, &RECIPIENT, &deriving_preheaders());
+
+        assert_eq!(
+            email.html_content,
+            "<div style=\"display:none;max-height:0;overflow:hidden;\
+            mso-hide:all\">Big news, Ursula &amp; more</div><p>Body</p>"
+        );
+        assert_eq!(email.text_content, "Big news, Ursula & more\n\nBody");
+    }
+
+    #[test]
+    fn a_missing_preheader_is_derived_from_the_first_text_line() {
+        let issue = issue("Title", "<p>Body</p>", "\n  First line\nSecond");
+
+        let email =
+            render_for_recipient(&issue, &RECIPIENT, &deriving_preheaders());
+
+        assert!(email.html_content.starts_with(
+            "<div style=\"display:none;max-height:0;overflow:hidden;\
+            mso-hide:all\">First line</div>"
+        ));
+        assert_eq!(email.text_content, "\n  First line\nSecond");
+    }
+
+    #[test]
+    fn derived_preheaders_are_shortened_on_a_word_boundary() {
+        let text = "word ".repeat(40);
+
+        let preheader = super::derive_preheader(&text).unwrap();
+
+        assert!(preheader.chars().count() <= 101);
+        assert!(preheader.ends_with("word…"));
+    }
+
+    #[test]
+    fn no_preheader_is_added_unless_set_or_derived() {
+        let issue = issue("Title", "<p>Body</p>", "Body");
+
+        let email =
+            render_for_recipient(&issue, &RECIPIENT, &Default::default());
+
+        assert_eq!(email.html_content, "<p>Body</p>");
+    }
+
+    #[test]
+    fn tokens_are_replaced_in_the_subject_and_both_bodies() {
+        let issue = issue(
+            "Hi {{name}}",
+            "<p>Hello {{ name }}</p>",
+            "Sent to {{email}}",
+        );
+
+        let email =
+            render_for_recipient(&issue, &RECIPIENT, &Default::default());
+
+        assert_eq!(email.subject, "Hi Ursula");
+        assert_eq!(email.html_content, "<p>Hello Ursula</p>");
+        assert_eq!(email.text_content, "Sent to ursula@example.com");
+    }
+
+    #[test]
+    fn token_values_are_escaped_in_the_html_body_only() {
+        let recipient = Recipient {
+            name: "<b>Tom & Jerry</b>",
+            email: "tom@example.com",
+        };
+        let issue = issue("{{name}}", "<p>{{name}}</p>", "{{name}}");
+
+        let email =
+            render_for_recipient(&issue, &recipient, &Default::default());
+
+        assert_eq!(
+            email.html_content,
+            "<p>&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;</p>"
+        );
+        assert_eq!(email.text_content, "<b>Tom & Jerry</b>");
+        assert_eq!(email.subject, "<b>Tom & Jerry</b>");
+    }
+
+    #[test]
+    fn unknown_and_unterminated_tokens_are_left_untouched() {
+        let issue = issue("t", "", "{{unknown}} and {{name");
+
+        let email =
+            render_for_recipient(&issue, &RECIPIENT, &Default::default());
+
+        assert_eq!(email.text_content, "{{unknown}} and {{name");
+    }
+
+    #[test]
+    fn substituted_values_are_not_expanded_again() {
+        let recipient = Recipient {
+            name: "{{email}}",
+            email: "ursula@example.com",
+        };
+        let issue = issue("t", "", "Hi {{name}}");
+
+        let email =
+            render_for_recipient(&issue, &recipient, &Default::default());
+
+        assert_eq!(email.text_content, "Hi {{email}}");
+    }
+
+    #[test]
+    fn a_name_cannot_add_line_breaks_to_the_subject() {
+        let recipient = Recipient {
+            name: "Ursula\r\nBcc: victim@example.com",
+            email: "ursula@example.com",
+        };
+        let issue = issue("Hi {{name}}", "", "text");
+
+        let email =
+            render_for_recipient(&issue, &recipient, &Default::default());
+
+        assert_eq!(email.subject, "Hi Ursula Bcc: victim@example.com");
+    }
+
+    #[test]
+    fn the_unsubscribe_footer_is_appended_to_both_bodies() {
+        let issue = issue("t", "<p>Body</p>", "Body");
+
+        let email = render_for_recipient(&issue, &RECIPIENT, &with_link());
+
+        assert_eq!(
+            email.html_content,
+            "<p>Body</p><p><a href=\"https://example.com/unsubscribe\">\
+            Unsubscribe</a></p>"
+        );
+        assert_eq!(
+            email.text_content,
+            "Body\n\nUnsubscribe: https://example.com/unsubscribe"
+        );
+    }
+
+    #[test]
+    fn the_preferences_link_follows_the_unsubscribe_footer() {
+        let issue = issue("t", "<p>Body</p>", "Body");
+        let options = RenderOptions {
+            preferences_link: Some("https://example.com/preferences".into()),
+            ..with_link()
+        };
+
+        let email = render_for_recipient(&issue, &RECIPIENT, &options);
+
+        assert_eq!(
+            email.html_content,
+            "<p>Body</p><p><a href=\"https://example.com/unsubscribe\">\
+            Unsubscribe</a></p><p><a href=\"https://example.com/preferences\">\
+            Manage your preferences</a></p>"
+        );
+        assert_eq!(
+            email.text_content,
+            "Body\n\nUnsubscribe: https://example.com/unsubscribe\n\
+            Manage your preferences: https://example.com/preferences"
+        );
+    }
+
+    #[test]
+    fn there_is_no_footer_without_an_unsubscribe_link() {
+        let issue = issue("t", "<p>Body</p>", "Body");
+
+        let email =
+            render_for_recipient(&issue, &RECIPIENT, &Default::default());
+
+        assert_eq!(email.html_content, "<p>Body</p>");
+        assert_eq!(email.text_content, "Body");
+    }
+
+    #[test]
+    fn explicit_text_content_is_not_replaced_by_the_html() {
+        let issue = issue("t", "<p>From HTML</p>", "Hand written");
+
+        let email =
+            render_for_recipient(&issue, &RECIPIENT, &Default::default());
+
+        assert_eq!(email.text_content, "Hand written");
+    }
+
+    #[test]
+    fn blank_text_content_is_derived_from_the_personalized_html() {
+        let issue = issue("t", "<p>Hi {{name}} &amp; friends</p>", " \n ");
+
+        let email = render_for_recipient(&issue, &RECIPIENT, &with_link());
+
+        // The footer is added once, after the derivation.
+        assert_eq!(
+            email.text_content,
+            "Hi Ursula & friends\n\n\
+            Unsubscribe: https://example.com/unsubscribe"
+        );
+    }
+
+    #[test]
+    fn derived_text_keeps_escaped_names_readable() {
+        let recipient = Recipient {
+            name: "Tom & Jerry",
+            email: "tom@example.com",
+        };
+        let issue = issue("t", "<p>Hi {{name}}</p>", "");
+
+        let email =
+            render_for_recipient(&issue, &recipient, &Default::default());
+
+        assert_eq!(email.text_content, "Hi Tom & Jerry");
+    }
+
+    #[test]
+    fn paragraphs_and_headings_are_separated_by_a_blank_line() {
+        let text =
+            text_from_html("<h1>Title</h1><p>First</p><div>Second</div>");
+
+        assert_eq!(text, "Title\n\nFirst\n\nSecond");
+    }
+
+    #[test]
+    fn line_breaks_start_a_new_line() {
+        assert_eq!(text_from_html("one<br>two<BR/>three"), "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn list_items_become_dashes() {
+        let text =
+            text_from_html("<p>Items:</p><ul><li>One</li><li> Two </li></ul>");
+
+        assert_eq!(text, "Items:\n\n- One\n- Two");
+    }
+
+    #[test]
+    fn links_keep_their_target() {
+        let text = text_from_html(
+            "Read <a class=\"x\" href=\"https://example.com/a?b=1&amp;c=2\">\
+            more</a>.",
+        );
+
+        assert_eq!(text, "Read more (https://example.com/a?b=1&c=2).");
+    }
+
+    #[test]
+    fn a_link_labelled_with_its_target_is_not_repeated() {
+        let text = text_from_html(
+            "<a href='https://example.com'>https://example.com</a>",
+        );
+
+        assert_eq!(text, "https://example.com");
+    }
+
+    #[test]
+    fn only_the_href_attribute_is_used_as_a_link_target() {
+        let text =
+            text_from_html("<a data-href=\"nope\" href=\"yes\">label</a>");
+
+        assert_eq!(text, "label (yes)");
+    }
+
+    #[test]
+    fn scripts_and_styles_are_dropped() {
+        let text = text_from_html(
+            "<style>p { color: red; }</style><p>Visible</p>\
+            <SCRIPT>alert('hidden')</SCRIPT>",
+        );
+
+        assert_eq!(text, "Visible");
+    }
+
+    #[test]
+    fn whitespace_is_collapsed_and_entities_are_decoded() {
+        let text = text_from_html("  <p>  a \n\t b&nbsp;&lt;c&gt; </p>  ");
+
+        assert_eq!(text, "a b <c>");
+    }
+
+    #[test]
+    fn an_unclosed_tag_is_dropped() {
+        assert_eq!(text_from_html("text <p"), "text");
+    }
+
+    #[test]
+    fn plain_text_passes_through() {
+        assert_eq!(text_from_html("just text"), "just text");
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use ammonia::Builder;
+
+use crate::configuration::HtmlSanitizerSettings;
+
+/// Strip everything the policy does not allow from newsletter HTML.
+/// # Arguments
+/// * `html` - The HTML body as submitted.
+/// * `policy` - The tags, attributes and URL schemes to keep.
+/// # Returns
+/// The sanitized HTML.
+pub fn sanitize_html(html: &str, policy: &HtmlSanitizerSettings) -> String {
+    fn strs(set: &HashSet<String>) -> HashSet<&str> {
+        set.iter().map(String::as_str).collect()
+    }
+    let tags = strs(&policy.tags);
+    // ammonia refuses a tag that is both allowed and dropped with its content.
+    let clean_content_tags = ["script", "style"]
+        .into_iter()
+        .filter(|tag| !tags.contains(tag))
+        .collect();
+    Builder::empty()
+        .tags(tags)
+        .clean_content_tags(clean_content_tags)
+        .generic_attributes(strs(&policy.generic_attributes))
+        .tag_attributes(
+            policy
+                .tag_attributes
+                .iter()
+                .map(|(tag, attributes)| (tag.as_str(), strs(attributes)))
+                .collect(),
+        )
+        .url_schemes(strs(&policy.url_schemes))
+        .clean(html)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::HtmlSanitizerSettings;
+
+    use super::sanitize_html;
+
+    fn sanitize(html: &str) -> String {
+        sanitize_html(html, &HtmlSanitizerSettings::default())
+    }
+
+    #[test]
+    fn scripts_are_removed_with_their_content() {
+        let html = sanitize("<p>Hello</p><script>alert('hi')</script>");
+
+        assert_eq!(html, "<p>Hello</p>");
+    }
+
+    #[test]
+    fn event_handlers_are_removed() {
+        let html = sanitize(r#"<p onclick="steal()">Hello</p>"#);
+
+        assert_eq!(html, "<p>Hello</p>");
+    }
+
+    #[test]
+    fn javascript_links_are_removed() {
+        let html = sanitize(r#"<a href="javascript:steal()">Click</a>"#);
+
+        assert!(!html.contains("javascript"));
+        assert!(html.contains("Click"));
+    }
+
+    #[test]
+    fn a_styled_table_survives() {
+        let input = concat!(
+            r#"<table width="100%" cellpadding="0" style="border:0">"#,
+            "<tbody><tr>",
+            r#"<td align="center" style="color: #333333; padding: 8px">"#,
+            "Hello</td>",
+            "</tr></tbody></table>"
+        );
+
+        assert_eq!(sanitize(input), input);
+    }
+
+    #[test]
+    fn links_and_images_survive() {
+        let html = sanitize(concat!(
+            r#"<a href="https://example.com">Read more</a>"#,
+            r#"<img src="https://example.com/logo.png" alt="Logo">"#
+        ));
+
+        assert!(html.contains(r#"href="https://example.com""#));
+        assert!(html.contains(r#"src="https://example.com/logo.png""#));
+    }
+
+    #[test]
+    fn tags_outside_the_allowlist_keep_their_text() {
+        let policy = HtmlSanitizerSettings {
+            tags: ["p".to_string()].into(),
+            ..HtmlSanitizerSettings::default()
+        };
+
+        let html = sanitize_html("<p><b>Bold</b> move</p>", &policy);
+
+        assert_eq!(html, "<p>Bold move</p>");
+    }
+
+    #[test]
+    fn style_blocks_can_be_allowed() {
+        let mut policy = HtmlSanitizerSettings::default();
+        policy.tags.insert("style".into());
+
+        let html = sanitize_html("<style>p { color: red; }</style>", &policy);
+
+        assert_eq!(html, "<style>p { color: red; }</style>");
+    }
+}
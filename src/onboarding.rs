@@ -0,0 +1,313 @@
+use std::time::Duration;
+
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{Span, field::display};
+use uuid::Uuid;
+
+use crate::configuration::OnboardingSettings;
+use crate::domain::SubscriberEmail;
+use crate::email_client::{EmailClient, EmailError};
+use crate::email_vault::EmailVault;
+use crate::issue_delivery_worker::ExecutionOutcome;
+use crate::newsletter::{
+    NewsletterIssue, Recipient, RenderOptions, render_for_recipient,
+};
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+struct OnboardingTask {
+    subscriber_id: Uuid,
+    step: String,
+    n_retries: i16,
+    email: String,
+    encrypted_email: Option<Vec<u8>>,
+    name: String,
+}
+
+/// Enqueue every step of the onboarding sequence for a subscriber, each at
+/// its configured delay from now.
+/// Steps the subscriber already has are left alone unless `restart` is set,
+/// in which case they are rescheduled to be sent again.
+/// # Arguments
+/// * `transaction` - The database transaction to enqueue in.
+/// * `subscriber_id` - The UUID of the subscriber.
+/// * `settings` - The onboarding sequence definition.
+/// * `restart` - Whether to send steps that were already sent again.
+/// # Returns
+/// A Result containing the number of steps (re)scheduled.
+#[tracing::instrument(skip(transaction, settings))]
+pub async fn schedule_onboarding(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    settings: &OnboardingSettings,
+    restart: bool,
+) -> Result<u64, sqlx::Error> {
+    let keys: Vec<String> =
+        settings.steps.iter().map(|s| s.key.clone()).collect();
+    let delays: Vec<i32> =
+        settings.steps.iter().map(|s| s.delay_hours).collect();
+    let result = if restart {
+        sqlx::query!(
+            r#"
+            INSERT INTO onboarding_queue (subscriber_id, step, execute_after)
+            SELECT $1, step, now() + make_interval(hours => delay_hours)
+            FROM UNNEST($2::text[], $3::int4[]) AS s(step, delay_hours)
+            ON CONFLICT (subscriber_id, step) DO UPDATE
+            SET
+                execute_after = EXCLUDED.execute_after,
+                n_retries = 0,
+                sent_at = NULL
+            "#,
+            subscriber_id,
+            &keys,
+            &delays
+        )
+        .execute(transaction.as_mut())
+        .await?
+    } else {
+        sqlx::query!(
+            r#"
+            INSERT INTO onboarding_queue (subscriber_id, step, execute_after)
+            SELECT $1, step, now() + make_interval(hours => delay_hours)
+            FROM UNNEST($2::text[], $3::int4[]) AS s(step, delay_hours)
+            ON CONFLICT DO NOTHING
+            "#,
+            subscriber_id,
+            &keys,
+            &delays
+        )
+        .execute(transaction.as_mut())
+        .await?
+    };
+    Ok(result.rows_affected())
+}
+
+/// Send at most one due onboarding email.
+/// Only confirmed subscribers are emailed. Failed sends are retried after the
+/// email client's retry backoff and dropped after `max_delivery_retries`
+/// attempts; steps that were removed from the configuration are dropped too.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `email_client` - The client used to send emails.
+/// * `settings` - The onboarding sequence definition.
+/// * `vault` - Recovers the subscriber's address.
+/// # Returns
+/// Whether a task was processed or the queue was empty.
+#[tracing::instrument(
+    skip_all,
+    fields(
+        subscriber_id = tracing::field::Empty,
+        step = tracing::field::Empty,
+    ),
+    err
+)]
+pub async fn try_execute_onboarding_task(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    settings: &OnboardingSettings,
+    vault: &EmailVault,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let Some((mut transaction, task)) = dequeue_task(pool).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current()
+        .record("subscriber_id", display(task.subscriber_id))
+        .record("step", display(&task.step));
+
+    let Some(step) = settings.step(&task.step) else {
+        tracing::warn!("The onboarding step is no longer configured.");
+        delete_task(&mut transaction, &task).await?;
+        transaction.commit().await?;
+        return Ok(ExecutionOutcome::TaskCompleted);
+    };
+    let address = vault.reveal(&task.email, task.encrypted_email.as_deref())?;
+    let email = match SubscriberEmail::parse(address) {
+        Ok(email) => email,
+        Err(e) => {
+            tracing::error!(
+                error.message = %e,
+                "Skipping an onboarding email. \
+                The subscriber's stored contact details are invalid.",
+            );
+            delete_task(&mut transaction, &task).await?;
+            transaction.commit().await?;
+            return Ok(ExecutionOutcome::TaskCompleted);
+        }
+    };
+    let rendered = render_for_recipient(
+        &NewsletterIssue {
+            title: step.subject.clone(),
+            html_content: step.html_content.clone(),
+            text_content: step.text_content.clone(),
+            preheader: None,
+        },
+        &Recipient {
+            name: &task.name,
+            email: email.as_ref(),
+        },
+        &RenderOptions::default(),
+    );
+    match email_client
+        .send_email(
+            &email,
+            &rendered.subject,
+            &rendered.html_content,
+            &rendered.text_content,
+            None,
+        )
+        .await
+    {
+        Ok(_) => mark_sent(&mut transaction, &task).await?,
+        Err(EmailError::CircuitOpen) => {
+            defer_task(
+                &mut transaction,
+                &task,
+                email_client.circuit_breaker_cooldown(),
+            )
+            .await?;
+            transaction.commit().await?;
+            return Ok(ExecutionOutcome::TaskDeferred);
+        }
+        Err(EmailError::DailySendCapReached) => {
+            defer_task(
+                &mut transaction,
+                &task,
+                email_client.send_cap_resets_in(),
+            )
+            .await?;
+            transaction.commit().await?;
+            return Ok(ExecutionOutcome::TaskDeferred);
+        }
+        Err(e) if task.n_retries + 1 >= settings.max_delivery_retries => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send an onboarding email. Giving up.",
+            );
+            delete_task(&mut transaction, &task).await?;
+        }
+        Err(e) => {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send an onboarding email. Retrying later.",
+            );
+            reschedule_task(
+                &mut transaction,
+                &task,
+                email_client.retry_delay(task.n_retries),
+            )
+            .await?;
+        }
+    }
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_task(
+    pool: &PgPool,
+) -> Result<Option<(PgTransaction, OnboardingTask)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let task = sqlx::query_as!(
+        OnboardingTask,
+        r#"
+        SELECT
+            q.subscriber_id, q.step, q.n_retries, s.email, s.encrypted_email,
+            s.name
+        FROM onboarding_queue q
+        JOIN subscriptions s ON s.id = q.subscriber_id
+        WHERE
+            q.sent_at IS NULL AND
+            q.execute_after <= now() AND
+            s.status = 'confirmed'
+        FOR UPDATE OF q
+        SKIP LOCKED
+        LIMIT 1
+        "#
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?;
+    Ok(task.map(|task| (transaction, task)))
+}
+
+#[tracing::instrument(skip_all)]
+async fn mark_sent(
+    transaction: &mut PgTransaction,
+    task: &OnboardingTask,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE onboarding_queue
+        SET sent_at = now()
+        WHERE subscriber_id = $1 AND step = $2
+        "#,
+        task.subscriber_id,
+        task.step
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(
+    transaction: &mut PgTransaction,
+    task: &OnboardingTask,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM onboarding_queue
+        WHERE subscriber_id = $1 AND step = $2
+        "#,
+        task.subscriber_id,
+        task.step
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn reschedule_task(
+    transaction: &mut PgTransaction,
+    task: &OnboardingTask,
+    delay: Duration,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE onboarding_queue
+        SET
+            n_retries = n_retries + 1,
+            execute_after = now() + make_interval(secs => $3)
+        WHERE subscriber_id = $1 AND step = $2
+        "#,
+        task.subscriber_id,
+        task.step,
+        delay.as_secs_f64()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn defer_task(
+    transaction: &mut PgTransaction,
+    task: &OnboardingTask,
+    delay: Duration,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE onboarding_queue
+        SET execute_after = now() + make_interval(secs => $3)
+        WHERE subscriber_id = $1 AND step = $2
+        "#,
+        task.subscriber_id,
+        task.step,
+        delay.as_secs_f64()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::configuration::{PendingSweepSettings, SchedulerSettings};
+use crate::scheduler::LeaderElection;
+
+/// Delete the pending subscribers who did not confirm within the TTL, every
+/// `interval_seconds`, for as long as the application runs.
+/// Only the elected instance sweeps, like other scheduled jobs.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `settings` - How often to sweep and how old pending subscribers get.
+/// * `scheduler` - Whether instances elect a leader to sweep.
+pub async fn run_pending_sweeper(
+    pool: PgPool,
+    settings: PendingSweepSettings,
+    scheduler: SchedulerSettings,
+) {
+    // The sweeper contends for its own lock: the delivery worker of this
+    // very instance holds the scheduler's on another connection.
+    let mut election = LeaderElection::new(
+        pool.clone(),
+        SchedulerSettings {
+            lock_key: settings.lock_key,
+            ..scheduler
+        },
+    );
+    loop {
+        actix_web::rt::time::sleep(settings.interval()).await;
+        if !election.is_leader().await {
+            continue;
+        }
+        match sweep_stale_pending_subscribers(&pool, &settings).await {
+            Ok(0) => {}
+            Ok(deleted) => {
+                tracing::info!(deleted, "Deleted stale pending subscribers.")
+            }
+            Err(e) => tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to delete stale pending subscribers.",
+            ),
+        }
+    }
+}
+
+/// Delete the pending subscribers older than the TTL, with their tokens and
+/// email change requests, in one transaction.
+/// The rows are locked before anything is deleted and rows being confirmed
+/// right now are skipped, so a subscriber confirming during the sweep is
+/// never deleted.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `settings` - How old pending subscribers get.
+/// # Returns
+/// A Result containing how many subscribers were deleted.
+#[tracing::instrument(skip_all)]
+pub async fn sweep_stale_pending_subscribers(
+    pool: &PgPool,
+    settings: &PendingSweepSettings,
+) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let stale: Vec<Uuid> = sqlx::query_scalar!(
+        r#"
+        SELECT id
+        FROM subscriptions
+        WHERE
+            status = 'pending_confirmation' AND
+            GREATEST(created_at, confirmation_resent_at)
+                < now() - make_interval(hours => $1)
+        FOR UPDATE SKIP LOCKED
+        "#,
+        i32::try_from(settings.ttl_hours).unwrap_or(i32::MAX)
+    )
+    .fetch_all(transaction.as_mut())
+    .await?;
+    if stale.is_empty() {
+        return Ok(0);
+    }
+    sqlx::query!(
+        "DELETE FROM subscription_tokens WHERE subscriber_id = ANY($1)",
+        &stale
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        "DELETE FROM email_change_requests WHERE subscriber_id = ANY($1)",
+        &stale
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    let deleted =
+        sqlx::query!("DELETE FROM subscriptions WHERE id = ANY($1)", &stale)
+            .execute(transaction.as_mut())
+            .await?
+            .rows_affected();
+    transaction.commit().await?;
+    Ok(deleted)
+}
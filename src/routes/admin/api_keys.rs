@@ -0,0 +1,108 @@
+use actix_web::error::ErrorNotFound;
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{
+    ApiKeyScope, UserId, generate_api_key, hash_api_key,
+};
+use crate::utils::{e400, e500};
+
+/// Request body for creating an API key.
+#[derive(serde::Deserialize)]
+pub struct NewApiKey {
+    /// Who the key is for, e.g. the partner's name.
+    name: String,
+    scopes: Vec<ApiKeyScope>,
+}
+
+/// Create an API key for server-to-server calls.
+/// The key is only ever returned in this response; just its digest is
+/// stored.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `body` - The key's name and scopes.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing the new key and its ID.
+#[tracing::instrument(
+    name = "Create an API key",
+    skip(pool, body, user_id),
+    fields(user_id=%*user_id, name=%body.name)
+)]
+pub async fn create_api_key(
+    pool: web::Data<PgPool>,
+    body: web::Json<NewApiKey>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let NewApiKey { name, scopes } = body.0;
+    let name = name.trim().to_owned();
+    if name.is_empty() {
+        return Err(e400("An API key needs a name."));
+    }
+    if scopes.is_empty() {
+        return Err(e400("An API key needs at least one scope."));
+    }
+    let id = Uuid::new_v4();
+    let key = generate_api_key();
+    let stored_scopes: Vec<String> =
+        scopes.iter().map(|s| s.as_str().to_owned()).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO api_keys (id, name, key_hash, scopes, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        id,
+        name,
+        hash_api_key(&key),
+        &stored_scopes,
+        **user_id
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to store the API key")
+    .map_err(e500)?;
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "id": id,
+        "name": name,
+        "scopes": scopes,
+        "key": key,
+    })))
+}
+
+/// Revoke an API key; requests using it are rejected from then on.
+/// Revoking a key twice is a no-op.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `key_id` - The ID of the API key.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(
+    name = "Revoke an API key",
+    skip(pool, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn revoke_api_key(
+    pool: web::Data<PgPool>,
+    key_id: web::Path<Uuid>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE api_keys
+        SET revoked_at = COALESCE(revoked_at, now())
+        WHERE id = $1
+        "#,
+        key_id.into_inner()
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to revoke the API key")
+    .map_err(e500)?;
+    if result.rows_affected() == 0 {
+        return Err(ErrorNotFound("There is no such API key."));
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
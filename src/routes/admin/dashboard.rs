@@ -40,6 +40,8 @@ pub async fn admin_dashboard(
             <p>Available actions:</p>
             <ol>
                 <li><a href="/admin/password">Change password</a></li>
+                <li><a href="/admin/features">Feature flags</a></li>
+                <li><a href="/admin/home">Home page</a></li>
                 <li>
                     <form name="logoutForm" action="/admin/logout" method="post">
                         <input type="submit" value="Logout">
@@ -0,0 +1,71 @@
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::utils::e500;
+
+/// Which dead-lettered deliveries to retry; all of them when empty.
+#[derive(serde::Deserialize)]
+pub struct RetryFilter {
+    issue_id: Option<Uuid>,
+    /// Only retry deliveries whose last error contains this text,
+    /// ignoring case.
+    error: Option<String>,
+}
+
+/// Move dead-lettered deliveries back into the delivery queue, e.g. once a
+/// provider outage is over.
+/// The retried deliveries start over with a fresh retry budget and keep the
+/// correlation id of the publish request. A delivery that is somehow queued
+/// already is not duplicated, and deliveries to subscribers who are no
+/// longer confirmed stay dead-lettered.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `filter` - The issue and error text to match.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing the number of requeued deliveries.
+#[tracing::instrument(
+    name = "Retry dead-lettered deliveries",
+    skip(pool, filter, user_id),
+    fields(user_id=%*user_id, issue_id=?filter.issue_id)
+)]
+pub async fn retry_dead_letters(
+    pool: web::Data<PgPool>,
+    filter: web::Query<RetryFilter>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let result = sqlx::query!(
+        r#"
+        WITH retried AS (
+            DELETE FROM issue_delivery_dead_letter d
+            USING subscriptions s
+            WHERE
+                s.email = d.subscriber_email AND
+                s.status = 'confirmed' AND
+                ($1::uuid IS NULL OR d.issue_id = $1) AND
+                ($2::text IS NULL OR strpos(lower(last_error), lower($2)) > 0)
+            RETURNING d.issue_id, d.subscriber_email
+        )
+        INSERT INTO issue_delivery_queue (
+            issue_id, subscriber_email, correlation_id
+        )
+        SELECT r.issue_id, r.subscriber_email, i.correlation_id
+        FROM retried r
+        JOIN issues i ON i.issue_id = r.issue_id
+        ON CONFLICT DO NOTHING
+        "#,
+        filter.issue_id,
+        filter.error
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to requeue the dead-lettered deliveries")
+    .map_err(e500)?;
+    tracing::info!(requeued = result.rows_affected(), "Requeued deliveries.");
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "requeued": result.rows_affected(),
+    })))
+}
@@ -0,0 +1,65 @@
+use actix_web::{HttpResponse, web};
+use sqlx::PgPool;
+
+use crate::authentication::UserId;
+use crate::configuration::{NewsletterSettings, WebhookSettings};
+use crate::email_client::EmailClient;
+use crate::email_vault::EmailVault;
+use crate::issue_delivery_worker::{ExecutionOutcome, drain_delivery_queue};
+use crate::subscriber_token::SubscriberLinks;
+use crate::utils::e500;
+
+/// Deliver every due newsletter email right away instead of waiting for
+/// the worker, e.g. to check a fix or in tests.
+/// Runs alongside the worker safely: deliveries are dequeued with
+/// SKIP LOCKED, so no email is sent twice.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `email_client` - The client used to send emails.
+/// * `links` - Builds the unsubscribe links.
+/// * `settings` - The newsletter delivery settings.
+/// * `webhooks` - The outbound webhook settings.
+/// * `vault` - Recovers subscribers' addresses.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing whether the queue was empty and how many deliveries
+/// were processed.
+#[tracing::instrument(
+    name = "Flush the delivery queue",
+    skip(pool, email_client, links, settings, webhooks, vault, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn flush_delivery_queue(
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    links: web::Data<SubscriberLinks>,
+    settings: web::Data<NewsletterSettings>,
+    webhooks: web::Data<WebhookSettings>,
+    vault: web::Data<EmailVault>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let summary = drain_delivery_queue(
+        &pool,
+        &email_client,
+        &links,
+        &settings,
+        &webhooks,
+        &vault,
+    )
+    .await
+    .map_err(e500)?;
+    tracing::info!(
+        completed = summary.completed,
+        deferred = summary.deferred,
+        "Flushed the delivery queue."
+    );
+    let outcome = match summary.outcome() {
+        ExecutionOutcome::EmptyQueue => "empty_queue",
+        _ => "tasks_completed",
+    };
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "outcome": outcome,
+        "completed": summary.completed,
+        "deferred": summary.deferred,
+    })))
+}
@@ -0,0 +1,96 @@
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::configuration::{ConnectionPoolSettings, EventListingSettings};
+use crate::database::acquire_for_read;
+use crate::utils::{e400, e500};
+
+/// Query parameters for listing email events.
+#[derive(serde::Deserialize, Debug)]
+pub struct EventQuery {
+    limit: Option<i64>,
+    /// The id of the last event on the previous page.
+    after: Option<Uuid>,
+    issue_id: Option<Uuid>,
+    event_type: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct EmailEvent {
+    id: Uuid,
+    issue_id: Option<Uuid>,
+    subscriber_email: String,
+    event_type: String,
+    occurred_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct EventPage {
+    events: Vec<EmailEvent>,
+    /// Pass as `after` to fetch the next page, absent on the last one.
+    next_cursor: Option<Uuid>,
+}
+
+/// List email events (bounces, complaints, unsubscribes), newest first,
+/// one page at a time.
+/// Pages follow the `(occurred_at, id)` index, so paging through with
+/// `after` neither skips nor repeats events and never scans the table.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `query` - The filters and pagination parameters.
+/// * `settings` - The default and maximum page size.
+/// * `pool_settings` - How to retry getting a connection.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(
+    name = "List email events",
+    skip(pool, settings, pool_settings)
+)]
+pub async fn list_email_events(
+    pool: web::Data<PgPool>,
+    query: web::Query<EventQuery>,
+    settings: web::Data<EventListingSettings>,
+    pool_settings: web::Data<ConnectionPoolSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page_size = settings.page_size(query.limit).map_err(e400)?;
+    let mut connection = acquire_for_read(&pool, &pool_settings)
+        .await
+        .context("Failed to acquire a database connection")
+        .map_err(e500)?;
+    let mut events = sqlx::query_as!(
+        EmailEvent,
+        r#"
+        SELECT id, issue_id, subscriber_email, event_type, occurred_at
+        FROM email_events
+        WHERE
+            ($1::uuid IS NULL OR (occurred_at, id) < (
+                SELECT occurred_at, id FROM email_events WHERE id = $1
+            )) AND
+            ($2::uuid IS NULL OR issue_id = $2) AND
+            ($3::text IS NULL OR event_type = $3)
+        ORDER BY occurred_at DESC, id DESC
+        LIMIT $4
+        "#,
+        query.after,
+        query.issue_id,
+        query.event_type,
+        page_size + 1
+    )
+    .fetch_all(connection.as_mut())
+    .await
+    .context("Failed to fetch email events")
+    .map_err(e500)?;
+    let next_cursor = if events.len() as i64 > page_size {
+        events.truncate(page_size as usize);
+        events.last().map(|e| e.id)
+    } else {
+        None
+    };
+    Ok(HttpResponse::Ok().json(EventPage {
+        events,
+        next_cursor,
+    }))
+}
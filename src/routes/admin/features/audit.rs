@@ -0,0 +1,76 @@
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::configuration::EventListingSettings;
+use crate::utils::{e400, e500};
+
+/// Query parameters for listing feature flag changes.
+#[derive(serde::Deserialize, Debug)]
+pub struct AuditQuery {
+    limit: Option<i64>,
+    /// The id of the last change on the previous page.
+    after: Option<Uuid>,
+}
+
+#[derive(serde::Serialize)]
+struct FeatureFlagChange {
+    id: Uuid,
+    feature: String,
+    enabled: bool,
+    changed_by: Uuid,
+    changed_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct AuditPage {
+    changes: Vec<FeatureFlagChange>,
+    /// Pass as `after` to fetch the next page, absent on the last one.
+    next_cursor: Option<Uuid>,
+}
+
+/// List feature flag changes, newest first, one page at a time.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `query` - The pagination parameters.
+/// * `settings` - The default and maximum page size.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(name = "List feature flag changes", skip(pool, settings))]
+pub async fn feature_flag_audit_log(
+    pool: web::Data<PgPool>,
+    query: web::Query<AuditQuery>,
+    settings: web::Data<EventListingSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page_size = settings.page_size(query.limit).map_err(e400)?;
+    let mut changes = sqlx::query_as!(
+        FeatureFlagChange,
+        r#"
+        SELECT id, feature, enabled, changed_by, changed_at
+        FROM feature_flag_audit
+        WHERE $1::uuid IS NULL OR (changed_at, id) < (
+            SELECT changed_at, id FROM feature_flag_audit WHERE id = $1
+        )
+        ORDER BY changed_at DESC, id DESC
+        LIMIT $2
+        "#,
+        query.after,
+        page_size + 1
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch feature flag changes")
+    .map_err(e500)?;
+    let next_cursor = if changes.len() as i64 > page_size {
+        changes.truncate(page_size as usize);
+        changes.last().map(|c| c.id)
+    } else {
+        None
+    };
+    Ok(HttpResponse::Ok().json(AuditPage {
+        changes,
+        next_cursor,
+    }))
+}
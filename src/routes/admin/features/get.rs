@@ -0,0 +1,77 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+
+use crate::feature_flags::FeatureFlags;
+
+/// Show every feature flag with its default and current value.
+/// # Arguments
+/// * `flash_messages` - Messages left by the previous request.
+/// * `feature_flags` - The runtime feature flags.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+pub async fn feature_flags_page(
+    flash_messages: IncomingFlashMessages,
+    feature_flags: web::Data<FeatureFlags>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for msg in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", msg.content()).unwrap();
+    }
+
+    let mut rows_html = String::new();
+    for state in feature_flags.states() {
+        let name = state.feature.as_str();
+        let enabled = state.enabled();
+        let source = if state.overridden.is_some() {
+            "runtime override"
+        } else {
+            "configuration"
+        };
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td>{name}</td>
+                <td>{}</td>
+                <td>{source}</td>
+                <td>
+                    <form action="/admin/features" method="post">
+                        <input type="hidden" name="feature" value="{name}">
+                        <input type="hidden" name="enabled" value="{}">
+                        <button type="submit">{}</button>
+                    </form>
+                </td>
+            </tr>"#,
+            if enabled { "on" } else { "off" },
+            !enabled,
+            if enabled { "Turn off" } else { "Turn on" },
+        )
+        .unwrap();
+    }
+
+    let html_content = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=UTF-8">
+            <title>Feature Flags</title>
+        </head>
+        <body>
+            {msg_html}
+            <table>
+                <tr><th>Feature</th><th>State</th><th>Set by</th><th></th></tr>
+                {rows_html}
+            </table>
+            <p><a href="/admin/dashboard">Back</a></p>
+        </body>
+        </html>
+    "#
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_content))
+}
@@ -0,0 +1,7 @@
+mod audit;
+mod get;
+mod post;
+
+pub use audit::feature_flag_audit_log;
+pub use get::feature_flags_page;
+pub use post::update_feature_flag;
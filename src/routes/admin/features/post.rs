@@ -0,0 +1,56 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::authentication::UserId;
+use crate::feature_flags::{Feature, FeatureFlags};
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    feature: String,
+    enabled: bool,
+}
+
+/// Turn a feature flag on or off at runtime.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `form` - The flag and its new value.
+/// * `user_id` - The ID of the authenticated user.
+/// * `feature_flags` - The runtime feature flags.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(
+    name = "Update a feature flag",
+    skip(pool, form, user_id, feature_flags),
+    fields(user_id=%*user_id, feature=%form.feature, enabled=%form.enabled)
+)]
+pub async fn update_feature_flag(
+    pool: web::Data<PgPool>,
+    form: web::Form<FormData>,
+    user_id: web::ReqData<UserId>,
+    feature_flags: web::Data<FeatureFlags>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(feature) = Feature::parse(&form.feature) else {
+        FlashMessage::error(format!(
+            "There is no feature called {:?}.",
+            form.feature
+        ))
+        .send();
+        return Ok(see_other("/admin/features"));
+    };
+
+    feature_flags
+        .set(&pool, feature, form.enabled, **user_id)
+        .await
+        .context("Failed to update the feature flag")
+        .map_err(e500)?;
+    FlashMessage::info(format!(
+        "{} is now {}.",
+        feature.as_str(),
+        if form.enabled { "on" } else { "off" }
+    ))
+    .send();
+    Ok(see_other("/admin/features"))
+}
@@ -0,0 +1,73 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::routes::get_home_page_content;
+use crate::utils::e500;
+
+/// Show the form for editing the home page.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `flash_messages` - Messages left by the previous request.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+pub async fn home_page_form(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for msg in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", msg.content()).unwrap();
+    }
+
+    let content = get_home_page_content(&pool)
+        .await
+        .context("Failed to load the home page content")
+        .map_err(e500)?;
+    let (heading, body, notice) = match content {
+        Some(content) => (content.heading, content.body, ""),
+        None => (
+            String::new(),
+            String::new(),
+            "<p>The home page is showing its built-in content.</p>",
+        ),
+    };
+    let heading = htmlescape::encode_attribute(&heading);
+    let body = htmlescape::encode_minimal(&body);
+
+    let html_content = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=UTF-8">
+            <title>Home Page</title>
+        </head>
+        <body>
+            {msg_html}
+            {notice}
+            <form action="/admin/home" method="post">
+                <label>Heading
+                    <input type="text" name="heading" value="{heading}">
+                </label>
+                <br>
+                <label>Body (one paragraph per line)
+                    <textarea name="body" rows="10" cols="60">{body}</textarea>
+                </label>
+                <br>
+                <button type="submit">Save</button>
+            </form>
+            <p><a href="/admin/dashboard">Back</a></p>
+        </body>
+        </html>
+    "#
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_content))
+}
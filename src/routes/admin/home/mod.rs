@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::home_page_form;
+pub use post::update_home_page;
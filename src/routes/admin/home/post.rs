@@ -0,0 +1,59 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::authentication::UserId;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    heading: String,
+    body: String,
+}
+
+/// Replace the content served on the home page.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `form` - The new heading and body.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(
+    name = "Update the home page",
+    skip(pool, form, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn update_home_page(
+    pool: web::Data<PgPool>,
+    form: web::Form<FormData>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let heading = form.heading.trim();
+    if heading.is_empty() {
+        FlashMessage::error("The heading cannot be empty.").send();
+        return Ok(see_other("/admin/home"));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO home_page_content (id, heading, body, updated_by, updated_at)
+        VALUES (TRUE, $1, $2, $3, now())
+        ON CONFLICT (id) DO UPDATE
+        SET
+            heading = EXCLUDED.heading,
+            body = EXCLUDED.body,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = EXCLUDED.updated_at
+        "#,
+        heading,
+        form.body,
+        **user_id
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to save the home page content")
+    .map_err(e500)?;
+    FlashMessage::info("The home page has been updated.").send();
+    Ok(see_other("/admin/home"))
+}
@@ -1,21 +1,28 @@
-use actix_web::HttpResponse;
+use actix_web::{HttpResponse, web};
 use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
 
+use crate::authentication::end_session;
 use crate::session_state::TypedSession;
 use crate::utils::{e500, see_other};
 
 /// Handle user logout by clearing the session and redirecting to the login page.
 /// Sends a flash message confirming the logout.
 /// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
 /// * `session` - The current user session.
 /// # Returns
 /// * `HttpResponse` - A redirection response to the login page.
 pub async fn log_out(
+    pool: web::Data<PgPool>,
     session: TypedSession,
 ) -> Result<HttpResponse, actix_web::Error> {
     if session.get_user_id().map_err(e500)?.is_none() {
         Ok(see_other("/login"))
     } else {
+        if let Some(session_id) = session.get_session_id().map_err(e500)? {
+            end_session(&pool, session_id).await.map_err(e500)?;
+        }
         session.log_out();
         FlashMessage::info("You have successfully logged out.").send();
         Ok(see_other("/login"))
@@ -1,9 +1,27 @@
+mod api_keys;
 mod dashboard;
+mod dead_letters;
+mod delivery_queue;
+mod events;
+mod features;
+mod home;
 mod logout;
 mod newsletter;
 mod password;
+mod subscribers;
+mod two_factor;
 
-pub use dashboard::admin_dashboard;
+pub use api_keys::{create_api_key, revoke_api_key};
+pub use dashboard::{admin_dashboard, get_username};
+pub use dead_letters::retry_dead_letters;
+pub use delivery_queue::flush_delivery_queue;
+pub use events::list_email_events;
+pub use features::*;
+pub use home::*;
 pub use logout::log_out;
 pub use newsletter::*;
 pub use password::*;
+pub use subscribers::*;
+pub use two_factor::{
+    confirm_two_factor, disable_two_factor, enroll_two_factor,
+};
@@ -0,0 +1,60 @@
+use actix_web::error::ErrorNotFound;
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::e500;
+
+/// Delivery progress of a single newsletter issue.
+#[derive(serde::Serialize)]
+struct DeliveryStatus {
+    issue_id: Uuid,
+    status: String,
+    recipient_count: Option<i32>,
+    pending: i64,
+    attempts: i32,
+    retries: i32,
+    successes: i32,
+    dead_letters: i32,
+}
+
+/// Report how delivery of a newsletter issue is going.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `issue_id` - The ID of the issue.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(name = "Get newsletter delivery status", skip(pool))]
+pub async fn newsletter_delivery_status(
+    pool: web::Data<PgPool>,
+    issue_id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let status = sqlx::query_as!(
+        DeliveryStatus,
+        r#"
+        SELECT
+            issue_id,
+            status,
+            recipient_count,
+            (
+                SELECT COUNT(*)
+                FROM issue_delivery_queue q
+                WHERE q.issue_id = issues.issue_id
+            ) AS "pending!",
+            delivery_attempts AS attempts,
+            delivery_retries AS retries,
+            delivery_successes AS successes,
+            delivery_dead_letters AS dead_letters
+        FROM issues
+        WHERE issue_id = $1
+        "#,
+        issue_id.into_inner()
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to look up the newsletter issue")
+    .map_err(e500)?
+    .ok_or_else(|| ErrorNotFound("There is no such newsletter issue."))?;
+    Ok(HttpResponse::Ok().json(status))
+}
@@ -32,6 +32,14 @@ pub async fn publish_newsletter_form(
                     >
                 </label>
                 <br>
+                <label>Preheader (inbox preview text, optional):<br>
+                    <input
+                        type="text"
+                        placeholder="Defaults to the first line of text"
+                        name="preheader"
+                    >
+                </label>
+                <br>
                 <label>Plain text content:<br>
                     <textarea
                         placeholder="Enter the content in plain text"
@@ -50,12 +58,37 @@ pub async fn publish_newsletter_form(
                     ></textarea>
                 </label>
                 <br>
+                <label>Send at (UTC, leave empty to send now):<br>
+                    <input type="datetime-local" name="scheduled_at">
+                </label>
+                <br>
+                <label>
+                    <input
+                        type="checkbox"
+                        name="confirm_duplicate"
+                        value="true"
+                    >
+                    Send even if it repeats a recent issue
+                </label>
+                <br>
                 <input
                     type="hidden"
                     name="idempotency_key"
                     value="{idempotency_key}"
                 >
                 <button type="submit">Publish</button>
+                <br>
+                <label>Send a test email to:<br>
+                    <input
+                        type="email"
+                        placeholder="Enter your email address"
+                        name="recipient"
+                    >
+                </label>
+                <button
+                    type="submit"
+                    formaction="/admin/newsletters/test"
+                >Send test</button>
             </form>
             <p><a href="/admin/dashboard">&lt;- Back</a></p>
         </body>
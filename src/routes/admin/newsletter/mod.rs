@@ -1,5 +1,16 @@
+mod delivery;
 mod get;
 mod post;
+mod resume;
+mod send_test;
+mod unschedule;
+mod unsubscribes;
 
+pub use delivery::newsletter_delivery_status;
 pub use get::publish_newsletter_form;
 pub use post::publish_newsletter;
+pub(crate) use post::{NewIssue, PublishOutcome, check_links, publish_issue};
+pub use resume::resume_newsletter;
+pub use send_test::send_test_newsletter;
+pub use unschedule::unschedule_newsletter;
+pub use unsubscribes::newsletter_unsubscribes;
@@ -1,13 +1,28 @@
+use actix_web::error::ErrorConflict;
 use actix_web::{HttpResponse, web};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sha2::{Digest, Sha256};
 use sqlx::{PgPool, Postgres, Transaction};
+use tracing_actix_web::RequestId;
 use uuid::Uuid;
 
 use crate::authentication::UserId;
-use crate::idempotency::{IdempotencyKey, save_response};
-use crate::idempotency::{NextAction, try_processing};
-use crate::utils::{e400, e500, see_other};
+use crate::configuration::{
+    IdempotencySettings, InsecureLinkPolicy, NewsletterSettings,
+    WebhookSettings,
+};
+use crate::domain::NewsletterTitle;
+use crate::feature_flags::{Feature, FeatureFlags};
+use crate::idempotency::try_processing;
+use crate::idempotency::{EXPIRED_KEY_MESSAGE, FailureMode, NextAction};
+use crate::idempotency::{IN_FLIGHT_MESSAGE, is_store_unavailable};
+use crate::idempotency::{IdempotencyKey, IdempotencyScope, save_response};
+use crate::issue_delivery_worker::enqueue_issue_delivery;
+use crate::newsletter::{insecure_links, sanitize_html};
+use crate::startup::PublishTransactionLimit;
+use crate::utils::{e400, e500, e503, see_other};
 
 /// Form data for publishing a newsletter issue.
 #[derive(serde::Deserialize)]
@@ -15,7 +30,13 @@ pub struct FormData {
     title: String,
     text_content: String,
     html_content: String,
+    /// Inbox preview text; derived from the body when empty.
+    #[serde(default)]
+    preheader: Option<String>,
     idempotency_key: String,
+    #[serde(default)]
+    confirm_duplicate: bool,
+    scheduled_at: Option<String>,
 }
 
 /// Handle the publishing of a newsletter issue.
@@ -23,6 +44,13 @@ pub struct FormData {
 /// * `pool` - The database connection pool.
 /// * `form` - The form data containing the newsletter issue details.
 /// * `user_id` - The ID of the authenticated user.
+/// * `publish_limit` - The limit on concurrent publish transactions.
+/// * `settings` - The newsletter publishing settings.
+/// * `webhooks` - The outbound webhook settings.
+/// * `feature_flags` - The runtime feature flags.
+/// * `idempotency` - How to behave when the idempotency store is down.
+/// * `request_id` - The id of this request, stored on the issue so its
+///   deliveries can be traced back to it.
 /// # Returns
 /// A Result containing an HttpResponse or an actix_web::Error.
 #[tracing::instrument(
@@ -30,55 +58,329 @@ pub struct FormData {
     skip_all,
     fields(user_id=%*user_id)
 )]
+#[allow(clippy::too_many_arguments)]
 pub async fn publish_newsletter(
     pool: web::Data<PgPool>,
     form: web::Form<FormData>,
     user_id: web::ReqData<UserId>,
+    publish_limit: web::Data<PublishTransactionLimit>,
+    settings: web::Data<NewsletterSettings>,
+    webhooks: web::Data<WebhookSettings>,
+    feature_flags: web::Data<FeatureFlags>,
+    idempotency: web::Data<IdempotencySettings>,
+    request_id: RequestId,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
     let FormData {
         title,
         text_content,
         html_content,
+        preheader,
         idempotency_key,
+        confirm_duplicate,
+        scheduled_at,
     } = form.0;
-    let idempotency_key: IdempotencyKey =
-        idempotency_key.try_into().map_err(e400)?;
+    let preheader = preheader
+        .map(|p| p.trim().to_owned())
+        .filter(|p| !p.is_empty());
+    let idempotency_key =
+        IdempotencyKey::parse(idempotency_key, idempotency.key_format)
+            .map_err(e400)?;
+    let title = match NewsletterTitle::parse(title, settings.max_title_length) {
+        Ok(title) => title,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/newsletters"));
+        }
+    };
+    let scheduled_at = match parse_scheduled_at(scheduled_at) {
+        Ok(scheduled_at) => scheduled_at,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/newsletters"));
+        }
+    };
+    let html_content = sanitize_html(&html_content, &settings.html_sanitizer);
+    let http_links = check_links(&html_content, settings.insecure_links);
+    if !http_links.is_empty() {
+        insecure_links_message(&http_links, settings.insecure_links).send();
+        if settings.insecure_links == InsecureLinkPolicy::Reject {
+            return Ok(see_other("/admin/newsletters"));
+        }
+    }
 
-    let mut transaction =
-        match try_processing(&pool, &idempotency_key, *user_id)
-            .await
-            .map_err(e500)?
+    let issue = NewIssue {
+        title,
+        text_content,
+        html_content,
+        preheader,
+        scheduled_at,
+        confirm_duplicate,
+        correlation_id: request_id.into(),
+    };
+    let outcome = publish_issue(
+        &pool,
+        issue,
+        &idempotency_key,
+        IdempotencyScope::PublishNewsletter,
+        *user_id,
+        &publish_limit,
+        &settings,
+        &webhooks,
+        &feature_flags,
+        &idempotency,
+        |_, _| see_other("/admin/newsletters"),
+    )
+    .await?;
+    let (response, recipient_count) = match outcome {
+        PublishOutcome::Published {
+            response,
+            recipient_count,
+        } => (response, recipient_count),
+        PublishOutcome::Replayed(saved_response) => {
+            success_message().send();
+            return Ok(saved_response);
+        }
+        PublishOutcome::Duplicate => {
+            duplicate_warning().send();
+            return Ok(see_other("/admin/newsletters"));
+        }
+    };
+    match (scheduled_at, recipient_count) {
+        (Some(scheduled_at), _) => scheduled_message(scheduled_at).send(),
+        (None, Some(recipient_count)) => {
+            success_message().send();
+            recipient_count_message(recipient_count).send();
+        }
+        (None, None) => success_message().send(),
+    }
+    Ok(response)
+}
+
+/// A validated newsletter issue, ready to be published.
+pub(crate) struct NewIssue {
+    pub title: NewsletterTitle,
+    pub text_content: String,
+    /// The body as HTML, already sanitized.
+    pub html_content: String,
+    pub preheader: Option<String>,
+    /// When to send the issue, or `None` to send it now.
+    pub scheduled_at: Option<DateTime<Utc>>,
+    /// Publish even if the content matches a recent issue.
+    pub confirm_duplicate: bool,
+    /// The id of the publish request.
+    pub correlation_id: Uuid,
+}
+
+/// What came of an attempt to publish an issue.
+pub(crate) enum PublishOutcome {
+    /// The issue was stored, and queued for delivery unless scheduled.
+    Published {
+        response: HttpResponse,
+        /// How many deliveries were queued; `None` for a scheduled issue.
+        recipient_count: Option<u64>,
+    },
+    /// The idempotency key was used before; this is the saved response.
+    Replayed(HttpResponse),
+    /// The content matches a recent issue and was not confirmed.
+    Duplicate,
+}
+
+/// Publish a newsletter issue, from the admin form or the API.
+/// The issue is stored under an idempotency key, so a resubmission replays
+/// the first response rather than publishing twice.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `issue` - The issue to publish.
+/// * `idempotency_key` - The key sent with the request.
+/// * `scope` - The endpoint the key was sent to.
+/// * `user_id` - The ID of the publishing user.
+/// * `publish_limit` - The limit on concurrent publish transactions.
+/// * `settings` - The newsletter publishing settings.
+/// * `webhooks` - The outbound webhook settings.
+/// * `feature_flags` - The runtime feature flags.
+/// * `idempotency` - How to behave when the idempotency store is down.
+/// * `respond` - Builds the response from the new issue's ID and recipient
+///   count; it is saved for replays.
+/// # Returns
+/// A Result containing the outcome or an actix_web::Error.
+#[tracing::instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn publish_issue(
+    pool: &PgPool,
+    issue: NewIssue,
+    idempotency_key: &IdempotencyKey,
+    scope: IdempotencyScope,
+    user_id: Uuid,
+    publish_limit: &PublishTransactionLimit,
+    settings: &NewsletterSettings,
+    webhooks: &WebhookSettings,
+    feature_flags: &FeatureFlags,
+    idempotency: &IdempotencySettings,
+    respond: impl FnOnce(Uuid, Option<u64>) -> HttpResponse,
+) -> Result<PublishOutcome, actix_web::Error> {
+    // Held until the publish transaction is committed (or dropped).
+    let _permit = publish_limit
+        .0
+        .try_acquire()
+        .context("Too many newsletter issues are being published right now.")
+        .map_err(e503)?;
+
+    let (mut transaction, is_idempotent) = match try_processing(
+        pool,
+        idempotency_key,
+        scope,
+        user_id,
+        idempotency.replay_window(),
+    )
+    .await
+    {
+        Ok(NextAction::StartProcessing(t)) => (t, true),
+        Ok(NextAction::ReturnSavedResponse(saved_response)) => {
+            return Ok(PublishOutcome::Replayed(saved_response));
+        }
+        Ok(NextAction::RejectExpiredKey) => {
+            return Err(e400(EXPIRED_KEY_MESSAGE));
+        }
+        Ok(NextAction::RejectInFlight) => {
+            return Err(ErrorConflict(IN_FLIGHT_MESSAGE));
+        }
+        Err(e)
+            if idempotency.failure_mode == FailureMode::FailOpen
+                && is_store_unavailable(&e) =>
         {
-            NextAction::StartProcessing(t) => t,
-            NextAction::ReturnSavedResponse(saved_response) => {
-                success_message().send();
-                return Ok(saved_response);
-            }
-        };
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "The idempotency store is unavailable. \
+                Publishing without duplicate-submission protection.",
+            );
+            let transaction = pool
+                .begin()
+                .await
+                .context("Failed to start a new database transaction")
+                .map_err(e500)?;
+            (transaction, false)
+        }
+        Err(e) => return Err(e500(e)),
+    };
+
+    let content_hash =
+        content_fingerprint(&issue.text_content, &issue.html_content);
+    if feature_flags.is_enabled(Feature::DuplicateContentDetection)
+        && !issue.confirm_duplicate
+    {
+        let is_duplicate = has_recent_duplicate(
+            &mut transaction,
+            &content_hash,
+            settings.duplicate_content_window_hours,
+        )
+        .await
+        .context("Failed to look up recent newsletter issues")
+        .map_err(e500)?;
+        if is_duplicate {
+            // Dropping the transaction releases the idempotency key,
+            // so the same request can be resubmitted once confirmed.
+            return Ok(PublishOutcome::Duplicate);
+        }
+    }
 
     let issue_id = insert_newsletter_issue(
         &mut transaction,
-        &title,
-        &text_content,
-        &html_content,
+        issue.title.as_ref(),
+        &issue.text_content,
+        &issue.html_content,
+        issue.preheader.as_deref(),
+        &content_hash,
+        issue.scheduled_at,
+        issue.correlation_id,
     )
     .await
     .context("Failed to insert newsletter issue")
     .map_err(e500)?;
 
-    enqueue_delivery_tasks(&mut transaction, issue_id)
-        .await
-        .context("Failed to enqueue delivery tasks")
-        .map_err(e500)?;
+    let recipient_count = match issue.scheduled_at {
+        Some(_) => None,
+        None => Some(
+            enqueue_issue_delivery(
+                &mut transaction,
+                issue_id,
+                issue.title.as_ref(),
+                webhooks,
+                &settings.send_time_optimization,
+            )
+            .await
+            .map_err(e500)?,
+        ),
+    };
 
-    let response = see_other("/admin/newsletters");
-    let response =
-        save_response(transaction, &idempotency_key, *user_id, response)
+    let response = respond(issue_id, recipient_count);
+    let response = if is_idempotent {
+        save_response(transaction, idempotency_key, scope, user_id, response)
+            .await
+            .map_err(e500)?
+    } else {
+        transaction
+            .commit()
             .await
+            .context("Failed to commit the newsletter issue")
             .map_err(e500)?;
-    success_message().send();
-    Ok(response)
+        response
+    };
+    Ok(PublishOutcome::Published {
+        response,
+        recipient_count,
+    })
+}
+
+/// Find the links in an issue that are not https, as the policy requires.
+/// # Arguments
+/// * `html_content` - The sanitized HTML body.
+/// * `policy` - Whether plain http links are checked at all.
+/// # Returns
+/// The plain `http` link targets.
+pub(crate) fn check_links(
+    html_content: &str,
+    policy: InsecureLinkPolicy,
+) -> Vec<String> {
+    let http_links = match policy {
+        InsecureLinkPolicy::Ignore => Vec::new(),
+        _ => insecure_links(html_content),
+    };
+    if !http_links.is_empty() {
+        tracing::warn!(
+            links = ?http_links,
+            "The newsletter issue links to plain http URLs."
+        );
+    }
+    http_links
+}
+
+/// Parse the optional send time submitted with the form.
+/// Accepts RFC 3339 timestamps as well as the timezone-less value produced by
+/// a `datetime-local` input, which is interpreted as UTC.
+/// # Arguments
+/// * `scheduled_at` - The raw form value.
+/// # Returns
+/// A Result containing the send time, if any, or a user-facing message.
+fn parse_scheduled_at(
+    scheduled_at: Option<String>,
+) -> Result<Option<DateTime<Utc>>, String> {
+    let Some(raw) = scheduled_at.filter(|s| !s.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let raw = raw.trim();
+    let scheduled_at = DateTime::parse_from_rfc3339(raw)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M")
+                .map(|t| t.and_utc())
+        })
+        .map_err(|_| format!("'{raw}' is not a valid send time."))?;
+    if scheduled_at <= Utc::now() {
+        return Err("The send time must be in the future.".into());
+    }
+    Ok(Some(scheduled_at))
 }
 
 /// Insert a newsletter issue into the database.
@@ -87,48 +389,136 @@ pub async fn publish_newsletter(
 /// * `title` - The title of the newsletter issue.
 /// * `text_content` - The plain text content of the newsletter issue.
 /// * `html_content` - The HTML content of the newsletter issue.
+/// * `preheader` - The inbox preview text, if set.
+/// * `content_hash` - The fingerprint of the issue content.
+/// * `scheduled_at` - When to send the issue, or `None` to send it now.
+/// * `correlation_id` - The id of the publish request.
 /// # Returns
 /// A Result containing the UUID of the inserted newsletter issue or a sqlx::Error.
 #[tracing::instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
 async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
     title: &str,
     text_content: &str,
     html_content: &str,
+    preheader: Option<&str>,
+    content_hash: &str,
+    scheduled_at: Option<DateTime<Utc>>,
+    correlation_id: Uuid,
 ) -> Result<Uuid, sqlx::Error> {
     let issue_id = Uuid::new_v4();
     sqlx::query!(
         r#"
-        INSERT INTO issues (issue_id, title, text_content, html_content, published_at)
-        VALUES ($1, $2, $3, $4, now())
+        INSERT INTO issues (
+            issue_id, title, text_content, html_content, published_at,
+            content_hash, status, scheduled_at, correlation_id, preheader
+        )
+        VALUES (
+            $1, $2, $3, $4,
+            CASE WHEN $6::timestamptz IS NULL THEN now()::text END,
+            $5,
+            CASE WHEN $6::timestamptz IS NULL
+                THEN 'published'
+                ELSE 'scheduled'
+            END,
+            $6,
+            $7,
+            $8
+        )
         "#,
         issue_id,
         title,
         text_content,
-        html_content
+        html_content,
+        content_hash,
+        scheduled_at,
+        correlation_id,
+        preheader
     )
     .execute(transaction.as_mut())
     .await?;
     Ok(issue_id)
 }
 
-#[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(
+/// Compute a fingerprint of the issue content, independent of its title.
+/// # Arguments
+/// * `text_content` - The plain text content of the newsletter issue.
+/// * `html_content` - The HTML content of the newsletter issue.
+/// # Returns
+/// The hex-encoded SHA-256 digest of both bodies.
+fn content_fingerprint(text_content: &str, html_content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(text_content.as_bytes());
+    // Separator so that moving text between the two bodies changes the hash
+    hasher.update([0u8]);
+    hasher.update(html_content.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Check whether an issue with the same content was published recently,
+/// or is scheduled to be sent. A scheduled issue counts from its send time.
+/// # Arguments
+/// * `transaction` - The database transaction.
+/// * `content_hash` - The fingerprint of the issue content.
+/// * `window_hours` - How far back to look for a matching issue.
+/// # Returns
+/// A Result containing true if a matching issue exists, or a sqlx::Error.
+#[tracing::instrument(skip(transaction))]
+async fn has_recent_duplicate(
     transaction: &mut Transaction<'_, Postgres>,
-    issue_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
+    content_hash: &str,
+    window_hours: u32,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
         r#"
-        INSERT INTO issue_delivery_queue (issue_id, subscriber_email)
-        SELECT $1, email
-        FROM subscriptions
-        WHERE status = 'confirmed'
+        SELECT EXISTS (
+            SELECT 1
+            FROM issues
+            WHERE
+                content_hash = $1 AND
+                COALESCE(scheduled_at, published_at::timestamptz)
+                    > now() - make_interval(hours => $2)
+        ) AS "exists!"
         "#,
-        issue_id
+        content_hash,
+        i32::try_from(window_hours).unwrap_or(i32::MAX)
     )
-    .execute(transaction.as_mut())
+    .fetch_one(transaction.as_mut())
     .await?;
-    Ok(())
+    Ok(row.exists)
+}
+
+/// Create a flash message warning that the content matches a recent issue.
+/// # Returns
+/// A FlashMessage asking the user to confirm the re-send.
+fn duplicate_warning() -> FlashMessage {
+    FlashMessage::warning(
+        "This content is identical to a recently published issue - \
+    tick the confirmation box and submit again to send it anyway.",
+    )
+}
+
+/// Create a flash message listing the links that are not https.
+/// # Arguments
+/// * `links` - The plain `http` link targets.
+/// * `policy` - Whether the issue was rejected or only warned about.
+/// # Returns
+/// An error FlashMessage when rejected, a warning otherwise.
+fn insecure_links_message(
+    links: &[String],
+    policy: InsecureLinkPolicy,
+) -> FlashMessage {
+    let links = links.join(", ");
+    match policy {
+        InsecureLinkPolicy::Reject => FlashMessage::error(format!(
+            "The issue was not published because these links are not \
+            https: {links}"
+        )),
+        _ => {
+            FlashMessage::warning(format!("These links are not https: {links}"))
+        }
+    }
 }
 
 /// Create a flash message indicating successful publication of the newsletter issue.
@@ -140,3 +530,26 @@ fn success_message() -> FlashMessage {
     emails will go out shortly.",
     )
 }
+
+/// Create a flash message confirming that the issue has been scheduled.
+/// # Arguments
+/// * `scheduled_at` - When the issue will be sent.
+/// # Returns
+/// A FlashMessage with the send time.
+fn scheduled_message(scheduled_at: DateTime<Utc>) -> FlashMessage {
+    FlashMessage::info(format!(
+        "The newsletter issue has been scheduled for {}.",
+        scheduled_at.format("%Y-%m-%d %H:%M UTC")
+    ))
+}
+
+/// Create a flash message reporting how many subscribers will receive the issue.
+/// # Arguments
+/// * `recipient_count` - The number of enqueued delivery tasks.
+/// # Returns
+/// A FlashMessage with the recipient count.
+fn recipient_count_message(recipient_count: u64) -> FlashMessage {
+    FlashMessage::info(format!(
+        "The issue will be delivered to {recipient_count} confirmed subscriber(s)."
+    ))
+}
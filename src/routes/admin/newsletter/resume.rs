@@ -0,0 +1,61 @@
+use actix_web::error::ErrorNotFound;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::utils::{e500, see_other};
+
+/// Resume delivery of an issue paused by the bounce/complaint alarm.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `issue_id` - The ID of the issue to resume.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(
+    name = "Resume a newsletter issue",
+    skip(pool, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn resume_newsletter(
+    pool: web::Data<PgPool>,
+    issue_id: web::Path<Uuid>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let status = sqlx::query_scalar!(
+        r#"
+        WITH previous AS (
+            SELECT status FROM issues WHERE issue_id = $1 FOR UPDATE
+        )
+        UPDATE issues
+        SET status = CASE
+            WHEN issues.status = 'paused' THEN 'published'
+            ELSE issues.status
+        END
+        FROM previous
+        WHERE issue_id = $1
+        RETURNING previous.status
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to resume the newsletter issue")
+    .map_err(e500)?
+    .ok_or_else(|| ErrorNotFound("There is no such newsletter issue."))?;
+
+    if status == "paused" {
+        FlashMessage::info("Delivery of the newsletter issue has resumed.")
+            .send();
+    } else {
+        FlashMessage::error(format!(
+            "The newsletter issue is {status}, not paused."
+        ))
+        .send();
+    }
+    Ok(see_other("/admin/newsletters"))
+}
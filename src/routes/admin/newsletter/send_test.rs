@@ -0,0 +1,114 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+
+use crate::authentication::UserId;
+use crate::configuration::NewsletterSettings;
+use crate::domain::{NewsletterTitle, SubscriberEmail};
+use crate::email_client::EmailClient;
+use crate::newsletter::{
+    NewsletterIssue, Recipient, RenderOptions, render_for_recipient,
+    sanitize_html,
+};
+use crate::utils::see_other;
+
+/// Form data for sending a test email of a newsletter issue.
+/// Other fields of the publish form are ignored.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    title: String,
+    text_content: String,
+    html_content: String,
+    #[serde(default)]
+    preheader: Option<String>,
+    recipient: String,
+}
+
+/// Send a single email of an issue that has not been published yet, so
+/// editors can check how it looks in a real inbox.
+/// Nothing is stored: the issue is neither saved nor queued for delivery.
+/// # Arguments
+/// * `form` - The issue content and the address to send it to.
+/// * `user_id` - The ID of the authenticated user.
+/// * `email_client` - The client used to send the email.
+/// * `settings` - The newsletter settings, for sanitizing and rendering.
+/// # Returns
+/// A redirect back to the publish form.
+#[tracing::instrument(
+    name = "Send a test newsletter email",
+    skip_all,
+    fields(user_id=%*user_id)
+)]
+pub async fn send_test_newsletter(
+    form: web::Form<FormData>,
+    user_id: web::ReqData<UserId>,
+    email_client: web::Data<EmailClient>,
+    settings: web::Data<NewsletterSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        title,
+        text_content,
+        html_content,
+        preheader,
+        recipient,
+    } = form.0;
+    let title = match NewsletterTitle::parse(title, settings.max_title_length) {
+        Ok(title) => title,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/newsletters"));
+        }
+    };
+    let recipient = match SubscriberEmail::parse(recipient) {
+        Ok(recipient) => recipient,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/newsletters"));
+        }
+    };
+    let issue = NewsletterIssue {
+        title: title.as_ref().to_owned(),
+        text_content,
+        html_content: sanitize_html(&html_content, &settings.html_sanitizer),
+        preheader: preheader
+            .map(|p| p.trim().to_owned())
+            .filter(|p| !p.is_empty()),
+    };
+    let rendered = render_for_recipient(
+        &issue,
+        &Recipient {
+            name: "",
+            email: recipient.as_ref(),
+        },
+        &RenderOptions {
+            unsubscribe_link: None,
+            preferences_link: None,
+            derive_preheader: settings.derive_preheader,
+        },
+    );
+    match email_client
+        .send_email_with_headers(
+            &recipient,
+            &rendered.subject,
+            &rendered.html_content,
+            &rendered.text_content,
+            &[],
+            settings.reply_to.as_deref(),
+        )
+        .await
+    {
+        Ok(_) => FlashMessage::info(format!(
+            "A test email has been sent to {}.",
+            recipient.as_ref()
+        ))
+        .send(),
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send a test newsletter email.",
+            );
+            FlashMessage::error("The test email could not be sent.").send();
+        }
+    }
+    Ok(see_other("/admin/newsletters"))
+}
@@ -0,0 +1,96 @@
+use actix_web::error::ErrorNotFound;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::utils::{e500, see_other};
+
+/// Return a scheduled newsletter issue to draft before it goes out.
+/// Issues that have already been released for delivery are left untouched.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `issue_id` - The ID of the issue to unschedule.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(
+    name = "Unschedule a newsletter issue",
+    skip(pool, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn unschedule_newsletter(
+    pool: web::Data<PgPool>,
+    issue_id: web::Path<Uuid>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    if return_to_draft(&pool, issue_id)
+        .await
+        .context("Failed to unschedule the newsletter issue")
+        .map_err(e500)?
+    {
+        FlashMessage::info("The newsletter issue has been returned to draft.")
+            .send();
+        return Ok(see_other("/admin/newsletters"));
+    }
+
+    let status = get_issue_status(&pool, issue_id)
+        .await
+        .context("Failed to look up the newsletter issue")
+        .map_err(e500)?
+        .ok_or_else(|| ErrorNotFound("There is no such newsletter issue."))?;
+    FlashMessage::error(format!(
+        "The newsletter issue is {status} and can no longer be unscheduled."
+    ))
+    .send();
+    Ok(see_other("/admin/newsletters"))
+}
+
+/// Clear the schedule of an issue that has not been released yet.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `issue_id` - The ID of the issue to unschedule.
+/// # Returns
+/// A Result containing true if the issue was returned to draft.
+#[tracing::instrument(skip(pool))]
+async fn return_to_draft(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let n_updated = sqlx::query!(
+        r#"
+        UPDATE issues
+        SET status = 'draft', scheduled_at = NULL
+        WHERE
+            issue_id = $1 AND
+            status = 'scheduled' AND
+            scheduled_at > now()
+        "#,
+        issue_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(n_updated > 0)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_issue_status(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT status
+        FROM issues
+        WHERE issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.status))
+}
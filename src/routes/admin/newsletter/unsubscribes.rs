@@ -0,0 +1,41 @@
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::e500;
+
+/// How many subscribers opted out from the emails of an issue.
+#[derive(serde::Serialize)]
+struct IssueUnsubscribes {
+    issue_id: Uuid,
+    title: String,
+    unsubscribes: i64,
+}
+
+/// Report unsubscribes per newsletter issue, most unsubscribes first.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(name = "Get unsubscribes per issue", skip(pool))]
+pub async fn newsletter_unsubscribes(
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let stats = sqlx::query_as!(
+        IssueUnsubscribes,
+        r#"
+        SELECT i.issue_id, i.title, COUNT(*) AS "unsubscribes!"
+        FROM email_events e
+        JOIN issues i ON i.issue_id = e.issue_id
+        WHERE e.event_type = 'unsubscribe'
+        GROUP BY i.issue_id, i.title
+        ORDER BY 3 DESC, i.issue_id
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to count unsubscribes per issue")
+    .map_err(e500)?;
+    Ok(HttpResponse::Ok().json(stats))
+}
@@ -32,8 +32,8 @@ pub async fn change_password_form(
                 <input type="password" placeholder="New Password" name="new_password">
                 </label>
                 
-                <label for="confirm_password">Confirm New Password
-                <input type="password" placeholder="Confirm New Password" name="confirm_password">
+                <label for="new_password_check">Confirm New Password
+                <input type="password" placeholder="Confirm New Password" name="new_password_check">
                 </label>
                 <br>
                 <button type="submit">Change Password</button>
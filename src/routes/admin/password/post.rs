@@ -1,12 +1,41 @@
+use std::fmt;
+
+use actix_web::error::InternalError;
 use actix_web::{HttpResponse, web};
 use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
 use secrecy::{ExposeSecret, SecretString};
 use sqlx::PgPool;
+use uuid::Uuid;
 
 use crate::authentication::UserId;
 use crate::authentication::{AuthError, Credentials, validate_credentials};
 use crate::routes::admin::dashboard::get_username;
-use crate::utils::{e500, see_other};
+use crate::routes::error_chain_fmt;
+use crate::utils::see_other;
+
+/// Error type for password change failures.
+/// Every variant but `UnexpectedError` is shown to the user as a flash
+/// message on the change password form.
+#[derive(thiserror::Error)]
+pub enum ChangePasswordError {
+    #[error(
+        "You entered two different new passwords - the field values must match."
+    )]
+    PasswordMismatch,
+    #[error("The new password must be between 12 and 128 characters long.")]
+    WeakPassword,
+    #[error("The current password is incorrect.")]
+    WrongCurrentPassword(#[source] anyhow::Error),
+    #[error("Something went wrong.")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl fmt::Debug for ChangePasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
 
 #[derive(serde::Deserialize)]
 pub struct FormData {
@@ -15,57 +44,116 @@ pub struct FormData {
     pub new_password_check: SecretString,
 }
 
+/// Changes the logged-in user's password.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `form` - The current password and the new one, entered twice.
+/// * `user_id` - The ID of the logged-in user.
+/// # Returns
+/// A redirect back to the form with a flash message, or an error response
+/// if the password could not be changed for an unexpected reason.
+#[tracing::instrument(skip(pool, form), fields(user_id=%*user_id))]
 pub async fn change_password(
     pool: web::Data<PgPool>,
     form: web::Form<FormData>,
     user_id: web::ReqData<UserId>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = user_id.into_inner();
+) -> Result<HttpResponse, InternalError<ChangePasswordError>> {
+    match try_change_password(&pool, form.0, *user_id.into_inner()).await {
+        Ok(()) => {
+            FlashMessage::info("Your password has been changed.").send();
+            Ok(see_other("/admin/password"))
+        }
+        Err(e) => Err(change_password_error(e)),
+    }
+}
 
+async fn try_change_password(
+    pool: &PgPool,
+    form: FormData,
+    user_id: Uuid,
+) -> Result<(), ChangePasswordError> {
     if form.new_password.expose_secret()
         != form.new_password_check.expose_secret()
     {
-        FlashMessage::error(
-            "You entered two different new passwords - the field values must match.",
-        )
-        .send();
-        return Ok(see_other("/admin/password"));
+        return Err(ChangePasswordError::PasswordMismatch);
     }
-
     if form.new_password.expose_secret().len() < 12
         || form.new_password.expose_secret().len() > 128
     {
-        FlashMessage::error(
-            "The new password must be between 12 and 128 characters long.",
-        )
-        .send();
-        return Ok(see_other("/admin/password"));
+        return Err(ChangePasswordError::WeakPassword);
     }
 
-    let username = get_username(&pool, *user_id).await.map_err(e500)?;
+    let username = get_username(pool, user_id)
+        .await
+        .context("Failed to look up the username")?;
     let credentials = Credentials {
         username,
-        password: form.0.current_password,
+        password: form.current_password,
     };
-
-    if let Err(e) = validate_credentials(&pool, credentials).await {
-        return match e {
+    validate_credentials(pool, credentials)
+        .await
+        .map_err(|e| match e {
             AuthError::InvalidCredentials(_) => {
-                FlashMessage::error("The current password is incorrect.")
-                    .send();
-                Ok(see_other("/admin/password"))
+                ChangePasswordError::WrongCurrentPassword(e.into())
+            }
+            AuthError::UnexpectedError(_) => {
+                ChangePasswordError::UnexpectedError(e.into())
             }
-            AuthError::UnexpectedError(_) => Err(e500(e).into()),
-        };
+        })?;
+
+    crate::authentication::change_password(pool, user_id, form.new_password)
+        .await
+        .context("Failed to store the new password")?;
+    Ok(())
+}
+
+/// Expected failures go back to the form with a flash message; the error is
+/// still attached to the response so its cause chain ends up in the logs.
+fn change_password_error(
+    e: ChangePasswordError,
+) -> InternalError<ChangePasswordError> {
+    let response = match e {
+        ChangePasswordError::UnexpectedError(_) => {
+            HttpResponse::InternalServerError().finish()
+        }
+        _ => {
+            FlashMessage::error(e.to_string()).send();
+            see_other("/admin/password")
+        }
+    };
+    InternalError::from_response(e, response)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::ChangePasswordError;
+
+    #[test]
+    fn the_unexpected_error_is_logged_with_its_cause_chain() {
+        let cause: Result<(), _> = Err(anyhow::anyhow!("connection refused"));
+        let e = ChangePasswordError::UnexpectedError(
+            cause
+                .context("Failed to store the new password")
+                .unwrap_err(),
+        );
+
+        assert_eq!(
+            format!("{:?}", e),
+            "Something went wrong.\n\
+            Caused by:\n\tFailed to store the new password\n\
+            Caused by:\n\tconnection refused\n"
+        );
     }
 
-    crate::authentication::change_password(
-        &pool,
-        *user_id,
-        form.0.new_password,
-    )
-    .await
-    .map_err(e500)?;
-    FlashMessage::info("Your password has been changed.").send();
-    Ok(see_other("/admin/password"))
+    #[test]
+    fn expected_errors_are_logged_with_the_message_the_user_sees() {
+        let e = ChangePasswordError::WeakPassword;
+
+        assert_eq!(
+            format!("{:?}", e),
+            "The new password must be between 12 and 128 characters long.\n"
+        );
+    }
 }
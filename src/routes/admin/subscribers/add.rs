@@ -0,0 +1,166 @@
+use actix_web::{HttpResponse, web};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::authentication::UserId;
+use crate::configuration::{
+    IdempotencySettings, SubscriberLimitSettings, WebhookSettings,
+};
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
+use crate::email_client::EmailClient;
+use crate::email_vault::EmailVault;
+use crate::idempotency::try_processing;
+use crate::idempotency::{EXPIRED_KEY_MESSAGE, FailureMode, NextAction};
+use crate::idempotency::{IN_FLIGHT_MESSAGE, is_store_unavailable};
+use crate::idempotency::{IdempotencyKey, IdempotencyScope, save_response};
+use crate::routes::{SubscribeError, register_subscriber};
+use crate::startup::{ApplicationBaseUrl, ConfirmationSendLimit};
+
+/// Request body for adding a subscriber as an admin.
+#[derive(serde::Deserialize)]
+pub struct BodyData {
+    email: String,
+    name: String,
+    /// When the subscriber originally signed up, e.g. when importing them
+    /// from another list. Defaults to now.
+    #[serde(default)]
+    subscribed_at: Option<DateTime<Utc>>,
+    /// Makes retries of the same request safe, e.g. when an import script
+    /// is rerun after a timeout.
+    #[serde(default)]
+    idempotency_key: Option<String>,
+}
+
+/// Add a subscriber, optionally keeping their original signup date.
+/// The subscriber still confirms by email, like one signing up through the
+/// form. A request with an idempotency key already used for this endpoint
+/// gets the saved response back instead.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `body` - The subscriber's details.
+/// * `email_client` - The client used to send the confirmation email.
+/// * `base_url` - The base URL for the confirmation link.
+/// * `webhooks` - The outbound webhook settings.
+/// * `limit` - The subscriber cap.
+/// * `send_limit` - Bounds concurrent confirmation email sends.
+/// * `idempotency` - The idempotency store settings.
+/// * `vault` - Turns the address into what is stored.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing the new subscriber's ID, status and signup date.
+#[tracing::instrument(
+    name = "Add a subscriber as an admin",
+    skip_all,
+    fields(user_id=%*user_id)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn add_subscriber(
+    pool: web::Data<PgPool>,
+    body: web::Json<BodyData>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    webhooks: web::Data<WebhookSettings>,
+    limit: web::Data<SubscriberLimitSettings>,
+    send_limit: web::Data<ConfirmationSendLimit>,
+    idempotency: web::Data<IdempotencySettings>,
+    vault: web::Data<EmailVault>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, SubscribeError> {
+    let user_id = user_id.into_inner();
+    let BodyData {
+        email,
+        name,
+        subscribed_at,
+        idempotency_key,
+    } = body.into_inner();
+    let idempotency_key = idempotency_key
+        .map(|key| IdempotencyKey::parse(key, idempotency.key_format))
+        .transpose()
+        .map_err(|e| SubscribeError::ValidationError(e.to_string()))?;
+    let new_subscriber = NewSubscriber {
+        email: SubscriberEmail::parse(email)
+            .map_err(SubscribeError::ValidationError)?,
+        name: SubscriberName::parse(name)
+            .map_err(SubscribeError::ValidationError)?,
+    };
+    if let Some(subscribed_at) = subscribed_at
+        && subscribed_at > Utc::now()
+    {
+        return Err(SubscribeError::ValidationError(format!(
+            "{} is in the future - the signup date cannot be.",
+            subscribed_at
+        )));
+    }
+    let subscribed_at = subscribed_at.unwrap_or_else(Utc::now);
+
+    // The subscriber is stored in a transaction of its own: holding the
+    // idempotency transaction open until the response is saved is what
+    // makes a concurrent retry wait for it.
+    let idempotency_transaction = match &idempotency_key {
+        Some(key) => match try_processing(
+            &pool,
+            key,
+            IdempotencyScope::AddSubscriber,
+            *user_id,
+            idempotency.replay_window(),
+        )
+        .await
+        {
+            Ok(NextAction::StartProcessing(t)) => Some(t),
+            Ok(NextAction::ReturnSavedResponse(saved_response)) => {
+                return Ok(saved_response);
+            }
+            Ok(NextAction::RejectExpiredKey) => {
+                return Err(SubscribeError::ValidationError(
+                    EXPIRED_KEY_MESSAGE.into(),
+                ));
+            }
+            Ok(NextAction::RejectInFlight) => {
+                return Err(SubscribeError::Conflict(IN_FLIGHT_MESSAGE.into()));
+            }
+            Err(e)
+                if idempotency.failure_mode == FailureMode::FailOpen
+                    && is_store_unavailable(&e) =>
+            {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "The idempotency store is unavailable. \
+                    Adding the subscriber without duplicate-submission \
+                    protection.",
+                );
+                None
+            }
+            Err(e) => return Err(e.into()),
+        },
+        None => None,
+    };
+    let subscriber_id = register_subscriber(
+        &pool,
+        new_subscriber,
+        Some(subscribed_at),
+        &email_client,
+        &base_url,
+        &webhooks,
+        &limit,
+        &send_limit,
+        &vault,
+    )
+    .await?;
+    let response = HttpResponse::Created().json(serde_json::json!({
+        "id": subscriber_id,
+        "status": "pending_confirmation",
+        "subscribed_at": subscribed_at,
+    }));
+    match (idempotency_transaction, &idempotency_key) {
+        (Some(transaction), Some(key)) => Ok(save_response(
+            transaction,
+            key,
+            IdempotencyScope::AddSubscriber,
+            *user_id,
+            response,
+        )
+        .await?),
+        _ => Ok(response),
+    }
+}
@@ -0,0 +1,64 @@
+use actix_web::error::ErrorPayloadTooLarge;
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+
+use super::list::{Subscriber, reveal_emails};
+use crate::configuration::{ConnectionPoolSettings, SubscriberListingSettings};
+use crate::database::acquire_for_read;
+use crate::email_vault::EmailVault;
+use crate::utils::e500;
+
+/// Export every subscriber as a single JSON document.
+/// The whole list is held in memory to build the response, so lists longer
+/// than the configured cap are refused rather than buffered; those have to be
+/// paged through the listing endpoint instead.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `settings` - The export row cap.
+/// * `vault` - Recovers subscribers' addresses.
+/// * `pool_settings` - How to retry getting a connection.
+/// # Returns
+/// A Result containing an HttpResponse, or a 413 when the list is over the
+/// cap.
+#[tracing::instrument(
+    name = "Export subscribers",
+    skip(pool, settings, vault, pool_settings)
+)]
+pub async fn export_subscribers(
+    pool: web::Data<PgPool>,
+    settings: web::Data<SubscriberListingSettings>,
+    vault: web::Data<EmailVault>,
+    pool_settings: web::Data<ConnectionPoolSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut connection = acquire_for_read(&pool, &pool_settings)
+        .await
+        .context("Failed to acquire a database connection")
+        .map_err(e500)?;
+    let max_rows = settings.export_max_rows;
+    // One row past the cap is enough to tell that the list is over it.
+    let mut subscribers = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, email, encrypted_email, name, status, subscribed_at
+        FROM subscriptions
+        ORDER BY subscribed_at, id
+        LIMIT $1
+        "#,
+        max_rows + 1
+    )
+    .fetch_all(connection.as_mut())
+    .await
+    .context("Failed to fetch subscribers for the export")
+    .map_err(e500)?;
+    if subscribers.len() as i64 > max_rows {
+        return Err(ErrorPayloadTooLarge(format!(
+            "The export is limited to {max_rows} subscribers. \
+            Page through /admin/subscribers instead."
+        )));
+    }
+    reveal_emails(&mut subscribers, &vault).map_err(e500)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "subscribers": subscribers,
+    })))
+}
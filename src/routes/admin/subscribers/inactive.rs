@@ -0,0 +1,202 @@
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::configuration::{
+    ConnectionPoolSettings, EngagementSettings, WebhookSettings,
+};
+use crate::database::acquire_for_read;
+use crate::domain::{StatusEvent, SubscriberStatus, transition_status};
+use crate::email_vault::EmailVault;
+use crate::routes::record_unsubscribe;
+use crate::utils::{e400, e500};
+use crate::webhooks::{WebhookEvent, enqueue_webhook};
+
+/// Query parameters selecting the inactivity window.
+#[derive(serde::Deserialize, Debug)]
+pub struct InactiveQuery {
+    /// Days without an open or click; the configured window when absent.
+    days: Option<u32>,
+}
+
+impl InactiveQuery {
+    /// The window in days; a window under a day would match every
+    /// subscriber who has not engaged today.
+    fn days(
+        &self,
+        settings: &EngagementSettings,
+    ) -> Result<i32, actix_web::Error> {
+        let days = self.days.unwrap_or(settings.inactive_after_days);
+        if days < 1 {
+            return Err(e400("The inactivity window must be at least a day."));
+        }
+        Ok(days.try_into().unwrap_or(i32::MAX))
+    }
+}
+
+struct SuppressedSubscriber {
+    id: Uuid,
+    email: String,
+    encrypted_email: Option<Vec<u8>>,
+}
+
+#[derive(serde::Serialize)]
+struct InactiveSubscriber {
+    id: Uuid,
+    email: String,
+    #[serde(skip)]
+    encrypted_email: Option<Vec<u8>>,
+    name: String,
+    /// Absent for subscribers who never engaged.
+    last_engaged_at: Option<DateTime<Utc>>,
+}
+
+/// List the confirmed subscribers who have not opened or clicked an email in
+/// the window.
+/// Subscribers who never engaged count from the day they subscribed, so new
+/// subscribers are not listed before they had a chance to.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `query` - The inactivity window.
+/// * `settings` - The default inactivity window.
+/// * `vault` - Recovers subscribers' addresses.
+/// * `pool_settings` - How to retry getting a connection.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(
+    name = "List inactive subscribers",
+    skip(pool, settings, vault, pool_settings)
+)]
+pub async fn list_inactive_subscribers(
+    pool: web::Data<PgPool>,
+    query: web::Query<InactiveQuery>,
+    settings: web::Data<EngagementSettings>,
+    vault: web::Data<EmailVault>,
+    pool_settings: web::Data<ConnectionPoolSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut connection = acquire_for_read(&pool, &pool_settings)
+        .await
+        .context("Failed to acquire a database connection")
+        .map_err(e500)?;
+    let mut subscribers = sqlx::query_as!(
+        InactiveSubscriber,
+        r#"
+        SELECT id, email, encrypted_email, name, last_engaged_at
+        FROM subscriptions
+        WHERE
+            status = 'confirmed' AND
+            COALESCE(last_engaged_at, subscribed_at AT TIME ZONE 'UTC')
+                < now() - make_interval(days => $1)
+        ORDER BY last_engaged_at ASC NULLS FIRST, id
+        "#,
+        query.days(&settings)?
+    )
+    .fetch_all(connection.as_mut())
+    .await
+    .context("Failed to fetch the inactive subscribers")
+    .map_err(e500)?;
+    for subscriber in &mut subscribers {
+        subscriber.email = vault
+            .reveal(&subscriber.email, subscriber.encrypted_email.as_deref())
+            .map_err(e500)?;
+    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "subscribers": subscribers,
+    })))
+}
+
+/// Unsubscribe the confirmed subscribers who have not opened or clicked an
+/// email in the window, to keep dead addresses from hurting the sender
+/// reputation.
+/// Each suppression is recorded as an unsubscribe email event and sent out
+/// as a `subscriber.unsubscribed` webhook, in the same transaction, like an
+/// opt-out through a link.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `query` - The inactivity window.
+/// * `settings` - The default inactivity window.
+/// * `webhooks` - The outbound webhook settings.
+/// * `vault` - Recovers subscribers' addresses for the webhook.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing the number of suppressed subscribers.
+#[tracing::instrument(
+    name = "Suppress inactive subscribers",
+    skip(pool, settings, webhooks, vault, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn suppress_inactive_subscribers(
+    pool: web::Data<PgPool>,
+    query: web::Query<InactiveQuery>,
+    settings: web::Data<EngagementSettings>,
+    webhooks: web::Data<WebhookSettings>,
+    vault: web::Data<EmailVault>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let days = query.days(&settings)?;
+    // Only confirmed subscribers are matched, so one transition covers them
+    // all.
+    let from = SubscriberStatus::Confirmed;
+    let to = transition_status(from, StatusEvent::Unsubscribe).map_err(e500)?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a new database transaction")
+        .map_err(e500)?;
+    let suppressed = sqlx::query_as!(
+        SuppressedSubscriber,
+        r#"
+        UPDATE subscriptions
+        SET status = $1
+        WHERE
+            status = $2 AND
+            COALESCE(last_engaged_at, subscribed_at AT TIME ZONE 'UTC')
+                < now() - make_interval(days => $3)
+        RETURNING id, email, encrypted_email
+        "#,
+        to.as_str(),
+        from.as_str(),
+        days
+    )
+    .fetch_all(transaction.as_mut())
+    .await
+    .context("Failed to suppress the inactive subscribers")
+    .map_err(e500)?;
+    for subscriber in &suppressed {
+        record_unsubscribe(&mut transaction, &subscriber.email, None)
+            .await
+            .context("Failed to record the unsubscribe")
+            .map_err(e500)?;
+        let email = vault
+            .reveal(&subscriber.email, subscriber.encrypted_email.as_deref())
+            .map_err(e500)?;
+        enqueue_webhook(
+            &mut transaction,
+            &webhooks,
+            WebhookEvent::SubscriberUnsubscribed,
+            serde_json::json!({
+                "subscriber_id": subscriber.id,
+                "email": email,
+                "issue_id": null,
+            }),
+        )
+        .await
+        .context("Failed to enqueue the `subscriber.unsubscribed` webhook")
+        .map_err(e500)?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction")
+        .map_err(e500)?;
+    tracing::info!(
+        suppressed = suppressed.len(),
+        "Suppressed inactive subscribers."
+    );
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "suppressed": suppressed.len(),
+    })))
+}
@@ -0,0 +1,157 @@
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use chrono::NaiveDateTime;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::configuration::{ConnectionPoolSettings, SubscriberListingSettings};
+use crate::database::acquire_for_read;
+use crate::email_vault::EmailVault;
+use crate::utils::e500;
+
+/// The columns subscribers can be sorted by.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriberSortField {
+    SubscribedAt,
+    Email,
+    Status,
+}
+
+impl SubscriberSortField {
+    fn column(self) -> &'static str {
+        match self {
+            SubscriberSortField::SubscribedAt => "subscribed_at",
+            SubscriberSortField::Email => "email",
+            SubscriberSortField::Status => "status",
+        }
+    }
+}
+
+/// The direction of a sort.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Query parameters for listing subscribers.
+#[derive(serde::Deserialize, Debug)]
+pub struct ListQuery {
+    sort: Option<SubscriberSortField>,
+    order: Option<SortOrder>,
+    /// The id of the last subscriber on the previous page.
+    after: Option<Uuid>,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+pub(super) struct Subscriber {
+    pub(super) id: Uuid,
+    pub(super) email: String,
+    #[serde(skip)]
+    pub(super) encrypted_email: Option<Vec<u8>>,
+    pub(super) name: String,
+    pub(super) status: String,
+    pub(super) subscribed_at: NaiveDateTime,
+}
+
+#[derive(serde::Serialize)]
+struct SubscriberPage {
+    subscribers: Vec<Subscriber>,
+    /// Pass as `after` to fetch the next page, absent on the last one.
+    next_cursor: Option<Uuid>,
+}
+
+/// List subscribers one page at a time.
+/// Results are ordered by the requested column with the id as a tie-breaker,
+/// so paging through with `after` neither skips nor repeats rows.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `query` - The sort and pagination parameters.
+/// * `settings` - The default sort and page size.
+/// * `vault` - Recovers subscribers' addresses.
+/// * `pool_settings` - How to retry getting a connection.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(
+    name = "List subscribers",
+    skip(pool, settings, vault, pool_settings)
+)]
+pub async fn list_subscribers(
+    pool: web::Data<PgPool>,
+    query: web::Query<ListQuery>,
+    settings: web::Data<SubscriberListingSettings>,
+    vault: web::Data<EmailVault>,
+    pool_settings: web::Data<ConnectionPoolSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let sort = query.sort.unwrap_or(settings.default_sort);
+    let order = query.order.unwrap_or(settings.default_order);
+    let page_size = settings.page_size;
+
+    let mut connection = acquire_for_read(&pool, &pool_settings)
+        .await
+        .context("Failed to acquire a database connection")
+        .map_err(e500)?;
+    let mut subscribers =
+        fetch_page(&mut connection, sort, order, query.after, page_size + 1)
+            .await
+            .context("Failed to fetch subscribers")
+            .map_err(e500)?;
+    let next_cursor = if subscribers.len() as i64 > page_size {
+        subscribers.truncate(page_size as usize);
+        subscribers.last().map(|s| s.id)
+    } else {
+        None
+    };
+    reveal_emails(&mut subscribers, &vault).map_err(e500)?;
+    Ok(HttpResponse::Ok().json(SubscriberPage {
+        subscribers,
+        next_cursor,
+    }))
+}
+
+/// Replace the stored addresses with the subscribers' real ones.
+pub(super) fn reveal_emails(
+    subscribers: &mut [Subscriber],
+    vault: &EmailVault,
+) -> Result<(), anyhow::Error> {
+    for subscriber in subscribers {
+        subscriber.email = vault
+            .reveal(&subscriber.email, subscriber.encrypted_email.as_deref())?;
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip(connection))]
+async fn fetch_page(
+    connection: &mut PgConnection,
+    sort: SubscriberSortField,
+    order: SortOrder,
+    after: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<Subscriber>, sqlx::Error> {
+    // Both the column and the direction come from closed enums, so
+    // interpolating them cannot inject SQL.
+    let column = sort.column();
+    let (direction, comparison) = match order {
+        SortOrder::Asc => ("ASC", ">"),
+        SortOrder::Desc => ("DESC", "<"),
+    };
+    let query = format!(
+        r#"
+        SELECT id, email, encrypted_email, name, status, subscribed_at
+        FROM subscriptions
+        WHERE $1::uuid IS NULL OR ({column}, id) {comparison} (
+            SELECT {column}, id FROM subscriptions WHERE id = $1
+        )
+        ORDER BY {column} {direction}, id {direction}
+        LIMIT $2
+        "#
+    );
+    sqlx::query_as::<_, Subscriber>(&query)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(connection)
+        .await
+}
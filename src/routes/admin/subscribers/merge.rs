@@ -0,0 +1,361 @@
+use actix_web::error::ErrorNotFound;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::domain::{StatusEvent, SubscriberStatus, transition_status};
+use crate::utils::{e400, e500, see_other};
+
+/// Form data for merging a duplicate subscriber into a primary one.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    primary_id: Uuid,
+    duplicate_id: Uuid,
+}
+
+struct Subscriber {
+    email: String,
+    status: String,
+    subscribed_at: NaiveDateTime,
+    snoozed_until: Option<DateTime<Utc>>,
+    last_engaged_at: Option<DateTime<Utc>>,
+}
+
+/// Merge a duplicate subscriber record into a primary one.
+/// The duplicate's tokens, pending deliveries and delivery history are
+/// moved to the primary, the earliest subscription date and the latest
+/// snooze and engagement are kept, and the status both records reach
+/// together through the status state machine wins.
+/// That deliberately departs from ranking confirmed above pending above
+/// unsubscribed: an opt-out or an invalid address on either record is
+/// kept, since a merge must not resubscribe someone who opted out.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `form` - The IDs of the primary and duplicate subscribers.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(
+    name = "Merge subscribers",
+    skip(pool, form, user_id),
+    fields(
+        user_id=%*user_id,
+        primary_id=%form.primary_id,
+        duplicate_id=%form.duplicate_id
+    )
+)]
+pub async fn merge_subscribers(
+    pool: web::Data<PgPool>,
+    form: web::Form<FormData>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        primary_id,
+        duplicate_id,
+    } = form.0;
+    if primary_id == duplicate_id {
+        return Err(e400("A subscriber cannot be merged into itself."));
+    }
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let primary = lock_subscriber(&mut transaction, primary_id)
+        .await
+        .context("Failed to look up the primary subscriber")
+        .map_err(e500)?
+        .ok_or_else(|| ErrorNotFound("There is no such primary subscriber."))?;
+    let duplicate = lock_subscriber(&mut transaction, duplicate_id)
+        .await
+        .context("Failed to look up the duplicate subscriber")
+        .map_err(e500)?
+        .ok_or_else(|| {
+            ErrorNotFound("There is no such duplicate subscriber.")
+        })?;
+
+    move_history(
+        &mut transaction,
+        primary_id,
+        &primary,
+        duplicate_id,
+        &duplicate,
+    )
+    .await
+    .context("Failed to move the duplicate subscriber's history")
+    .map_err(e500)?;
+    let status = merged_status(&primary.status, &duplicate.status)
+        .context("Failed to work out the merged subscriber's status")
+        .map_err(e500)?;
+    let merged = Subscriber {
+        email: primary.email.clone(),
+        status: status.as_str().to_owned(),
+        subscribed_at: primary.subscribed_at.min(duplicate.subscribed_at),
+        snoozed_until: primary.snoozed_until.max(duplicate.snoozed_until),
+        last_engaged_at: primary.last_engaged_at.max(duplicate.last_engaged_at),
+    };
+    update_primary(&mut transaction, primary_id, &merged)
+        .await
+        .context("Failed to update the primary subscriber")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the subscriber merge")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!(
+        "{} has been merged into {}.",
+        duplicate.email, primary.email
+    ))
+    .send();
+    Ok(see_other("/admin/dashboard"))
+}
+
+/// Work out the status of the merged subscriber by replaying, through the
+/// state machine, the events that brought each record to its status:
+/// confirmations first, then addresses flagged invalid, then opt-outs.
+/// # Arguments
+/// * `primary` - The status of the primary subscriber.
+/// * `duplicate` - The status of the duplicate subscriber.
+/// # Returns
+/// A Result containing the merged status, or an error if either status is
+/// unknown.
+fn merged_status(
+    primary: &str,
+    duplicate: &str,
+) -> Result<SubscriberStatus, anyhow::Error> {
+    fn history(status: SubscriberStatus) -> &'static [StatusEvent] {
+        match status {
+            SubscriberStatus::PendingConfirmation => &[],
+            SubscriberStatus::Confirmed => &[StatusEvent::Confirm],
+            SubscriberStatus::Invalid => {
+                &[StatusEvent::Confirm, StatusEvent::FlagInvalid]
+            }
+            SubscriberStatus::Unsubscribed => &[StatusEvent::Unsubscribe],
+        }
+    }
+    fn order(event: &StatusEvent) -> u8 {
+        match event {
+            StatusEvent::Confirm => 0,
+            StatusEvent::FlagInvalid => 1,
+            StatusEvent::Unsubscribe => 2,
+        }
+    }
+    let primary =
+        SubscriberStatus::parse(primary).map_err(anyhow::Error::msg)?;
+    let duplicate =
+        SubscriberStatus::parse(duplicate).map_err(anyhow::Error::msg)?;
+    let mut events: Vec<_> = history(primary)
+        .iter()
+        .chain(history(duplicate))
+        .copied()
+        .collect();
+    events.sort_by_key(order);
+    let status = events
+        .into_iter()
+        .try_fold(SubscriberStatus::PendingConfirmation, transition_status)?;
+    Ok(status)
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn lock_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<Option<Subscriber>, sqlx::Error> {
+    sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT email, status, subscribed_at, snoozed_until, last_engaged_at
+        FROM subscriptions
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(transaction.as_mut())
+    .await
+}
+
+/// Re-point everything owned by the duplicate at the primary, then delete it.
+/// Rows keyed by email address are re-keyed to the primary's address, and
+/// the duplicate's pending address changes are dropped.
+#[tracing::instrument(skip(transaction, primary, duplicate))]
+async fn move_history(
+    transaction: &mut Transaction<'_, Postgres>,
+    primary_id: Uuid,
+    primary: &Subscriber,
+    duplicate_id: Uuid,
+    duplicate: &Subscriber,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscription_tokens
+        SET subscriber_id = $1
+        WHERE subscriber_id = $2
+        "#,
+        primary_id,
+        duplicate_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    // The primary may already be queued for the same issue, or have
+    // received it. Retries and backoff carry over so a failing delivery
+    // does not start over.
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            issue_id, subscriber_email, n_retries, execute_after,
+            correlation_id
+        )
+        SELECT q.issue_id, $1, q.n_retries, q.execute_after, q.correlation_id
+        FROM issue_delivery_queue q
+        WHERE
+            q.subscriber_email = $2 AND
+            NOT EXISTS (
+                SELECT 1 FROM issue_deliveries d
+                WHERE d.issue_id = q.issue_id AND d.subscriber_email = $1
+            )
+        ON CONFLICT DO NOTHING
+        "#,
+        primary.email,
+        duplicate.email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE subscriber_email = $1
+        "#,
+        duplicate.email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    // Each issue is delivered at most once per address; the primary's own
+    // delivery is kept when both received the same issue.
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_deliveries (
+            issue_id, subscriber_email, message_id, delivered_at
+        )
+        SELECT issue_id, $1, message_id, delivered_at
+        FROM issue_deliveries
+        WHERE subscriber_email = $2
+        ON CONFLICT DO NOTHING
+        "#,
+        primary.email,
+        duplicate.email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_deliveries
+        WHERE subscriber_email = $1
+        "#,
+        duplicate.email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    // Likewise an issue is dead-lettered at most once per address.
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_dead_letter (
+            issue_id, subscriber_email, n_retries, last_error, failed_at
+        )
+        SELECT issue_id, $1, n_retries, last_error, failed_at
+        FROM issue_delivery_dead_letter
+        WHERE subscriber_email = $2
+        ON CONFLICT DO NOTHING
+        "#,
+        primary.email,
+        duplicate.email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_dead_letter
+        WHERE subscriber_email = $1
+        "#,
+        duplicate.email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE delivery_attempts
+        SET subscriber_email = $1
+        WHERE subscriber_email = $2
+        "#,
+        primary.email,
+        duplicate.email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE email_events
+        SET subscriber_email = $1
+        WHERE subscriber_email = $2
+        "#,
+        primary.email,
+        duplicate.email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    // A pending address change of the duplicate must not rewrite the
+    // primary's address, so it is dropped as for swept subscribers.
+    sqlx::query!(
+        r#"
+        DELETE FROM email_change_requests
+        WHERE subscriber_id = $1
+        "#,
+        duplicate_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM subscriptions
+        WHERE id = $1
+        "#,
+        duplicate_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(transaction, merged))]
+async fn update_primary(
+    transaction: &mut Transaction<'_, Postgres>,
+    primary_id: Uuid,
+    merged: &Subscriber,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET
+            status = $1,
+            subscribed_at = $2,
+            snoozed_until = $3,
+            last_engaged_at = $4
+        WHERE id = $5
+        "#,
+        merged.status,
+        merged.subscribed_at,
+        merged.snoozed_until,
+        merged.last_engaged_at,
+        primary_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,13 @@
+mod add;
+mod export;
+mod inactive;
+mod list;
+mod merge;
+mod onboarding;
+
+pub use add::add_subscriber;
+pub use export::export_subscribers;
+pub use inactive::{list_inactive_subscribers, suppress_inactive_subscribers};
+pub use list::{SortOrder, SubscriberSortField, list_subscribers};
+pub use merge::merge_subscribers;
+pub use onboarding::restart_onboarding;
@@ -0,0 +1,73 @@
+use actix_web::error::ErrorNotFound;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::configuration::OnboardingSettings;
+use crate::onboarding::schedule_onboarding;
+use crate::utils::{e500, see_other};
+
+/// Send a subscriber the onboarding sequence again from the start.
+/// Steps still pending are rescheduled rather than duplicated.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `subscriber_id` - The ID of the subscriber.
+/// * `onboarding` - The onboarding sequence definition.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(
+    name = "Restart the onboarding sequence",
+    skip(pool, onboarding, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn restart_onboarding(
+    pool: web::Data<PgPool>,
+    subscriber_id: web::Path<Uuid>,
+    onboarding: web::Data<OnboardingSettings>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let subscriber = sqlx::query!(
+        "SELECT email, status FROM subscriptions WHERE id = $1 FOR UPDATE",
+        subscriber_id
+    )
+    .fetch_optional(transaction.as_mut())
+    .await
+    .context("Failed to look up the subscriber")
+    .map_err(e500)?
+    .ok_or_else(|| ErrorNotFound("There is no such subscriber."))?;
+
+    if subscriber.status != "confirmed" {
+        FlashMessage::error(format!(
+            "{} is {}, not confirmed.",
+            subscriber.email, subscriber.status
+        ))
+        .send();
+        return Ok(see_other("/admin/subscribers"));
+    }
+    schedule_onboarding(&mut transaction, subscriber_id, &onboarding, true)
+        .await
+        .context("Failed to reschedule the onboarding sequence")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the onboarding restart")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!(
+        "The onboarding sequence will be sent to {} again.",
+        subscriber.email
+    ))
+    .send();
+    Ok(see_other("/admin/subscribers"))
+}
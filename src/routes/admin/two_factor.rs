@@ -0,0 +1,136 @@
+use actix_web::error::{ErrorConflict, ErrorForbidden};
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use secrecy::SecretString;
+use sqlx::PgPool;
+
+use crate::authentication::{
+    AuthError, Credentials, UserId, confirm_totp_enrollment, disable_totp,
+    start_totp_enrollment, validate_credentials,
+};
+use crate::clock_skew::ClockSkewTolerance;
+use crate::configuration::TotpSettings;
+use crate::routes::admin::dashboard::get_username;
+use crate::utils::{e400, e500};
+
+/// Request body confirming an enrollment.
+#[derive(serde::Deserialize)]
+pub struct TotpCode {
+    code: String,
+}
+
+/// Request body for turning two-factor authentication off.
+#[derive(serde::Deserialize)]
+pub struct DisableTwoFactor {
+    current_password: SecretString,
+}
+
+/// Start enrolling the logged-in user in two-factor authentication.
+/// Login does not ask for a code until the enrollment is confirmed.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `settings` - The issuer name and the key encrypting the secret.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing the secret and its `otpauth://` URI.
+#[tracing::instrument(
+    name = "Enroll in two-factor authentication",
+    skip(pool, settings, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn enroll_two_factor(
+    pool: web::Data<PgPool>,
+    settings: web::Data<TotpSettings>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = **user_id;
+    let username = get_username(&pool, user_id).await.map_err(e500)?;
+    let enrollment =
+        start_totp_enrollment(&pool, &settings, user_id, &username)
+            .await
+            .map_err(e500)?
+            .ok_or_else(|| {
+                ErrorConflict("Two-factor authentication is already enabled.")
+            })?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "secret": enrollment.secret,
+        "otpauth_uri": enrollment.otpauth_uri,
+    })))
+}
+
+/// Turn two-factor authentication on with a code from the app the secret
+/// was added to.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `settings` - The key decrypting the secret.
+/// * `clock_skew` - How far the app's clock may be off.
+/// * `body` - The code.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing an empty response, or a 400 if the code is wrong.
+#[tracing::instrument(
+    name = "Confirm two-factor enrollment",
+    skip(pool, settings, clock_skew, body, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn confirm_two_factor(
+    pool: web::Data<PgPool>,
+    settings: web::Data<TotpSettings>,
+    clock_skew: web::Data<ClockSkewTolerance>,
+    body: web::Json<TotpCode>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let confirmed = confirm_totp_enrollment(
+        &pool,
+        &settings,
+        &clock_skew,
+        **user_id,
+        &body.code,
+    )
+    .await
+    .map_err(e500)?;
+    if !confirmed {
+        return Err(e400("The code does not match a pending enrollment."));
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Turn two-factor authentication off.
+/// The current password is asked for again, so an unattended session cannot
+/// be used to weaken the account.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `body` - The current password.
+/// * `user_id` - The ID of the authenticated user.
+/// # Returns
+/// A Result containing an empty response, or a 403 if the password is
+/// wrong.
+#[tracing::instrument(
+    name = "Disable two-factor authentication",
+    skip(pool, body, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn disable_two_factor(
+    pool: web::Data<PgPool>,
+    body: web::Json<DisableTwoFactor>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = **user_id;
+    let username = get_username(&pool, user_id).await.map_err(e500)?;
+    let credentials = Credentials {
+        username,
+        password: body.0.current_password,
+    };
+    match validate_credentials(&pool, credentials).await {
+        Ok(_) => {}
+        Err(AuthError::InvalidCredentials(_)) => {
+            return Err(ErrorForbidden("The current password is incorrect."));
+        }
+        Err(e) => return Err(e500(e)),
+    }
+    disable_totp(&pool, user_id)
+        .await
+        .context("Failed to disable two-factor authentication")
+        .map_err(e500)?;
+    Ok(HttpResponse::NoContent().finish())
+}
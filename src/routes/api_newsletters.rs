@@ -0,0 +1,132 @@
+use actix_web::error::ErrorConflict;
+use actix_web::{HttpResponse, web};
+use sqlx::PgPool;
+use tracing_actix_web::RequestId;
+
+use crate::authentication::ApiUser;
+use crate::configuration::{
+    IdempotencySettings, InsecureLinkPolicy, NewsletterSettings,
+    WebhookSettings,
+};
+use crate::domain::NewsletterTitle;
+use crate::feature_flags::FeatureFlags;
+use crate::idempotency::{IdempotencyKey, IdempotencyScope};
+use crate::newsletter::sanitize_html;
+use crate::routes::{NewIssue, PublishOutcome, check_links, publish_issue};
+use crate::startup::PublishTransactionLimit;
+use crate::utils::e400;
+
+/// A newsletter issue published through the API.
+#[derive(serde::Deserialize)]
+pub struct BodyData {
+    title: String,
+    text_content: String,
+    html_content: String,
+    /// Inbox preview text; derived from the body when absent.
+    #[serde(default)]
+    preheader: Option<String>,
+    idempotency_key: String,
+    /// Publish even if the content matches a recent issue.
+    #[serde(default)]
+    confirm_duplicate: bool,
+}
+
+/// Publish a newsletter issue on behalf of e.g. a CI pipeline,
+/// authenticated as a user, with a password or a token from `/auth/token`.
+/// The issue goes out right away, as if published from the admin form.
+/// # Arguments
+/// * `user` - The user publishing the issue.
+/// * `pool` - The database connection pool.
+/// * `body` - The newsletter issue details.
+/// * `publish_limit` - The limit on concurrent publish transactions.
+/// * `settings` - The newsletter publishing settings.
+/// * `webhooks` - The outbound webhook settings.
+/// * `feature_flags` - The runtime feature flags.
+/// * `idempotency` - How to behave when the idempotency store is down.
+/// * `request_id` - The id of this request, stored on the issue so its
+///   deliveries can be traced back to it.
+/// # Returns
+/// A Result containing the new issue's ID and how many deliveries were
+/// queued.
+#[tracing::instrument(
+    name = "Publish a newsletter issue through the API",
+    skip_all,
+    fields(user_id = %user.0)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn api_publish_newsletter(
+    user: ApiUser,
+    pool: web::Data<PgPool>,
+    body: web::Json<BodyData>,
+    publish_limit: web::Data<PublishTransactionLimit>,
+    settings: web::Data<NewsletterSettings>,
+    webhooks: web::Data<WebhookSettings>,
+    feature_flags: web::Data<FeatureFlags>,
+    idempotency: web::Data<IdempotencySettings>,
+    request_id: RequestId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let BodyData {
+        title,
+        text_content,
+        html_content,
+        preheader,
+        idempotency_key,
+        confirm_duplicate,
+    } = body.0;
+    let idempotency_key =
+        IdempotencyKey::parse(idempotency_key, idempotency.key_format)
+            .map_err(e400)?;
+    let title = NewsletterTitle::parse(title, settings.max_title_length)
+        .map_err(e400)?;
+    let preheader = preheader
+        .map(|p| p.trim().to_owned())
+        .filter(|p| !p.is_empty());
+    let html_content = sanitize_html(&html_content, &settings.html_sanitizer);
+    let http_links = check_links(&html_content, settings.insecure_links);
+    if !http_links.is_empty()
+        && settings.insecure_links == InsecureLinkPolicy::Reject
+    {
+        return Err(e400(format!(
+            "The issue was not published because these links are not \
+            https: {}",
+            http_links.join(", ")
+        )));
+    }
+
+    let issue = NewIssue {
+        title,
+        text_content,
+        html_content,
+        preheader,
+        scheduled_at: None,
+        confirm_duplicate,
+        correlation_id: request_id.into(),
+    };
+    let outcome = publish_issue(
+        &pool,
+        issue,
+        &idempotency_key,
+        IdempotencyScope::ApiPublishNewsletter,
+        *user.0,
+        &publish_limit,
+        &settings,
+        &webhooks,
+        &feature_flags,
+        &idempotency,
+        |issue_id, recipient_count| {
+            HttpResponse::Created().json(serde_json::json!({
+                "issue_id": issue_id,
+                "recipients": recipient_count,
+            }))
+        },
+    )
+    .await?;
+    match outcome {
+        PublishOutcome::Published { response, .. } => Ok(response),
+        PublishOutcome::Replayed(saved_response) => Ok(saved_response),
+        PublishOutcome::Duplicate => Err(ErrorConflict(
+            "This content is identical to a recently published issue - \
+            set `confirm_duplicate` to send it anyway.",
+        )),
+    }
+}
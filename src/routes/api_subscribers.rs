@@ -0,0 +1,122 @@
+use actix_web::error::ErrorNotFound;
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{ApiKey, ApiKeyScope};
+use crate::configuration::{
+    ConnectionPoolSettings, SubscriberLimitSettings, WebhookSettings,
+};
+use crate::database::acquire_for_read;
+use crate::domain::NewSubscriber;
+use crate::email_client::EmailClient;
+use crate::email_vault::EmailVault;
+use crate::routes::{FormData, SubscribeError, register_subscriber};
+use crate::startup::{ApplicationBaseUrl, ConfirmationSendLimit};
+use crate::utils::e500;
+
+/// Add a subscriber on behalf of a partner, authenticated by an API key
+/// with the `subscribers:write` scope.
+/// The subscriber still confirms by email, like one signing up through the
+/// form.
+/// # Arguments
+/// * `api_key` - The caller's API key.
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `body` - The subscriber's name and email address.
+/// * `email_client` - The client used to send the confirmation email.
+/// * `base_url` - The base URL for the confirmation link.
+/// * `webhooks` - The outbound webhook settings.
+/// * `limit` - The subscriber cap.
+/// * `send_limit` - Bounds concurrent confirmation email sends.
+/// * `vault` - Turns the address into what is stored.
+/// # Returns
+/// A Result containing the new subscriber's ID and status.
+#[tracing::instrument(
+    name = "Add a subscriber through the API",
+    skip_all,
+    fields(api_key_id = %api_key.id)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn api_subscribe(
+    api_key: ApiKey,
+    pool: web::Data<PgPool>,
+    body: web::Json<FormData>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    webhooks: web::Data<WebhookSettings>,
+    limit: web::Data<SubscriberLimitSettings>,
+    send_limit: web::Data<ConfirmationSendLimit>,
+    vault: web::Data<EmailVault>,
+) -> Result<HttpResponse, actix_web::Error> {
+    api_key.require(ApiKeyScope::SubscribersWrite)?;
+    let new_subscriber: NewSubscriber =
+        body.0.try_into().map_err(SubscribeError::ValidationError)?;
+    let subscriber_id = register_subscriber(
+        &pool,
+        new_subscriber,
+        None,
+        &email_client,
+        &base_url,
+        &webhooks,
+        &limit,
+        &send_limit,
+        &vault,
+    )
+    .await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "id": subscriber_id,
+        "status": "pending_confirmation",
+    })))
+}
+
+/// Look up a subscriber, authenticated by an API key with the
+/// `subscribers:read` scope.
+/// # Arguments
+/// * `api_key` - The caller's API key.
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `subscriber_id` - The ID of the subscriber.
+/// * `vault` - Recovers the subscriber's address.
+/// * `pool_settings` - How to retry getting a connection.
+/// # Returns
+/// A Result containing the subscriber's details and status.
+#[tracing::instrument(
+    name = "Look up a subscriber through the API",
+    skip(api_key, pool, vault, pool_settings),
+    fields(api_key_id = %api_key.id)
+)]
+pub async fn api_get_subscriber(
+    api_key: ApiKey,
+    pool: web::Data<PgPool>,
+    subscriber_id: web::Path<Uuid>,
+    vault: web::Data<EmailVault>,
+    pool_settings: web::Data<ConnectionPoolSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    api_key.require(ApiKeyScope::SubscribersRead)?;
+    let mut connection = acquire_for_read(&pool, &pool_settings)
+        .await
+        .context("Failed to acquire a database connection")
+        .map_err(e500)?;
+    let subscriber = sqlx::query!(
+        r#"
+        SELECT id, email, encrypted_email, name, status
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id.into_inner()
+    )
+    .fetch_optional(connection.as_mut())
+    .await
+    .context("Failed to look up the subscriber")
+    .map_err(e500)?
+    .ok_or_else(|| ErrorNotFound("There is no such subscriber."))?;
+    let email = vault
+        .reveal(&subscriber.email, subscriber.encrypted_email.as_deref())
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "id": subscriber.id,
+        "email": email,
+        "name": subscriber.name,
+        "status": subscriber.status,
+    })))
+}
@@ -0,0 +1,33 @@
+use actix_web::{HttpRequest, HttpResponse, web};
+use chrono::Utc;
+
+use crate::authentication::{
+    ApiAuthError, authenticate_basic, basic_credentials, issue_token,
+};
+use crate::configuration::ApiTokenSettings;
+use crate::startup::HmacSecret;
+
+/// Exchange a username and password (`Authorization: Basic`) for a
+/// short-lived bearer token for the API.
+/// Only credentials are accepted, so a token cannot be used to renew itself.
+/// # Arguments
+/// * `request` - The incoming request, carrying the credentials.
+/// * `settings` - The token issuer and lifetime.
+/// * `secret` - The key tokens are signed with.
+/// # Returns
+/// A Result containing the token and its lifetime in seconds.
+#[tracing::instrument(name = "Issue an API token", skip_all)]
+pub async fn issue_api_token(
+    request: HttpRequest,
+    settings: web::Data<ApiTokenSettings>,
+    secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, ApiAuthError> {
+    let credentials = basic_credentials(request.headers())?;
+    let user_id = authenticate_basic(&request, credentials).await?;
+    let token = issue_token(user_id, Utc::now(), &settings, &secret.0);
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "access_token": token,
+        "token_type": "Bearer",
+        "expires_in": settings.ttl().num_seconds(),
+    })))
+}
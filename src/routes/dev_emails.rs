@@ -0,0 +1,26 @@
+use actix_web::{HttpResponse, web};
+
+use crate::email_capture::CAPTURED_EMAILS;
+
+#[derive(serde::Deserialize)]
+pub struct CapturedEmailsQuery {
+    /// Only list emails sent to this address.
+    to: Option<String>,
+}
+
+/// List the emails captured by the email client in test mode.
+/// Only routed when test mode is enabled.
+/// # Arguments
+/// * `query` - An optional recipient to filter on.
+/// # Returns
+/// An HttpResponse listing the captured emails, most recent first.
+pub async fn captured_emails(
+    query: web::Query<CapturedEmailsQuery>,
+) -> HttpResponse {
+    let emails: Vec<_> = CAPTURED_EMAILS
+        .list()
+        .into_iter()
+        .filter(|email| query.to.as_ref().is_none_or(|to| &email.to == to))
+        .collect();
+    HttpResponse::Ok().json(emails)
+}
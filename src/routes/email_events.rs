@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
+use anyhow::Context;
+use secrecy::ExposeSecret;
+use sqlx::{PgPool, Postgres, Transaction};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use crate::configuration::{InboundWebhookSettings, WebhookSettings};
+use crate::domain::{StatusEvent, transition_status};
+use crate::email_vault::EmailVault;
+use crate::routes::{
+    error_chain_fmt, lock_subscriber_status_by_email, set_subscriber_status,
+};
+use crate::webhooks::{WebhookEvent, enqueue_webhook};
+
+/// The header carrying the token shared with the email provider.
+pub const INBOUND_WEBHOOK_TOKEN_HEADER: &str = "X-Webhook-Token";
+
+/// A bounce, spam complaint, open or click reported by the email provider.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ProviderEvent {
+    record_type: String,
+    /// The provider's id for the event, stable across retried deliveries.
+    /// Only bounces and complaints have one.
+    #[serde(rename = "ID", default)]
+    id: Option<ProviderEventId>,
+    /// Bounces and complaints carry the address in `Email`, opens and
+    /// clicks in `Recipient`.
+    #[serde(alias = "Recipient")]
+    email: String,
+    /// The email the event is about.
+    #[serde(rename = "MessageID", default)]
+    message_id: Option<String>,
+    /// When the provider recorded an open or click, as sent.
+    #[serde(default)]
+    received_at: Option<String>,
+    /// Set on send; carries the issue the email belonged to.
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+}
+
+impl ProviderEvent {
+    /// The key retried deliveries of the event share: the provider's event
+    /// id, or for events without one the email and the time it was opened or
+    /// clicked. None if the payload carries neither.
+    fn event_id(&self) -> Option<String> {
+        if let Some(id) = &self.id {
+            return Some(id.to_string());
+        }
+        let message_id = self.message_id.as_deref()?;
+        Some(match &self.received_at {
+            Some(received_at) => format!("{message_id}@{received_at}"),
+            None => message_id.to_owned(),
+        })
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum ProviderEventId {
+    Number(u64),
+    Text(String),
+}
+
+impl fmt::Display for ProviderEventId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(id) => write!(f, "{}", id),
+            Self::Text(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+/// Error type for inbound provider webhook failures.
+#[derive(thiserror::Error)]
+pub enum EmailEventError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+    #[error("Missing or invalid webhook token.")]
+    Unauthorized,
+}
+
+impl std::fmt::Debug for EmailEventError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for EmailEventError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Receives bounce, spam complaint, open and click webhooks from the email
+/// provider.
+/// Bounces, complaints and opens are recorded in `email_events`; a complaint
+/// also unsubscribes the subscriber and sends out a `subscriber.unsubscribed`
+/// webhook. Opens and clicks move the subscriber's
+/// `last_engaged_at`. Providers retry deliveries they consider
+/// failed, so each event is keyed on the provider's event id and a repeated
+/// id is acknowledged without being processed again. Record types we do not
+/// handle are acknowledged and ignored.
+/// # Arguments
+/// * `request` - The incoming request, carrying the webhook token.
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `event` - The provider's event payload.
+/// * `settings` - The inbound webhook settings.
+/// * `webhooks` - The outbound webhook settings.
+/// * `vault` - Matches the reported address to a stored subscriber.
+/// # Returns
+/// A Result indicating success or failure of processing the event.
+#[tracing::instrument(
+    name = "Receive an email provider event",
+    skip_all,
+    fields(record_type = %event.record_type, event_id = ?event.event_id())
+)]
+pub async fn receive_email_event(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    event: web::Json<ProviderEvent>,
+    settings: web::Data<InboundWebhookSettings>,
+    webhooks: web::Data<WebhookSettings>,
+    vault: web::Data<EmailVault>,
+) -> Result<HttpResponse, EmailEventError> {
+    let token = request
+        .headers()
+        .get(INBOUND_WEBHOOK_TOKEN_HEADER)
+        .map(|h| h.as_bytes());
+    // Compared in constant time, so response times do not reveal how much
+    // of a guessed token is right.
+    let authorized = token.is_some_and(|token| {
+        token
+            .ct_eq(settings.token.expose_secret().as_bytes())
+            .into()
+    });
+    if !authorized {
+        return Err(EmailEventError::Unauthorized);
+    }
+    let subscriber_email = vault.lookup_key(&event.email);
+    if matches!(event.record_type.as_str(), "Open" | "Click") {
+        // Only the latest engagement is kept, so a retried delivery is
+        // harmless here.
+        record_engagement(&pool, &subscriber_email)
+            .await
+            .context("Failed to record the subscriber's engagement")?;
+    }
+    let event_type = match event.record_type.as_str() {
+        "Bounce" => "bounce",
+        "SpamComplaint" => "complaint",
+        // Kept as the history send-time optimization learns open hours from.
+        "Open" => "open",
+        "Click" => return Ok(HttpResponse::Ok().finish()),
+        _ => {
+            tracing::info!("Ignoring an unhandled provider event.");
+            return Ok(HttpResponse::Ok().finish());
+        }
+    };
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a new database transaction")?;
+    if settings.deduplicate
+        && !mark_processed(&mut transaction, &event)
+            .await
+            .context("Failed to record the provider event id")?
+    {
+        tracing::info!("The provider event was already processed.");
+        return Ok(HttpResponse::Ok().finish());
+    }
+    let issue_id = event
+        .metadata
+        .get("issue_id")
+        .and_then(|id| Uuid::parse_str(id).ok());
+    record_email_event(
+        &mut transaction,
+        &subscriber_email,
+        issue_id,
+        event_type,
+    )
+    .await
+    .context("Failed to record the email event")?;
+    if event_type == "complaint" {
+        suppress_subscriber(
+            &mut transaction,
+            &subscriber_email,
+            issue_id,
+            &webhooks,
+            &vault,
+        )
+        .await
+        .context("Failed to unsubscribe the complaining subscriber")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction")?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Claim the provider event id.
+/// An event without anything to key it on cannot be recognized when
+/// retried, so it is always processed.
+/// # Returns
+/// A Result containing false if the event was processed before.
+#[tracing::instrument(skip_all)]
+async fn mark_processed(
+    transaction: &mut Transaction<'_, Postgres>,
+    event: &ProviderEvent,
+) -> Result<bool, sqlx::Error> {
+    let Some(event_id) = event.event_id() else {
+        tracing::warn!("The provider event has no id to deduplicate it on.");
+        return Ok(true);
+    };
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO processed_webhook_events (record_type, event_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        event.record_type,
+        event_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Store the event; an issue id that does not match any issue is dropped.
+#[tracing::instrument(skip(transaction))]
+async fn record_email_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_email: &str,
+    issue_id: Option<Uuid>,
+    event_type: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO email_events (id, issue_id, subscriber_email, event_type)
+        VALUES (
+            $1,
+            (SELECT issue_id FROM issues WHERE issue_id = $2),
+            $3,
+            $4
+        )
+        "#,
+        Uuid::new_v4(),
+        issue_id,
+        subscriber_email,
+        event_type
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn record_engagement(
+    pool: &PgPool,
+    subscriber_email: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET last_engaged_at = now()
+        WHERE email = $1
+        "#,
+        subscriber_email
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Unsubscribe the subscriber the event is about, if they are on the list,
+/// and queue the `subscriber.unsubscribed` webhook when their status
+/// changed.
+#[tracing::instrument(skip(transaction, webhooks, vault))]
+async fn suppress_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_email: &str,
+    issue_id: Option<Uuid>,
+    webhooks: &WebhookSettings,
+    vault: &EmailVault,
+) -> Result<(), anyhow::Error> {
+    let Some((subscriber_id, current)) =
+        lock_subscriber_status_by_email(transaction, subscriber_email).await?
+    else {
+        return Ok(());
+    };
+    let status = transition_status(current, StatusEvent::Unsubscribe)?;
+    if status == current {
+        return Ok(());
+    }
+    let stored =
+        set_subscriber_status(transaction, subscriber_id, status).await?;
+    let email = vault
+        .reveal(&stored.email, stored.encrypted_email.as_deref())
+        .context("Failed to recover the subscriber's address")?;
+    enqueue_webhook(
+        transaction,
+        webhooks,
+        WebhookEvent::SubscriberUnsubscribed,
+        serde_json::json!({
+            "subscriber_id": subscriber_id,
+            "email": email,
+            "issue_id": issue_id,
+        }),
+    )
+    .await
+    .context("Failed to enqueue the `subscriber.unsubscribed` webhook")?;
+    Ok(())
+}
@@ -1,9 +1,32 @@
-use actix_web::HttpResponse;
+use actix_web::http::header::{self, Header};
+use actix_web::{HttpRequest, HttpResponse, mime, web};
+
+use crate::startup::StartedAt;
 
 /// A simple health check endpoint that returns HTTP 200 OK.
 /// This can be used by monitoring systems to verify that the application is running.
+/// Clients asking for JSON get the uptime and build version too; liveness
+/// probes keep getting an empty body.
+/// # Arguments
+/// * `request` - The incoming request, checked for `Accept: application/json`.
+/// * `started_at` - When the application was built.
 /// # Returns
 /// An HTTP response with status 200 OK.
-pub async fn health_check() -> HttpResponse {
-    HttpResponse::Ok().finish()
+pub async fn health_check(
+    request: HttpRequest,
+    started_at: web::Data<StartedAt>,
+) -> HttpResponse {
+    let wants_json = header::Accept::parse(&request).is_ok_and(|accept| {
+        accept
+            .iter()
+            .any(|item| item.item == mime::APPLICATION_JSON)
+    });
+    if !wants_json {
+        return HttpResponse::Ok().finish();
+    }
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "ok",
+        "uptime_seconds": started_at.0.elapsed().as_secs_f64(),
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
 }
@@ -1,10 +1,78 @@
 use actix_web::HttpResponse;
 use actix_web::http::header::ContentType;
+use actix_web::web;
+use sqlx::PgPool;
+
+/// Landing page copy edited from the admin area.
+pub struct HomePageContent {
+    pub heading: String,
+    pub body: String,
+}
 
 /// Handler for the home page
-/// Returns the contents of the home.html file as an HTTP response.
-pub async fn home() -> HttpResponse {
+/// Renders the content stored in the database, falling back to the
+/// contents of the home.html file when none has been saved (or the
+/// database cannot be reached).
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// # Returns
+/// The home page as an HTTP response.
+pub async fn home(pool: web::Data<PgPool>) -> HttpResponse {
+    let body = match get_home_page_content(&pool).await {
+        Ok(Some(content)) => render_home_page(&content),
+        Ok(None) => include_str!("home.html").to_owned(),
+        Err(e) => {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                "Failed to load the home page content. Serving the default.",
+            );
+            include_str!("home.html").to_owned()
+        }
+    };
     HttpResponse::Ok()
         .content_type(ContentType::html())
-        .body(include_str!("home.html"))
+        .body(body)
+}
+
+/// Load the saved home page content, if any.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// # Returns
+/// A Result containing the content, or None if none has been saved.
+#[tracing::instrument(skip_all)]
+pub async fn get_home_page_content(
+    pool: &PgPool,
+) -> Result<Option<HomePageContent>, sqlx::Error> {
+    sqlx::query_as!(
+        HomePageContent,
+        "SELECT heading, body FROM home_page_content"
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Render the home page, escaping the stored copy.
+/// Every non-empty line of the body becomes a paragraph.
+fn render_home_page(content: &HomePageContent) -> String {
+    let paragraphs: String = content
+        .body
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("<p>{}</p>\n", htmlescape::encode_minimal(line)))
+        .collect();
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=UTF-8" />
+        <title>Melierx Home</title>
+    </head>
+    <body>
+        <h1>{}</h1>
+        {paragraphs}
+    </body>
+</html>"#,
+        htmlescape::encode_minimal(&content.heading)
+    )
 }
@@ -7,6 +7,7 @@ use actix_web_flash_messages::IncomingFlashMessages;
 /// Handler to serve the login form.
 /// Arguments:
 /// - `flash_messages`: Incoming flash messages to be displayed on the login page.
+///
 /// Returns:
 /// - `HttpResponse`: The HTTP response containing the login form HTML.
 pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
@@ -42,6 +43,7 @@ pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
                 </label>
                 <button type="submit">Login</button>
             </form>
+            <p><a href="/password-reset/request">Forgot your password?</a></p>
         </body>
         </html>
     "#,
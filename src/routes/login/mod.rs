@@ -1,5 +1,9 @@
 mod get;
 mod post;
+mod totp;
+mod verify_email;
 
 pub use get::login_form;
 pub use post::login;
+pub use totp::{login_totp, login_totp_form};
+pub use verify_email::{resend_email_verification, verify_email};
@@ -2,21 +2,34 @@ use std::fmt;
 
 use actix_web::error::InternalError;
 use actix_web::http::header::LOCATION;
-use actix_web::{HttpResponse, web};
+use actix_web::{HttpRequest, HttpResponse, web};
 use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
 use secrecy::SecretString;
 use sqlx::PgPool;
+use uuid::Uuid;
 
-use crate::authentication::AuthError;
-use crate::authentication::{Credentials, validate_credentials};
+use crate::authentication::{AuthError, is_totp_enabled};
+use crate::authentication::{Credentials, start_session, validate_credentials};
+use crate::authentication::{
+    clear_failed_logins, is_login_locked, record_failed_login,
+};
+use crate::configuration::{
+    EmailVerificationSettings, LoginThrottleSettings, SessionSettings,
+};
 use crate::routes::error_chain_fmt;
 use crate::session_state::TypedSession;
+use crate::utils::client_ip;
+
+use super::verify_email::{is_email_verified, unverified_email_page};
 
 /// Error type for login failures.
 #[derive(thiserror::Error)]
 pub enum LoginError {
     #[error("Authentication failed.")]
     AuthError(#[source] anyhow::Error),
+    #[error("Too many failed login attempts - please try again later.")]
+    TooManyAttempts,
     #[error("Something went wrong.")]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -35,14 +48,25 @@ pub struct FormData {
 }
 
 /// Handles user login.
+/// Users with two-factor authentication enabled are sent on to enter a
+/// code instead of being logged in.
+/// After too many failed attempts for the username or from the client's IP
+/// address, logins are refused without checking the password until the
+/// failures age out of the lockout window.
+/// When email verification is required, users who have not verified their
+/// address are refused with a page offering to send them a link.
 /// # Arguments
 /// * `pool` - A reference to the PostgreSQL connection pool.
 /// * `session` - The typed session for managing user sessions.
 /// * `form` - The form data containing username and password.
+/// * `settings` - The login session settings.
+/// * `throttle` - The failed login lockout settings.
+/// * `request` - The incoming request, to identify the client.
+/// * `email_verification` - Whether a verified email address is required.
 /// # Returns
 /// A Result indicating success or failure of the login process.
 #[tracing::instrument(
-    skip(pool, session, form)
+    skip(pool, session, form, settings, throttle, request, email_verification)
     fields(
         username = tracing::field::Empty,
         user_id = tracing::field::Empty,
@@ -52,29 +76,76 @@ pub async fn login(
     pool: web::Data<PgPool>,
     session: TypedSession,
     form: web::Form<FormData>,
+    settings: web::Data<SessionSettings>,
+    throttle: web::Data<LoginThrottleSettings>,
+    request: HttpRequest,
+    email_verification: web::Data<EmailVerificationSettings>,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
+    let username = form.0.username;
     let credentials = Credentials {
-        username: form.0.username,
+        username: username.clone(),
         password: form.0.password,
     };
     tracing::Span::current()
-        .record("username", tracing::field::display(&credentials.username));
+        .record("username", tracing::field::display(&username));
+    let ip = client_ip(&request, throttle.trust_forwarded_for);
+    let locked = is_login_locked(&pool, &throttle, &username, &ip)
+        .await
+        .context("Failed to check for a login lockout")
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+    if locked {
+        tracing::warn!(%ip, "Refusing a login during a lockout.");
+        return Err(login_redirect(LoginError::TooManyAttempts));
+    }
     match validate_credentials(&pool, credentials).await {
         Ok(user_id) => {
             tracing::Span::current()
                 .record("user_id", tracing::field::display(&user_id));
-            session.renew();
-            session.insert_user_id(user_id).map_err(|e| {
-                login_redirect(LoginError::UnexpectedError(e.into()))
-            })?;
-            let result = HttpResponse::SeeOther()
-                .insert_header((LOCATION, "/admin/dashboard"))
-                .finish();
-            Ok(result)
+            if email_verification.required {
+                let verified = is_email_verified(&pool, user_id)
+                    .await
+                    .context("Failed to check the email verification")
+                    .map_err(|e| {
+                        login_redirect(LoginError::UnexpectedError(e))
+                    })?;
+                if !verified {
+                    session.renew();
+                    session.insert_unverified_user_id(user_id).map_err(
+                        |e| {
+                            login_redirect(LoginError::UnexpectedError(
+                                e.into(),
+                            ))
+                        },
+                    )?;
+                    return Ok(unverified_email_page());
+                }
+            }
+            let totp_enabled =
+                is_totp_enabled(&pool, user_id).await.map_err(|e| {
+                    login_redirect(LoginError::UnexpectedError(e.into()))
+                })?;
+            if totp_enabled {
+                session.renew();
+                session.insert_pending_totp_user_id(user_id).map_err(|e| {
+                    login_redirect(LoginError::UnexpectedError(e.into()))
+                })?;
+                return Ok(HttpResponse::SeeOther()
+                    .insert_header((LOCATION, "/login/totp"))
+                    .finish());
+            }
+            log_in(&pool, &session, user_id, &username, &settings)
+                .await
+                .map_err(login_redirect)
         }
         Err(e) => {
             let e = match e {
                 AuthError::InvalidCredentials(_) => {
+                    record_failed_login(&pool, &throttle, &username, &ip)
+                        .await
+                        .context("Failed to record a failed login")
+                        .map_err(|e| {
+                            login_redirect(LoginError::UnexpectedError(e))
+                        })?;
                     LoginError::AuthError(e.into())
                 }
                 AuthError::UnexpectedError(_) => {
@@ -86,7 +157,41 @@ pub async fn login(
     }
 }
 
-fn login_redirect(e: LoginError) -> InternalError<LoginError> {
+/// Start a login session for a user who passed every check, forgetting
+/// the failed logins of their username.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `session` - The typed session for managing user sessions.
+/// * `user_id` - The ID of the user logging in.
+/// * `username` - The username of the user logging in.
+/// * `settings` - The login session settings.
+/// # Returns
+/// A Result containing the redirect to the admin dashboard.
+pub(super) async fn log_in(
+    pool: &PgPool,
+    session: &TypedSession,
+    user_id: Uuid,
+    username: &str,
+    settings: &SessionSettings,
+) -> Result<HttpResponse, LoginError> {
+    clear_failed_logins(pool, username)
+        .await
+        .context("Failed to reset the failed login count")?;
+    let session_id =
+        start_session(pool, user_id, settings.max_per_user).await?;
+    session.renew();
+    session
+        .insert_user_id(user_id)
+        .context("Failed to store the user id")?;
+    session
+        .insert_session_id(session_id)
+        .context("Failed to store the session id")?;
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/dashboard"))
+        .finish())
+}
+
+pub(super) fn login_redirect(e: LoginError) -> InternalError<LoginError> {
     FlashMessage::error(e.to_string()).send();
     let response = HttpResponse::SeeOther()
         .insert_header((LOCATION, "/login"))
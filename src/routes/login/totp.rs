@@ -0,0 +1,125 @@
+use std::fmt::Write;
+
+use actix_web::error::InternalError;
+use actix_web::http::header::{ContentType, LOCATION};
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use super::post::{LoginError, log_in, login_redirect};
+use crate::authentication::{record_failed_login, verify_totp_code};
+use crate::clock_skew::ClockSkewTolerance;
+use crate::configuration::{
+    LoginThrottleSettings, SessionSettings, TotpSettings,
+};
+use crate::routes::get_username;
+use crate::session_state::TypedSession;
+use crate::utils::client_ip;
+
+/// Form data structure for the two-factor login step.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    code: String,
+}
+
+/// Serve the form asking for a two-factor code.
+/// # Arguments
+/// * `flash_messages` - Messages to show above the form.
+pub async fn login_totp_form(
+    flash_messages: IncomingFlashMessages,
+) -> HttpResponse {
+    let mut message_html = String::new();
+    for message in flash_messages.iter() {
+        writeln!(message_html, "<p><i>{}</i></p>", message.content()).unwrap();
+    }
+
+    let html_content = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Two-factor authentication</title>
+        </head>
+        <body>
+            {message_html}
+            <form action="/login/totp" method="post">
+                <label>Code from your authenticator app
+                    <input
+                        type="text"
+                        inputmode="numeric"
+                        autocomplete="one-time-code"
+                        placeholder="123456"
+                        name="code"
+                    >
+                </label>
+                <button type="submit">Verify</button>
+            </form>
+        </body>
+        </html>
+    "#,
+    );
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_content)
+}
+
+/// Finish logging in a user who entered their password, by checking the
+/// code from their authenticator app.
+/// A wrong code sends the user back to enter their password again, and
+/// counts as a failed login towards the lockout.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `session` - The typed session holding the user waiting for this step.
+/// * `form` - The form data containing the code.
+/// * `settings` - The login session settings.
+/// * `totp` - The key decrypting the stored secrets.
+/// * `clock_skew` - How far the user's clock may be off.
+/// * `throttle` - The failed login lockout settings.
+/// * `request` - The incoming request, to identify the client.
+/// # Returns
+/// A Result indicating success or failure of the login process.
+#[tracing::instrument(
+    skip(pool, session, form, settings, totp, clock_skew, throttle, request),
+    fields(user_id = tracing::field::Empty)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn login_totp(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    form: web::Form<FormData>,
+    settings: web::Data<SessionSettings>,
+    totp: web::Data<TotpSettings>,
+    clock_skew: web::Data<ClockSkewTolerance>,
+    throttle: web::Data<LoginThrottleSettings>,
+    request: HttpRequest,
+) -> Result<HttpResponse, InternalError<LoginError>> {
+    let Some(user_id) = session.take_pending_totp_user_id() else {
+        return Ok(HttpResponse::SeeOther()
+            .insert_header((LOCATION, "/login"))
+            .finish());
+    };
+    tracing::Span::current()
+        .record("user_id", tracing::field::display(&user_id));
+    let valid =
+        verify_totp_code(&pool, &totp, &clock_skew, user_id, &form.code)
+            .await
+            .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+    let username = get_username(&pool, user_id)
+        .await
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+    if !valid {
+        let ip = client_ip(&request, throttle.trust_forwarded_for);
+        record_failed_login(&pool, &throttle, &username, &ip)
+            .await
+            .context("Failed to record a failed login")
+            .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+        return Err(login_redirect(LoginError::AuthError(anyhow::anyhow!(
+            "Invalid two-factor code."
+        ))));
+    }
+    log_in(&pool, &session, user_id, &username, &settings)
+        .await
+        .map_err(login_redirect)
+}
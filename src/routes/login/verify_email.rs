@@ -0,0 +1,206 @@
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::clock_skew::ClockSkewTolerance;
+use crate::configuration::EmailVerificationSettings;
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::routes::generate_subscription_token;
+use crate::routes::password_reset::hash_token;
+use crate::session_state::TypedSession;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{e500, see_other};
+
+/// Query parameters for verifying an email address.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    token: String,
+}
+
+/// Whether the user has verified their email address.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `user_id` - The ID of the user.
+/// # Returns
+/// A Result containing true if the address is verified.
+#[tracing::instrument(skip(pool))]
+pub(super) async fn is_email_verified(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let verified = sqlx::query_scalar!(
+        "SELECT email_verified FROM users WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(verified.unwrap_or(false))
+}
+
+/// The page refusing a login until the user's email address is verified.
+pub(super) fn unverified_email_page() -> HttpResponse {
+    HttpResponse::Forbidden()
+        .content_type(ContentType::html())
+        .body(
+            r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Verify your email address</title>
+        </head>
+        <body>
+            <p>Please verify your email address before logging in.</p>
+            <form action="/login/verify-email" method="post">
+                <button type="submit">Resend the verification email</button>
+            </form>
+            <p><a href="/login">Back</a></p>
+        </body>
+        </html>
+    "#,
+        )
+}
+
+/// Email a new verification link to the user whose login was refused for
+/// want of a verified email address.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `session` - The session remembering the refused user.
+/// * `email_client` - A reference to the EmailClient for sending emails.
+/// * `base_url` - The base URL of the application for constructing links.
+/// * `settings` - How long the link stays valid.
+/// # Returns
+/// A redirect to the login page.
+#[tracing::instrument(
+    name = "Resend an email verification link",
+    skip(pool, session, email_client, base_url, settings)
+)]
+pub async fn resend_email_verification(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<EmailVerificationSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(user_id) = session.get_unverified_user_id().map_err(e500)? else {
+        return Ok(see_other("/login"));
+    };
+    let email = sqlx::query_scalar!(
+        "SELECT email FROM users WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to look up the user's email address")
+    .map_err(e500)?
+    .flatten();
+    let Some(email) = email else {
+        FlashMessage::error(
+            "Your account has no email address to verify - \
+            ask an administrator to add one.",
+        )
+        .send();
+        return Ok(see_other("/login"));
+    };
+    let token = generate_subscription_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO email_verification_tokens (token_hash, user_id, expires_at)
+        VALUES ($1, $2, now() + make_interval(mins => $3))
+        "#,
+        hash_token(&token),
+        user_id,
+        i32::try_from(settings.token_ttl_minutes).unwrap_or(i32::MAX)
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to store the email verification token")
+    .map_err(e500)?;
+    send_verification_email(&email_client, email, &base_url.0, &token)
+        .await
+        .context("Failed to send the email verification link")
+        .map_err(e500)?;
+    FlashMessage::info("A verification link has been sent to your email.")
+        .send();
+    Ok(see_other("/login"))
+}
+
+/// Mark the user's email address as verified, using up the link.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `parameters` - The query parameters containing the token.
+/// * `clock_skew` - How long past its expiry the link is still accepted.
+/// # Returns
+/// A redirect to the login page.
+#[tracing::instrument(name = "Verify an email address", skip_all)]
+pub async fn verify_email(
+    pool: web::Data<PgPool>,
+    parameters: web::Query<Parameters>,
+    clock_skew: web::Data<ClockSkewTolerance>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let verified = sqlx::query!(
+        r#"
+        WITH used AS (
+            DELETE FROM email_verification_tokens
+            WHERE token_hash = $1 AND expires_at > $2
+            RETURNING user_id
+        )
+        UPDATE users
+        SET email_verified = true
+        WHERE user_id IN (SELECT user_id FROM used)
+        "#,
+        hash_token(&parameters.token),
+        clock_skew.expiry_cutoff(Utc::now())
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to verify the email address")
+    .map_err(e500)?
+    .rows_affected()
+        > 0;
+    if verified {
+        FlashMessage::info(
+            "Your email address has been verified. You can log in now.",
+        )
+        .send();
+    } else {
+        FlashMessage::error("The verification link is invalid or has expired.")
+            .send();
+    }
+    Ok(see_other("/login"))
+}
+
+/// Send the verification link to the user.
+#[tracing::instrument(skip_all)]
+async fn send_verification_email(
+    email_client: &EmailClient,
+    email: String,
+    base_url: &str,
+    token: &str,
+) -> Result<(), anyhow::Error> {
+    let recipient =
+        SubscriberEmail::parse(email).map_err(anyhow::Error::msg)?;
+    let verification_link =
+        format!("{}/verify-email?token={}", base_url, token);
+    let plain_body =
+        format!("Visit {} to verify your email address.", verification_link);
+    let html_body = format!(
+        "Click <a href=\"{}\">here</a> to verify your email address.",
+        verification_link
+    );
+    email_client
+        .send_email(
+            &recipient,
+            "Verify your email address",
+            &html_body,
+            &plain_body,
+            None,
+        )
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use actix_web::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::{HttpRequest, HttpResponse, web};
+use anyhow::Context;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use subtle::ConstantTimeEq;
+
+use crate::configuration::MetricsSettings;
+use crate::metrics::{DELIVERY_METRICS, IDEMPOTENCY_METRICS, render_metric};
+use crate::utils::e500;
+
+/// Expose delivery and idempotency metrics in the Prometheus text exposition
+/// format.
+/// In-process counters are complemented by aggregates read from the
+/// delivery queue at scrape time. Scrapers must send the configured
+/// scrape token, checked before the queue is read.
+/// # Arguments
+/// * `request` - The incoming request, carrying the scrape token.
+/// * `pool` - The database connection pool.
+/// * `settings` - The metrics endpoint settings.
+/// # Returns
+/// A Result containing an HttpResponse or an actix_web::Error.
+#[tracing::instrument(name = "Render metrics", skip_all)]
+pub async fn metrics(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    settings: web::Data<MetricsSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !is_authorized(&request, &settings) {
+        return Ok(HttpResponse::Unauthorized()
+            .insert_header((WWW_AUTHENTICATE, "Bearer"))
+            .finish());
+    }
+    let queue = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) AS "pending!",
+            COUNT(*) FILTER (WHERE n_retries > 0) AS "retrying!"
+        FROM issue_delivery_queue
+        "#
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .context("Failed to aggregate the delivery queue")
+    .map_err(e500)?;
+
+    let mut body = String::new();
+    DELIVERY_METRICS.render(&mut body);
+    IDEMPOTENCY_METRICS.render(&mut body);
+    render_metric(
+        &mut body,
+        "newsletter_delivery_queue_pending",
+        "gauge",
+        "Deliveries waiting in the queue.",
+        queue.pending,
+    );
+    render_metric(
+        &mut body,
+        "newsletter_delivery_queue_retrying",
+        "gauge",
+        "Queued deliveries that have failed at least once.",
+        queue.retrying,
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body))
+}
+
+/// Check the request carries the configured scrape token.
+/// Compared in constant time, so response times do not reveal how much of a
+/// guessed token is right.
+fn is_authorized(request: &HttpRequest, settings: &MetricsSettings) -> bool {
+    let Some(expected) = &settings.scrape_token else {
+        return false;
+    };
+    request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .is_some_and(|token| {
+            token
+                .as_bytes()
+                .ct_eq(expected.expose_secret().as_bytes())
+                .into()
+        })
+}
@@ -1,13 +1,38 @@
 mod admin;
+mod api_newsletters;
+mod api_subscribers;
+mod auth_token;
+mod dev_emails;
+mod email_events;
 mod health_check;
 mod home;
 mod login;
+mod metrics;
+mod password_reset;
+mod preferences;
+mod subscriber_limit;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_resend;
+mod subscriptions_unsubscribe;
 
 pub use admin::*;
+pub use api_newsletters::*;
+pub use api_subscribers::*;
+pub use auth_token::*;
+pub use dev_emails::*;
+pub use email_events::*;
 pub use health_check::*;
 pub use home::*;
 pub use login::*;
+pub use metrics::*;
+pub use password_reset::*;
+pub use preferences::*;
+pub use subscriber_limit::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_resend::resend_confirmation;
+pub use subscriptions_unsubscribe::{
+    UnsubscribeError, confirm_unsubscribe, record_unsubscribe, unsubscribe,
+    unsubscribe_confirmation_page,
+};
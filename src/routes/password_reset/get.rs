@@ -0,0 +1,60 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+
+/// Query parameters of the link in the password reset email.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    token: String,
+}
+
+/// Serve the form choosing a new password for the token in the link.
+/// The token is only checked once the form is submitted.
+/// # Arguments
+/// * `parameters` - The query parameters containing the reset token.
+/// * `flash_messages` - Messages to show above the form.
+pub async fn password_reset_form(
+    parameters: web::Query<Parameters>,
+    flash_messages: IncomingFlashMessages,
+) -> HttpResponse {
+    let mut msg_html = String::new();
+    for msg in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", msg.content()).unwrap();
+    }
+    let token = htmlescape::encode_attribute(&parameters.token);
+
+    let html_content = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta charset="UTF-8">
+            <meta http-equiv="content-type" content="text/html;">
+            <title>Reset Password</title>
+        </head>
+        <body>
+            {msg_html}
+            <form action="/password-reset" method="post">
+                <input type="hidden" name="token" value="{token}">
+
+                <label for="new_password">New Password
+                <input type="password" placeholder="New Password" name="new_password">
+                </label>
+
+                <label for="new_password_check">Confirm New Password
+                <input type="password" placeholder="Confirm New Password" name="new_password_check">
+                </label>
+                <br>
+                <button type="submit">Reset Password</button>
+            </form>
+        </body>
+        </html>
+    "#
+    );
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_content)
+}
@@ -0,0 +1,15 @@
+mod get;
+mod post;
+mod request;
+
+use sha2::{Digest, Sha256};
+
+pub use get::password_reset_form;
+pub use post::reset_password;
+pub use request::{password_reset_request_form, request_password_reset};
+
+/// The digest an emailed token, for a password reset or an email
+/// verification, is stored under.
+pub(crate) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use actix_web::error::InternalError;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::hash_token;
+use crate::authentication::{change_password_in_transaction, end_all_sessions};
+use crate::clock_skew::ClockSkewTolerance;
+use crate::routes::error_chain_fmt;
+use crate::utils::see_other;
+
+/// Error type for password reset failures.
+/// Every variant but `UnexpectedError` is shown to the user as a flash
+/// message.
+#[derive(thiserror::Error)]
+pub enum PasswordResetError {
+    #[error(
+        "You entered two different new passwords - the field values must match."
+    )]
+    PasswordMismatch,
+    #[error("The new password must be between 12 and 128 characters long.")]
+    WeakPassword,
+    #[error("This password reset link is invalid, expired or already used.")]
+    InvalidToken,
+    #[error("Something went wrong.")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl fmt::Debug for PasswordResetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    pub token: String,
+    pub new_password: SecretString,
+    pub new_password_check: SecretString,
+}
+
+/// Set a new password through a password reset link.
+/// The link can be used once, before it expires; using it also invalidates
+/// any other link sent to the user and logs out all of their sessions.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `form` - The reset token and the new password, entered twice.
+/// * `clock_skew` - How long past its expiry the link is still accepted.
+/// # Returns
+/// A redirect to the login page, or back to a form with a flash message.
+#[tracing::instrument(
+    name = "Reset password",
+    skip(pool, form, clock_skew),
+    fields(user_id = tracing::field::Empty)
+)]
+pub async fn reset_password(
+    pool: web::Data<PgPool>,
+    form: web::Form<FormData>,
+    clock_skew: web::Data<ClockSkewTolerance>,
+) -> Result<HttpResponse, InternalError<PasswordResetError>> {
+    let retry_path =
+        format!("/password-reset?token={}", urlencoding::encode(&form.token));
+    match try_reset_password(&pool, form.0, &clock_skew).await {
+        Ok(()) => {
+            FlashMessage::info(
+                "Your password has been reset. You can now log in.",
+            )
+            .send();
+            Ok(see_other("/login"))
+        }
+        Err(e) => Err(password_reset_error(e, &retry_path)),
+    }
+}
+
+async fn try_reset_password(
+    pool: &PgPool,
+    form: FormData,
+    clock_skew: &ClockSkewTolerance,
+) -> Result<(), PasswordResetError> {
+    if form.new_password.expose_secret()
+        != form.new_password_check.expose_secret()
+    {
+        return Err(PasswordResetError::PasswordMismatch);
+    }
+    if form.new_password.expose_secret().len() < 12
+        || form.new_password.expose_secret().len() > 128
+    {
+        return Err(PasswordResetError::WeakPassword);
+    }
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a new database transaction")?;
+    let user_id = consume_reset_token(
+        &mut transaction,
+        &form.token,
+        clock_skew.expiry_cutoff(Utc::now()),
+    )
+    .await
+    .context("Failed to look up the password reset token")?
+    .ok_or(PasswordResetError::InvalidToken)?;
+    tracing::Span::current()
+        .record("user_id", tracing::field::display(&user_id));
+    change_password_in_transaction(
+        &mut transaction,
+        user_id,
+        form.new_password,
+    )
+    .await
+    .context("Failed to store the new password")?;
+    end_all_sessions(&mut transaction, user_id)
+        .await
+        .context("Failed to revoke the user's sessions")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the password reset")?;
+    Ok(())
+}
+
+/// Mark the token, and every other one of its user, as used.
+/// Concurrent uses of the same token wait on the row lock, then find it
+/// used.
+/// # Returns
+/// A Result containing the user's ID, or None if the token is unknown,
+/// expired or already used.
+#[tracing::instrument(skip_all)]
+async fn consume_reset_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    token: &str,
+    expiry_cutoff: DateTime<Utc>,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let user_id = sqlx::query_scalar!(
+        r#"
+        UPDATE password_reset_tokens
+        SET used_at = now()
+        WHERE
+            token_hash = $1 AND
+            used_at IS NULL AND
+            expires_at > $2
+        RETURNING user_id
+        "#,
+        hash_token(token),
+        expiry_cutoff
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?;
+    let Some(user_id) = user_id else {
+        return Ok(None);
+    };
+    sqlx::query!(
+        r#"
+        UPDATE password_reset_tokens
+        SET used_at = now()
+        WHERE user_id = $1 AND used_at IS NULL
+        "#,
+        user_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(Some(user_id))
+}
+
+/// Expected failures go back to a form with a flash message; the error is
+/// still attached to the response so its cause chain ends up in the logs.
+fn password_reset_error(
+    e: PasswordResetError,
+    retry_path: &str,
+) -> InternalError<PasswordResetError> {
+    let response = match e {
+        PasswordResetError::UnexpectedError(_) => {
+            HttpResponse::InternalServerError().finish()
+        }
+        PasswordResetError::InvalidToken => {
+            FlashMessage::error(e.to_string()).send();
+            see_other("/password-reset/request")
+        }
+        _ => {
+            FlashMessage::error(e.to_string()).send();
+            see_other(retry_path)
+        }
+    };
+    InternalError::from_response(e, response)
+}
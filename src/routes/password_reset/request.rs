@@ -0,0 +1,295 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpRequest, HttpResponse, rt, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use sqlx::PgPool;
+use tracing::Instrument;
+use uuid::Uuid;
+
+use super::hash_token;
+use crate::configuration::{LoginThrottleSettings, PasswordResetSettings};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::routes::generate_subscription_token;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{client_ip, e500, see_other};
+
+/// Form data for requesting a password reset link.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    /// The username or email address of the account.
+    username: String,
+}
+
+/// Serve the form asking for a password reset link.
+/// # Arguments
+/// * `flash_messages` - Messages to show above the form.
+pub async fn password_reset_request_form(
+    flash_messages: IncomingFlashMessages,
+) -> HttpResponse {
+    let mut message_html = String::new();
+    for message in flash_messages.iter() {
+        writeln!(message_html, "<p><i>{}</i></p>", message.content()).unwrap();
+    }
+
+    let html_content = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Reset Password</title>
+        </head>
+        <body>
+            {message_html}
+            <form action="/password-reset/request" method="post">
+                <label>Username or email
+                    <input
+                        type="text"
+                        placeholder="Enter Username or Email"
+                        name="username"
+                    >
+                </label>
+                <button type="submit">Send reset link</button>
+            </form>
+            <p><a href="/login">Back</a></p>
+        </body>
+        </html>
+    "#,
+    );
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_content)
+}
+
+/// Email a single-use password reset link to the account with the given
+/// username or email address.
+/// The response is the same whether or not there is such an account, so the
+/// form cannot be used to find out who has one: the account is looked up
+/// and the email sent after responding, so the response time does not tell
+/// either.
+/// Requests are throttled per IP address like failed logins.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `form` - The username or email address.
+/// * `email_client` - A reference to the EmailClient for sending emails.
+/// * `base_url` - The base URL of the application for constructing links.
+/// * `settings` - How long the link stays valid.
+/// * `throttle` - The per-IP limit shared with logins.
+/// * `request` - The incoming request, to identify the client.
+/// # Returns
+/// A redirect to the login page, or back to the form when throttled.
+#[tracing::instrument(
+    name = "Request a password reset",
+    skip(pool, form, email_client, base_url, settings, throttle, request)
+)]
+pub async fn request_password_reset(
+    pool: web::Data<PgPool>,
+    form: web::Form<FormData>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<PasswordResetSettings>,
+    throttle: web::Data<LoginThrottleSettings>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let ip = client_ip(&request, throttle.trust_forwarded_for);
+    let allowed = record_reset_request(&pool, &throttle, &ip)
+        .await
+        .context("Failed to record the password reset request")
+        .map_err(e500)?;
+    if !allowed {
+        tracing::warn!(%ip, "Refusing a throttled password reset request.");
+        FlashMessage::error(
+            "Too many password reset requests - please try again later.",
+        )
+        .send();
+        return Ok(see_other("/password-reset/request"));
+    }
+    rt::spawn(
+        send_reset_link(
+            pool.into_inner(),
+            email_client.into_inner(),
+            base_url.into_inner(),
+            settings.token_ttl_minutes,
+            form.0.username,
+        )
+        .in_current_span(),
+    );
+    FlashMessage::info(
+        "If the account has an email address, \
+        a password reset link has been sent to it.",
+    )
+    .send();
+    Ok(see_other("/login"))
+}
+
+/// Record a password reset request from an IP address, pruning the ones
+/// that fell out of the window.
+/// # Returns
+/// A Result containing false if the address already made as many requests
+/// within the window as it may make failed logins.
+#[tracing::instrument(skip(pool, settings))]
+async fn record_reset_request(
+    pool: &PgPool,
+    settings: &LoginThrottleSettings,
+    ip: &str,
+) -> Result<bool, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM password_reset_requests
+        WHERE requested_at < now() - make_interval(secs => $1)
+        "#,
+        f64::from(settings.window_seconds)
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    let recent = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM password_reset_requests
+        WHERE ip = $1
+        "#,
+        ip
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    if recent >= settings.max_failures_per_ip {
+        return Ok(false);
+    }
+    sqlx::query!("INSERT INTO password_reset_requests (ip) VALUES ($1)", ip)
+        .execute(transaction.as_mut())
+        .await?;
+    transaction.commit().await?;
+    Ok(true)
+}
+
+/// Look up the account and email it a reset link, if it has an address.
+/// Runs after the response is sent, so failures are only logged.
+#[tracing::instrument(skip_all)]
+async fn send_reset_link(
+    pool: Arc<PgPool>,
+    email_client: Arc<EmailClient>,
+    base_url: Arc<ApplicationBaseUrl>,
+    token_ttl_minutes: u32,
+    username: String,
+) {
+    let user = match find_user(&pool, &username).await {
+        Ok(user) => user,
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to look up the user."
+            );
+            return;
+        }
+    };
+    let Some((user_id, email)) = user else {
+        return;
+    };
+    let token = generate_subscription_token();
+    if let Err(e) =
+        store_reset_token(&pool, user_id, &token, token_ttl_minutes).await
+    {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            %user_id,
+            "Failed to store the password reset token."
+        );
+        return;
+    }
+    if let Err(e) =
+        send_password_reset_email(&email_client, email, &base_url.0, &token)
+            .await
+    {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            %user_id,
+            "Failed to send the password reset email."
+        );
+    }
+}
+
+/// Find the user with the given username or email address.
+/// # Returns
+/// A Result containing the user's ID and email address, or None if there
+/// is no such user or they have no email address.
+#[tracing::instrument(skip(pool))]
+async fn find_user(
+    pool: &PgPool,
+    username_or_email: &str,
+) -> Result<Option<(Uuid, String)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT user_id, email AS "email!"
+        FROM users
+        WHERE
+            email IS NOT NULL AND
+            (username = $1 OR lower(email) = lower($1))
+        "#,
+        username_or_email
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| (r.user_id, r.email)))
+}
+
+#[tracing::instrument(skip(pool, token))]
+async fn store_reset_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    token: &str,
+    ttl_minutes: u32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (token_hash, user_id, expires_at)
+        VALUES ($1, $2, now() + make_interval(mins => $3))
+        "#,
+        hash_token(token),
+        user_id,
+        i32::try_from(ttl_minutes).unwrap_or(i32::MAX)
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Send the password reset link to the user.
+#[tracing::instrument(skip_all)]
+async fn send_password_reset_email(
+    email_client: &EmailClient,
+    email: String,
+    base_url: &str,
+    token: &str,
+) -> Result<(), anyhow::Error> {
+    let recipient =
+        SubscriberEmail::parse(email).map_err(anyhow::Error::msg)?;
+    let reset_link = format!("{}/password-reset?token={}", base_url, token);
+    let plain_body = format!(
+        "Visit {} to choose a new password. \
+        If you did not ask for this, you can ignore this email.",
+        reset_link
+    );
+    let html_body = format!(
+        "Click <a href=\"{}\">here</a> to choose a new password. \
+        If you did not ask for this, you can ignore this email.",
+        reset_link
+    );
+    email_client
+        .send_email(
+            &recipient,
+            "Reset your password",
+            &html_body,
+            &plain_body,
+            None,
+        )
+        .await?;
+    Ok(())
+}
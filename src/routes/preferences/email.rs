@@ -0,0 +1,309 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError, web};
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::clock_skew::ClockSkewTolerance;
+use crate::configuration::EmailChangeSettings;
+use crate::domain::SubscriberEmail;
+use crate::email_client::{EmailClient, EmailError};
+use crate::email_vault::EmailVault;
+use crate::routes::{error_chain_fmt, generate_subscription_token};
+use crate::startup::{ApplicationBaseUrl, HmacSecret};
+use crate::subscriber_token::{SubscriberAction, verify_subscriber_token};
+
+/// Form data for requesting an email address change.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    /// A token scoped to changing the address, not the confirmation token.
+    token: String,
+    email: String,
+}
+
+/// Query parameters for confirming an email address change.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    confirmation_token: String,
+}
+
+/// Error type for email address changes.
+#[derive(thiserror::Error)]
+pub enum EmailChangeError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("There is no subscriber associated with the provided token.")]
+    UnknownToken,
+    #[error("The email address is already subscribed.")]
+    EmailTaken,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for EmailChangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for EmailChangeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+            Self::UnknownToken => StatusCode::UNAUTHORIZED,
+            Self::EmailTaken => StatusCode::CONFLICT,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Request a change of a subscriber's email address.
+/// The old address stays active until the new one has been confirmed
+/// through the link sent to it.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `form` - The subscriber's change-address token and new email address.
+/// * `email_client` - A reference to the EmailClient for sending emails.
+/// * `base_url` - The base URL of the application for constructing links.
+/// * `vault` - Turns the address into what is stored.
+/// * `secret` - The key change-address tokens are signed with.
+/// # Returns
+/// A Result indicating success or failure of the request.
+#[tracing::instrument(
+    name = "Request an email address change",
+    skip(pool, form, email_client, base_url, vault, secret),
+    fields(new_email = %form.email)
+)]
+pub async fn request_email_change(
+    pool: web::Data<PgPool>,
+    form: web::Form<FormData>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    vault: web::Data<EmailVault>,
+    secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, EmailChangeError> {
+    let FormData { token, email } = form.0;
+    let new_email = SubscriberEmail::parse(email)
+        .map_err(EmailChangeError::ValidationError)?;
+    let subscriber_id = verify_subscriber_token(
+        &token,
+        SubscriberAction::ChangeEmail,
+        &secret.0,
+    )
+    .ok_or(EmailChangeError::UnknownToken)?;
+    // The token outlives the subscriber if they are deleted.
+    if !subscriber_exists(&pool, subscriber_id)
+        .await
+        .context("Failed to look up the subscriber")?
+    {
+        return Err(EmailChangeError::UnknownToken);
+    }
+    let lookup_key = vault.lookup_key(new_email.as_ref());
+    if is_email_taken(&pool, &lookup_key)
+        .await
+        .context("Failed to check whether the email address is in use")?
+    {
+        return Err(EmailChangeError::EmailTaken);
+    }
+
+    let encrypted_email = vault
+        .seal(new_email.as_ref())
+        .context("Failed to encrypt the email address")?;
+    let confirmation_token = generate_subscription_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO email_change_requests (
+            confirmation_token, subscriber_id, new_email, encrypted_new_email,
+            requested_at
+        )
+        VALUES ($1, $2, $3, $4, now())
+        "#,
+        confirmation_token,
+        subscriber_id,
+        lookup_key,
+        encrypted_email
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to store the email change request")?;
+    send_email_change_confirmation(
+        &email_client,
+        &new_email,
+        &base_url.0,
+        &confirmation_token,
+    )
+    .await
+    .context("Failed to send the email change confirmation")?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Switch a subscriber to the new email address they confirmed.
+/// The link expires `email_change.token_ttl_minutes` after it was requested.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `parameters` - The query parameters containing the confirmation token.
+/// * `settings` - How long the link can be used.
+/// * `clock_skew` - How long past its expiry the link is still accepted.
+/// # Returns
+/// A Result indicating success or failure of the change.
+#[tracing::instrument(name = "Confirm an email address change", skip_all)]
+pub async fn confirm_email_change(
+    pool: web::Data<PgPool>,
+    parameters: web::Query<Parameters>,
+    settings: web::Data<EmailChangeSettings>,
+    clock_skew: web::Data<ClockSkewTolerance>,
+) -> Result<HttpResponse, EmailChangeError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a new database transaction")?;
+    let request = sqlx::query!(
+        r#"
+        DELETE FROM email_change_requests
+        WHERE confirmation_token = $1 AND requested_at > $2
+        RETURNING subscriber_id, new_email, encrypted_new_email
+        "#,
+        parameters.confirmation_token,
+        clock_skew.expiry_cutoff(Utc::now()) - settings.token_ttl()
+    )
+    .fetch_optional(transaction.as_mut())
+    .await
+    .context("Failed to look up the email change request")?
+    .ok_or(EmailChangeError::UnknownToken)?;
+
+    // Someone else may have subscribed with the address in the meantime.
+    if !switch_email(
+        &mut transaction,
+        request.subscriber_id,
+        &request.new_email,
+        request.encrypted_new_email.as_deref(),
+    )
+    .await
+    .context("Failed to update the subscriber's email address")?
+    {
+        return Err(EmailChangeError::EmailTaken);
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the email address change")?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn subscriber_exists(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let exists = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM subscriptions WHERE id = $1
+        ) AS "exists!"
+        "#,
+        subscriber_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(exists)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn is_email_taken(
+    pool: &PgPool,
+    email: &str,
+) -> Result<bool, sqlx::Error> {
+    let taken = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM subscriptions WHERE lower(email) = lower($1)
+        ) AS "taken!"
+        "#,
+        email
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(taken)
+}
+
+/// Point the subscriber at their new address.
+/// Deliveries already queued for the old address follow it.
+/// # Returns
+/// A Result containing false if the address is now taken by someone else.
+#[tracing::instrument(skip(transaction, encrypted_email))]
+async fn switch_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    new_email: &str,
+    encrypted_email: Option<&[u8]>,
+) -> Result<bool, sqlx::Error> {
+    let old_email = sqlx::query_scalar!(
+        r#"
+        UPDATE subscriptions new
+        SET email = $2, encrypted_email = $3
+        FROM subscriptions old
+        WHERE
+            new.id = $1 AND
+            old.id = new.id AND
+            NOT EXISTS (
+                SELECT 1 FROM subscriptions
+                WHERE lower(email) = lower($2) AND id <> $1
+            )
+        RETURNING old.email
+        "#,
+        subscriber_id,
+        new_email,
+        encrypted_email
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?;
+    let Some(old_email) = old_email else {
+        return Ok(false);
+    };
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET subscriber_email = $2
+        WHERE subscriber_email = $1
+        "#,
+        old_email,
+        new_email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(true)
+}
+
+/// Send the link confirming a new email address to that address.
+#[tracing::instrument(skip_all)]
+async fn send_email_change_confirmation(
+    email_client: &EmailClient,
+    new_email: &SubscriberEmail,
+    base_url: &str,
+    confirmation_token: &str,
+) -> Result<(), EmailError> {
+    let confirmation_link = format!(
+        "{}/preferences/email/confirm?confirmation_token={}",
+        base_url, confirmation_token
+    );
+    let plain_body = format!(
+        "Visit {} to start receiving our newsletter at this address.",
+        confirmation_link
+    );
+    let html_body = format!(
+        "Click <a href=\"{}\">here</a> to start receiving our newsletter \
+        at this address.",
+        confirmation_link
+    );
+    email_client
+        .send_email(
+            new_email,
+            "Confirm your new email address",
+            &html_body,
+            &plain_body,
+            None,
+        )
+        .await?;
+    Ok(())
+}
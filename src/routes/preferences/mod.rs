@@ -0,0 +1,7 @@
+mod email;
+mod page;
+mod snooze;
+
+pub use email::{confirm_email_change, request_email_change};
+pub use page::preferences_page;
+pub use snooze::snooze_emails;
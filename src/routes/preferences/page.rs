@@ -0,0 +1,76 @@
+use actix_web::error::ErrorUnauthorized;
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+
+use crate::startup::HmacSecret;
+use crate::subscriber_token::{
+    SubscriberAction, issue_subscriber_token, verify_subscriber_token,
+};
+
+/// Query parameters of the preferences link in each newsletter.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    token: String,
+}
+
+/// Show a subscriber the forms to snooze their emails or change their
+/// address.
+/// Each form carries a token scoped to its own action, so the page link
+/// itself cannot be used to change anything.
+/// # Arguments
+/// * `parameters` - The subscriber's preferences token.
+/// * `secret` - The key subscriber tokens are signed with.
+/// # Returns
+/// A Result containing the preferences page.
+#[tracing::instrument(name = "Show the preferences page", skip_all)]
+pub async fn preferences_page(
+    parameters: web::Query<Parameters>,
+    secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = verify_subscriber_token(
+        &parameters.token,
+        SubscriberAction::ManagePreferences,
+        &secret.0,
+    )
+    .ok_or_else(|| ErrorUnauthorized("The preferences link is invalid."))?;
+    let snooze_token = issue_subscriber_token(
+        subscriber_id,
+        SubscriberAction::Snooze,
+        &secret.0,
+    );
+    let change_email_token = issue_subscriber_token(
+        subscriber_id,
+        SubscriberAction::ChangeEmail,
+        &secret.0,
+    );
+    let html_content = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=UTF-8">
+            <title>Your preferences</title>
+        </head>
+        <body>
+            <form action="/preferences/snooze" method="post">
+                <input type="hidden" name="token" value="{snooze_token}">
+                <label>Pause emails until
+                    <input type="date" name="until" required>
+                </label>
+                <button type="submit">Snooze</button>
+            </form>
+            <form action="/preferences/email" method="post">
+                <input type="hidden" name="token" value="{change_email_token}">
+                <label>New email address
+                    <input type="email" name="email" required>
+                </label>
+                <button type="submit">Change address</button>
+            </form>
+        </body>
+        </html>
+    "#
+    );
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_content))
+}
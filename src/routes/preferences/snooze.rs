@@ -0,0 +1,138 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError, web};
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::PgPool;
+
+use crate::routes::error_chain_fmt;
+use crate::startup::HmacSecret;
+use crate::subscriber_token::{SubscriberAction, verify_subscriber_token};
+
+/// Form data for snoozing newsletter emails.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    /// A token scoped to snoozing, not the confirmation token.
+    token: String,
+    /// A date (`2026-12-01`, midnight UTC) or an RFC 3339 timestamp.
+    until: String,
+}
+
+/// Error type for snooze requests.
+#[derive(thiserror::Error)]
+pub enum SnoozeError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("There is no subscriber associated with the provided token.")]
+    UnknownToken,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for SnoozeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for SnoozeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+            Self::UnknownToken => StatusCode::UNAUTHORIZED,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Pause newsletter emails to a subscriber until the given time.
+/// Deliveries due in the meantime are held back and go out once the
+/// snooze ends.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `form` - The subscriber's snooze token and the end of the snooze.
+/// * `secret` - The key snooze tokens are signed with.
+/// # Returns
+/// A Result indicating success or failure of the request.
+#[tracing::instrument(
+    name = "Snooze newsletter emails",
+    skip(pool, form, secret),
+    fields(until = %form.until)
+)]
+pub async fn snooze_emails(
+    pool: web::Data<PgPool>,
+    form: web::Form<FormData>,
+    secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, SnoozeError> {
+    let until =
+        parse_until(&form.until).map_err(SnoozeError::ValidationError)?;
+    let subscriber_id = verify_subscriber_token(
+        &form.token,
+        SubscriberAction::Snooze,
+        &secret.0,
+    )
+    .ok_or(SnoozeError::UnknownToken)?;
+    let snoozed = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET snoozed_until = $2
+        WHERE id = $1
+        "#,
+        subscriber_id,
+        until
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to snooze the subscriber")?;
+    // The token outlives the subscriber if they are deleted.
+    if snoozed.rows_affected() == 0 {
+        return Err(SnoozeError::UnknownToken);
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Parse the end of the snooze, which must be in the future.
+fn parse_until(raw: &str) -> Result<DateTime<Utc>, String> {
+    let raw = raw.trim();
+    let until = DateTime::parse_from_rfc3339(raw)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        })
+        .map_err(|_| format!("'{raw}' is not a valid date."))?;
+    if until <= Utc::now() {
+        return Err("The snooze must end in the future.".into());
+    }
+    Ok(until)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use claim::assert_err;
+
+    use super::parse_until;
+
+    #[test]
+    fn a_future_date_is_midnight_utc() {
+        let until = parse_until("2999-01-31").unwrap();
+        assert_eq!(until.to_rfc3339(), "2999-01-31T00:00:00+00:00");
+    }
+
+    #[test]
+    fn a_future_timestamp_is_accepted() {
+        let tomorrow = Utc::now() + Duration::days(1);
+        let until = parse_until(&tomorrow.to_rfc3339()).unwrap();
+        assert_eq!(until.timestamp(), tomorrow.timestamp());
+    }
+
+    #[test]
+    fn past_dates_are_rejected() {
+        assert_err!(parse_until("2000-01-01"));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert_err!(parse_until("next tuesday"));
+    }
+}
@@ -0,0 +1,61 @@
+use sqlx::{Postgres, Transaction};
+
+use crate::configuration::SubscriberLimitSettings;
+
+/// Which subscribers count toward `max_subscribers`.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CountedSubscribers {
+    /// Only confirmed subscribers; the cap is checked on confirmation.
+    Confirmed,
+    /// Pending subscribers too; the cap is checked on signup.
+    ConfirmedAndPending,
+}
+
+impl CountedSubscribers {
+    fn statuses(self) -> &'static [&'static str] {
+        match self {
+            CountedSubscribers::Confirmed => &["confirmed"],
+            CountedSubscribers::ConfirmedAndPending => {
+                &["confirmed", "pending_confirmation"]
+            }
+        }
+    }
+}
+
+/// Arbitrary key of the advisory lock serialising subscriber cap checks.
+const SUBSCRIBER_LIMIT_LOCK: i64 = 0x6d65_6c69_6572_7801;
+
+/// Check whether the subscriber cap has been reached.
+/// Holds a transaction-scoped lock until the caller's transaction ends, so
+/// concurrent signups or confirmations cannot overshoot the cap together.
+/// # Arguments
+/// * `transaction` - The transaction adding or confirming the subscriber.
+/// * `settings` - The subscriber cap.
+/// # Returns
+/// A Result containing true if no further subscriber may be counted.
+#[tracing::instrument(skip_all)]
+pub async fn subscriber_limit_reached(
+    transaction: &mut Transaction<'_, Postgres>,
+    settings: &SubscriberLimitSettings,
+) -> Result<bool, sqlx::Error> {
+    let Some(max_subscribers) = settings.max_subscribers else {
+        return Ok(false);
+    };
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(SUBSCRIBER_LIMIT_LOCK)
+        .execute(transaction.as_mut())
+        .await?;
+    let statuses = settings.counted.statuses();
+    let counted = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM subscriptions
+        WHERE status = ANY($1)
+        "#,
+        statuses as &[&str]
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    Ok(counted >= max_subscribers)
+}
@@ -1,19 +1,30 @@
+use std::collections::HashMap;
 use std::error;
 use std::fmt;
 use std::iter;
 
 use actix_web::http::StatusCode;
-use actix_web::{HttpResponse, ResponseError, web};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
 use anyhow::Context;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use rand::{Rng, distr::Alphanumeric};
 use sqlx::Executor;
 use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
-use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
-use crate::email_client::EmailClient;
-use crate::startup::ApplicationBaseUrl;
+use crate::configuration::{
+    HoneypotSettings, SignupVelocitySettings, SubscriberLimitSettings,
+    WebhookSettings,
+};
+use crate::domain::{
+    NewSubscriber, SubscriberEmail, SubscriberName, SubscriberStatus,
+};
+use crate::email_client::{EmailClient, EmailError};
+use crate::email_vault::EmailVault;
+use crate::routes::{CountedSubscribers, subscriber_limit_reached};
+use crate::startup::{ApplicationBaseUrl, ConfirmationSendLimit};
+use crate::utils::client_ip;
+use crate::webhooks::{SignupNotificationTrigger, enqueue_signup_notification};
 
 /// Form data structure for new subscriber.
 #[derive(serde::Deserialize)]
@@ -22,6 +33,16 @@ pub struct FormData {
     name: String,
 }
 
+/// The public subscribe form: the subscriber details plus whatever else the
+/// form posts, which may include the honeypot field.
+#[derive(serde::Deserialize)]
+pub struct SubscribeFormData {
+    #[serde(flatten)]
+    subscriber: FormData,
+    #[serde(flatten)]
+    other_fields: HashMap<String, String>,
+}
+
 impl TryFrom<FormData> for NewSubscriber {
     type Error = String;
 
@@ -37,6 +58,14 @@ impl TryFrom<FormData> for NewSubscriber {
 pub enum SubscribeError {
     #[error("{0}")]
     ValidationError(String),
+    #[error("Too many signups - please try again later.")]
+    TooManySignups,
+    #[error("This newsletter has reached its subscriber limit.")]
+    SubscriberLimitReached,
+    #[error("This newsletter is invite-only - public signup is disabled.")]
+    PublicSignupDisabled,
+    #[error("{0}")]
+    Conflict(String),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -51,6 +80,10 @@ impl ResponseError for SubscribeError {
     fn status_code(&self) -> StatusCode {
         match self {
             SubscribeError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            SubscribeError::TooManySignups => StatusCode::TOO_MANY_REQUESTS,
+            SubscribeError::SubscriberLimitReached
+            | SubscribeError::PublicSignupDisabled => StatusCode::FORBIDDEN,
+            SubscribeError::Conflict(_) => StatusCode::CONFLICT,
             SubscribeError::UnexpectedError(_) => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
@@ -82,85 +115,360 @@ impl fmt::Display for StoreTokenError {
     }
 }
 
+/// Stands in for `subscribe` when public signup is disabled.
+/// Pending subscribers can still confirm and existing subscribers are
+/// unaffected.
+pub async fn public_signup_disabled() -> Result<HttpResponse, SubscribeError> {
+    Err(SubscribeError::PublicSignupDisabled)
+}
+
 /// Handles the subscription of a new user.
+/// Submissions that fill in the honeypot field are answered as if they
+/// succeeded, but nothing is stored or sent.
 /// # Arguments
 /// * `pool` - A reference to the PostgreSQL connection pool.
 /// * `form` - The form data containing subscriber details.
 /// * `email_client` - A reference to the EmailClient for sending emails.
 /// * `base_url` - The base URL of the application for constructing confirmation links.
+/// * `webhooks` - The outbound webhook settings.
+/// * `velocity` - The signup velocity thresholds.
+/// * `limit` - The subscriber cap.
+/// * `send_limit` - Bounds concurrent confirmation email sends.
+/// * `vault` - Turns the address into what is stored.
+/// * `honeypot` - The honeypot field settings.
+/// * `request` - The incoming request, used to identify the client.
 /// # Returns
 /// An HTTP response indicating the result of the subscription process.
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(pool, form, email_client, base_url),
+    skip_all,
     fields(
-        subscriber_email = %form.email,
-        subscriber_name = %form.name
+        subscriber_email = %form.subscriber.email,
+        subscriber_name = %form.subscriber.name
     )
 )]
+#[allow(clippy::too_many_arguments)]
 pub async fn subscribe(
     pool: web::Data<PgPool>,
-    form: web::Form<FormData>,
+    form: web::Form<SubscribeFormData>,
     email_client: web::Data<EmailClient>,
     base_url: web::Data<ApplicationBaseUrl>,
+    webhooks: web::Data<WebhookSettings>,
+    velocity: web::Data<SignupVelocitySettings>,
+    limit: web::Data<SubscriberLimitSettings>,
+    send_limit: web::Data<ConfirmationSendLimit>,
+    vault: web::Data<EmailVault>,
+    honeypot: web::Data<HoneypotSettings>,
+    request: HttpRequest,
 ) -> Result<HttpResponse, SubscribeError> {
-    let new_subscriber =
-        form.0.try_into().map_err(SubscribeError::ValidationError)?;
+    let ip = client_ip(&request, velocity.trust_forwarded_for);
+    let SubscribeFormData {
+        subscriber,
+        other_fields,
+    } = form.0;
+    if honeypot.enabled
+        && other_fields
+            .get(&honeypot.field_name)
+            .is_some_and(|value| !value.is_empty())
+    {
+        tracing::warn!(%ip, "Dropping a signup that filled in the honeypot.");
+        return Ok(HttpResponse::Ok().finish());
+    }
+    let new_subscriber: NewSubscriber = subscriber
+        .try_into()
+        .map_err(SubscribeError::ValidationError)?;
+    if !check_signup_velocity(
+        &pool,
+        &velocity,
+        &ip,
+        &new_subscriber.email,
+        &vault,
+    )
+    .await
+    .context("Failed to check the signup velocity")?
+    {
+        tracing::warn!(%ip, "Throttling signups.");
+        return Err(SubscribeError::TooManySignups);
+    }
+    register_subscriber(
+        &pool,
+        new_subscriber,
+        None,
+        &email_client,
+        &base_url,
+        &webhooks,
+        &limit,
+        &send_limit,
+        &vault,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Store a new pending subscriber and send them a confirmation email.
+/// Shared by the public signup form, the partner API and the admin API.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `new_subscriber` - The validated subscriber details.
+/// * `subscribed_at` - When the subscriber originally signed up, for
+///   imports; now if `None`.
+/// * `email_client` - The client used to send the confirmation email.
+/// * `base_url` - The base URL for the confirmation link.
+/// * `webhooks` - The outbound webhook settings.
+/// * `limit` - The subscriber cap.
+/// * `send_limit` - Bounds concurrent confirmation email sends.
+/// * `vault` - Turns the address into what is stored.
+/// # Returns
+/// A Result containing the ID of the new subscriber.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn register_subscriber(
+    pool: &PgPool,
+    new_subscriber: NewSubscriber,
+    subscribed_at: Option<DateTime<Utc>>,
+    email_client: &EmailClient,
+    base_url: &ApplicationBaseUrl,
+    webhooks: &WebhookSettings,
+    limit: &SubscriberLimitSettings,
+    send_limit: &ConfirmationSendLimit,
+    vault: &EmailVault,
+) -> Result<Uuid, SubscribeError> {
     let mut transaction = pool
         .begin()
         .await
         .context("Failed to start a new database transaction")?;
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
-        .await
-        .context("Failed to insert new subscriber in the database")?;
+    if limit.counted == CountedSubscribers::ConfirmedAndPending
+        && subscriber_limit_reached(&mut transaction, limit)
+            .await
+            .context("Failed to check the subscriber limit")?
+    {
+        return Err(SubscribeError::SubscriberLimitReached);
+    }
+    let subscriber_id = insert_subscriber(
+        &mut transaction,
+        &new_subscriber,
+        subscribed_at,
+        vault,
+    )
+    .await
+    .context("Failed to insert new subscriber in the database")?;
     let subscription_token = generate_subscription_token();
     store_token(&mut transaction, subscriber_id, &subscription_token)
         .await
         .context("Failed to store subscription token in the database")?;
+    enqueue_signup_notification(
+        &mut transaction,
+        webhooks,
+        SignupNotificationTrigger::Subscribe,
+        new_subscriber.email.as_ref(),
+    )
+    .await
+    .context("Failed to enqueue the signup notification")?;
     transaction
         .commit()
         .await
         .context("Failed to commit SQL transaction.")?;
+    let _permit = send_limit
+        .0
+        .acquire()
+        .await
+        .context("The confirmation send limit was closed.")?;
     send_confirmation_email(
-        &email_client,
+        email_client,
         new_subscriber,
         &base_url.0,
         &subscription_token,
     )
     .await
     .context("Failed to send a confirmation email.")?;
-    Ok(HttpResponse::Ok().finish())
+    Ok(subscriber_id)
+}
+
+/// Record a signup attempt and check it against the velocity thresholds.
+/// Rejected attempts are recorded too, so a client that keeps hammering
+/// stays throttled until it backs off for a whole window.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `settings` - The signup velocity thresholds.
+/// * `ip` - The client's IP address.
+/// * `email` - The email address being subscribed.
+/// * `vault` - Hashes the email pattern when addresses are stored hashed.
+/// # Returns
+/// A Result containing true if the signup may go ahead.
+#[tracing::instrument(skip(pool, settings, email, vault))]
+async fn check_signup_velocity(
+    pool: &PgPool,
+    settings: &SignupVelocitySettings,
+    ip: &str,
+    email: &SubscriberEmail,
+    vault: &EmailVault,
+) -> Result<bool, sqlx::Error> {
+    let window = f64::from(settings.window_seconds);
+    let email_pattern = vault.lookup_key(&email.signup_pattern());
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM signup_attempts
+        WHERE attempted_at < now() - make_interval(secs => $1)
+        "#,
+        window
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO signup_attempts (ip, email_pattern)
+        VALUES ($1, $2)
+        "#,
+        ip,
+        email_pattern
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    let recent = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE ip = $1) AS "by_ip!",
+            COUNT(*) FILTER (WHERE email_pattern = $2) AS "by_pattern!"
+        FROM signup_attempts
+        WHERE
+            (ip = $1 OR email_pattern = $2) AND
+            attempted_at >= now() - make_interval(secs => $3)
+        "#,
+        ip,
+        email_pattern,
+        window
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    transaction.commit().await?;
+    Ok(recent.by_ip <= settings.max_signups_per_ip
+        && recent.by_pattern <= settings.max_signups_per_email_pattern)
 }
 
 /// Saves the new subscriber details in the database.
 /// # Arguments
 /// * `transaction` - A mutable reference to the database transaction.
 /// * `new_subscriber` - A reference to the NewSubscriber struct containing subscriber details.
+/// * `subscribed_at` - When the subscriber signed up; now if `None`.
+/// * `vault` - Turns the address into what is stored.
 /// # Returns
 /// The UUID of the newly created subscriber.
 #[tracing::instrument(
     name = "Saving new subscriber details in the database",
-    skip(transaction, new_subscriber)
+    skip(transaction, new_subscriber, vault)
 )]
 pub async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
-) -> Result<Uuid, sqlx::Error> {
+    subscribed_at: Option<DateTime<Utc>>,
+    vault: &EmailVault,
+) -> Result<Uuid, anyhow::Error> {
     let subscriber_id = Uuid::new_v4();
+    let email = new_subscriber.email.as_ref();
     let query = sqlx::query!(
         r#"
-        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        INSERT INTO subscriptions
+            (id, email, encrypted_email, name, subscribed_at, status)
+        VALUES ($1, $2, $3, $4, $5, $6)
         "#,
         subscriber_id,
-        new_subscriber.email.as_ref(),
+        vault.lookup_key(email),
+        vault.seal(email)?,
         new_subscriber.name.as_ref(),
-        Utc::now().naive_utc()
+        subscribed_at.unwrap_or_else(Utc::now).naive_utc(),
+        SubscriberStatus::PendingConfirmation.as_str()
     );
     transaction.execute(query).await?;
     Ok(subscriber_id)
 }
 
+/// Look up a subscriber's status, locking the row until the transaction ends
+/// so the status cannot change before `set_subscriber_status` is called.
+/// # Arguments
+/// * `transaction` - A mutable reference to the database transaction.
+/// * `subscriber_id` - The UUID of the subscriber.
+/// # Returns
+/// A Result containing the status, or None if there is no such subscriber.
+#[tracing::instrument(skip(transaction))]
+pub async fn lock_subscriber_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<Option<SubscriberStatus>, anyhow::Error> {
+    let status = sqlx::query_scalar!(
+        "SELECT status FROM subscriptions WHERE id = $1 FOR UPDATE",
+        subscriber_id
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?;
+    status
+        .map(|s| SubscriberStatus::parse(&s).map_err(anyhow::Error::msg))
+        .transpose()
+}
+
+/// Look up a subscriber's ID and status by email, locking the row until the
+/// transaction ends.
+/// # Arguments
+/// * `transaction` - A mutable reference to the database transaction.
+/// * `subscriber_email` - The subscriber's email address.
+/// # Returns
+/// A Result containing the ID and status, or None if there is no such
+/// subscriber.
+#[tracing::instrument(skip(transaction))]
+pub async fn lock_subscriber_status_by_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_email: &str,
+) -> Result<Option<(Uuid, SubscriberStatus)>, anyhow::Error> {
+    let row = sqlx::query!(
+        "SELECT id, status FROM subscriptions WHERE email = $1 FOR UPDATE",
+        subscriber_email
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?;
+    row.map(|r| {
+        let status =
+            SubscriberStatus::parse(&r.status).map_err(anyhow::Error::msg)?;
+        Ok((r.id, status))
+    })
+    .transpose()
+}
+
+/// A subscriber's address as stored in `subscriptions`.
+/// With hashed storage `email` is only a lookup key; integrations that are
+/// sent the address get it from `EmailVault::reveal`.
+pub struct StoredEmail {
+    pub email: String,
+    pub encrypted_email: Option<Vec<u8>>,
+}
+
+/// Store a subscriber's new status.
+/// Get it from `transition_status`, so only legal changes are written.
+/// # Arguments
+/// * `transaction` - A mutable reference to the database transaction.
+/// * `subscriber_id` - The UUID of the subscriber.
+/// * `status` - The new status.
+/// # Returns
+/// A Result containing the subscriber's stored address, or a sqlx::Error.
+#[tracing::instrument(skip(transaction))]
+pub async fn set_subscriber_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    status: SubscriberStatus,
+) -> Result<StoredEmail, sqlx::Error> {
+    sqlx::query_as!(
+        StoredEmail,
+        r#"
+        UPDATE subscriptions
+        SET status = $1
+        WHERE id = $2
+        RETURNING email, encrypted_email
+        "#,
+        status.as_str(),
+        subscriber_id
+    )
+    .fetch_one(transaction.as_mut())
+    .await
+}
+
 /// Sends a confirmation email to the new subscriber.
 /// # Arguments
 /// * `email_client` - A reference to the EmailClient for sending emails.
@@ -178,7 +486,7 @@ pub async fn send_confirmation_email(
     new_subscriber: NewSubscriber,
     base_url: &str,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), EmailError> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token
@@ -193,8 +501,15 @@ pub async fn send_confirmation_email(
         confirmation_link
     );
     email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
-        .await
+        .send_email(
+            &new_subscriber.email,
+            "Welcome!",
+            &html_body,
+            &plain_body,
+            None,
+        )
+        .await?;
+    Ok(())
 }
 
 /// Stores the subscription token in the database associated with the subscriber ID.
@@ -228,7 +543,7 @@ pub async fn store_token(
 /// Generates a random subscription token.
 /// # Returns
 /// A randomly generated subscription token string.
-fn generate_subscription_token() -> String {
+pub(crate) fn generate_subscription_token() -> String {
     let mut rng = rand::rng();
     iter::repeat_with(|| rng.sample(Alphanumeric))
         .take(25)
@@ -246,7 +561,7 @@ pub fn error_chain_fmt(
     e: &impl error::Error,
     f: &mut fmt::Formatter<'_>,
 ) -> fmt::Result {
-    write!(f, "{}\n", e)?;
+    writeln!(f, "{}", e)?;
     let mut current = e.source();
     while let Some(cause) = current {
         writeln!(f, "Caused by:\n\t{}", cause)?;
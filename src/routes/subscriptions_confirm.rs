@@ -4,7 +4,20 @@ use anyhow::Context;
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::routes::error_chain_fmt;
+use crate::configuration::{
+    OnboardingSettings, SubscriberLimitSettings, WebhookSettings,
+};
+use crate::domain::{
+    InvalidTransition, StatusEvent, SubscriberStatus, transition_status,
+};
+use crate::email_vault::EmailVault;
+use crate::onboarding::schedule_onboarding;
+use crate::routes::{
+    CountedSubscribers, error_chain_fmt, lock_subscriber_status,
+    set_subscriber_status, subscriber_limit_reached,
+};
+use crate::webhooks::{SignupNotificationTrigger, WebhookEvent};
+use crate::webhooks::{enqueue_signup_notification, enqueue_webhook};
 
 /// Query parameters structure for subscription confirmation.
 #[derive(serde::Deserialize)]
@@ -19,6 +32,10 @@ pub enum ConfirmationError {
     UnexpectedError(#[from] anyhow::Error),
     #[error("There is no subscriber associated with the provided token.")]
     UnknownToken,
+    #[error("This newsletter has reached its subscriber limit.")]
+    SubscriberLimitReached,
+    #[error("This subscription can no longer be confirmed.")]
+    NotConfirmable(#[from] InvalidTransition),
 }
 
 impl std::fmt::Debug for ConfirmationError {
@@ -31,33 +48,93 @@ impl ResponseError for ConfirmationError {
     fn status_code(&self) -> StatusCode {
         match self {
             Self::UnknownToken => StatusCode::UNAUTHORIZED,
+            Self::SubscriberLimitReached => StatusCode::FORBIDDEN,
+            Self::NotConfirmable(_) => StatusCode::CONFLICT,
             Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
 /// Handles the confirmation of a pending subscription.
+/// Confirming starts the onboarding sequence; confirming again does not
+/// schedule it twice. A subscriber who has since unsubscribed is not
+/// confirmed again by an old link.
 /// # Arguments
 /// * `pool` - A reference to the PostgreSQL connection pool.
 /// * `parameters` - The query parameters containing the subscription token.
+/// * `webhooks` - The outbound webhook settings.
+/// * `limit` - The subscriber cap.
+/// * `onboarding` - The onboarding sequence definition.
+/// * `vault` - Recovers the address sent to the webhooks.
 /// # Returns
 /// A Result indicating success or failure of the confirmation process.
 #[tracing::instrument(
     name = "Confirm a pending subscription",
-    skip(pool, parameters)
+    skip(pool, parameters, webhooks, limit, onboarding, vault)
 )]
 pub async fn confirm(
     pool: web::Data<PgPool>,
     parameters: web::Query<Parameters>,
+    webhooks: web::Data<WebhookSettings>,
+    limit: web::Data<SubscriberLimitSettings>,
+    onboarding: web::Data<OnboardingSettings>,
+    vault: web::Data<EmailVault>,
 ) -> Result<HttpResponse, ConfirmationError> {
     let subscriber_id = get_subscriber_id_from_token(&pool, &parameters.subscription_token)
         .await
         .context("Failed to retrieve the subscriber id associated with the provided token.")?
         .ok_or(ConfirmationError::UnknownToken)?;
 
-    confirm_subscriber(&pool, subscriber_id)
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a new database transaction")?;
+    let current = lock_subscriber_status(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to look up the subscriber status.")?
+        .ok_or(ConfirmationError::UnknownToken)?;
+    let status = transition_status(current, StatusEvent::Confirm)?;
+    // Only a newly confirmed subscriber adds to the confirmed count.
+    if limit.counted == CountedSubscribers::Confirmed
+        && current != SubscriberStatus::Confirmed
+        && subscriber_limit_reached(&mut transaction, &limit)
+            .await
+            .context("Failed to check the subscriber limit.")?
+    {
+        return Err(ConfirmationError::SubscriberLimitReached);
+    }
+    let stored = set_subscriber_status(&mut transaction, subscriber_id, status)
         .await
         .context("Failed to update the subscriber status to `confirmed`.")?;
+    let email = vault
+        .reveal(&stored.email, stored.encrypted_email.as_deref())
+        .context("Failed to recover the subscriber's address.")?;
+    enqueue_webhook(
+        &mut transaction,
+        &webhooks,
+        WebhookEvent::SubscriberConfirmed,
+        serde_json::json!({
+            "subscriber_id": subscriber_id,
+            "email": email,
+        }),
+    )
+    .await
+    .context("Failed to enqueue the `subscriber.confirmed` webhook.")?;
+    enqueue_signup_notification(
+        &mut transaction,
+        &webhooks,
+        SignupNotificationTrigger::Confirm,
+        &email,
+    )
+    .await
+    .context("Failed to enqueue the signup notification.")?;
+    schedule_onboarding(&mut transaction, subscriber_id, &onboarding, false)
+        .await
+        .context("Failed to schedule the onboarding sequence.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction.")?;
 
     Ok(HttpResponse::Ok().finish())
 }
@@ -87,30 +164,3 @@ pub async fn get_subscriber_id_from_token(
     .await?;
     Ok(result.map(|r| r.subscriber_id))
 }
-
-/// Marks the subscriber as confirmed in the database.
-/// # Arguments
-/// * `pool` - A reference to the PostgreSQL connection pool.
-/// * `subscriber_id` - The UUID of the subscriber to be confirmed.
-/// # Returns
-/// A Result indicating success or failure of the operation.
-#[tracing::instrument(
-    name = "Marking subscription as confirmed",
-    skip(subscriber_id, pool)
-)]
-pub async fn confirm_subscriber(
-    pool: &PgPool,
-    subscriber_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"
-        UPDATE subscriptions
-        SET status = 'confirmed'
-        WHERE id = $1
-        "#,
-        subscriber_id
-    )
-    .execute(pool)
-    .await?;
-    Ok(())
-}
@@ -0,0 +1,173 @@
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::configuration::ConfirmationResendSettings;
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
+use crate::email_client::EmailClient;
+use crate::email_vault::EmailVault;
+use crate::routes::{
+    SubscribeError, generate_subscription_token, send_confirmation_email,
+    store_token,
+};
+use crate::startup::{ApplicationBaseUrl, ConfirmationSendLimit};
+
+/// Form data for resending a confirmation email.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+}
+
+struct PendingSubscriber {
+    id: Uuid,
+    name: String,
+}
+
+/// Resend the confirmation email to a pending subscriber who lost it.
+/// The response is the same whether or not the address belongs to a pending
+/// subscriber, so the form cannot be used to find out who subscribed. Each
+/// subscriber is sent at most one email per cooldown, and their existing
+/// token is reused, so links from earlier emails keep working.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `form` - The subscriber's email address.
+/// * `email_client` - The client used to send the confirmation email.
+/// * `base_url` - The base URL for the confirmation link.
+/// * `settings` - The resend cooldown.
+/// * `send_limit` - Bounds concurrent confirmation email sends.
+/// * `vault` - Turns the address into what is stored.
+/// # Returns
+/// A Result indicating success, or a 400 for an invalid address.
+#[tracing::instrument(
+    name = "Resend a confirmation email",
+    skip_all,
+    fields(subscriber_email = %form.email)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn resend_confirmation(
+    pool: web::Data<PgPool>,
+    form: web::Form<FormData>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<ConfirmationResendSettings>,
+    send_limit: web::Data<ConfirmationSendLimit>,
+    vault: web::Data<EmailVault>,
+) -> Result<HttpResponse, SubscribeError> {
+    let email = SubscriberEmail::parse(form.0.email)
+        .map_err(SubscribeError::ValidationError)?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a new database transaction")?;
+    let Some(subscriber) = claim_resend(
+        &mut transaction,
+        &vault.lookup_key(email.as_ref()),
+        settings.cooldown_seconds,
+    )
+    .await
+    .context("Failed to look up the pending subscriber")?
+    else {
+        return Ok(HttpResponse::Ok().finish());
+    };
+    let subscription_token = match get_token(&mut transaction, subscriber.id)
+        .await
+        .context("Failed to look up the subscription token")?
+    {
+        Some(token) => token,
+        None => {
+            let token = generate_subscription_token();
+            store_token(&mut transaction, subscriber.id, &token)
+                .await
+                .context(
+                    "Failed to store subscription token in the database",
+                )?;
+            token
+        }
+    };
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction.")?;
+
+    let new_subscriber = NewSubscriber {
+        email,
+        name: SubscriberName::parse(subscriber.name)
+            .map_err(anyhow::Error::msg)
+            .context("The stored subscriber name is invalid")?,
+    };
+    let _permit = send_limit
+        .0
+        .acquire()
+        .await
+        .context("The confirmation send limit was closed.")?;
+    // A failure is not reported to the client: it would tell them that the
+    // address is pending.
+    if let Err(e) = send_confirmation_email(
+        &email_client,
+        new_subscriber,
+        &base_url.0,
+        &subscription_token,
+    )
+    .await
+    {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            subscriber_id = %subscriber.id,
+            "Failed to resend a confirmation email."
+        );
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Find the pending subscriber with the given address and start their
+/// resend cooldown.
+/// # Returns
+/// A Result containing the subscriber, or None if there is no pending
+/// subscriber with the address or they are still cooling down.
+#[tracing::instrument(skip(transaction, lookup_key))]
+async fn claim_resend(
+    transaction: &mut Transaction<'_, Postgres>,
+    lookup_key: &str,
+    cooldown_seconds: u32,
+) -> Result<Option<PendingSubscriber>, sqlx::Error> {
+    sqlx::query_as!(
+        PendingSubscriber,
+        r#"
+        UPDATE subscriptions
+        SET confirmation_resent_at = now()
+        WHERE
+            lower(email) = lower($1) AND
+            status = 'pending_confirmation' AND
+            (
+                confirmation_resent_at IS NULL OR
+                confirmation_resent_at
+                    < now() - make_interval(secs => $2)
+            )
+        RETURNING id, name
+        "#,
+        lookup_key,
+        f64::from(cooldown_seconds)
+    )
+    .fetch_optional(transaction.as_mut())
+    .await
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn get_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT subscription_token
+        FROM subscription_tokens
+        WHERE subscriber_id = $1
+        LIMIT 1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(transaction.as_mut())
+    .await
+}
@@ -0,0 +1,322 @@
+use actix_web::http::StatusCode;
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, ResponseError, web};
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::configuration::WebhookSettings;
+use crate::domain::{StatusEvent, transition_status};
+use crate::email_vault::EmailVault;
+use crate::routes::{
+    StoredEmail, error_chain_fmt, get_subscriber_id_from_token,
+    lock_subscriber_status, set_subscriber_status,
+};
+use crate::startup::HmacSecret;
+use crate::subscriber_token::{SubscriberAction, verify_subscriber_token};
+use crate::webhooks::{WebhookEvent, enqueue_webhook};
+
+/// Parameters of the unsubscribe links embedded in each newsletter, also
+/// posted back by the confirmation page.
+/// On a POST the confirmation page sends them in the form; mail clients
+/// doing an RFC 8058 one-click unsubscribe post `List-Unsubscribe=One-Click`
+/// to the link from the `List-Unsubscribe` header, which carries them in the
+/// query string.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    /// A token scoped to unsubscribing, not the confirmation token.
+    token: Option<String>,
+    /// The confirmation token, which unsubscribe links carried before they
+    /// got tokens of their own. Newsletters already in inboxes still carry
+    /// it, so it keeps unsubscribing, and only unsubscribing, for a
+    /// deprecation period.
+    subscription_token: Option<String>,
+    /// The issue whose email carried the link, if any.
+    issue_id: Option<Uuid>,
+}
+
+/// Error type for unsubscribe failures.
+#[derive(thiserror::Error)]
+pub enum UnsubscribeError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+    #[error("There is no subscriber associated with the provided token.")]
+    UnknownToken,
+    #[error("The unsubscribe token is missing.")]
+    MissingToken,
+}
+
+impl std::fmt::Debug for UnsubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for UnsubscribeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::UnknownToken | Self::MissingToken => StatusCode::BAD_REQUEST,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Handles a subscriber opting out in one click, either through the
+/// `List-Unsubscribe` header or a one-click footer link.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `parameters` - The unsubscribe token and originating issue.
+/// * `webhooks` - The outbound webhook settings.
+/// * `secret` - The key unsubscribe tokens are signed with.
+/// * `vault` - Recovers the address sent to the webhooks.
+/// # Returns
+/// A Result containing the page confirming the unsubscribe.
+#[tracing::instrument(
+    name = "Unsubscribe a subscriber",
+    skip(pool, parameters, webhooks, secret, vault),
+    fields(issue_id = ?parameters.issue_id)
+)]
+pub async fn unsubscribe(
+    pool: web::Data<PgPool>,
+    parameters: web::Query<Parameters>,
+    webhooks: web::Data<WebhookSettings>,
+    secret: web::Data<HmacSecret>,
+    vault: web::Data<EmailVault>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    unsubscribe_subscriber(&pool, &parameters, &webhooks, &secret, &vault)
+        .await?;
+    Ok(unsubscribed_page())
+}
+
+/// Show a page asking the subscriber to confirm they want to unsubscribe.
+/// Nothing changes until the page's form is posted.
+/// # Arguments
+/// * `parameters` - The unsubscribe token and originating issue.
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `secret` - The key unsubscribe tokens are signed with.
+/// # Returns
+/// A Result containing the confirmation page.
+#[tracing::instrument(
+    name = "Show the unsubscribe confirmation page",
+    skip(parameters, pool, secret),
+    fields(issue_id = ?parameters.issue_id)
+)]
+pub async fn unsubscribe_confirmation_page(
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+    secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    find_subscriber(&pool, &parameters, &secret).await?;
+
+    // A legacy link is posted back as it came.
+    let (token_name, token) = match &parameters.token {
+        Some(token) => ("token", token),
+        None => (
+            "subscription_token",
+            parameters
+                .subscription_token
+                .as_ref()
+                .ok_or(UnsubscribeError::MissingToken)?,
+        ),
+    };
+    let token = htmlescape::encode_attribute(token);
+    let issue_input = match parameters.issue_id {
+        Some(issue_id) => format!(
+            r#"<input type="hidden" name="issue_id" value="{issue_id}">"#
+        ),
+        None => String::new(),
+    };
+    let html_content = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=UTF-8">
+            <title>Unsubscribe</title>
+        </head>
+        <body>
+            <p>Do you want to stop receiving this newsletter?</p>
+            <form action="/subscriptions/unsubscribe" method="post">
+                <input type="hidden" name="{token_name}" value="{token}">
+                {issue_input}
+                <button type="submit">Unsubscribe</button>
+            </form>
+        </body>
+        </html>
+    "#
+    );
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_content))
+}
+
+/// Handles a subscriber confirming the unsubscribe on the confirmation page,
+/// or a mail client unsubscribing them in one click (RFC 8058).
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `query` - The unsubscribe token and originating issue of a one-click
+///   unsubscribe.
+/// * `form` - The unsubscribe token and originating issue of the
+///   confirmation page.
+/// * `webhooks` - The outbound webhook settings.
+/// * `secret` - The key unsubscribe tokens are signed with.
+/// * `vault` - Recovers the address sent to the webhooks.
+/// # Returns
+/// A Result indicating success or failure of the unsubscribe.
+#[tracing::instrument(
+    name = "Confirm an unsubscribe",
+    skip(pool, query, form, webhooks, secret, vault)
+)]
+pub async fn confirm_unsubscribe(
+    pool: web::Data<PgPool>,
+    query: web::Query<Parameters>,
+    form: web::Form<Parameters>,
+    webhooks: web::Data<WebhookSettings>,
+    secret: web::Data<HmacSecret>,
+    vault: web::Data<EmailVault>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    let (query, form) = (query.into_inner(), form.into_inner());
+    let parameters = Parameters {
+        token: form.token.or(query.token),
+        subscription_token: form
+            .subscription_token
+            .or(query.subscription_token),
+        issue_id: form.issue_id.or(query.issue_id),
+    };
+    unsubscribe_subscriber(&pool, &parameters, &webhooks, &secret, &vault)
+        .await?;
+    Ok(unsubscribed_page())
+}
+
+/// The page shown once the subscriber is off the list, however they got
+/// there and whether or not they already were.
+fn unsubscribed_page() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body("<p>You have been unsubscribed.</p>")
+}
+
+/// Unsubscribe the subscriber the token was issued for.
+/// The opt-out is recorded in `email_events` against the issue the link
+/// came from, so unsubscribes can be attributed per issue. Unsubscribing
+/// again, or after the subscriber was deleted, is a no-op.
+async fn unsubscribe_subscriber(
+    pool: &PgPool,
+    parameters: &Parameters,
+    webhooks: &WebhookSettings,
+    secret: &HmacSecret,
+    vault: &EmailVault,
+) -> Result<(), UnsubscribeError> {
+    let subscriber_id = find_subscriber(pool, parameters, secret).await?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to start a new database transaction")?;
+    let Some(stored) = mark_unsubscribed(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to update the subscriber status to `unsubscribed`")?
+    else {
+        return Ok(());
+    };
+    record_unsubscribe(&mut transaction, &stored.email, parameters.issue_id)
+        .await
+        .context("Failed to record the unsubscribe")?;
+    let email = vault
+        .reveal(&stored.email, stored.encrypted_email.as_deref())
+        .context("Failed to recover the subscriber's address")?;
+    enqueue_webhook(
+        &mut transaction,
+        webhooks,
+        WebhookEvent::SubscriberUnsubscribed,
+        serde_json::json!({
+            "subscriber_id": subscriber_id,
+            "email": email,
+            "issue_id": parameters.issue_id,
+        }),
+    )
+    .await
+    .context("Failed to enqueue the `subscriber.unsubscribed` webhook")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction")?;
+    Ok(())
+}
+
+/// Find the subscriber an unsubscribe request is for, from its scoped
+/// token or, failing that, a legacy confirmation token.
+async fn find_subscriber(
+    pool: &PgPool,
+    parameters: &Parameters,
+    secret: &HmacSecret,
+) -> Result<Uuid, UnsubscribeError> {
+    if let Some(token) = &parameters.token {
+        return verify_subscriber_token(
+            token,
+            SubscriberAction::Unsubscribe,
+            &secret.0,
+        )
+        .ok_or(UnsubscribeError::UnknownToken);
+    }
+    let token = parameters
+        .subscription_token
+        .as_ref()
+        .ok_or(UnsubscribeError::MissingToken)?;
+    tracing::info!("Unsubscribing through a legacy unsubscribe link.");
+    get_subscriber_id_from_token(pool, token)
+        .await
+        .context("Failed to look up the subscriber")?
+        .ok_or(UnsubscribeError::UnknownToken)
+}
+
+/// Marks the subscriber as unsubscribed.
+/// # Returns
+/// A Result containing the subscriber's stored address, or None if they had
+/// already unsubscribed.
+#[tracing::instrument(skip(transaction))]
+async fn mark_unsubscribed(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<Option<StoredEmail>, anyhow::Error> {
+    let Some(current) =
+        lock_subscriber_status(transaction, subscriber_id).await?
+    else {
+        return Ok(None);
+    };
+    let status = transition_status(current, StatusEvent::Unsubscribe)?;
+    if status == current {
+        return Ok(None);
+    }
+    let stored =
+        set_subscriber_status(transaction, subscriber_id, status).await?;
+    Ok(Some(stored))
+}
+
+/// Store the unsubscribe as an email event.
+/// An issue id that does not match any issue is dropped rather than
+/// failing the opt-out.
+#[tracing::instrument(skip(transaction))]
+pub async fn record_unsubscribe(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_email: &str,
+    issue_id: Option<Uuid>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO email_events (id, issue_id, subscriber_email, event_type)
+        VALUES (
+            $1,
+            (SELECT issue_id FROM issues WHERE issue_id = $2),
+            $3,
+            'unsubscribe'
+        )
+        "#,
+        Uuid::new_v4(),
+        issue_id,
+        subscriber_email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use sqlx::{Connection, PgConnection, PgPool};
+
+use crate::configuration::SchedulerSettings;
+
+/// Elects a single instance to run scheduled jobs.
+/// Leadership is a session-level Postgres advisory lock held on a
+/// connection taken out of the pool for as long as the instance leads.
+/// Dropping the election (e.g. when the worker task is cancelled on
+/// shutdown) closes that connection, which releases the lock so another
+/// instance can take over.
+pub struct LeaderElection {
+    pool: PgPool,
+    settings: SchedulerSettings,
+    /// The connection holding the lock while this instance is the leader.
+    lease: Option<PgConnection>,
+}
+
+impl LeaderElection {
+    pub fn new(pool: PgPool, settings: SchedulerSettings) -> Self {
+        Self {
+            pool,
+            settings,
+            lease: None,
+        }
+    }
+
+    /// Whether this instance should run scheduled jobs right now.
+    /// A follower tries to take over leadership on every call; a leader
+    /// checks that the connection holding the lock is still alive.
+    /// Every instance leads when leader election is disabled.
+    /// # Returns
+    /// True if this instance is the leader.
+    #[tracing::instrument(skip(self))]
+    pub async fn is_leader(&mut self) -> bool {
+        if !self.settings.leader_election {
+            return true;
+        }
+        if let Some(lease) = self.lease.as_mut() {
+            if lease.ping().await.is_ok() {
+                return true;
+            }
+            tracing::warn!("Lost the scheduler leadership connection.");
+            self.lease = None;
+        }
+        match self.try_acquire().await {
+            Ok(lease) => {
+                if lease.is_some() {
+                    tracing::info!("Acquired the scheduler leadership.");
+                }
+                self.lease = lease;
+                self.lease.is_some()
+            }
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to contend for the scheduler leadership.",
+                );
+                false
+            }
+        }
+    }
+
+    async fn try_acquire(&self) -> Result<Option<PgConnection>, sqlx::Error> {
+        // A pooled connection would go back to the pool still holding the
+        // lock, so the lease owns its connection outright.
+        let mut connection = self.pool.acquire().await?.detach();
+        let acquired: bool =
+            sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+                .bind(self.settings.lock_key)
+                .fetch_one(&mut connection)
+                .await?;
+        if acquired {
+            Ok(Some(connection))
+        } else {
+            connection.close().await?;
+            Ok(None)
+        }
+    }
+
+    /// Give up leadership so another instance can take over.
+    #[tracing::instrument(skip(self))]
+    pub async fn step_down(&mut self) {
+        if let Some(lease) = self.lease.take()
+            && let Err(e) = lease.close().await
+        {
+            // The lock goes away with the session either way.
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to close the scheduler leadership connection.",
+            );
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+
+#[derive(Debug)]
+struct Usage {
+    day: NaiveDate,
+    sent: u64,
+}
+
+/// A process-wide cap on the number of emails sent per UTC day.
+/// This is a last-resort safety valve against a bug sending in a loop, not
+/// a rate limit: once the cap is hit every send is refused until midnight
+/// UTC, when the count starts over.
+#[derive(Debug)]
+pub struct DailySendCap {
+    max_per_day: u64,
+    usage: Mutex<Usage>,
+}
+
+impl DailySendCap {
+    pub fn new(max_per_day: u64) -> Self {
+        Self {
+            max_per_day,
+            usage: Mutex::new(Usage {
+                day: Utc::now().date_naive(),
+                sent: 0,
+            }),
+        }
+    }
+
+    /// Count a send against today's cap.
+    /// # Returns
+    /// True if the send may go ahead.
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_on(Utc::now().date_naive())
+    }
+
+    fn try_acquire_on(&self, today: NaiveDate) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        if usage.day != today {
+            *usage = Usage {
+                day: today,
+                sent: 0,
+            };
+        }
+        if usage.sent >= self.max_per_day {
+            return false;
+        }
+        usage.sent += 1;
+        if usage.sent == self.max_per_day {
+            tracing::error!(
+                max_per_day = self.max_per_day,
+                "Reached the daily email send cap. \
+                Refusing to send more emails until midnight UTC."
+            );
+        }
+        true
+    }
+
+    /// How long until the count starts over.
+    pub fn resets_in(&self) -> Duration {
+        let now = Utc::now();
+        let midnight = now
+            .date_naive()
+            .succ_opt()
+            .and_then(|tomorrow| tomorrow.and_hms_opt(0, 0, 0))
+            .expect("Tomorrow is a valid date")
+            .and_utc();
+        (midnight - now).to_std().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::DailySendCap;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, d).unwrap()
+    }
+
+    #[test]
+    fn sends_beyond_the_cap_are_refused() {
+        let cap = DailySendCap::new(2);
+
+        assert!(cap.try_acquire_on(day(15)));
+        assert!(cap.try_acquire_on(day(15)));
+
+        assert!(!cap.try_acquire_on(day(15)));
+        assert!(!cap.try_acquire_on(day(15)));
+    }
+
+    #[test]
+    fn the_cap_resets_on_a_new_day() {
+        let cap = DailySendCap::new(1);
+        assert!(cap.try_acquire_on(day(15)));
+        assert!(!cap.try_acquire_on(day(15)));
+
+        assert!(cap.try_acquire_on(day(16)));
+        assert!(!cap.try_acquire_on(day(16)));
+    }
+
+    #[test]
+    fn a_zero_cap_refuses_every_send() {
+        let cap = DailySendCap::new(0);
+
+        assert!(!cap.try_acquire_on(day(15)));
+    }
+
+    #[test]
+    fn the_reset_is_at_most_a_day_away() {
+        let cap = DailySendCap::new(1);
+
+        assert!(cap.resets_in() <= std::time::Duration::from_secs(86_400));
+    }
+}
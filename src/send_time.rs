@@ -0,0 +1,146 @@
+use chrono::{DateTime, Duration, DurationRound, Timelike, Utc};
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::configuration::SendTimeOptimizationSettings;
+
+/// Work out when to deliver an issue to a subscriber who usually opens
+/// emails during `preferred_hour` (UTC).
+/// Delivery is immediate when there is no history, when the subscriber's
+/// hour is the current one, or when waiting for it would take longer than
+/// `max_delay_hours`.
+/// # Arguments
+/// * `now` - The time the issue is released for delivery.
+/// * `preferred_hour` - The hour of day the subscriber opens most emails in.
+/// * `max_delay_hours` - The longest a delivery may be held back.
+/// # Returns
+/// The time the delivery becomes due.
+pub fn optimized_send_time(
+    now: DateTime<Utc>,
+    preferred_hour: Option<u32>,
+    max_delay_hours: u32,
+) -> DateTime<Utc> {
+    let Some(preferred_hour) = preferred_hour.filter(|h| *h < 24) else {
+        return now;
+    };
+    let hours_ahead = (preferred_hour + 24 - now.hour()) % 24;
+    if hours_ahead == 0 {
+        return now;
+    }
+    let send_at = now.duration_trunc(Duration::hours(1)).unwrap_or(now)
+        + Duration::hours(hours_ahead.into());
+    if send_at - now > Duration::hours(max_delay_hours.into()) {
+        return now;
+    }
+    send_at
+}
+
+/// Hold back each enqueued delivery of an issue until the hour its
+/// recipient usually opens emails in, judging by their recorded opens.
+/// # Arguments
+/// * `transaction` - The transaction the deliveries were enqueued in.
+/// * `issue_id` - The UUID of the newsletter issue.
+/// * `settings` - The send-time optimization settings.
+/// # Returns
+/// A Result containing the number of deliveries that were held back.
+#[tracing::instrument(skip(transaction, settings))]
+pub async fn optimize_send_times(
+    transaction: &mut Transaction<'static, Postgres>,
+    issue_id: Uuid,
+    settings: &SendTimeOptimizationSettings,
+) -> Result<u64, sqlx::Error> {
+    let preferred_hours = sqlx::query!(
+        r#"
+        SELECT DISTINCT ON (q.subscriber_email)
+            q.subscriber_email,
+            EXTRACT(HOUR FROM e.occurred_at AT TIME ZONE 'UTC')::int4
+                AS "hour!"
+        FROM issue_delivery_queue q
+        JOIN email_events e
+            ON e.subscriber_email = q.subscriber_email AND
+               e.event_type = 'open'
+        WHERE q.issue_id = $1
+        GROUP BY q.subscriber_email, 2
+        ORDER BY q.subscriber_email, COUNT(*) DESC, 2
+        "#,
+        issue_id
+    )
+    .fetch_all(transaction.as_mut())
+    .await?;
+
+    let now = Utc::now();
+    let (emails, send_times): (Vec<String>, Vec<DateTime<Utc>>) =
+        preferred_hours
+            .into_iter()
+            .filter_map(|row| {
+                let send_at = optimized_send_time(
+                    now,
+                    u32::try_from(row.hour).ok(),
+                    settings.max_delay_hours,
+                );
+                (send_at > now).then_some((row.subscriber_email, send_at))
+            })
+            .unzip();
+    let held_back = sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue q
+        SET execute_after = t.send_at
+        FROM UNNEST($2::text[], $3::timestamptz[]) AS t(subscriber_email, send_at)
+        WHERE q.issue_id = $1 AND q.subscriber_email = t.subscriber_email
+        "#,
+        issue_id,
+        &emails,
+        &send_times
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+    Ok(held_back)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::optimized_send_time;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 15, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn without_history_delivery_is_immediate() {
+        assert_eq!(optimized_send_time(at(9, 30), None, 12), at(9, 30));
+    }
+
+    #[test]
+    fn delivery_waits_for_the_start_of_the_preferred_hour() {
+        assert_eq!(optimized_send_time(at(9, 30), Some(14), 12), at(14, 0));
+    }
+
+    #[test]
+    fn delivery_during_the_preferred_hour_is_immediate() {
+        assert_eq!(optimized_send_time(at(14, 45), Some(14), 12), at(14, 45));
+    }
+
+    #[test]
+    fn the_preferred_hour_can_be_on_the_next_day() {
+        let now = at(22, 10);
+        let send_at = optimized_send_time(now, Some(3), 12);
+
+        assert_eq!(send_at, at(3, 0) + chrono::Duration::days(1));
+    }
+
+    #[test]
+    fn delays_beyond_the_window_are_not_applied() {
+        // 08:00 is over 22 hours away.
+        assert_eq!(optimized_send_time(at(9, 30), Some(8), 12), at(9, 30));
+    }
+
+    #[test]
+    fn the_window_is_measured_from_now() {
+        // 14:00 is 4h30m away, just over a 4 hour window.
+        assert_eq!(optimized_send_time(at(9, 30), Some(14), 4), at(9, 30));
+        assert_eq!(optimized_send_time(at(10, 0), Some(14), 4), at(14, 0));
+    }
+}
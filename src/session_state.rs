@@ -9,6 +9,9 @@ pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    const SESSION_ID_KEY: &'static str = "session_id";
+    const PENDING_TOTP_USER_ID_KEY: &'static str = "pending_totp_user_id";
+    const UNVERIFIED_USER_ID_KEY: &'static str = "unverified_user_id";
 
     pub fn renew(&self) {
         self.0.renew();
@@ -25,6 +28,50 @@ impl TypedSession {
         self.0.get::<Uuid>(Self::USER_ID_KEY)
     }
 
+    /// Remember which `user_sessions` row the session belongs to.
+    pub fn insert_session_id(
+        &self,
+        session_id: Uuid,
+    ) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::SESSION_ID_KEY, session_id)
+    }
+
+    pub fn get_session_id(&self) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get::<Uuid>(Self::SESSION_ID_KEY)
+    }
+
+    /// Remember a user who entered the right password but still has to
+    /// enter a two-factor code; they are not logged in yet.
+    pub fn insert_pending_totp_user_id(
+        &self,
+        user_id: Uuid,
+    ) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::PENDING_TOTP_USER_ID_KEY, user_id)
+    }
+
+    /// Take the user waiting for the two-factor step; each password entry
+    /// allows a single code attempt.
+    pub fn take_pending_totp_user_id(&self) -> Option<Uuid> {
+        self.0
+            .remove_as::<Uuid>(Self::PENDING_TOTP_USER_ID_KEY)
+            .and_then(Result::ok)
+    }
+
+    /// Remember a user who entered the right password but has not verified
+    /// their email address, so they can ask for the link again.
+    pub fn insert_unverified_user_id(
+        &self,
+        user_id: Uuid,
+    ) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::UNVERIFIED_USER_ID_KEY, user_id)
+    }
+
+    pub fn get_unverified_user_id(
+        &self,
+    ) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get::<Uuid>(Self::UNVERIFIED_USER_ID_KEY)
+    }
+
     pub fn log_out(&self) {
         self.0.purge();
     }
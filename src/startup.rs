@@ -1,26 +1,69 @@
 use std::io;
 use std::net::TcpListener;
+use std::time::Instant;
 
 use actix_session::SessionMiddleware;
 use actix_session::storage::RedisSessionStore;
 use actix_web::cookie::Key;
 use actix_web::dev::Server;
-use actix_web::middleware::from_fn;
+use actix_web::middleware::{Compress, Condition, from_fn};
 use actix_web::{App, HttpServer, web};
 use actix_web_flash_messages::FlashMessagesFramework;
 use actix_web_flash_messages::storage::CookieMessageStore;
 use secrecy::{ExposeSecret, SecretString};
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
+use tokio::sync::Semaphore;
 use tracing_actix_web::TracingLogger;
 
-use crate::authentication::reject_anonymous_users;
-use crate::configuration::{DatabaseSettings, Settings};
-use crate::email_client::EmailClient;
-use crate::routes::admin_dashboard;
+use crate::authentication::{create_bootstrap_admin, reject_anonymous_users};
+use crate::compression::limit_compression_to_content_types;
+use crate::configuration::{
+    ApplicationSettings, DatabaseSettings, EmailClientSettings,
+    EmailProviderKind, Settings,
+};
+use crate::email_client::{EmailClient, SenderVerification};
+use crate::email_vault::EmailVault;
+use crate::feature_flags::FeatureFlags;
+use crate::form_charset;
+use crate::migrations::prepare_schema;
+use crate::pending_sweeper::run_pending_sweeper;
+use crate::routes::flush_delivery_queue;
+use crate::routes::send_test_newsletter;
+use crate::routes::{add_subscriber, export_subscribers, list_subscribers};
+use crate::routes::{admin_dashboard, captured_emails};
+use crate::routes::{
+    api_get_subscriber, api_publish_newsletter, api_subscribe, issue_api_token,
+};
 use crate::routes::{change_password, change_password_form};
 use crate::routes::{confirm, health_check, home, log_out, login, login_form};
-use crate::routes::{publish_newsletter, publish_newsletter_form, subscribe};
+use crate::routes::{
+    confirm_email_change, preferences_page, request_email_change, snooze_emails,
+};
+use crate::routes::{
+    confirm_two_factor, disable_two_factor, enroll_two_factor,
+};
+use crate::routes::{
+    confirm_unsubscribe, unsubscribe, unsubscribe_confirmation_page,
+};
+use crate::routes::{create_api_key, retry_dead_letters, revoke_api_key};
+use crate::routes::{feature_flag_audit_log, list_email_events};
+use crate::routes::{feature_flags_page, update_feature_flag};
+use crate::routes::{home_page_form, update_home_page};
+use crate::routes::{list_inactive_subscribers, suppress_inactive_subscribers};
+use crate::routes::{login_totp, login_totp_form};
+use crate::routes::{merge_subscribers, restart_onboarding};
+use crate::routes::{metrics, newsletter_delivery_status};
+use crate::routes::{newsletter_unsubscribes, receive_email_event};
+use crate::routes::{password_reset_form, reset_password};
+use crate::routes::{password_reset_request_form, request_password_reset};
+use crate::routes::{public_signup_disabled, resend_confirmation, subscribe};
+use crate::routes::{publish_newsletter, publish_newsletter_form};
+use crate::routes::{resend_email_verification, verify_email};
+use crate::routes::{resume_newsletter, unschedule_newsletter};
+use crate::subscriber_token::SubscriberLinks;
+use crate::telemetry::{RouteLevelRootSpanBuilder, request_id_header};
+use crate::utils::json_error_handler;
 
 /// Application struct representing the running application.
 pub struct Application {
@@ -28,6 +71,96 @@ pub struct Application {
     pub server: Server,
 }
 
+/// Check once at startup that the email provider accepts the configured
+/// sender, so a misconfiguration shows up before the first newsletter.
+/// # Arguments
+/// * `settings` - The email client settings.
+/// * `email_client` - The client built from those settings.
+/// # Returns
+/// An error if the sender could not be verified and the policy is `fail`.
+async fn verify_sending_identity(
+    settings: &EmailClientSettings,
+    email_client: &EmailClient,
+) -> Result<(), anyhow::Error> {
+    let policy = settings.sender_verification;
+    // Nothing reaches the provider in test mode.
+    if policy == SenderVerification::Skip || email_client.is_test_mode() {
+        return Ok(());
+    }
+    if settings.provider == EmailProviderKind::Smtp {
+        tracing::info!(
+            "SMTP servers cannot be asked about senders. \
+            Skipping the sender check."
+        );
+        return Ok(());
+    }
+    let problem = match &settings.account_token {
+        None => "No email provider account token is configured, \
+            so the sender cannot be verified."
+            .to_string(),
+        Some(account_token) => {
+            match settings
+                .postmark_client()
+                .is_sender_verified(&settings.sender_email, account_token)
+                .await
+            {
+                Ok(true) => return Ok(()),
+                Ok(false) => format!(
+                    "{} is not a confirmed sender with the email provider.",
+                    settings.sender_email
+                ),
+                Err(e) => format!("Failed to verify the sender: {e}"),
+            }
+        }
+    };
+    match policy {
+        SenderVerification::Fail => Err(anyhow::anyhow!(problem)),
+        _ => {
+            tracing::warn!("{problem} Newsletters may fail to send.");
+            Ok(())
+        }
+    }
+}
+
+/// Create the configured bootstrap admin if the database has no users yet.
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `settings` - The application settings holding the admin credentials.
+/// # Returns
+/// An error if only one of the credentials is set or the admin could not
+/// be created.
+async fn bootstrap_admin(
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<(), anyhow::Error> {
+    let (username, password) = match (
+        &settings.bootstrap_admin_username,
+        &settings.bootstrap_admin_password,
+    ) {
+        (Some(username), Some(password)) => (username, password),
+        (None, None) => return Ok(()),
+        _ => anyhow::bail!(
+            "Both `bootstrap_admin_username` and `bootstrap_admin_password` \
+            must be set to create a bootstrap admin."
+        ),
+    };
+    if let Some(user_id) = create_bootstrap_admin(
+        pool,
+        username,
+        password.clone(),
+        settings.bootstrap_admin_email.as_deref(),
+    )
+    .await?
+    {
+        tracing::warn!(
+            %user_id,
+            username,
+            "Created the bootstrap admin user. Log in and change its password."
+        );
+    }
+    Ok(())
+}
+
 impl Application {
     /// Build and configure the application.
     /// # Arguments
@@ -35,11 +168,30 @@ impl Application {
     /// # Returns
     /// A Result containing the Application or an io::Error.
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
+        let started_at = StartedAt(Instant::now());
         let connection_pool = get_connection_pool(&configuration.database)
             .await
             .expect("Failed to create database connection pool.");
+        prepare_schema(&connection_pool, configuration.database.migrations)
+            .await?;
+        bootstrap_admin(&connection_pool, &configuration.application).await?;
+        actix_web::rt::spawn(run_pending_sweeper(
+            connection_pool.clone(),
+            configuration.pending_sweep.clone(),
+            configuration.scheduler.clone(),
+        ));
 
-        let email_client = configuration.email_client.client();
+        let email_client = configuration.email_client.clone().client();
+        verify_sending_identity(&configuration.email_client, &email_client)
+            .await?;
+        let feature_flags = FeatureFlags::new(&configuration);
+        if let Err(e) = feature_flags.load_overrides(&connection_pool).await {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                "Failed to load feature flag overrides. \
+                Falling back to the configured defaults."
+            );
+        }
         let address = format!(
             "{}:{}",
             configuration.application.host, configuration.application.port
@@ -50,9 +202,9 @@ impl Application {
             listener,
             connection_pool,
             email_client,
-            configuration.application.base_url,
-            configuration.application.hmac_secret,
-            configuration.redis_uri,
+            feature_flags,
+            configuration,
+            started_at,
         )
         .await?;
 
@@ -77,26 +229,120 @@ pub struct ApplicationBaseUrl(pub String);
 #[derive(Clone)]
 pub struct HmacSecret(pub SecretString);
 
+/// Bounds the number of newsletter publish transactions held open at once,
+/// so a burst of publishes cannot starve other endpoints of connections.
+pub struct PublishTransactionLimit(pub Semaphore);
+
+/// Bounds the number of confirmation emails being sent at once.
+pub struct ConfirmationSendLimit(pub Semaphore);
+
+/// When the application was built, for reporting uptime.
+pub struct StartedAt(pub Instant);
+
 /// Run the HTTP server.
 /// # Arguments
 /// * `listener` - A TcpListener for incoming connections.
 /// * `db_pool` - A PgPool for database connections.
 /// * `email_client` - An EmailClient for sending emails.
-/// * `base_url` - The base URL of the application.
+/// * `feature_flags` - The runtime feature flags.
+/// * `configuration` - The application settings.
+/// * `started_at` - When the application was built.
 /// # Returns
 /// A Result containing the Server or an io::Error.
 async fn run(
     listener: TcpListener,
     db_pool: PgPool,
     email_client: EmailClient,
-    base_url: String,
-    hmac_secret: SecretString,
-    redis_uri: SecretString,
+    feature_flags: FeatureFlags,
+    configuration: Settings,
+    started_at: StartedAt,
 ) -> Result<Server, anyhow::Error> {
+    let Settings {
+        database: DatabaseSettings { pool, .. },
+        application:
+            ApplicationSettings {
+                base_url,
+                hmac_secret,
+                require_utf8_forms,
+                public_signup_enabled,
+                compression,
+                request_logging,
+                ..
+            },
+        email_client:
+            EmailClientSettings {
+                max_concurrent_confirmation_sends,
+                ..
+            },
+        newsletter,
+        onboarding,
+        subscriber_listing,
+        engagement,
+        event_listing,
+        subscriber_limit,
+        signup_velocity,
+        honeypot,
+        idempotency,
+        sessions,
+        login_throttle,
+        password_reset,
+        email_change,
+        api_tokens,
+        clock_skew,
+        confirmation_resend,
+        email_verification,
+        totp,
+        subscriber_emails,
+        webhooks,
+        inbound_webhooks,
+        metrics: metrics_settings,
+        redis_uri,
+        ..
+    } = configuration;
     let db_pool = web::Data::new(db_pool);
+    let pool_settings = web::Data::new(pool);
+    let test_mode = email_client.is_test_mode();
     let email_client = web::Data::new(email_client);
+    let feature_flags = web::Data::new(feature_flags);
+    let subscriber_links = web::Data::new(SubscriberLinks::new(
+        base_url.clone(),
+        hmac_secret.clone(),
+    ));
     let base_url: web::Data<ApplicationBaseUrl> =
         web::Data::new(ApplicationBaseUrl(base_url));
+    let publish_limit = web::Data::new(PublishTransactionLimit(
+        Semaphore::new(newsletter.max_concurrent_publish_transactions.get()),
+    ));
+    let confirmation_send_limit = web::Data::new(ConfirmationSendLimit(
+        Semaphore::new(max_concurrent_confirmation_sends.get()),
+    ));
+    let newsletter = web::Data::new(newsletter);
+    let onboarding = web::Data::new(onboarding);
+    let subscriber_listing = web::Data::new(subscriber_listing);
+    let engagement = web::Data::new(engagement);
+    let event_listing = web::Data::new(event_listing);
+    let subscriber_limit = web::Data::new(subscriber_limit);
+    let signup_velocity = web::Data::new(signup_velocity);
+    let honeypot = web::Data::new(honeypot);
+    let idempotency = web::Data::new(idempotency);
+    let sessions = web::Data::new(sessions);
+    let login_throttle = web::Data::new(login_throttle);
+    let password_reset = web::Data::new(password_reset);
+    let email_change = web::Data::new(email_change);
+    let api_tokens = web::Data::new(api_tokens);
+    let clock_skew = web::Data::new(clock_skew);
+    let confirmation_resend = web::Data::new(confirmation_resend);
+    let email_verification = web::Data::new(email_verification);
+    let totp = web::Data::new(totp);
+    let email_vault = web::Data::new(EmailVault::new(&subscriber_emails));
+    let webhooks = web::Data::new(webhooks);
+    let inbound_webhooks = web::Data::new(inbound_webhooks);
+    let metrics_enabled = metrics_settings.enabled;
+    let metrics_settings = web::Data::new(metrics_settings);
+    let compress = compression.enabled;
+    let compression = web::Data::new(compression);
+    let request_logging = web::Data::new(request_logging);
+    let started_at = web::Data::new(started_at);
     let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(Key::from(
         hmac_secret.expose_secret().as_bytes(),
@@ -107,23 +353,117 @@ async fn run(
     let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
     let server = HttpServer::new(move || {
         App::new()
+            .wrap(Condition::new(
+                compress,
+                from_fn(limit_compression_to_content_types),
+            ))
+            .wrap(Condition::new(compress, Compress::default()))
             .wrap(message_framework.clone())
             .wrap(SessionMiddleware::new(
                 redis_store.clone(),
                 secret_key.clone(),
             ))
-            .wrap(TracingLogger::default())
+            .wrap(Condition::new(
+                require_utf8_forms,
+                from_fn(form_charset::require_utf8_forms),
+            ))
+            .wrap(from_fn(request_id_header))
+            .wrap(TracingLogger::<RouteLevelRootSpanBuilder>::new())
             .route("/", web::get().to(home))
             .route("/login", web::get().to(login_form))
             .route("/login", web::post().to(login))
+            .route("/login/totp", web::get().to(login_totp_form))
+            .route("/login/totp", web::post().to(login_totp))
+            .route(
+                "/login/verify-email",
+                web::post().to(resend_email_verification),
+            )
+            .route("/verify-email", web::get().to(verify_email))
+            .route(
+                "/password-reset/request",
+                web::get().to(password_reset_request_form),
+            )
+            .route(
+                "/password-reset/request",
+                web::post().to(request_password_reset),
+            )
+            .route("/password-reset", web::get().to(password_reset_form))
+            .route("/password-reset", web::post().to(reset_password))
             .route("/health_check", web::get().to(health_check))
-            .route("/subscriptions", web::post().to(subscribe))
+            .configure(|cfg| {
+                if metrics_enabled {
+                    cfg.route("/metrics", web::get().to(metrics));
+                }
+                if test_mode {
+                    cfg.route("/dev/emails", web::get().to(captured_emails));
+                }
+            })
+            .route(
+                "/subscriptions",
+                if public_signup_enabled {
+                    web::post().to(subscribe)
+                } else {
+                    web::post().to(public_signup_disabled)
+                },
+            )
             .route("/subscriptions/confirm", web::get().to(confirm))
-            .route("/newsletters", web::post().to(publish_newsletter))
+            .route(
+                "/subscriptions/resend-confirmation",
+                web::post().to(resend_confirmation),
+            )
+            .route("/subscriptions/unsubscribe", web::get().to(unsubscribe))
+            .route(
+                "/subscriptions/unsubscribe",
+                web::post().to(confirm_unsubscribe),
+            )
+            .route(
+                "/subscriptions/unsubscribe/confirm",
+                web::get().to(unsubscribe_confirmation_page),
+            )
+            .route("/api/subscribers", web::post().to(api_subscribe))
+            .route(
+                "/api/subscribers/{subscriber_id}",
+                web::get().to(api_get_subscriber),
+            )
+            .route("/preferences", web::get().to(preferences_page))
+            .route("/preferences/email", web::post().to(request_email_change))
+            .route(
+                "/preferences/email/confirm",
+                web::get().to(confirm_email_change),
+            )
+            .route("/preferences/snooze", web::post().to(snooze_emails))
+            .route("/auth/token", web::post().to(issue_api_token))
+            .route("/newsletters", web::post().to(api_publish_newsletter))
+            .route(
+                "/webhooks/email-events",
+                web::post().to(receive_email_event),
+            )
             .service(
                 web::scope("/admin")
                     .wrap(from_fn(reject_anonymous_users))
+                    .route("/api-keys", web::post().to(create_api_key))
+                    .route(
+                        "/api-keys/{key_id}/revoke",
+                        web::post().to(revoke_api_key),
+                    )
                     .route("/dashboard", web::get().to(admin_dashboard))
+                    .route(
+                        "/dead-letters/retry",
+                        web::post().to(retry_dead_letters),
+                    )
+                    .route(
+                        "/delivery-queue/flush",
+                        web::post().to(flush_delivery_queue),
+                    )
+                    .route("/features", web::get().to(feature_flags_page))
+                    .route("/features", web::post().to(update_feature_flag))
+                    .route(
+                        "/features/audit",
+                        web::get().to(feature_flag_audit_log),
+                    )
+                    .route("/events", web::get().to(list_email_events))
+                    .route("/home", web::get().to(home_page_form))
+                    .route("/home", web::post().to(update_home_page))
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
                     .route(
@@ -131,12 +471,90 @@ async fn run(
                         web::get().to(publish_newsletter_form),
                     )
                     .route("/newsletters", web::post().to(publish_newsletter))
-                    .route("/logout", web::post().to(log_out)),
+                    .route(
+                        "/newsletters/test",
+                        web::post().to(send_test_newsletter),
+                    )
+                    .route(
+                        "/newsletters/unsubscribes",
+                        web::get().to(newsletter_unsubscribes),
+                    )
+                    .route(
+                        "/newsletters/{issue_id}/delivery",
+                        web::get().to(newsletter_delivery_status),
+                    )
+                    .route(
+                        "/newsletters/{issue_id}/resume",
+                        web::post().to(resume_newsletter),
+                    )
+                    .route(
+                        "/newsletters/{issue_id}/unschedule",
+                        web::post().to(unschedule_newsletter),
+                    )
+                    .route("/subscribers", web::get().to(list_subscribers))
+                    .route("/subscribers", web::post().to(add_subscriber))
+                    .route(
+                        "/subscribers/export",
+                        web::get().to(export_subscribers),
+                    )
+                    .route(
+                        "/subscribers/inactive",
+                        web::get().to(list_inactive_subscribers),
+                    )
+                    .route(
+                        "/subscribers/inactive/suppress",
+                        web::post().to(suppress_inactive_subscribers),
+                    )
+                    .route(
+                        "/subscribers/merge",
+                        web::post().to(merge_subscribers),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/onboarding",
+                        web::post().to(restart_onboarding),
+                    )
+                    .route("/logout", web::post().to(log_out))
+                    .route("/totp/enroll", web::post().to(enroll_two_factor))
+                    .route("/totp/confirm", web::post().to(confirm_two_factor))
+                    .route("/totp/disable", web::post().to(disable_two_factor)),
             )
             // Get a pointer copy and attach it to the application state
+            .app_data(
+                web::JsonConfig::default().error_handler(json_error_handler),
+            )
             .app_data(db_pool.clone())
+            .app_data(pool_settings.clone())
             .app_data(email_client.clone())
+            .app_data(feature_flags.clone())
             .app_data(base_url.clone())
+            .app_data(subscriber_links.clone())
+            .app_data(publish_limit.clone())
+            .app_data(confirmation_send_limit.clone())
+            .app_data(newsletter.clone())
+            .app_data(onboarding.clone())
+            .app_data(subscriber_listing.clone())
+            .app_data(engagement.clone())
+            .app_data(event_listing.clone())
+            .app_data(subscriber_limit.clone())
+            .app_data(signup_velocity.clone())
+            .app_data(honeypot.clone())
+            .app_data(idempotency.clone())
+            .app_data(sessions.clone())
+            .app_data(login_throttle.clone())
+            .app_data(password_reset.clone())
+            .app_data(email_change.clone())
+            .app_data(api_tokens.clone())
+            .app_data(clock_skew.clone())
+            .app_data(confirmation_resend.clone())
+            .app_data(email_verification.clone())
+            .app_data(totp.clone())
+            .app_data(email_vault.clone())
+            .app_data(webhooks.clone())
+            .app_data(inbound_webhooks.clone())
+            .app_data(metrics_settings.clone())
+            .app_data(compression.clone())
+            .app_data(request_logging.clone())
+            .app_data(started_at.clone())
             .app_data(web::Data::new(HmacSecret(hmac_secret.clone())))
     })
     .listen(listener)?
@@ -154,6 +572,7 @@ pub async fn get_connection_pool(
     configuration: &DatabaseSettings,
 ) -> Result<PgPool, sqlx::Error> {
     PgPoolOptions::new()
+        .acquire_timeout(configuration.pool.acquire_timeout())
         .connect_with(configuration.connect_options())
         .await
 }
@@ -0,0 +1,209 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// What a subscriber token lets its bearer do.
+/// Each link sent to a subscriber carries a token for its own action, so a
+/// leaked unsubscribe link cannot be used to redirect their emails, and none
+/// of them can confirm a subscription; that takes the confirmation token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubscriberAction {
+    Unsubscribe,
+    /// Open the preferences page, which hands out the snooze and
+    /// change-address tokens.
+    ManagePreferences,
+    Snooze,
+    ChangeEmail,
+}
+
+impl SubscriberAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unsubscribe => "unsubscribe",
+            Self::ManagePreferences => "manage_preferences",
+            Self::Snooze => "snooze",
+            Self::ChangeEmail => "change_email",
+        }
+    }
+}
+
+/// Issue a token letting a subscriber take one action.
+/// The token is the subscriber ID followed by an HMAC-SHA256 of the ID and
+/// the action, so it needs no storage and stays valid for as long as the
+/// secret does.
+/// # Arguments
+/// * `subscriber_id` - The subscriber the token acts for.
+/// * `action` - The only action the token is good for.
+/// * `secret` - The key the token is signed with.
+/// # Returns
+/// The encoded token, safe to put in a URL.
+pub fn issue_subscriber_token(
+    subscriber_id: Uuid,
+    action: SubscriberAction,
+    secret: &SecretString,
+) -> String {
+    let signature = mac(secret, subscriber_id, action).finalize().into_bytes();
+    format!("{}.{}", subscriber_id, URL_SAFE_NO_PAD.encode(signature))
+}
+
+/// Check a token issued by `issue_subscriber_token`.
+/// # Arguments
+/// * `token` - The encoded token.
+/// * `action` - The action being taken.
+/// * `secret` - The key the token must be signed with.
+/// # Returns
+/// The subscriber the token acts for, or None if it is malformed, forged or
+/// issued for another action.
+pub fn verify_subscriber_token(
+    token: &str,
+    action: SubscriberAction,
+    secret: &SecretString,
+) -> Option<Uuid> {
+    let (subscriber_id, signature) = token.split_once('.')?;
+    let subscriber_id = Uuid::parse_str(subscriber_id).ok()?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    // Compared in constant time
+    mac(secret, subscriber_id, action)
+        .verify_slice(&signature)
+        .ok()?;
+    Some(subscriber_id)
+}
+
+fn mac(
+    secret: &SecretString,
+    subscriber_id: Uuid,
+    action: SubscriberAction,
+) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any length");
+    mac.update(
+        format!("subscriber:{}:{}", action.as_str(), subscriber_id).as_bytes(),
+    );
+    mac
+}
+
+/// Builds the links in the emails sent to subscribers.
+#[derive(Clone)]
+pub struct SubscriberLinks {
+    base_url: String,
+    secret: SecretString,
+}
+
+impl SubscriberLinks {
+    pub fn new(base_url: String, secret: SecretString) -> Self {
+        Self { base_url, secret }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// A token letting the subscriber take `action`.
+    pub fn token(
+        &self,
+        subscriber_id: Uuid,
+        action: SubscriberAction,
+    ) -> String {
+        issue_subscriber_token(subscriber_id, action, &self.secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_none, assert_some_eq};
+    use secrecy::SecretString;
+    use uuid::Uuid;
+
+    use super::{
+        SubscriberAction, issue_subscriber_token, verify_subscriber_token,
+    };
+
+    fn secret() -> SecretString {
+        SecretString::from("long-and-very-secret-random-key-value")
+    }
+
+    #[test]
+    fn a_token_yields_the_subscriber_it_was_issued_for() {
+        let subscriber_id = Uuid::new_v4();
+        let token = issue_subscriber_token(
+            subscriber_id,
+            SubscriberAction::Snooze,
+            &secret(),
+        );
+
+        assert_some_eq!(
+            verify_subscriber_token(
+                &token,
+                SubscriberAction::Snooze,
+                &secret()
+            ),
+            subscriber_id
+        );
+    }
+
+    #[test]
+    fn a_token_is_only_good_for_its_own_action() {
+        let token = issue_subscriber_token(
+            Uuid::new_v4(),
+            SubscriberAction::Unsubscribe,
+            &secret(),
+        );
+
+        assert_none!(verify_subscriber_token(
+            &token,
+            SubscriberAction::ChangeEmail,
+            &secret()
+        ));
+        assert_none!(verify_subscriber_token(
+            &token,
+            SubscriberAction::Snooze,
+            &secret()
+        ));
+    }
+
+    #[test]
+    fn a_token_moved_to_another_subscriber_is_rejected() {
+        let token = issue_subscriber_token(
+            Uuid::new_v4(),
+            SubscriberAction::Unsubscribe,
+            &secret(),
+        );
+        let (_, signature) = token.split_once('.').unwrap();
+        let forged = format!("{}.{}", Uuid::new_v4(), signature);
+
+        assert_none!(verify_subscriber_token(
+            &forged,
+            SubscriberAction::Unsubscribe,
+            &secret()
+        ));
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let token = issue_subscriber_token(
+            Uuid::new_v4(),
+            SubscriberAction::Unsubscribe,
+            &SecretString::from("another-secret"),
+        );
+
+        assert_none!(verify_subscriber_token(
+            &token,
+            SubscriberAction::Unsubscribe,
+            &secret()
+        ));
+    }
+
+    #[test]
+    fn a_confirmation_token_is_rejected() {
+        // Confirmation tokens are 25 random alphanumeric characters
+        assert_none!(verify_subscriber_token(
+            "abcdefghijklmnopqrstuvwxy",
+            SubscriberAction::Unsubscribe,
+            &secret()
+        ));
+    }
+}
@@ -1,11 +1,23 @@
+use std::io::{self, Write};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
 use actix_web::rt::task::{JoinHandle, spawn_blocking};
-use tracing::Subscriber;
+use actix_web::{HttpMessage, web};
 use tracing::subscriber::set_global_default;
+use tracing::{Level, Span, Subscriber};
+use tracing_actix_web::{
+    DefaultRootSpanBuilder, RequestId, RootSpanBuilder, root_span,
+};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::{EnvFilter, Registry, layer::SubscriberExt};
 
+use crate::configuration::RequestLogSettings;
+
 /// Create a tracing subscriber
 /// # Arguments
 /// * `name` - The name of the application
@@ -36,10 +48,34 @@ where
 /// Initialize the tracing subscriber as global default
 /// # Arguments
 /// * `subscriber` - The tracing subscriber to set as global default
-/// Returns nothing (It should only be called once!)
-pub fn init_subscriber(subscriber: impl Subscriber + Sync + Send) {
+///
+/// Returns a guard that flushes telemetry when dropped; hold it until the
+/// process exits (It should only be called once!)
+pub fn init_subscriber(
+    subscriber: impl Subscriber + Sync + Send,
+) -> TelemetryGuard {
     LogTracer::init().expect("Failed to set logger.");
     set_global_default(subscriber).expect("Failed to set subscriber.");
+    TelemetryGuard { _private: () }
+}
+
+/// Flushes buffered telemetry when dropped, so the last logs and spans
+/// emitted before shutdown are not lost.
+/// Exporters that batch in the background (e.g. OTLP) should be shut down
+/// from here too.
+#[must_use = "telemetry is flushed when the guard is dropped"]
+pub struct TelemetryGuard {
+    _private: (),
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        tracing::info!("Flushing telemetry before shutdown.");
+        if let Err(e) = io::stdout().flush() {
+            // The subscriber writes to stdout, so report on stderr instead.
+            eprintln!("Failed to flush telemetry: {e}");
+        }
+    }
 }
 
 /// Spawns a blocking task with the current tracing span
@@ -55,3 +91,164 @@ where
     let current_span = tracing::Span::current();
     spawn_blocking(move || current_span.in_scope(f))
 }
+
+/// Echo the id assigned to each request by `TracingLogger` in the
+/// `X-Request-Id` response header, so a request can be found in the logs
+/// (and in the deliveries it spawned).
+/// Must be registered inside `TracingLogger`.
+/// # Arguments
+/// * `req` - The incoming request.
+/// * `next` - The next service in the chain.
+/// # Returns
+/// The downstream response with the header added.
+pub async fn request_id_header(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let request_id = req.extensions().get::<RequestId>().copied();
+    let mut response = next.call(req).await?;
+    if let Some(request_id) = request_id
+        && let Ok(value) = HeaderValue::from_str(&request_id.to_string())
+    {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static("x-request-id"), value);
+    }
+    Ok(response)
+}
+
+/// The level a route's access log is written at, or `off` to silence it.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestLogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl RequestLogLevel {
+    fn as_level(self) -> Option<Level> {
+        match self {
+            RequestLogLevel::Off => None,
+            RequestLogLevel::Error => Some(Level::ERROR),
+            RequestLogLevel::Warn => Some(Level::WARN),
+            RequestLogLevel::Info => Some(Level::INFO),
+            RequestLogLevel::Debug => Some(Level::DEBUG),
+            RequestLogLevel::Trace => Some(Level::TRACE),
+        }
+    }
+}
+
+/// Builds the `TracingLogger` root span at the level configured for the
+/// matched route, so noisy routes such as health checks can be silenced
+/// without losing the access log of the others.
+/// Routes without `RequestLogSettings` registered are logged at INFO.
+pub struct RouteLevelRootSpanBuilder;
+
+impl RootSpanBuilder for RouteLevelRootSpanBuilder {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        let route = request.match_pattern();
+        let level = request.app_data::<web::Data<RequestLogSettings>>().map_or(
+            RequestLogLevel::Info,
+            |settings| {
+                settings.level_for(route.as_deref().unwrap_or(request.path()))
+            },
+        );
+        match level.as_level() {
+            Some(level) => root_span!(level = level, request),
+            None => Span::none(),
+        }
+    }
+
+    fn on_request_end<B: MessageBody>(
+        span: Span,
+        outcome: &Result<ServiceResponse<B>, actix_web::Error>,
+    ) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{App, HttpResponse, web};
+    use tracing_actix_web::TracingLogger;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use super::{RouteLevelRootSpanBuilder, TelemetryGuard, get_subscriber};
+    use crate::configuration::RequestLogSettings;
+
+    /// Collects everything the subscriber writes.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    /// Serve a request to `path` and return the resulting logs.
+    async fn access_log(path: &str) -> String {
+        let settings: RequestLogSettings =
+            serde_json::from_value(serde_json::json!({
+                "default_level": "info",
+                "routes": [{ "path": "/health_check", "level": "off" }],
+            }))
+            .unwrap();
+        let buffer = Buffer::default();
+        let subscriber =
+            get_subscriber("test".into(), "info".into(), buffer.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let app = test::init_service(
+            App::new()
+                .wrap(TracingLogger::<RouteLevelRootSpanBuilder>::new())
+                .app_data(web::Data::new(settings))
+                .route("/health_check", web::get().to(HttpResponse::Ok))
+                .route("/subscriptions", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        test::call_service(&app, TestRequest::with_uri(path).to_request())
+            .await;
+        let output = buffer.0.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn a_silenced_route_is_not_logged() {
+        assert_eq!(access_log("/health_check").await, "");
+    }
+
+    #[actix_web::test]
+    async fn other_routes_are_still_logged() {
+        let log = access_log("/subscriptions").await;
+
+        assert!(log.contains("[HTTP REQUEST - END]"), "{log}");
+        assert!(log.contains(r#""http.route":"/subscriptions""#), "{log}");
+    }
+
+    #[test]
+    fn dropping_the_guard_flushes_without_panicking() {
+        let guard = TelemetryGuard { _private: () };
+
+        drop(guard);
+    }
+}
@@ -1,7 +1,9 @@
 use std::fmt;
 
-use actix_web::HttpResponse;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::http::StatusCode;
 use actix_web::http::header::LOCATION;
+use actix_web::{HttpRequest, HttpResponse};
 
 /// Convert any error into an Internal Server Error actix_web::Error.
 /// # Arguments
@@ -20,13 +22,81 @@ where
 /// * `e` - The error to convert.
 /// # Returns
 /// An actix_web::Error representing a Bad Request.
-pub fn e400<T: fmt::Debug + fmt::Display>(e: T) -> actix_web::Error
+pub fn e400<T>(e: T) -> actix_web::Error
 where
     T: fmt::Debug + fmt::Display + 'static,
 {
     actix_web::error::ErrorBadRequest(e)
 }
 
+/// Convert any error into a Service Unavailable actix_web::Error.
+/// # Arguments
+/// * `e` - The error to convert.
+/// # Returns
+/// An actix_web::Error representing a Service Unavailable.
+pub fn e503<T>(e: T) -> actix_web::Error
+where
+    T: fmt::Debug + fmt::Display + 'static,
+{
+    actix_web::error::ErrorServiceUnavailable(e)
+}
+
+/// Turn a JSON body that could not be extracted into a problem+json
+/// response (RFC 9457).
+/// Bodies that fail to parse get a 400 saying where parsing stopped; the
+/// body itself is never echoed back.
+/// # Arguments
+/// * `e` - The extraction error.
+/// * `_request` - The request whose body was rejected.
+/// # Returns
+/// An actix_web::Error carrying the problem+json response.
+pub fn json_error_handler(
+    e: JsonPayloadError,
+    _request: &HttpRequest,
+) -> actix_web::Error {
+    let (status, detail) = match &e {
+        JsonPayloadError::Deserialize(e) if e.is_data() => (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "The JSON body does not have the expected fields or types \
+                (line {}, column {}).",
+                e.line(),
+                e.column()
+            ),
+        ),
+        JsonPayloadError::Deserialize(e) => (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "The body is not valid JSON (line {}, column {}).",
+                e.line(),
+                e.column()
+            ),
+        ),
+        JsonPayloadError::ContentType => (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Send the body as `application/json`.".to_string(),
+        ),
+        JsonPayloadError::Overflow { limit }
+        | JsonPayloadError::OverflowKnownLength { limit, .. } => (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("The body is larger than the {} byte limit.", limit),
+        ),
+        _ => (
+            StatusCode::BAD_REQUEST,
+            "The JSON body could not be read.".to_string(),
+        ),
+    };
+    let response = HttpResponse::build(status)
+        .content_type("application/problem+json")
+        .json(serde_json::json!({
+            "type": "about:blank",
+            "title": status.canonical_reason(),
+            "status": status.as_u16(),
+            "detail": detail,
+        }));
+    InternalError::from_response(e, response).into()
+}
+
 /// Create a See Other HttpResponse redirecting to the specified location.
 /// # Arguments
 /// * `location` - The URL to redirect to.
@@ -37,3 +107,19 @@ pub fn see_other(location: &str) -> HttpResponse {
         .insert_header((LOCATION, location))
         .finish()
 }
+
+/// Identify the client for velocity checks and lockouts.
+/// # Arguments
+/// * `request` - The incoming request.
+/// * `trust_forwarded_for` - Whether proxy headers can be trusted.
+/// # Returns
+/// The client's IP address, or "unknown".
+pub fn client_ip(request: &HttpRequest, trust_forwarded_for: bool) -> String {
+    let connection_info = request.connection_info();
+    let ip = if trust_forwarded_for {
+        connection_info.realip_remote_addr().map(str::to_owned)
+    } else {
+        request.peer_addr().map(|addr| addr.ip().to_string())
+    };
+    ip.unwrap_or_else(|| "unknown".into())
+}
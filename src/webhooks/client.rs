@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+
+use crate::backoff::Backoff;
+
+/// Header carrying the hex-encoded HMAC-SHA256 of the request body.
+pub const SIGNATURE_HEADER: &str = "X-Melierx-Signature";
+
+/// Webhook client structure.
+pub struct WebhookClient {
+    http_client: Client,
+    target_url: Option<Url>,
+    signing_secret: SecretString,
+    retry_backoff: Backoff,
+}
+
+impl WebhookClient {
+    pub fn new(
+        target_url: Option<Url>,
+        signing_secret: SecretString,
+        timeout: Duration,
+        retry_backoff: Backoff,
+    ) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            http_client,
+            target_url,
+            signing_secret,
+            retry_backoff,
+        }
+    }
+
+    /// How long a failed delivery waits before it is retried.
+    /// # Arguments
+    /// * `n_retries` - How many times the delivery was retried already.
+    pub fn retry_delay(&self, n_retries: i16) -> Duration {
+        self.retry_backoff.delay(n_retries)
+    }
+
+    /// POST a signed event payload to the configured target.
+    /// # Arguments
+    /// * `target_url` - Overrides the configured target, if set.
+    /// * `event_type` - The name of the lifecycle event.
+    /// * `payload` - The JSON payload to deliver.
+    /// # Returns
+    /// A Result indicating whether the receiver accepted the event.
+    pub async fn post_event(
+        &self,
+        target_url: Option<&str>,
+        event_type: &str,
+        payload: &serde_json::Value,
+    ) -> Result<(), anyhow::Error> {
+        let target_url = match target_url {
+            Some(url) => url.parse().context("Invalid webhook target URL.")?,
+            None => self
+                .target_url
+                .clone()
+                .context("No webhook target URL is configured.")?,
+        };
+        let body = serde_json::to_vec(payload)?;
+        let signature = sign_payload(&self.signing_secret, &body);
+        self.http_client
+            .post(target_url)
+            .header("Content-Type", "application/json")
+            .header("X-Melierx-Event", event_type)
+            .header(SIGNATURE_HEADER, format!("sha256={signature}"))
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Sign a webhook body so receivers can verify it came from us.
+/// # Arguments
+/// * `secret` - The secret shared with the receiver.
+/// * `body` - The raw request body.
+/// # Returns
+/// The hex-encoded HMAC-SHA256 of the body.
+pub fn sign_payload(secret: &SecretString, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+            .expect("HMAC can take a key of any size");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
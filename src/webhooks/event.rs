@@ -0,0 +1,130 @@
+use chrono::Utc;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::configuration::WebhookSettings;
+
+/// Lifecycle events that can be forwarded to an external system.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebhookEvent {
+    #[serde(rename = "subscriber.confirmed")]
+    SubscriberConfirmed,
+    #[serde(rename = "subscriber.unsubscribed")]
+    SubscriberUnsubscribed,
+    #[serde(rename = "issue.sent")]
+    IssueSent,
+    #[serde(rename = "issue.delivery_alarm")]
+    IssueDeliveryAlarm,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::SubscriberConfirmed => "subscriber.confirmed",
+            WebhookEvent::SubscriberUnsubscribed => "subscriber.unsubscribed",
+            WebhookEvent::IssueSent => "issue.sent",
+            WebhookEvent::IssueDeliveryAlarm => "issue.delivery_alarm",
+        }
+    }
+}
+
+/// When to send the dedicated new-signup notification.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SignupNotificationTrigger {
+    /// As soon as the subscription form is submitted.
+    Subscribe,
+    /// Once the subscriber has confirmed their email address.
+    Confirm,
+}
+
+/// Queue a webhook for delivery as part of the caller's transaction,
+/// so the event is only sent if the change that triggered it commits.
+/// Events that are not enabled in the settings are silently ignored.
+/// # Arguments
+/// * `transaction` - The database transaction.
+/// * `settings` - The outbound webhook settings.
+/// * `event` - The lifecycle event that occurred.
+/// * `data` - The event-specific payload.
+/// # Returns
+/// A Result indicating success or a sqlx::Error.
+#[tracing::instrument(skip(transaction, settings, data))]
+pub async fn enqueue_webhook(
+    transaction: &mut Transaction<'_, Postgres>,
+    settings: &WebhookSettings,
+    event: WebhookEvent,
+    data: serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    if !settings.is_enabled_for(event) {
+        return Ok(());
+    }
+
+    let id = Uuid::new_v4();
+    let payload = serde_json::json!({
+        "id": id,
+        "event": event.as_str(),
+        "occurred_at": Utc::now(),
+        "data": data,
+    });
+    sqlx::query!(
+        r#"
+        INSERT INTO webhook_delivery_queue (id, event_type, payload)
+        VALUES ($1, $2, $3)
+        "#,
+        id,
+        event.as_str(),
+        payload
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// Queue the dedicated new-signup notification, if it is configured for the
+/// given trigger, as part of the caller's transaction.
+/// # Arguments
+/// * `transaction` - The database transaction.
+/// * `settings` - The outbound webhook settings.
+/// * `trigger` - The step of the signup flow that just happened.
+/// * `email` - The subscriber's email address.
+/// # Returns
+/// A Result indicating success or a sqlx::Error.
+#[tracing::instrument(skip(transaction, settings, email))]
+pub async fn enqueue_signup_notification(
+    transaction: &mut Transaction<'_, Postgres>,
+    settings: &WebhookSettings,
+    trigger: SignupNotificationTrigger,
+    email: &str,
+) -> Result<(), sqlx::Error> {
+    let Some(target_url) = &settings.signup_notification_url else {
+        return Ok(());
+    };
+    if settings.signup_notification_trigger != trigger {
+        return Ok(());
+    }
+
+    let id = Uuid::new_v4();
+    let occurred_at = Utc::now();
+    // `text` (Slack) and `content` (Discord) make the ping readable as-is.
+    let message = format!("New subscriber: {email}");
+    let payload = serde_json::json!({
+        "text": message,
+        "content": message,
+        "email": email,
+        "timestamp": occurred_at,
+    });
+    sqlx::query!(
+        r#"
+        INSERT INTO webhook_delivery_queue (
+            id, event_type, payload, target_url
+        )
+        VALUES ($1, 'subscriber.signup', $2, $3)
+        "#,
+        id,
+        payload,
+        target_url
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,8 @@
+mod client;
+mod event;
+mod worker;
+
+pub use client::{SIGNATURE_HEADER, WebhookClient, sign_payload};
+pub use event::{SignupNotificationTrigger, WebhookEvent};
+pub use event::{enqueue_signup_notification, enqueue_webhook};
+pub use worker::{run_webhook_worker_until_stopped, try_execute_webhook_task};
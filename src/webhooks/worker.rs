@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{Span, field::display};
+use uuid::Uuid;
+
+use crate::configuration::Settings;
+use crate::issue_delivery_worker::ExecutionOutcome;
+use crate::startup::get_connection_pool;
+use crate::webhooks::WebhookClient;
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+struct WebhookTask {
+    id: Uuid,
+    event_type: String,
+    payload: serde_json::Value,
+    n_retries: i16,
+    target_url: Option<String>,
+}
+
+/// Deliver at most one pending webhook.
+/// Failed deliveries are rescheduled after the client's retry backoff and moved
+/// to the dead-letter table once `max_retries` attempts have failed.
+/// # Arguments
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `webhook_client` - The client used to POST events.
+/// * `max_retries` - The number of attempts before giving up on an event.
+/// # Returns
+/// Whether a task was processed or the queue was empty.
+#[tracing::instrument(
+    skip_all,
+    fields(
+        webhook_id = tracing::field::Empty,
+        event_type = tracing::field::Empty,
+    ),
+    err
+)]
+pub async fn try_execute_webhook_task(
+    pool: &PgPool,
+    webhook_client: &WebhookClient,
+    max_retries: i16,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let task = dequeue_task(pool).await?;
+    let Some((transaction, task)) = task else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+
+    Span::current()
+        .record("webhook_id", display(task.id))
+        .record("event_type", display(&task.event_type));
+
+    match webhook_client
+        .post_event(task.target_url.as_deref(), &task.event_type, &task.payload)
+        .await
+    {
+        Ok(()) => delete_task(transaction, task.id).await?,
+        Err(e) if task.n_retries + 1 >= max_retries => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to deliver a webhook. Moving it to the dead-letter table.",
+            );
+            dead_letter_task(transaction, &task, &e.to_string()).await?;
+        }
+        Err(e) => {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to deliver a webhook. Retrying later.",
+            );
+            let delay = webhook_client.retry_delay(task.n_retries);
+            reschedule_task(transaction, &task, delay).await?;
+        }
+    }
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_task(
+    pool: &PgPool,
+) -> Result<Option<(PgTransaction, WebhookTask)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let task = sqlx::query_as!(
+        WebhookTask,
+        r#"
+        SELECT id, event_type, payload, n_retries, target_url
+        FROM webhook_delivery_queue
+        WHERE execute_after <= now()
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?;
+    Ok(task.map(|t| (transaction, t)))
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(
+    mut transaction: PgTransaction,
+    id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM webhook_delivery_queue
+        WHERE id = $1
+        "#,
+        id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn reschedule_task(
+    mut transaction: PgTransaction,
+    task: &WebhookTask,
+    delay: Duration,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE webhook_delivery_queue
+        SET
+            n_retries = n_retries + 1,
+            execute_after = now() + make_interval(secs => $2)
+        WHERE id = $1
+        "#,
+        task.id,
+        delay.as_secs_f64()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn dead_letter_task(
+    mut transaction: PgTransaction,
+    task: &WebhookTask,
+    last_error: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO webhook_dead_letter (
+            id, event_type, payload, n_retries, last_error,
+            created_at, failed_at, target_url
+        )
+        SELECT
+            id, event_type, payload, n_retries + 1, $2,
+            created_at, now(), target_url
+        FROM webhook_delivery_queue
+        WHERE id = $1
+        "#,
+        task.id,
+        last_error
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    delete_task(transaction, task.id).await
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    webhook_client: WebhookClient,
+    max_retries: i16,
+    poll_interval: Duration,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_execute_webhook_task(&pool, &webhook_client, max_retries)
+            .await
+        {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                actix_web::rt::time::sleep(poll_interval).await;
+            }
+            Err(_) => {
+                actix_web::rt::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(
+                ExecutionOutcome::TaskCompleted
+                | ExecutionOutcome::TaskDeferred,
+            ) => {}
+        }
+    }
+}
+
+pub async fn run_webhook_worker_until_stopped(
+    configuration: Settings,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database).await?;
+    let webhook_client = configuration.webhooks.client();
+    worker_loop(
+        connection_pool,
+        webhook_client,
+        configuration.webhooks.max_retries,
+        configuration.worker.poll_interval,
+    )
+    .await
+}
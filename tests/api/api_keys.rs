@@ -0,0 +1,182 @@
+use reqwest::Response;
+use wiremock::Mock;
+use wiremock::matchers::{method, path};
+
+use crate::helpers::{TestApp, email_accepted, spawn_app};
+
+/// Create an API key through the admin endpoint.
+/// # Returns
+/// The key's ID and the key itself.
+async fn create_api_key(app: &TestApp, scopes: &[&str]) -> (String, String) {
+    app.test_user.login(app).await;
+    let response = app
+        .api_client
+        .post(format!("{}/admin/api-keys", &app.address))
+        .json(&serde_json::json!({ "name": "Partner", "scopes": scopes }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    let body: serde_json::Value = response.json().await.unwrap();
+    (
+        body["id"].as_str().unwrap().to_owned(),
+        body["key"].as_str().unwrap().to_owned(),
+    )
+}
+
+async fn api_subscribe(app: &TestApp, key: Option<&str>) -> Response {
+    let mut request = reqwest::Client::new()
+        .post(format!("{}/api/subscribers", &app.address))
+        .json(&serde_json::json!({
+            "name": "Ursula Le Guin",
+            "email": "ursula_le_guin@gmail.com",
+        }));
+    if let Some(key) = key {
+        request = request.bearer_auth(key);
+    }
+    request.send().await.unwrap()
+}
+
+#[actix_web::test]
+async fn a_key_with_the_write_scope_can_add_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, key) = create_api_key(&app, &["subscribers:write"]).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = api_subscribe(&app, Some(&key)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 201);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "pending_confirmation");
+    let saved = sqlx::query!("SELECT id, email, status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(body["id"], saved.id.to_string());
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+#[actix_web::test]
+async fn a_revoked_key_is_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+    let (id, key) = create_api_key(&app, &["subscribers:write"]).await;
+    let response = app
+        .api_client
+        .post(format!("{}/admin/api-keys/{}/revoke", &app.address, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Act
+    let response = api_subscribe(&app, Some(&key)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let count = sqlx::query!("SELECT COUNT(*) AS count FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(count, Some(0));
+}
+
+#[actix_web::test]
+async fn a_key_without_the_write_scope_is_rejected_with_a_403() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, key) = create_api_key(&app, &["subscribers:read"]).await;
+
+    // Act
+    let response = api_subscribe(&app, Some(&key)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[actix_web::test]
+async fn requests_without_a_valid_key_are_rejected_with_a_401() {
+    let app = spawn_app().await;
+
+    for key in [None, Some("mlx_notarealkey")] {
+        let response = api_subscribe(&app, key).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(response.headers()["WWW-Authenticate"], "Bearer");
+    }
+}
+
+#[actix_web::test]
+async fn a_key_with_the_read_scope_can_look_up_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, write_key) = create_api_key(&app, &["subscribers:write"]).await;
+    let (_, read_key) = create_api_key(&app, &["subscribers:read"]).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    let response = api_subscribe(&app, Some(&write_key)).await;
+    let created: serde_json::Value = response.json().await.unwrap();
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/api/subscribers/{}",
+            &app.address,
+            created["id"].as_str().unwrap()
+        ))
+        .bearer_auth(&read_key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["email"], "ursula_le_guin@gmail.com");
+    assert_eq!(body["status"], "pending_confirmation");
+}
+
+#[actix_web::test]
+async fn api_keys_are_not_stored_in_plaintext() {
+    let app = spawn_app().await;
+    let (_, key) = create_api_key(&app, &["subscribers:write"]).await;
+
+    let stored = sqlx::query!("SELECT key_hash FROM api_keys")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_ne!(stored.key_hash, key);
+    assert!(!stored.key_hash.contains(&key));
+}
+
+#[actix_web::test]
+async fn keys_without_valid_scopes_are_not_created() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    for scopes in [serde_json::json!([]), serde_json::json!(["admin"])] {
+        let response = app
+            .api_client
+            .post(format!("{}/admin/api-keys", &app.address))
+            .json(&serde_json::json!({ "name": "Partner", "scopes": scopes }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status().as_u16(), 400);
+    }
+}
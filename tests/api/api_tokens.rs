@@ -0,0 +1,223 @@
+use chrono::{Duration, Utc};
+use melierx_backend::authentication::issue_token;
+use reqwest::RequestBuilder;
+use reqwest::Response;
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, spawn_app, spawn_app_with};
+
+/// Exchange the test user's credentials for a bearer token.
+/// # Returns
+/// The token.
+async fn get_token(app: &TestApp) -> String {
+    let response = reqwest::Client::new()
+        .post(format!("{}/auth/token", &app.address))
+        .basic_auth(&app.test_user.username, Some(&app.test_user.password))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["token_type"], "Bearer");
+    assert_eq!(body["expires_in"], 15 * 60);
+    body["access_token"].as_str().unwrap().to_owned()
+}
+
+fn newsletter_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    })
+}
+
+async fn api_publish(
+    app: &TestApp,
+    body: &serde_json::Value,
+    auth: impl FnOnce(RequestBuilder) -> RequestBuilder,
+) -> Response {
+    let request = reqwest::Client::new()
+        .post(format!("{}/newsletters", &app.address))
+        .json(body);
+    auth(request).send().await.unwrap()
+}
+
+async fn issue_count(app: &TestApp) -> i64 {
+    sqlx::query!("SELECT COUNT(*) AS count FROM issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn a_valid_token_can_publish_newsletters() {
+    // Arrange
+    let app = spawn_app().await;
+    sqlx::query(
+        "INSERT INTO subscriptions (id, email, name, status, subscribed_at)
+        VALUES ($1, 'ursula_le_guin@gmail.com', 'Ursula', 'confirmed', now())",
+    )
+    .bind(Uuid::new_v4())
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let token = get_token(&app).await;
+
+    // Act
+    let response =
+        api_publish(&app, &newsletter_body(), |r| r.bearer_auth(&token)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 201);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["recipients"], 1);
+    let issue = sqlx::query!("SELECT issue_id, title FROM issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(body["issue_id"], issue.issue_id.to_string());
+    assert_eq!(issue.title, "Newsletter title");
+}
+
+#[actix_web::test]
+async fn basic_credentials_can_publish_newsletters() {
+    // Arrange
+    let app = spawn_app().await;
+    let user = &app.test_user;
+
+    // Act
+    let response = api_publish(&app, &newsletter_body(), |r| {
+        r.basic_auth(&user.username, Some(&user.password))
+    })
+    .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(issue_count(&app).await, 1);
+}
+
+#[actix_web::test]
+async fn an_expired_token_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let settings = &app.configuration.api_tokens;
+    let issued_at = Utc::now() - settings.ttl() - Duration::minutes(1);
+    let token = issue_token(
+        app.test_user.user_id,
+        issued_at,
+        settings,
+        &app.configuration.application.hmac_secret,
+    );
+
+    // Act
+    let response =
+        api_publish(&app, &newsletter_body(), |r| r.bearer_auth(&token)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(issue_count(&app).await, 0);
+}
+
+#[actix_web::test]
+async fn a_token_with_a_tampered_signature_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = get_token(&app).await;
+    let (signed, signature) = token.rsplit_once('.').unwrap();
+    let first = if signature.starts_with('A') { 'B' } else { 'A' };
+    let tampered = format!("{signed}.{first}{}", &signature[1..]);
+
+    // Act
+    let response =
+        api_publish(&app, &newsletter_body(), |r| r.bearer_auth(&tampered))
+            .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(issue_count(&app).await, 0);
+}
+
+#[actix_web::test]
+async fn publishing_without_credentials_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = api_publish(&app, &newsletter_body(), |r| r).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(response.headers().contains_key("WWW-Authenticate"));
+    assert_eq!(issue_count(&app).await, 0);
+}
+
+#[actix_web::test]
+async fn a_token_is_not_issued_for_a_wrong_password() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .post(format!("{}/auth/token", &app.address))
+        .basic_auth(&app.test_user.username, Some("wrong-password"))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn publishing_through_the_api_is_idempotent() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = get_token(&app).await;
+    let body = newsletter_body();
+
+    // Act
+    let first = api_publish(&app, &body, |r| r.bearer_auth(&token)).await;
+    let second = api_publish(&app, &body, |r| r.bearer_auth(&token)).await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 201);
+    assert_eq!(second.status().as_u16(), 201);
+    let first: serde_json::Value = first.json().await.unwrap();
+    let second: serde_json::Value = second.json().await.unwrap();
+    assert_eq!(first, second);
+    assert_eq!(issue_count(&app).await, 1);
+}
+
+#[actix_web::test]
+async fn the_api_requires_confirmation_to_republish_recent_content() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.newsletter.detect_duplicate_content = true;
+    })
+    .await;
+    let token = get_token(&app).await;
+    let response =
+        api_publish(&app, &newsletter_body(), |r| r.bearer_auth(&token)).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Act - Part 1 - The same content again
+    let mut duplicate = newsletter_body();
+    let response =
+        api_publish(&app, &duplicate, |r| r.bearer_auth(&token)).await;
+
+    // Assert - Part 1
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(issue_count(&app).await, 1);
+
+    // Act - Part 2 - Confirmed, with the same idempotency key
+    duplicate["confirm_duplicate"] = true.into();
+    let response =
+        api_publish(&app, &duplicate, |r| r.bearer_auth(&token)).await;
+
+    // Assert - Part 2
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(issue_count(&app).await, 2);
+}
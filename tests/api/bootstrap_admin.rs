@@ -0,0 +1,55 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use melierx_backend::configuration::{Settings, get_configuration};
+use melierx_backend::email_client::SenderVerification;
+use melierx_backend::startup::Application;
+
+use crate::helpers::configure_database;
+
+/// Create a migrated database and settings asking for a bootstrap admin
+async fn bootstrap_admin_configuration() -> (Settings, PgPool) {
+    let configuration = {
+        let mut c = get_configuration().expect("Failed to read configuration.");
+        c.database.database_name = Uuid::new_v4().to_string();
+        c.application.port = 0;
+        c.application.bootstrap_admin_username = Some("bootstrap".into());
+        c.application.bootstrap_admin_password =
+            Some("a-long-enough-password".to_string().into());
+        c.email_client.sender_verification = SenderVerification::Skip;
+        c
+    };
+    let pool = configure_database(&configuration.database).await;
+    (configuration, pool)
+}
+
+async fn usernames(pool: &PgPool) -> Vec<String> {
+    sqlx::query_scalar!("SELECT username FROM users ORDER BY username")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn the_bootstrap_admin_is_created_when_there_are_no_users() {
+    let (configuration, pool) = bootstrap_admin_configuration().await;
+    sqlx::query!("DELETE FROM users")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    Application::build(configuration).await.unwrap();
+
+    assert_eq!(usernames(&pool).await, vec!["bootstrap"]);
+}
+
+#[actix_web::test]
+async fn the_bootstrap_admin_is_not_created_once_a_user_exists() {
+    let (configuration, pool) = bootstrap_admin_configuration().await;
+    let before = usernames(&pool).await;
+    assert!(!before.is_empty());
+
+    Application::build(configuration).await.unwrap();
+
+    assert_eq!(usernames(&pool).await, before);
+}
@@ -130,3 +130,52 @@ async fn changing_password_works() {
     let response = app.post_login(&login_body).await;
     assert_is_redirect_to(&response, "/admin/dashboard");
 }
+
+#[actix_web::test]
+async fn the_change_password_form_round_trips() {
+    let app = spawn_app().await;
+    let new_password = Uuid::new_v4().to_string();
+    app.test_user.login(&app).await;
+
+    // Fill in the inputs the form actually renders, in order.
+    let html_page = app.get_change_password_html().await;
+    let names: Vec<&str> = html_page
+        .split("<input ")
+        .skip(1)
+        .filter_map(|input| input.split("name=\"").nth(1))
+        .filter_map(|rest| rest.split('"').next())
+        .collect();
+    assert_eq!(names.len(), 3);
+    let values = [&app.test_user.password, &new_password, &new_password];
+    let form: std::collections::HashMap<_, _> =
+        names.into_iter().zip(values).collect();
+
+    let response = app.post_change_password(&form).await;
+    assert_is_redirect_to(&response, "/admin/password");
+    let html_page = app.get_change_password_html().await;
+    assert!(
+        html_page.contains("<p><i>Your password has been changed.</i></p>")
+    );
+}
+
+#[actix_web::test]
+async fn an_unexpected_failure_returns_a_500() {
+    let app = spawn_app().await;
+    let new_password = Uuid::new_v4().to_string();
+    app.test_user.login(&app).await;
+    // Sabotage the database
+    sqlx::query!("ALTER TABLE users DROP COLUMN password_hash;")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 500);
+}
@@ -0,0 +1,61 @@
+use fake::Fake;
+use fake::faker::internet::en::SafeEmail;
+
+use crate::helpers::{spawn_app, spawn_app_with};
+
+#[actix_web::test]
+async fn emails_sent_in_test_mode_are_listed_instead_of_sent() {
+    // Arrange
+    let app = spawn_app_with(|c| c.email_client.test_mode = true).await;
+    let email: String = SafeEmail().fake();
+    let body = serde_urlencoded::to_string([
+        ("name", "Ursula Le Guin"),
+        ("email", email.as_str()),
+    ])
+    .unwrap();
+
+    // Act
+    let response = app.post_subscriptions(body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app
+        .api_client
+        .get(format!("{}/dev/emails", &app.address))
+        .query(&[("to", &email)])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let emails: serde_json::Value = response.json().await.unwrap();
+    let emails = emails.as_array().unwrap();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0]["to"], email);
+    assert!(
+        emails[0]["html_content"]
+            .as_str()
+            .unwrap()
+            .contains("/subscriptions/confirm?subscription_token=")
+    );
+    assert!(
+        app.email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[actix_web::test]
+async fn the_captured_emails_are_not_routed_outside_test_mode() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/dev/emails", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 404);
+}
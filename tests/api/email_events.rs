@@ -0,0 +1,382 @@
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+use wiremock::Mock;
+use wiremock::matchers::{method, path};
+
+use melierx_backend::configuration::EmailStorage;
+use melierx_backend::webhooks::WebhookEvent;
+
+use crate::helpers::{TestApp, email_accepted, spawn_app, spawn_app_with};
+
+fn bounce(id: u64, email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "ID": id,
+        "Type": "HardBounce",
+        "Email": email,
+    })
+}
+
+async fn count_events(app: &TestApp, event_type: &str) -> i64 {
+    sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM email_events WHERE event_type = $1"#,
+        event_type
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn a_retried_event_is_processed_once() {
+    // Arrange
+    let app = spawn_app().await;
+    let event = bounce(4323372036854775807, "ursula@example.com");
+
+    // Act
+    let first = app.post_email_event(&event).await;
+    let retry = app.post_email_event(&event).await;
+
+    // Assert
+    assert_eq!(200, first.status().as_u16());
+    assert_eq!(200, retry.status().as_u16());
+    assert_eq!(count_events(&app, "bounce").await, 1);
+}
+
+#[actix_web::test]
+async fn distinct_event_ids_are_all_processed() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    app.post_email_event(&bounce(1, "ursula@example.com")).await;
+    app.post_email_event(&bounce(2, "ursula@example.com")).await;
+
+    // Assert
+    assert_eq!(count_events(&app, "bounce").await, 2);
+}
+
+#[actix_web::test]
+async fn retried_events_are_processed_again_when_deduplication_is_off() {
+    // Arrange
+    let app = spawn_app_with(|c| c.inbound_webhooks.deduplicate = false).await;
+    let event = bounce(1, "ursula@example.com");
+
+    // Act
+    app.post_email_event(&event).await;
+    app.post_email_event(&event).await;
+
+    // Assert
+    assert_eq!(count_events(&app, "bounce").await, 2);
+}
+
+#[actix_web::test]
+async fn a_retried_complaint_unsubscribes_the_subscriber_once() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=Ursula&email=ursula%40example.com".into())
+        .await;
+    let complaint = serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "ID": "complaint-1",
+        "Email": "ursula@example.com",
+        "Metadata": { "issue_id": Uuid::new_v4().to_string() },
+    });
+
+    // Act
+    app.post_email_event(&complaint).await;
+    app.post_email_event(&complaint).await;
+
+    // Assert
+    assert_eq!(count_events(&app, "complaint").await, 1);
+    let status = sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "unsubscribed");
+}
+
+#[actix_web::test]
+async fn complaints_match_hashed_subscribers() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.subscriber_emails.storage = EmailStorage::Hashed;
+    })
+    .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=Ursula&email=ursula%40example.com".into())
+        .await;
+    let complaint = serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "ID": "complaint-1",
+        "Email": "Ursula@Example.com",
+    });
+
+    // Act
+    app.post_email_event(&complaint).await;
+
+    // Assert
+    let status = sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "unsubscribed");
+    let recorded_for = sqlx::query_scalar!(
+        "SELECT subscriber_email FROM email_events WHERE event_type = 'complaint'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        recorded_for,
+        app.email_vault.lookup_key("ursula@example.com")
+    );
+}
+
+#[actix_web::test]
+async fn a_complaint_sends_out_the_unsubscribed_webhook_once() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.subscriber_emails.storage = EmailStorage::Hashed;
+        c.webhooks.target_url = Some("http://127.0.0.1:1/hooks".into());
+        c.webhooks.events = vec![WebhookEvent::SubscriberUnsubscribed];
+    })
+    .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=Ursula&email=ursula%40example.com".into())
+        .await;
+
+    // Act
+    for id in ["complaint-1", "complaint-2"] {
+        app.post_email_event(&serde_json::json!({
+            "RecordType": "SpamComplaint",
+            "ID": id,
+            "Email": "ursula@example.com",
+        }))
+        .await;
+    }
+
+    // Assert
+    let emails = sqlx::query_scalar!(
+        r#"SELECT payload -> 'data' ->> 'email' AS "email!"
+        FROM webhook_delivery_queue
+        WHERE event_type = 'subscriber.unsubscribed'"#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(emails, vec!["ursula@example.com".to_string()]);
+}
+
+#[actix_web::test]
+async fn unhandled_record_types_are_acknowledged_without_side_effects() {
+    // Arrange
+    let app = spawn_app().await;
+    let event = serde_json::json!({
+        "RecordType": "Delivery",
+        "ID": 1,
+        "Email": "ursula@example.com",
+    });
+
+    // Act
+    let response = app.post_email_event(&event).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let processed = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM processed_webhook_events"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(processed, 0);
+}
+
+/// An open as Postmark reports it: no `ID`, the address in `Recipient`
+fn open(message_id: &str, received_at: &str, email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Open",
+        "MessageStream": "outbound",
+        "FirstOpen": true,
+        "Client": { "Name": "Chrome 35.0.1916.153", "Company": "Google" },
+        "OS": { "Name": "OS X 10.7 Lion", "Company": "Apple Computer, Inc." },
+        "Platform": "WebMail",
+        "UserAgent": "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_7_5)",
+        "ReadSeconds": 5,
+        "Geo": {},
+        "MessageID": message_id,
+        "Metadata": {},
+        "ReceivedAt": received_at,
+        "Tag": "",
+        "Recipient": email,
+    })
+}
+
+/// A click as Postmark reports it: no `ID`, the address in `Recipient`
+fn click(
+    message_id: &str,
+    received_at: &str,
+    email: &str,
+) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Click",
+        "MessageStream": "outbound",
+        "ClickLocation": "HTML",
+        "Client": { "Name": "Chrome 35.0.1916.153", "Company": "Google" },
+        "OS": { "Name": "OS X 10.7 Lion", "Company": "Apple Computer, Inc." },
+        "Platform": "Desktop",
+        "UserAgent": "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_7_5)",
+        "OriginalLink": "https://example.com",
+        "Geo": {},
+        "MessageID": message_id,
+        "Metadata": {},
+        "ReceivedAt": received_at,
+        "Tag": "",
+        "Recipient": email,
+    })
+}
+
+#[actix_web::test]
+async fn opens_and_clicks_mark_the_subscriber_as_engaged() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=Ursula&email=ursula%40example.com".into())
+        .await;
+    let message_id = Uuid::new_v4().to_string();
+    let received_at = "2026-10-15T12:00:00Z";
+
+    for event in [
+        open(&message_id, received_at, "ursula@example.com"),
+        click(&message_id, received_at, "ursula@example.com"),
+    ] {
+        sqlx::query!("UPDATE subscriptions SET last_engaged_at = NULL")
+            .execute(&app.db_pool)
+            .await
+            .unwrap();
+
+        // Act
+        let response = app.post_email_event(&event).await;
+
+        // Assert
+        assert_eq!(200, response.status().as_u16());
+        let last_engaged_at = sqlx::query_scalar!(
+            "SELECT last_engaged_at FROM subscriptions WHERE email = $1",
+            "ursula@example.com"
+        )
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+        assert!(
+            last_engaged_at.is_some(),
+            "{} was not recorded",
+            event["RecordType"]
+        );
+    }
+}
+
+#[actix_web::test]
+async fn retried_opens_are_deduplicated_on_the_message_and_time() {
+    // Arrange
+    let app = spawn_app().await;
+    let message_id = Uuid::new_v4().to_string();
+    let first = open(&message_id, "2026-10-15T12:00:00Z", "ursula@example.com");
+    let second =
+        open(&message_id, "2026-10-15T13:00:00Z", "ursula@example.com");
+
+    // Act
+    app.post_email_event(&first).await;
+    app.post_email_event(&first).await;
+    app.post_email_event(&second).await;
+
+    // Assert
+    assert_eq!(count_events(&app, "open").await, 2);
+}
+
+#[actix_web::test]
+async fn events_without_the_webhook_token_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/webhooks/email-events", &app.address))
+        .header("X-Webhook-Token", "not-the-token")
+        .json(&bounce(1, "ursula@example.com"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+    assert_eq!(count_events(&app, "bounce").await, 0);
+}
+
+#[actix_web::test]
+async fn malformed_json_is_rejected_with_a_problem_json_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = r#"{"RecordType": "Bounce", "ID": 1, "Email": "secret@exa"#;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/webhooks/email-events", &app.address))
+        .header(
+            "X-Webhook-Token",
+            app.inbound_webhooks.token.expose_secret(),
+        )
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/problem+json"
+    );
+    let problem: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(problem["status"], 400);
+    assert_eq!(problem["title"], "Bad Request");
+    let detail = problem["detail"].as_str().unwrap();
+    assert!(detail.contains("not valid JSON"), "{}", detail);
+    assert!(!detail.contains("secret"));
+}
+
+#[actix_web::test]
+async fn json_of_the_wrong_shape_is_rejected_with_a_problem_json_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_email_event(&serde_json::json!({ "RecordType": "Bounce" }))
+        .await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+    let problem: serde_json::Value = response.json().await.unwrap();
+    let detail = problem["detail"].as_str().unwrap();
+    assert!(detail.contains("expected fields or types"), "{}", detail);
+}
@@ -0,0 +1,97 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, email_accepted, spawn_app_with,
+};
+
+async fn spawn_app_requiring_verification() -> TestApp {
+    let app = spawn_app_with(|c| {
+        c.email_verification.required = true;
+    })
+    .await;
+    sqlx::query!(
+        "UPDATE users SET email = 'admin@example.com' WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app
+}
+
+async fn verify(app: &TestApp, token: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!("{}/verify-email", &app.address))
+        .query(&[("token", token)])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[actix_web::test]
+async fn an_unverified_user_is_refused_when_verification_is_required() {
+    let app = spawn_app_requiring_verification().await;
+
+    let response = app.post_correct_login().await;
+
+    assert_eq!(response.status().as_u16(), 403);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Please verify your email address"));
+    assert!(html_page.contains(r#"action="/login/verify-email""#));
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn the_verification_link_lets_the_user_log_in() {
+    let app = spawn_app_requiring_verification().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_correct_login().await;
+
+    let response = app.post_resend_email_verification().await;
+    assert_is_redirect_to(&response, "/login");
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "admin@example.com");
+    let link = app.get_confirmation_links(email_request).html;
+    assert_eq!(link.path(), "/verify-email");
+    let token = link
+        .query_pairs()
+        .find(|(name, _)| name == "token")
+        .map(|(_, token)| token.into_owned())
+        .unwrap();
+
+    let response = verify(&app, &token).await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Your email address has been verified."));
+    let response = app.post_correct_login().await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // The link is used up.
+    let response = verify(&app, &token).await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("invalid or has expired"));
+}
+
+#[actix_web::test]
+async fn no_verification_email_is_sent_without_a_refused_login() {
+    let app = spawn_app_requiring_verification().await;
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_resend_email_verification().await;
+
+    assert_is_redirect_to(&response, "/login");
+}
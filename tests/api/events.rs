@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, spawn_app, spawn_app_with,
+};
+
+/// Record `n` events that all happened at the same instant, so only the id
+/// tie-breaker keeps their order stable.
+async fn record_events(app: &TestApp, n: usize) {
+    for i in 0..n {
+        sqlx::query!(
+            r#"
+            INSERT INTO email_events (
+                id, subscriber_email, event_type, occurred_at
+            )
+            VALUES ($1, $2, 'bounce', '2026-10-01T00:00:00Z')
+            "#,
+            Uuid::new_v4(),
+            format!("bounced{i}@example.com")
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_list_email_events() {
+    let app = spawn_app().await;
+
+    let response = app.get_email_events("").await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn email_events_are_paged_without_skipping_or_repeating() {
+    // Arrange
+    let app = spawn_app().await;
+    record_events(&app, 5).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let mut seen = Vec::new();
+    let mut query = "limit=2".to_string();
+    loop {
+        let response = app.get_email_events(&query).await;
+        assert_eq!(response.status().as_u16(), 200);
+        let page: serde_json::Value = response.json().await.unwrap();
+        let events = page["events"].as_array().unwrap();
+        assert!(events.len() <= 2);
+        seen.extend(
+            events.iter().map(|e| e["id"].as_str().unwrap().to_owned()),
+        );
+        match page["next_cursor"].as_str() {
+            Some(cursor) => query = format!("limit=2&after={cursor}"),
+            None => break,
+        }
+    }
+
+    // Assert
+    assert_eq!(seen.len(), 5);
+    assert_eq!(seen.iter().collect::<HashSet<_>>().len(), 5);
+    let mut expected = seen.clone();
+    expected.sort_by(|a, b| b.cmp(a));
+    assert_eq!(seen, expected);
+}
+
+#[actix_web::test]
+async fn pages_larger_than_the_configured_maximum_are_rejected() {
+    // Arrange
+    let app = spawn_app_with(|c| c.event_listing.max_page_size = 10).await;
+    app.test_user.login(&app).await;
+
+    for query in ["limit=11", "limit=0", "limit=-1"] {
+        // Act
+        let events = app.get_email_events(query).await;
+        let audit = app.get_feature_audit(query).await;
+
+        // Assert
+        assert_eq!(events.status().as_u16(), 400, "{query}");
+        assert_eq!(audit.status().as_u16(), 400, "{query}");
+    }
+    let response = app.get_email_events("limit=10").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[actix_web::test]
+async fn feature_flag_changes_are_listed_newest_first_in_pages() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    for enabled in ["true", "false", "true"] {
+        app.post_feature(&serde_json::json!({
+            "feature": "duplicate_content_detection",
+            "enabled": enabled,
+        }))
+        .await;
+    }
+
+    // Act
+    let first: serde_json::Value =
+        app.get_feature_audit("limit=2").await.json().await.unwrap();
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let second: serde_json::Value = app
+        .get_feature_audit(&format!("limit=2&after={cursor}"))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    // Assert
+    let enabled: Vec<_> = first["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .chain(second["changes"].as_array().unwrap())
+        .map(|c| c["enabled"].as_bool().unwrap())
+        .collect();
+    assert_eq!(enabled, [true, false, true]);
+    assert!(second["next_cursor"].is_null());
+}
@@ -0,0 +1,89 @@
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_change_a_feature_flag() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_feature(&serde_json::json!({
+            "feature": "duplicate_content_detection",
+            "enabled": "true",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn toggling_a_flag_takes_effect_immediately() {
+    // Arrange - duplicate detection is off in the base configuration
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter = |key: Uuid| {
+        serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": key.to_string()
+        })
+    };
+    app.post_publish_newsletter(&newsletter(Uuid::new_v4()))
+        .await;
+    app.get_publish_newsletter_html().await;
+
+    // Act - Part 1 - Turn the flag on
+    let response = app
+        .post_feature(&serde_json::json!({
+            "feature": "duplicate_content_detection",
+            "enabled": "true",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/features");
+    let html_page = app.get_features_html().await;
+    assert!(
+        html_page
+            .contains("<p><i>duplicate_content_detection is now on.</i></p>")
+    );
+
+    // Act - Part 2 - Publish the same content again
+    app.post_publish_newsletter(&newsletter(Uuid::new_v4()))
+        .await;
+
+    // Assert
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>This content is identical to a recently published issue"
+    ));
+    let audit = sqlx::query!(
+        "SELECT feature, enabled, changed_by FROM feature_flag_audit"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(audit.feature, "duplicate_content_detection");
+    assert!(audit.enabled);
+    assert_eq!(audit.changed_by, app.test_user.user_id);
+}
+
+#[actix_web::test]
+async fn unknown_features_are_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_feature(&serde_json::json!({
+            "feature": "time_travel",
+            "enabled": "true",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/features");
+    let html_page = app.get_features_html().await;
+    assert!(
+        html_page.contains(
+            "<p><i>There is no feature called \"time_travel\".</i></p>"
+        )
+    );
+}
@@ -8,7 +8,7 @@ async fn health_check_works() {
     let client = Client::new();
 
     let response = client
-        .get(&format!("{}/health_check", app.address))
+        .get(format!("{}/health_check", app.address))
         .send()
         .await
         .expect("Failed to execute request.");
@@ -16,3 +16,21 @@ async fn health_check_works() {
     assert!(response.status().is_success());
     assert_eq!(Some(0), response.content_length());
 }
+
+#[actix_web::test]
+async fn health_check_reports_uptime_and_version_as_json() {
+    let app = spawn_app().await;
+    let client = Client::new();
+
+    let response = client
+        .get(format!("{}/health_check", app.address))
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["uptime_seconds"].as_f64().unwrap() > 0.0);
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+}
@@ -6,18 +6,27 @@ use argon2::password_hash::{SaltString, rand_core::OsRng};
 use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
 use linkify::{LinkFinder, LinkKind};
 use reqwest::{Client, Response, Url, redirect};
-use secrecy::SecretString;
+use secrecy::ExposeSecret;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use uuid::Uuid;
-use wiremock::{MockServer, Request};
+use wiremock::{MockServer, Request, Respond, ResponseTemplate};
 
-use melierx_backend::configuration::{DatabaseSettings, get_configuration};
-use melierx_backend::email_client::EmailClient;
+use melierx_backend::configuration::{
+    DatabaseSettings, InboundWebhookSettings, NewsletterSettings,
+    OnboardingSettings, Settings, WebhookSettings, get_configuration,
+};
+use melierx_backend::email_client::{EmailClient, SenderVerification};
+use melierx_backend::email_vault::EmailVault;
 use melierx_backend::issue_delivery_worker::{
-    ExecutionOutcome, try_execute_task,
+    ExecutionOutcome, drain_delivery_queue, release_scheduled_issues,
+    run_worker_until_stopped,
 };
+use melierx_backend::onboarding::try_execute_onboarding_task;
+use melierx_backend::routes::INBOUND_WEBHOOK_TOKEN_HEADER;
 use melierx_backend::startup::{Application, get_connection_pool};
+use melierx_backend::subscriber_token::{SubscriberAction, SubscriberLinks};
 use melierx_backend::telemetry::{get_subscriber, init_subscriber};
+use melierx_backend::webhooks::try_execute_webhook_task;
 
 // Ensure that the tracing stack is only initialized once
 static TRACING: LazyLock<()> = LazyLock::new(|| {
@@ -26,11 +35,11 @@ static TRACING: LazyLock<()> = LazyLock::new(|| {
     if env::var("TEST_LOG").is_ok() {
         let subscriber =
             get_subscriber(subscriber_name, default_filter_level, io::stdout);
-        init_subscriber(subscriber);
+        let _ = init_subscriber(subscriber);
     } else {
         let subscriber =
             get_subscriber(subscriber_name, default_filter_level, io::sink);
-        init_subscriber(subscriber);
+        let _ = init_subscriber(subscriber);
     };
 });
 
@@ -43,13 +52,20 @@ pub struct TestApp {
     pub test_user: TestUser,
     pub api_client: Client,
     pub email_client: EmailClient,
+    pub subscriber_links: SubscriberLinks,
+    pub newsletter_settings: NewsletterSettings,
+    pub onboarding_settings: OnboardingSettings,
+    pub webhook_settings: WebhookSettings,
+    pub inbound_webhooks: InboundWebhookSettings,
+    pub email_vault: EmailVault,
+    pub configuration: Settings,
 }
 
 impl TestApp {
     /// Send a POST request to the subscriptions endpoint
     pub async fn post_subscriptions(&self, body: String) -> Response {
         self.api_client
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send()
@@ -57,6 +73,71 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    /// Ask for a pending subscriber's confirmation email again
+    pub async fn post_resend_confirmation(&self, email: &str) -> Response {
+        self.api_client
+            .post(format!(
+                "{}/subscriptions/resend-confirmation",
+                &self.address
+            ))
+            .form(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Issue the token a subscriber's link for `action` carries
+    pub fn subscriber_token(
+        &self,
+        subscriber_id: Uuid,
+        action: SubscriberAction,
+    ) -> String {
+        self.subscriber_links.token(subscriber_id, action)
+    }
+
+    /// Request a change of a subscriber's email address
+    pub async fn post_email_change(
+        &self,
+        token: &str,
+        email: &str,
+    ) -> Response {
+        self.api_client
+            .post(format!("{}/preferences/email", &self.address))
+            .form(&serde_json::json!({
+                "token": token,
+                "email": email,
+            }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Snooze a subscriber's emails until the given date
+    pub async fn post_snooze(&self, token: &str, until: &str) -> Response {
+        self.api_client
+            .post(format!("{}/preferences/snooze", &self.address))
+            .form(&serde_json::json!({
+                "token": token,
+                "until": until,
+            }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Wait for the mock email server to receive `count` emails, e.g. sent
+    /// in the background after responding
+    pub async fn wait_for_emails(&self, count: usize) -> Vec<Request> {
+        for _ in 0..50 {
+            let received = self.email_server.received_requests().await.unwrap();
+            if received.len() >= count {
+                return received;
+            }
+            rt::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        panic!("Timed out waiting for {count} emails.");
+    }
+
     /// Extract the confirmation links from the email request
     pub fn get_confirmation_links(
         &self,
@@ -64,15 +145,30 @@ impl TestApp {
     ) -> ConfirmationLinks {
         let body: serde_json::Value =
             serde_json::from_slice(&email_request.body).unwrap();
+        self.get_links(&body)
+    }
 
+    /// Extract the link from an email's bodies, e.g. one sent in a batch
+    pub fn get_links(&self, body: &serde_json::Value) -> ConfirmationLinks {
+        self.get_links_to(body, "/")
+    }
+
+    /// Extract the one link to a path starting with `path` from an email's
+    /// bodies, e.g. one of the links in a newsletter's footer
+    pub fn get_links_to(
+        &self,
+        body: &serde_json::Value,
+        path: &str,
+    ) -> ConfirmationLinks {
         let get_link = |s: &str| {
             let links: Vec<_> = LinkFinder::new()
                 .links(s)
                 .filter(|l| *l.kind() == LinkKind::Url)
+                .map(|l| Url::parse(l.as_str()).unwrap())
+                .filter(|l| l.path().starts_with(path))
                 .collect();
             assert_eq!(links.len(), 1);
-            let raw_link = links[0].as_str().to_owned();
-            let mut confirmation_link = Url::parse(&raw_link).unwrap();
+            let mut confirmation_link = links[0].clone();
 
             // Make sure we don't call random APIs during tests
             assert_eq!(confirmation_link.host_str().unwrap(), "127.0.0.1");
@@ -88,7 +184,7 @@ impl TestApp {
     /// Send a GET request to the publish newsletter page
     pub async fn get_publish_newsletter(&self) -> Response {
         self.api_client
-            .get(&format!("{}/admin/newsletters", &self.address))
+            .get(format!("{}/admin/newsletters", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -105,7 +201,257 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/newsletters", &self.address))
+            .post(format!("{}/admin/newsletters", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to send a test email of a newsletter issue
+    pub async fn post_test_newsletter<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/newsletters/test", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to resume a paused newsletter issue
+    pub async fn post_resume_newsletter(&self, issue_id: Uuid) -> Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/resume",
+                &self.address, issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to requeue dead-lettered deliveries
+    pub async fn post_retry_dead_letters(
+        &self,
+        query: &[(&str, &str)],
+    ) -> Response {
+        self.api_client
+            .post(format!("{}/admin/dead-letters/retry", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to flush the delivery queue
+    pub async fn post_flush_delivery_queue(&self) -> Response {
+        self.api_client
+            .post(format!("{}/admin/delivery-queue/flush", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to unschedule a newsletter issue
+    pub async fn post_unschedule_newsletter(&self, issue_id: Uuid) -> Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/unschedule",
+                &self.address, issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a GET request to list subscribers with the given query string
+    pub async fn get_subscribers(&self, query: &str) -> Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a GET request to export every subscriber
+    pub async fn get_subscriber_export(&self) -> Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/export", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a GET request to list inactive subscribers
+    pub async fn get_inactive_subscribers(&self, query: &str) -> Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/subscribers/inactive?{}",
+                &self.address, query
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to suppress inactive subscribers
+    pub async fn post_suppress_inactive_subscribers(
+        &self,
+        query: &str,
+    ) -> Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/subscribers/inactive/suppress?{}",
+                &self.address, query
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a GET request to list email events
+    pub async fn get_email_events(&self, query: &str) -> Response {
+        self.api_client
+            .get(format!("{}/admin/events?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request delivering an email provider webhook event
+    pub async fn post_email_event(&self, body: &serde_json::Value) -> Response {
+        self.api_client
+            .post(format!("{}/webhooks/email-events", &self.address))
+            .header(
+                INBOUND_WEBHOOK_TOKEN_HEADER,
+                self.inbound_webhooks.token.expose_secret(),
+            )
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a GET request to list feature flag changes
+    pub async fn get_feature_audit(&self, query: &str) -> Response {
+        self.api_client
+            .get(format!("{}/admin/features/audit?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to add a subscriber as an admin
+    pub async fn post_add_subscriber(
+        &self,
+        body: &serde_json::Value,
+    ) -> Response {
+        self.api_client
+            .post(format!("{}/admin/subscribers", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to merge two subscriber records
+    pub async fn post_merge_subscribers<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/subscribers/merge", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to restart a subscriber's onboarding sequence
+    pub async fn post_restart_onboarding(
+        &self,
+        subscriber_id: Uuid,
+    ) -> Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/subscribers/{}/onboarding",
+                &self.address, subscriber_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a GET request for the delivery status of a newsletter issue
+    pub async fn get_newsletter_unsubscribes(&self) -> Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters/unsubscribes", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_delivery_status(
+        &self,
+        issue_id: Uuid,
+    ) -> Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/{}/delivery",
+                &self.address, issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a GET request to the feature flags page and return the HTML content
+    pub async fn get_features_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/features", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// Send a POST request to change a feature flag
+    pub async fn post_feature<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/features", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a GET request to the home page and return the HTML content
+    pub async fn get_home_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// Send a POST request to edit the home page
+    pub async fn post_home_page<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/home", &self.address))
             .form(body)
             .send()
             .await
@@ -115,7 +461,7 @@ impl TestApp {
     /// Send a GET request to the login page and return the HTML content
     pub async fn get_login_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/login", &self.address))
+            .get(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -130,17 +476,26 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .form(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    /// Log in as the test user with the right password
+    pub async fn post_correct_login(&self) -> Response {
+        self.post_login(&serde_json::json!({
+            "username": &self.test_user.username,
+            "password": &self.test_user.password
+        }))
+        .await
+    }
+
     /// Send a GET request to the admin dashboard and return the response
     pub async fn get_admin_dashboard(&self) -> Response {
         self.api_client
-            .get(&format!("{}/admin/dashboard", &self.address))
+            .get(format!("{}/admin/dashboard", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -154,7 +509,7 @@ impl TestApp {
     /// Send a GET request to the change password page
     pub async fn get_change_password(&self) -> Response {
         self.api_client
-            .get(&format!("{}/admin/password", &self.address))
+            .get(format!("{}/admin/password", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -171,7 +526,83 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/password", &self.address))
+            .post(format!("{}/admin/password", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to one of the two-factor authentication
+    /// endpoints: `enroll`, `confirm` or `disable`
+    pub async fn post_totp(
+        &self,
+        action: &str,
+        body: &serde_json::Value,
+    ) -> Response {
+        self.api_client
+            .post(format!("{}/admin/totp/{}", &self.address, action))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request with a code to the two-factor login step
+    pub async fn post_login_totp(&self, code: &str) -> Response {
+        self.api_client
+            .post(format!("{}/login/totp", &self.address))
+            .form(&serde_json::json!({ "code": code }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request asking for a new email verification link
+    pub async fn post_resend_email_verification(&self) -> Response {
+        self.api_client
+            .post(format!("{}/login/verify-email", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a GET request to the password reset request page and return the
+    /// HTML content
+    pub async fn get_password_reset_request_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/password-reset/request", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// Send a POST request asking for a password reset link
+    pub async fn post_password_reset_request<Body>(
+        &self,
+        body: &Body,
+    ) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/password-reset/request", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Send a POST request to the password reset endpoint
+    pub async fn post_password_reset<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/password-reset", &self.address))
             .form(body)
             .send()
             .await
@@ -181,18 +612,65 @@ impl TestApp {
     /// Send a POST request to the logout endpoint
     pub async fn post_logout(&self) -> Response {
         self.api_client
-            .post(&format!("{}/admin/logout", &self.address))
+            .post(format!("{}/admin/logout", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn dispatch_all_due_onboarding_emails(&self) {
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_execute_onboarding_task(
+                &self.db_pool,
+                &self.email_client,
+                &self.onboarding_settings,
+                &self.email_vault,
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
     pub async fn dispatch_all_pending_emails(&self) {
+        drain_delivery_queue(
+            &self.db_pool,
+            &self.email_client,
+            &self.subscriber_links,
+            &self.newsletter_settings,
+            &self.webhook_settings,
+            &self.email_vault,
+        )
+        .await
+        .unwrap();
+    }
+
+    /// Run the delivery worker in the background, as the binary does
+    pub fn spawn_delivery_worker(&self) {
+        rt::spawn(run_worker_until_stopped(self.configuration.clone()));
+    }
+
+    pub async fn release_scheduled_issues(&self) -> u64 {
+        release_scheduled_issues(
+            &self.db_pool,
+            &self.webhook_settings,
+            &self.newsletter_settings.send_time_optimization,
+        )
+        .await
+        .unwrap()
+    }
+
+    pub async fn dispatch_all_pending_webhooks(&self) {
         loop {
-            if let ExecutionOutcome::EmptyQueue =
-                try_execute_task(&self.db_pool, &self.email_client)
-                    .await
-                    .unwrap()
+            if let ExecutionOutcome::EmptyQueue = try_execute_webhook_task(
+                &self.db_pool,
+                &self.webhook_settings.client(),
+                self.webhook_settings.max_retries,
+            )
+            .await
+            .unwrap()
             {
                 break;
             }
@@ -208,7 +686,7 @@ pub struct ConfirmationLinks {
 
 /// Structure representing a test user.
 pub struct TestUser {
-    user_id: Uuid,
+    pub user_id: Uuid,
     pub username: String,
     pub password: String,
 }
@@ -264,6 +742,18 @@ impl TestUser {
 /// A `TestApp` instance containing the application address, port, database connection pool,
 /// and email server.
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawns the application after applying test-specific configuration overrides.
+/// # Arguments
+/// * `customise` - A closure that adjusts the randomized configuration.
+/// # Returns
+/// A `TestApp` instance, as for `spawn_app`.
+pub async fn spawn_app_with<F>(customise: F) -> TestApp
+where
+    F: FnOnce(&mut Settings),
+{
     LazyLock::force(&TRACING);
 
     // Launch a mock email server (PostMark equivalent)
@@ -278,6 +768,12 @@ pub async fn spawn_app() -> TestApp {
         c.application.port = 0;
         // Use the mock email server
         c.email_client.base_url = email_server.uri();
+        // The mock provider does not know about sender signatures
+        c.email_client.sender_verification = SenderVerification::Skip;
+        // Failed sends go straight back to the queue, so tests can count
+        // delivery attempts
+        c.email_client.max_retries = 0;
+        customise(&mut c);
         c
     };
 
@@ -290,7 +786,7 @@ pub async fn spawn_app() -> TestApp {
         .expect("Failed to build application.");
 
     let application_port = application.port();
-    let _ = rt::spawn(application.run_until_stopped());
+    rt::spawn(application.run_until_stopped());
     let client = Client::builder()
         .redirect(redirect::Policy::none())
         .cookie_store(true)
@@ -306,7 +802,17 @@ pub async fn spawn_app() -> TestApp {
         email_server,
         test_user: TestUser::generate(),
         api_client: client,
-        email_client: configuration.email_client.client(),
+        email_client: configuration.email_client.clone().client(),
+        subscriber_links: SubscriberLinks::new(
+            configuration.application.base_url.clone(),
+            configuration.application.hmac_secret.clone(),
+        ),
+        newsletter_settings: configuration.newsletter.clone(),
+        onboarding_settings: configuration.onboarding.clone(),
+        webhook_settings: configuration.webhooks.clone(),
+        email_vault: EmailVault::new(&configuration.subscriber_emails),
+        inbound_webhooks: configuration.inbound_webhooks.clone(),
+        configuration,
     };
     test_app.test_user.store(&test_app.db_pool).await;
     test_app
@@ -318,13 +824,21 @@ pub async fn spawn_app() -> TestApp {
 /// # Returns
 /// A `PgPool` instance connected to the configured database.
 pub async fn configure_database(config: &DatabaseSettings) -> PgPool {
-    // Create database
-    let maintenance_settings = DatabaseSettings {
-        database_name: "postgres".to_string(),
-        username: "postgres".to_string(),
-        password: SecretString::new(Box::from("password")),
-        ..config.clone()
-    };
+    let connection_pool = create_database(config).await;
+    sqlx::migrate!("./migrations")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database");
+    connection_pool
+}
+
+/// Creates an empty database, without running any migration.
+/// # Arguments
+/// * `config` - The settings of the database to create.
+/// # Returns
+/// A `PgPool` instance connected to the new database.
+pub async fn create_database(config: &DatabaseSettings) -> PgPool {
+    let maintenance_settings = config.maintenance();
 
     let mut connection =
         PgConnection::connect_with(&maintenance_settings.connect_options())
@@ -337,15 +851,65 @@ pub async fn configure_database(config: &DatabaseSettings) -> PgPool {
         .await
         .expect("Failed to create database.");
 
-    // Migrate database
-    let connection_pool = PgPool::connect_with(config.connect_options())
+    PgPool::connect_with(config.connect_options())
         .await
-        .expect("Failed to connect to Postgres.");
-    sqlx::migrate!("./migrations")
-        .run(&connection_pool)
+        .expect("Failed to connect to Postgres.")
+}
+
+/// Postmark's response to an accepted email.
+pub fn email_accepted() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "To": "subscriber@example.com",
+        "SubmittedAt": "2026-10-15T09:30:00.1234567-04:00",
+        "MessageID": Uuid::new_v4(),
+        "ErrorCode": 0,
+        "Message": "OK"
+    }))
+}
+
+/// Postmark's response to a batch that accepts every email in it
+pub fn batch_accepted() -> BatchAccepted {
+    BatchAccepted
+}
+
+pub struct BatchAccepted;
+
+impl Respond for BatchAccepted {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let results: Vec<_> = batch_emails(request)
+            .iter()
+            .map(|email| {
+                serde_json::json!({
+                    "To": email["To"],
+                    "SubmittedAt": "2026-10-15T09:30:00.1234567-04:00",
+                    "MessageID": Uuid::new_v4(),
+                    "ErrorCode": 0,
+                    "Message": "OK"
+                })
+            })
+            .collect();
+        ResponseTemplate::new(200).set_body_json(results)
+    }
+}
+
+/// The emails in a request to the mock email server, whether sent on their
+/// own or in a batch
+pub fn batch_emails(request: &Request) -> Vec<serde_json::Value> {
+    match serde_json::from_slice(&request.body).unwrap() {
+        serde_json::Value::Array(emails) => emails,
+        email => vec![email],
+    }
+}
+
+/// Every email the mock email server received, in order
+pub async fn sent_emails(email_server: &MockServer) -> Vec<serde_json::Value> {
+    email_server
+        .received_requests()
         .await
-        .expect("Failed to migrate the database");
-    connection_pool
+        .unwrap()
+        .iter()
+        .flat_map(batch_emails)
+        .collect()
 }
 
 /// Asserts that the response is a redirect to the specified location.
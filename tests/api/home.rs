@@ -0,0 +1,67 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[actix_web::test]
+async fn the_built_in_home_page_is_served_when_none_is_stored() {
+    let app = spawn_app().await;
+
+    let html_page = app.get_home_html().await;
+
+    assert!(html_page.contains("<h1>Welcome to Melierx!</h1>"));
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_edit_the_home_page() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_home_page(&serde_json::json!({
+            "heading": "Hello",
+            "body": "World",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn editing_the_home_page_changes_the_served_content() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_home_page(&serde_json::json!({
+            "heading": "Spring <sale>",
+            "body": "First paragraph\nSecond & last",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/home");
+    let html_page = app.get_home_html().await;
+    assert!(html_page.contains("<h1>Spring &lt;sale&gt;</h1>"));
+    assert!(html_page.contains("<p>First paragraph</p>"));
+    assert!(html_page.contains("<p>Second &amp; last</p>"));
+    assert!(!html_page.contains("Welcome to Melierx!"));
+}
+
+#[actix_web::test]
+async fn an_empty_heading_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_home_page(&serde_json::json!({
+            "heading": "   ",
+            "body": "Body",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/home");
+    let html_page = app.get_home_html().await;
+    assert!(html_page.contains("<h1>Welcome to Melierx!</h1>"));
+}
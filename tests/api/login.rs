@@ -1,4 +1,8 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use reqwest::{Client, redirect};
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, spawn_app, spawn_app_with,
+};
 
 #[actix_web::test]
 async fn an_error_flash_message_is_set_on_failure() {
@@ -41,3 +45,162 @@ async fn redirect_to_admin_dashboard_after_successful_login() {
         html_page.contains(&format!("Welcome {}", &app.test_user.username))
     );
 }
+
+/// Log the test user in from a fresh client, i.e. a separate browser.
+async fn log_in_from_new_client(app: &TestApp) -> Client {
+    let client = Client::builder()
+        .redirect(redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let response = client
+        .post(format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    client
+}
+
+async fn get_admin_dashboard(
+    app: &TestApp,
+    client: &Client,
+) -> reqwest::Response {
+    client
+        .get(format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[actix_web::test]
+async fn logging_in_beyond_the_session_limit_evicts_the_oldest_session() {
+    let app = spawn_app_with(|c| c.sessions.max_per_user = 2).await;
+
+    let oldest = log_in_from_new_client(&app).await;
+    let middle = log_in_from_new_client(&app).await;
+    let newest = log_in_from_new_client(&app).await;
+
+    let response = get_admin_dashboard(&app, &oldest).await;
+    assert_is_redirect_to(&response, "/login");
+    assert_eq!(get_admin_dashboard(&app, &middle).await.status(), 200);
+    assert_eq!(get_admin_dashboard(&app, &newest).await.status(), 200);
+    let revoked = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM user_sessions WHERE revoked_at IS NOT NULL"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(revoked, Some(1));
+}
+
+#[actix_web::test]
+async fn a_session_limit_of_zero_keeps_every_session() {
+    let app = spawn_app().await;
+
+    let mut clients = Vec::new();
+    for _ in 0..3 {
+        clients.push(log_in_from_new_client(&app).await);
+    }
+
+    for client in &clients {
+        assert_eq!(get_admin_dashboard(&app, client).await.status(), 200);
+    }
+}
+
+/// Try the test user's username with a wrong password
+async fn fail_login(app: &TestApp) {
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": "wrong-password"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn repeated_failures_lock_out_even_the_right_password() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.max_failures_per_username = 3;
+    })
+    .await;
+    for _ in 0..3 {
+        fail_login(&app).await;
+    }
+
+    let response = app.post_correct_login().await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts"));
+}
+
+#[actix_web::test]
+async fn the_lockout_clears_after_the_window() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.max_failures_per_username = 3;
+        c.login_throttle.window_seconds = 60;
+    })
+    .await;
+    for _ in 0..3 {
+        fail_login(&app).await;
+    }
+    assert_is_redirect_to(&app.post_correct_login().await, "/login");
+
+    sqlx::query!(
+        "UPDATE login_attempts \
+        SET attempted_at = attempted_at - interval '61 seconds'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app.post_correct_login().await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[actix_web::test]
+async fn a_successful_login_resets_the_failure_count() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.max_failures_per_username = 3;
+    })
+    .await;
+    for _ in 0..2 {
+        fail_login(&app).await;
+    }
+    assert_is_redirect_to(&app.post_correct_login().await, "/admin/dashboard");
+    app.post_logout().await;
+
+    for _ in 0..2 {
+        fail_login(&app).await;
+    }
+
+    let response = app.post_correct_login().await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[actix_web::test]
+async fn repeated_failures_from_one_address_lock_out_other_usernames() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.max_failures_per_ip = 3;
+    })
+    .await;
+    for i in 0..3 {
+        app.post_login(&serde_json::json!({
+            "username": format!("guess-{i}"),
+            "password": "wrong-password"
+        }))
+        .await;
+    }
+
+    let response = app.post_correct_login().await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts"));
+}
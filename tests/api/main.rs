@@ -1,9 +1,29 @@
 mod admin_dashboard;
+mod api_keys;
+mod api_tokens;
+mod bootstrap_admin;
 mod change_password;
+mod dev_emails;
+mod email_events;
+mod email_verification;
+mod events;
+mod features;
 mod health_check;
 mod helpers;
+mod home;
 mod login;
+mod metrics;
+mod migrations;
 mod newsletter;
+mod onboarding;
+mod password_reset;
+mod pending_sweeper;
+mod preferences;
+mod scheduler;
+mod sender_verification;
+mod subscribers;
 mod subscriptions;
 mod subscriptions_confirm;
 mod test_user;
+mod two_factor;
+mod webhooks;
@@ -0,0 +1,116 @@
+use reqwest::Response;
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, spawn_app, spawn_app_with};
+
+const SCRAPE_TOKEN: &str = "metrics-scrape-token";
+
+/// Spawn the application with `/metrics` enabled
+async fn spawn_app_with_metrics() -> TestApp {
+    spawn_app_with(|c| {
+        c.metrics.enabled = true;
+        c.metrics.scrape_token = Some(SCRAPE_TOKEN.into());
+    })
+    .await
+}
+
+/// Scrape `/metrics` with the configured token
+async fn scrape(app: &TestApp) -> Response {
+    app.api_client
+        .get(format!("{}/metrics", app.address))
+        .bearer_auth(SCRAPE_TOKEN)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Read a counter from `/metrics`
+async fn read_counter(app: &TestApp, name: &str) -> i64 {
+    let body = scrape(app).await.text().await.unwrap();
+    body.lines()
+        .find_map(|line| line.strip_prefix(&format!("{name} ")))
+        .expect("The counter is not exposed")
+        .parse()
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn metrics_expose_delivery_counters() {
+    let app = spawn_app_with_metrics().await;
+
+    let response = scrape(&app).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("# TYPE newsletter_delivery_retries_total counter"));
+    assert!(body.contains("newsletter_delivery_queue_pending 0\n"));
+}
+
+#[actix_web::test]
+async fn an_unauthenticated_scrape_is_rejected() {
+    let app = spawn_app_with_metrics().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/metrics", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn a_scrape_with_the_wrong_token_is_rejected() {
+    let app = spawn_app_with_metrics().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/metrics", app.address))
+        .bearer_auth("not-the-scrape-token")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn metrics_are_not_served_unless_enabled() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/metrics", app.address))
+        .bearer_auth(SCRAPE_TOKEN)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn a_repeated_idempotency_key_counts_as_a_replay() {
+    // Arrange
+    let app = spawn_app_with_metrics().await;
+    app.test_user.login(&app).await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    let replays =
+        read_counter(&app, "idempotency_responses_replayed_total").await;
+
+    // Act
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Assert
+    // Other tests share the process-wide counter, so it may have moved on
+    let after =
+        read_counter(&app, "idempotency_responses_replayed_total").await;
+    assert!(after > replays, "{after} replays, was {replays}");
+}
@@ -0,0 +1,78 @@
+use std::borrow::Cow;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use melierx_backend::configuration::{Settings, get_configuration};
+use melierx_backend::email_client::SenderVerification;
+use melierx_backend::migrations::{MIGRATOR, MigrationMode};
+use melierx_backend::startup::Application;
+
+use crate::helpers::create_database;
+
+/// Create a database with every migration but the latest one applied
+async fn database_missing_the_latest_migration(
+    mode: MigrationMode,
+) -> (Settings, PgPool) {
+    let configuration = {
+        let mut c = get_configuration().expect("Failed to read configuration.");
+        c.database.database_name = Uuid::new_v4().to_string();
+        c.database.migrations = mode;
+        c.application.port = 0;
+        c.email_client.sender_verification = SenderVerification::Skip;
+        c
+    };
+    let pool = create_database(&configuration.database).await;
+    let mut migrator = sqlx::migrate!("./migrations");
+    let all_but_latest =
+        migrator.migrations[..migrator.migrations.len() - 1].to_vec();
+    migrator.migrations = Cow::Owned(all_but_latest);
+    migrator.run(&pool).await.unwrap();
+    (configuration, pool)
+}
+
+async fn is_latest_migration_applied(pool: &PgPool) -> bool {
+    let latest = MIGRATOR.iter().last().unwrap().version;
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM _sqlx_migrations WHERE version = $1)",
+    )
+    .bind(latest)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn startup_is_refused_while_migrations_are_pending_in_check_mode() {
+    let (configuration, pool) =
+        database_missing_the_latest_migration(MigrationMode::Check).await;
+
+    let app = Application::build(configuration).await;
+
+    let error = app.err().expect("The application started").to_string();
+    let latest = MIGRATOR.iter().last().unwrap();
+    assert!(error.contains(&latest.version.to_string()), "{error}");
+    assert!(!is_latest_migration_applied(&pool).await);
+}
+
+#[actix_web::test]
+async fn pending_migrations_are_applied_at_startup_in_apply_mode() {
+    let (configuration, pool) =
+        database_missing_the_latest_migration(MigrationMode::Apply).await;
+
+    let app = Application::build(configuration).await;
+
+    assert!(app.is_ok());
+    assert!(is_latest_migration_applied(&pool).await);
+}
+
+#[actix_web::test]
+async fn an_up_to_date_schema_passes_the_check() {
+    let (configuration, pool) =
+        database_missing_the_latest_migration(MigrationMode::Check).await;
+    MIGRATOR.run(&pool).await.unwrap();
+
+    let app = Application::build(configuration).await;
+
+    assert!(app.is_ok());
+}
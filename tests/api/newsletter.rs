@@ -1,19 +1,33 @@
 use std::time::Duration;
 
+use actix_web::rt;
+use chrono::Timelike;
 use fake::Fake;
 use fake::faker::internet::en::SafeEmail;
 use fake::faker::name::en::Name;
+use sqlx::{Connection, Executor};
 use uuid::Uuid;
 use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
 
+use melierx_backend::configuration::{
+    ConcurrencyLimit, EmailStorage, InsecureLinkPolicy, UnsubscribeLink,
+};
+use melierx_backend::idempotency::{FailureMode, IdempotencyKeyFormat};
+use melierx_backend::issue_delivery_worker::try_execute_tasks;
+use melierx_backend::subscriber_token::SubscriberAction;
+use melierx_backend::webhooks::WebhookEvent;
+
 use crate::helpers::assert_is_redirect_to;
-use crate::helpers::{ConfirmationLinks, TestApp, spawn_app};
+use crate::helpers::{
+    ConfirmationLinks, TestApp, batch_accepted, email_accepted, sent_emails,
+    spawn_app, spawn_app_with,
+};
 
 async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
     let name: String = Name().fake();
     let email: String = SafeEmail().fake();
-    let body = serde_urlencoded::to_string(&serde_json::json!({
+    let body = serde_urlencoded::to_string(serde_json::json!({
         "name": name,
         "email": email
     }))
@@ -21,13 +35,13 @@ async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
 
     let _mock_guard = Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(email_accepted())
         .named("Create unconfirmed subscriber.")
         .expect(1)
         .mount_as_scoped(&app.email_server)
         .await;
 
-    app.post_subscriptions(body.into())
+    app.post_subscriptions(body)
         .await
         .error_for_status()
         .unwrap();
@@ -59,7 +73,7 @@ async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     app.test_user.login(&app).await;
 
     Mock::given(any())
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(email_accepted())
         .expect(0)
         .mount(&app.email_server)
         .await;
@@ -90,9 +104,9 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     create_confirmed_subscriber(&app).await;
     app.test_user.login(&app).await;
 
-    Mock::given(path("/email"))
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(batch_accepted())
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -116,6 +130,208 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     app.dispatch_all_pending_emails().await;
 }
 
+#[actix_web::test]
+async fn the_background_worker_delivers_each_email_exactly_once() {
+    // The worker also sends onboarding emails, which would be counted too.
+    let app = spawn_app_with(|c| c.onboarding.steps.clear()).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    app.spawn_delivery_worker();
+
+    let mut queued = 1;
+    for _ in 0..100 {
+        queued = sqlx::query_scalar!(
+            r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#
+        )
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+        if queued == 0 {
+            break;
+        }
+        rt::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(queued, 0);
+    // Give a second delivery the chance to happen before the mock checks
+    // that there was only one.
+    rt::time::sleep(Duration::from_millis(500)).await;
+}
+
+#[actix_web::test]
+async fn newsletters_are_delivered_to_the_decrypted_address_when_hashing_is_enabled()
+ {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.subscriber_emails.storage = EmailStorage::Hashed;
+    })
+    .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=Ursula&email=ursula%40example.com".into())
+        .await;
+    let confirmation_links = app.get_confirmation_links(
+        &app.email_server.received_requests().await.unwrap()[0],
+    );
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(
+        newsletter_recipients(&app, 1).await,
+        vec!["ursula@example.com"]
+    );
+    let delivered_to =
+        sqlx::query_scalar!("SELECT subscriber_email FROM issue_deliveries")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        delivered_to,
+        app.email_vault.lookup_key("ursula@example.com")
+    );
+}
+
+#[actix_web::test]
+async fn the_provider_message_id_of_each_delivery_is_stored() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(
+            serde_json::json!([{
+                "To": "ursula_le_guin@gmail.com",
+                "SubmittedAt": "2026-10-15T09:30:00.1234567-04:00",
+                "MessageID": "0a129aee-e1cd-480d-b08d-4f48548ff48d",
+                "ErrorCode": 0,
+                "Message": "OK"
+            }]),
+        ))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let message_id: String =
+        sqlx::query_scalar("SELECT message_id FROM issue_deliveries")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(message_id, "0a129aee-e1cd-480d-b08d-4f48548ff48d");
+}
+
+#[actix_web::test]
+async fn a_successful_delivery_is_logged_as_one_attempt() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(
+            serde_json::json!([{
+                "To": "ursula_le_guin@gmail.com",
+                "SubmittedAt": "2026-10-15T09:30:00.1234567-04:00",
+                "MessageID": "0a129aee-e1cd-480d-b08d-4f48548ff48d",
+                "ErrorCode": 0,
+                "Message": "OK"
+            }]),
+        ))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let (issue_id, _) = get_issue_status(&app).await;
+    let email = sqlx::query_scalar!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let attempts = sqlx::query!(
+        r#"
+        SELECT issue_id, subscriber_email, attempt, outcome, message_id, error
+        FROM delivery_attempts
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(attempts.len(), 1);
+    let attempt = &attempts[0];
+    assert_eq!(attempt.issue_id, issue_id);
+    assert_eq!(attempt.subscriber_email, email);
+    assert_eq!(attempt.attempt, 1);
+    assert_eq!(attempt.outcome, "delivered");
+    assert_eq!(
+        attempt.message_id.as_deref(),
+        Some("0a129aee-e1cd-480d-b08d-4f48548ff48d")
+    );
+    assert_eq!(attempt.error, None);
+}
+
 #[actix_web::test]
 async fn you_must_be_logged_in_to_see_the_newsletter_form() {
     let app = spawn_app().await;
@@ -136,15 +352,109 @@ async fn you_must_be_logged_in_to_publish_a_newsletter() {
     assert_is_redirect_to(&response, "/login");
 }
 
+#[actix_web::test]
+async fn a_test_email_is_sent_without_publishing_the_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_test_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "recipient": "editor@example.com"
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(
+        html_page.contains("A test email has been sent to editor@example.com.")
+    );
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "editor@example.com");
+    assert_eq!(body["Subject"], "Newsletter title");
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+    let issues = sqlx::query!(r#"SELECT count(*) AS "n!" FROM issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(issues, 0);
+}
+
+#[actix_web::test]
+async fn a_test_email_to_an_invalid_address_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(any())
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_test_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "recipient": "not-an-email"
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("not-an-email"));
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_send_a_test_email() {
+    let app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_test_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "recipient": "editor@example.com"
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
 #[actix_web::test]
 async fn newsletters_creation_is_idempotent() {
     let app = spawn_app().await;
     create_confirmed_subscriber(&app).await;
     app.test_user.login(&app).await;
 
-    Mock::given(path("/email"))
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(batch_accepted())
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -179,17 +489,99 @@ async fn newsletters_creation_is_idempotent() {
     app.dispatch_all_pending_emails().await;
 }
 
+#[actix_web::test]
+async fn idempotency_keys_must_be_uuids_when_so_configured() {
+    let app = spawn_app_with(|c| {
+        c.idempotency.key_format = IdempotencyKeyFormat::Uuid;
+    })
+    .await;
+    app.test_user.login(&app).await;
+    let newsletter_request_body = |idempotency_key: &str| {
+        serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": idempotency_key
+        })
+    };
+
+    let response = app
+        .post_publish_newsletter(&newsletter_request_body("my-key"))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("The idempotency key must be a UUID")
+    );
+    let issues = sqlx::query!(r#"SELECT count(*) AS "n!" FROM issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(issues, 0);
+
+    let response = app
+        .post_publish_newsletter(&newsletter_request_body(
+            &Uuid::new_v4().to_string(),
+        ))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+#[actix_web::test]
+async fn an_idempotency_key_repeated_after_the_replay_window_is_rejected() {
+    let app =
+        spawn_app_with(|c| c.idempotency.replay_window_seconds = 60).await;
+    app.test_user.login(&app).await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    // A repeat within the window still replays the saved response.
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    sqlx::query!(
+        "UPDATE idempotency SET created_at = now() - interval '61 seconds'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("first used too long ago")
+    );
+    let issues = sqlx::query!(r#"SELECT count(*) AS "n!" FROM issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(issues, 1);
+}
+
 #[actix_web::test]
 async fn concurrent_form_submissions_is_handled_gracefully() {
     let app = spawn_app().await;
     create_confirmed_subscriber(&app).await;
     app.test_user.login(&app).await;
 
-    Mock::given(path("/email"))
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
-        .respond_with(
-            ResponseTemplate::new(200).set_delay(Duration::from_secs(2)),
-        )
+        .respond_with(batch_accepted())
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -215,3 +607,2334 @@ async fn concurrent_form_submissions_is_handled_gracefully() {
     );
     app.dispatch_all_pending_emails().await;
 }
+
+#[actix_web::test]
+async fn publishing_returns_503_when_publish_transactions_are_saturated() {
+    let app = spawn_app_with(|c| {
+        c.newsletter.max_concurrent_publish_transactions =
+            ConcurrencyLimit::try_from(1).unwrap();
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Block inserts into `issues` so the first publish holds its permit
+    let mut blocker = app.db_pool.acquire().await.unwrap();
+    let mut blocker = blocker.begin().await.unwrap();
+    blocker
+        .execute("LOCK TABLE issues IN ACCESS EXCLUSIVE MODE")
+        .await
+        .unwrap();
+
+    // Part1 - Start a publish that gets stuck inside its transaction
+    let client = app.api_client.clone();
+    let url = format!("{}/admin/newsletters", &app.address);
+    let stuck_publish = rt::spawn(async move {
+        client
+            .post(url)
+            .form(&serde_json::json!({
+                "title": "Newsletter title",
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+                "idempotency_key": Uuid::new_v4().to_string()
+            }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    });
+    rt::time::sleep(Duration::from_millis(500)).await;
+
+    // Part2 - A second publish is turned away
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Another title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 503);
+
+    // Part3 - Other endpoints keep responding
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Part4 - Releasing the lock lets the first publish complete
+    blocker.rollback().await.unwrap();
+    let response = stuck_publish.await.unwrap();
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+#[actix_web::test]
+async fn republishing_recent_content_requires_confirmation() {
+    let app = spawn_app_with(|c| {
+        c.newsletter.detect_duplicate_content = true;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Both issues go out in one batch
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Part1 - Publish an issue
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.get_publish_newsletter_html().await;
+
+    // Part2 - Publish the same content under a new title
+    let duplicate_request_body = serde_json::json!({
+        "title": "Next week's title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&duplicate_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Part3 - Follow the redirect
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>This content is identical to a recently published issue"
+    ));
+    let n_issues = sqlx::query!(r#"SELECT count(*) AS "n!" FROM issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_issues, 1);
+
+    // Part4 - Confirm and resubmit with the same idempotency key
+    let mut confirmed_request_body = duplicate_request_body.clone();
+    confirmed_request_body["confirm_duplicate"] = "true".into();
+    let response = app.post_publish_newsletter(&confirmed_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - \
+                emails will go out shortly.</i></p>"
+    ));
+    app.dispatch_all_pending_emails().await;
+}
+
+#[actix_web::test]
+async fn scheduling_the_same_content_twice_requires_confirmation() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.newsletter.detect_duplicate_content = true;
+    })
+    .await;
+    app.test_user.login(&app).await;
+    let scheduled_at = chrono::Utc::now() + chrono::Duration::days(1);
+    let schedule = |title: &str| {
+        serde_json::json!({
+            "title": title,
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "scheduled_at": scheduled_at.to_rfc3339(),
+            "idempotency_key": Uuid::new_v4().to_string()
+        })
+    };
+    let response = app
+        .post_publish_newsletter(&schedule("Newsletter title"))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.get_publish_newsletter_html().await;
+
+    // Act - Schedule the same content again before the first one is sent
+    let response = app
+        .post_publish_newsletter(&schedule("Next week's title"))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>This content is identical to a recently published issue"
+    ));
+    let n_issues = sqlx::query!(r#"SELECT count(*) AS "n!" FROM issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_issues, 1);
+}
+
+#[actix_web::test]
+async fn the_recipient_count_is_stored_on_the_issue() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    create_unconfirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Part1 - Submit newsletter issue
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Part2 - Follow the redirect
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>The issue will be delivered to 2 confirmed subscriber(s).</i></p>"
+    ));
+
+    let issue = sqlx::query!("SELECT recipient_count FROM issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(issue.recipient_count, Some(2));
+    app.dispatch_all_pending_emails().await;
+}
+
+fn issue_with_an_http_link() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": r#"<p>Read <a href="http://example.com/post">this</a>
+            or <a href="https://example.com/other">that</a>.</p>"#,
+        "idempotency_key": Uuid::new_v4().to_string()
+    })
+}
+
+#[actix_web::test]
+async fn issues_with_http_links_are_rejected_under_the_reject_policy() {
+    let app = spawn_app_with(|c| {
+        c.newsletter.insecure_links = InsecureLinkPolicy::Reject
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_publish_newsletter(&issue_with_an_http_link())
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "The issue was not published because these links are not https: \
+        http://example.com/post"
+    ));
+    assert!(!html_page.contains("https://example.com/other"));
+    let issues = sqlx::query_scalar!(r#"SELECT count(*) AS "n!" FROM issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issues, 0);
+}
+
+#[actix_web::test]
+async fn issues_with_http_links_are_published_with_a_warning_under_the_warn_policy()
+ {
+    let app = spawn_app_with(|c| {
+        c.newsletter.insecure_links = InsecureLinkPolicy::Warn
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_publish_newsletter(&issue_with_an_http_link())
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(
+        html_page
+            .contains("These links are not https: http://example.com/post")
+    );
+    assert!(html_page.contains("The newsletter issue has been accepted"));
+    app.dispatch_all_pending_emails().await;
+}
+
+#[actix_web::test]
+async fn http_links_are_not_checked_by_default() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_publish_newsletter(&issue_with_an_http_link())
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(!html_page.contains("not https"));
+    assert!(html_page.contains("The newsletter issue has been accepted"));
+}
+
+/// Record past opens by a subscriber at the given hour of day (UTC)
+async fn insert_opens(app: &TestApp, email: &str, hour: u32, count: i32) {
+    for day in 1..=count {
+        sqlx::query!(
+            r#"
+            INSERT INTO email_events
+                (id, subscriber_email, event_type, occurred_at)
+            VALUES (
+                $1,
+                $2,
+                'open',
+                date_trunc('day', now()) - make_interval(days => $3)
+                    + make_interval(hours => $4, mins => 20)
+            )
+            "#,
+            Uuid::new_v4(),
+            email,
+            day,
+            hour as i32
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+}
+
+#[actix_web::test]
+async fn deliveries_are_held_back_until_the_hour_subscribers_usually_open() {
+    let app = spawn_app_with(|c| {
+        c.newsletter.send_time_optimization.enabled = true;
+        c.newsletter.send_time_optimization.max_delay_hours = 12;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let emails = sqlx::query_scalar!("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    let (with_history, without_history) = (&emails[0], &emails[1]);
+    let now = chrono::Utc::now();
+    let preferred_hour = (now.hour() + 3) % 24;
+    insert_opens(&app, with_history, preferred_hour, 3).await;
+    insert_opens(&app, with_history, (now.hour() + 5) % 24, 1).await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let send_at = |email: &str| {
+        let email = email.to_owned();
+        let pool = app.db_pool.clone();
+        async move {
+            sqlx::query_scalar!(
+                "SELECT execute_after FROM issue_delivery_queue
+                WHERE subscriber_email = $1",
+                email
+            )
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        }
+    };
+    let held_back = send_at(with_history).await;
+    assert_eq!(held_back.hour(), preferred_hour);
+    assert_eq!((held_back.minute(), held_back.second()), (0, 0));
+    assert!(held_back > now);
+    assert!(held_back - now <= chrono::Duration::hours(3));
+    assert!(send_at(without_history).await <= chrono::Utc::now());
+}
+
+#[actix_web::test]
+async fn held_back_deliveries_are_dropped_if_the_subscriber_unsubscribes() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.newsletter.send_time_optimization.enabled = true;
+        c.newsletter.send_time_optimization.max_delay_hours = 12;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    let email = sqlx::query_scalar!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    insert_opens(&app, &email, (chrono::Utc::now().hour() + 3) % 24, 3).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .respond_with(batch_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(count_queued_deliveries(&app).await, 1);
+
+    // Act - unsubscribe before the subscriber's usual hour comes round
+    let subscriber_id = sqlx::query_scalar!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    reqwest::get(format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        app.address,
+        app.subscriber_token(subscriber_id, SubscriberAction::Unsubscribe)
+    ))
+    .await
+    .unwrap()
+    .error_for_status()
+    .unwrap();
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - the mock expects no email
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+}
+
+#[actix_web::test]
+async fn deliveries_are_not_held_back_beyond_the_window() {
+    let app = spawn_app_with(|c| {
+        c.newsletter.send_time_optimization.enabled = true;
+        c.newsletter.send_time_optimization.max_delay_hours = 2;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    let email = sqlx::query_scalar!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let hour = (chrono::Utc::now().hour() + 3) % 24;
+    insert_opens(&app, &email, hour, 3).await;
+    app.test_user.login(&app).await;
+
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+
+    let send_at =
+        sqlx::query_scalar!("SELECT execute_after FROM issue_delivery_queue")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert!(send_at <= chrono::Utc::now());
+}
+
+async fn get_issue_status(app: &TestApp) -> (Uuid, String) {
+    let issue = sqlx::query!("SELECT issue_id, status FROM issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    (issue.issue_id, issue.status)
+}
+
+async fn count_queued_deliveries(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT count(*) AS "n!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n
+}
+
+#[actix_web::test]
+async fn a_scheduled_issue_can_be_returned_to_draft() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Part1 - Schedule an issue for tomorrow
+    let scheduled_at = chrono::Utc::now() + chrono::Duration::days(1);
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "scheduled_at": scheduled_at.to_rfc3339(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The newsletter issue has been scheduled for"));
+    let (issue_id, status) = get_issue_status(&app).await;
+    assert_eq!(status, "scheduled");
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+
+    // Part2 - Unschedule it
+    let response = app.post_unschedule_newsletter(issue_id).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been returned to draft.</i></p>"
+    ));
+    let issue =
+        sqlx::query!("SELECT status, scheduled_at, published_at FROM issues")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(issue.status, "draft");
+    assert!(issue.scheduled_at.is_none());
+    assert!(issue.published_at.is_none());
+
+    // Part3 - Drafts are never released
+    assert_eq!(app.release_scheduled_issues().await, 0);
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+}
+
+#[actix_web::test]
+async fn a_sent_issue_cannot_be_unscheduled() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    let (issue_id, _) = get_issue_status(&app).await;
+
+    let response = app.post_unschedule_newsletter(issue_id).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "The newsletter issue is published and can no longer be unscheduled."
+    ));
+    let (_, status) = get_issue_status(&app).await;
+    assert_eq!(status, "published");
+    app.dispatch_all_pending_emails().await;
+}
+
+#[actix_web::test]
+async fn unscheduling_an_unknown_issue_returns_404() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.post_unschedule_newsletter(Uuid::new_v4()).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn scheduled_issues_are_delivered_once_due() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let scheduled_at = chrono::Utc::now() + chrono::Duration::hours(1);
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "scheduled_at": scheduled_at.to_rfc3339(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_eq!(app.release_scheduled_issues().await, 0);
+
+    // Fast-forward past the send time
+    sqlx::query!(
+        "UPDATE issues SET scheduled_at = now() - interval '1 minute'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(app.release_scheduled_issues().await, 1);
+    let (_, status) = get_issue_status(&app).await;
+    assert_eq!(status, "published");
+    app.dispatch_all_pending_emails().await;
+}
+
+#[actix_web::test]
+async fn a_failed_delivery_is_retried_and_counted_once() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    let (issue_id, _) = get_issue_status(&app).await;
+
+    // Act - Part 1 - The first attempt fails and is rescheduled
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(count_queued_deliveries(&app).await, 1);
+
+    // Act - Part 2 - Skip the backoff and try again
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+    let response = app.get_newsletter_delivery_status(issue_id).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let status: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(status["attempts"], 2);
+    assert_eq!(status["retries"], 1);
+    assert_eq!(status["successes"], 1);
+    assert_eq!(status["dead_letters"], 0);
+    assert_eq!(status["pending"], 0);
+}
+
+#[actix_web::test]
+async fn deliveries_are_dead_lettered_once_the_retry_budget_is_spent() {
+    // Arrange
+    let app = spawn_app_with(|c| c.newsletter.max_delivery_retries = 1).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+    let dead_letters = sqlx::query!(
+        r#"SELECT count(*) AS "n!" FROM issue_delivery_dead_letter"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .n;
+    assert_eq!(dead_letters, 1);
+}
+
+#[actix_web::test]
+async fn failed_deliveries_back_off_exponentially_until_dead_lettered() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.newsletter.max_delivery_retries = 3;
+        c.email_client.retry_backoff.base_milliseconds = 60_000;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+
+    // Act & Assert - Each failure is counted and waits twice as long
+    for (n_retries, delay_seconds) in [(1, 60.0), (2, 120.0)] {
+        app.dispatch_all_pending_emails().await;
+        let queued = sqlx::query!(
+            r#"
+            SELECT
+                n_retries,
+                EXTRACT(EPOCH FROM execute_after - now())::float8 AS "delay!"
+            FROM issue_delivery_queue
+            "#
+        )
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+        assert_eq!(queued.n_retries, n_retries);
+        assert!((queued.delay - delay_seconds).abs() < 5.0);
+        sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+            .execute(&app.db_pool)
+            .await
+            .unwrap();
+    }
+
+    // Act & Assert - The last attempt moves the delivery to the dead letters
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+    let dead_letter = sqlx::query!(
+        "SELECT n_retries, last_error FROM issue_delivery_dead_letter"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(dead_letter.n_retries, 3);
+    assert!(!dead_letter.last_error.is_empty());
+}
+
+#[actix_web::test]
+async fn subscribers_with_an_invalid_stored_address_are_flagged_at_send_time() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET email = 'not-an-email'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let status = sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "invalid");
+
+    // The next issue skips the subscriber altogether
+    let newsletter_request_body = serde_json::json!({
+        "title": "Another newsletter title",
+        "text_content": "Another newsletter body as plain text",
+        "html_content": "<p>Another newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+}
+
+#[actix_web::test]
+async fn invalid_stored_addresses_are_left_alone_when_flagging_is_disabled() {
+    // Arrange
+    let app =
+        spawn_app_with(|c| c.newsletter.flag_invalid_subscribers = false).await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET email = 'not-an-email'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(count_dead_letters(&app).await, 1);
+    let status = sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "confirmed");
+}
+
+/// The recipients of the emails sent after the first `skip` ones
+async fn newsletter_recipients(app: &TestApp, skip: usize) -> Vec<String> {
+    sent_emails(&app.email_server).await[skip..]
+        .iter()
+        .map(|email| email["To"].as_str().unwrap().to_owned())
+        .collect()
+}
+
+#[actix_web::test]
+async fn an_interrupted_send_resumes_without_emailing_delivered_recipients() {
+    // Arrange
+    let app = spawn_app().await;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    let confirmations =
+        app.email_server.received_requests().await.unwrap().len();
+
+    // The first delivery goes through, the provider hangs on the second
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .up_to_n_times(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200).set_delay(Duration::from_secs(5)),
+        )
+        .up_to_n_times(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .mount(&app.email_server)
+        .await;
+    let deliver_one = || {
+        try_execute_tasks(
+            &app.db_pool,
+            &app.email_client,
+            &app.subscriber_links,
+            &app.newsletter_settings,
+            &app.webhook_settings,
+            &app.email_vault,
+            1,
+        )
+    };
+    deliver_one().await.unwrap();
+    let delivered = newsletter_recipients(&app, confirmations).await;
+
+    // Act - Part 1 - The worker stops while the second email is in flight
+    let interrupted =
+        rt::time::timeout(Duration::from_millis(500), deliver_one()).await;
+    assert!(interrupted.is_err());
+    assert_eq!(count_queued_deliveries(&app).await, 2);
+
+    // Act - Part 2 - Another worker takes over
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+    let newsletters = newsletter_recipients(&app, confirmations).await;
+    assert_eq!(
+        newsletters.iter().filter(|to| **to == delivered[0]).count(),
+        1,
+        "A recipient was emailed again after their delivery committed"
+    );
+    let mut recipients = newsletters.clone();
+    recipients.sort();
+    recipients.dedup();
+    assert_eq!(recipients.len(), 3, "Not every subscriber got the issue");
+    // At most the email in flight when the worker stopped went out twice
+    assert!(newsletters.len() <= 4);
+    let delivered_count =
+        sqlx::query_scalar!(r#"SELECT delivery_successes AS "n!" FROM issues"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(delivered_count, 3);
+}
+
+async fn count_dead_letters(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT count(*) AS "n!" FROM issue_delivery_dead_letter"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n
+}
+
+/// Publish an issue to `n` confirmed subscribers and dead-letter every
+/// delivery.
+async fn dead_letter_an_issue(app: &TestApp, n: usize) -> Uuid {
+    for _ in 0..n {
+        create_confirmed_subscriber(app).await;
+    }
+    app.test_user.login(app).await;
+    // The deliveries go out, and fail, in a single batch
+    let _guard = Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(count_dead_letters(app).await, n as i64);
+    get_issue_status(app).await.0
+}
+
+#[actix_web::test]
+async fn dead_lettered_deliveries_of_an_issue_can_be_retried() {
+    // Arrange
+    let app = spawn_app_with(|c| c.newsletter.max_delivery_retries = 1).await;
+    let issue_id = dead_letter_an_issue(&app, 3).await;
+
+    // Act
+    let response = app
+        .post_retry_dead_letters(&[("issue_id", &issue_id.to_string())])
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["requeued"], 3);
+    assert_eq!(count_dead_letters(&app).await, 0);
+    assert_eq!(count_queued_deliveries(&app).await, 3);
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+}
+
+#[actix_web::test]
+async fn retried_dead_letters_skip_unsubscribed_subscribers_and_keep_the_correlation_id()
+ {
+    // Arrange
+    let app = spawn_app_with(|c| c.newsletter.max_delivery_retries = 1).await;
+    dead_letter_an_issue(&app, 2).await;
+    sqlx::query!(
+        "UPDATE subscriptions SET status = 'unsubscribed'
+        WHERE email = (SELECT email FROM subscriptions LIMIT 1)"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = app.post_retry_dead_letters(&[]).await;
+
+    // Assert
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["requeued"], 1);
+    assert_eq!(count_dead_letters(&app).await, 1);
+    let requeued = sqlx::query!(
+        "SELECT q.correlation_id, i.correlation_id AS issue_correlation_id
+        FROM issue_delivery_queue q
+        JOIN issues i ON i.issue_id = q.issue_id"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert!(requeued.correlation_id.is_some());
+    assert_eq!(requeued.correlation_id, requeued.issue_correlation_id);
+}
+
+#[actix_web::test]
+async fn only_dead_letters_matching_the_filters_are_retried() {
+    // Arrange
+    let app = spawn_app_with(|c| c.newsletter.max_delivery_retries = 1).await;
+    let issue_id = dead_letter_an_issue(&app, 2).await;
+
+    // Act - Part 1 - Another issue
+    let response = app
+        .post_retry_dead_letters(&[("issue_id", &Uuid::new_v4().to_string())])
+        .await;
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["requeued"], 0);
+
+    // Act - Part 2 - Another error
+    let response = app
+        .post_retry_dead_letters(&[
+            ("issue_id", &issue_id.to_string()),
+            ("error", "timed out"),
+        ])
+        .await;
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["requeued"], 0);
+    assert_eq!(count_dead_letters(&app).await, 2);
+
+    // Act - Part 3 - The error the deliveries failed with
+    let response = app
+        .post_retry_dead_letters(&[("error", "internal server error")])
+        .await;
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["requeued"], 2);
+    assert_eq!(count_dead_letters(&app).await, 0);
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_retry_dead_letters() {
+    let app = spawn_app().await;
+
+    let response = app.post_retry_dead_letters(&[]).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn flushing_an_empty_delivery_queue_reports_it_was_empty() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.post_flush_delivery_queue().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["outcome"], "empty_queue");
+    assert_eq!(body["completed"], 0);
+}
+
+#[actix_web::test]
+async fn a_batch_goes_out_in_one_call_and_each_email_is_settled_on_its_own() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(
+            serde_json::json!([
+                {
+                    "SubmittedAt": "2026-10-15T09:30:00.1234567-04:00",
+                    "MessageID": "0a129aee-e1cd-480d-b08d-4f48548ff48d",
+                    "ErrorCode": 0,
+                    "Message": "OK"
+                },
+                { "ErrorCode": 406, "Message": "Inactive recipient" },
+            ]),
+        ))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    deliver_at_most(&app, 2).await;
+
+    // Assert - the accepted email is done, the rejected one is retried
+    let message_ids =
+        sqlx::query_scalar!("SELECT message_id FROM issue_deliveries")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        message_ids,
+        vec![Some("0a129aee-e1cd-480d-b08d-4f48548ff48d".to_owned())]
+    );
+    let retries: Vec<i16> =
+        sqlx::query_scalar!("SELECT n_retries FROM issue_delivery_queue")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(retries, vec![1]);
+}
+
+#[actix_web::test]
+async fn flushing_the_delivery_queue_delivers_every_due_email() {
+    // Arrange
+    let app = spawn_app().await;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    let response = app.post_flush_delivery_queue().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["outcome"], "tasks_completed");
+    assert_eq!(body["completed"], 3);
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_flush_the_delivery_queue() {
+    let app = spawn_app().await;
+
+    let response = app.post_flush_delivery_queue().await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn delivery_status_of_an_unknown_issue_returns_404() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_newsletter_delivery_status(Uuid::new_v4()).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn deliveries_are_deferred_while_the_email_provider_is_down() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.email_client.circuit_breaker.failure_threshold = 1;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act - the failed batch opens the circuit breaker
+    app.dispatch_all_pending_emails().await;
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - the second pass never reached the provider and spent none of
+    // the retry budget
+    let retries: Vec<i16> = sqlx::query_scalar!(
+        "SELECT n_retries FROM issue_delivery_queue ORDER BY n_retries"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(retries, vec![1, 1]);
+}
+
+/// Run one pass of the delivery worker over at most `max_tasks` deliveries
+async fn deliver_at_most(app: &TestApp, max_tasks: usize) {
+    try_execute_tasks(
+        &app.db_pool,
+        &app.email_client,
+        &app.subscriber_links,
+        &app.newsletter_settings,
+        &app.webhook_settings,
+        &app.email_vault,
+        max_tasks,
+    )
+    .await
+    .unwrap();
+}
+
+/// Report a bounce of a sent email the way the provider does, echoing back
+/// the metadata it was sent with.
+async fn bounce_back(app: &TestApp, email: &serde_json::Value) {
+    let response = app
+        .post_email_event(&serde_json::json!({
+            "RecordType": "Bounce",
+            "ID": Uuid::new_v4().to_string(),
+            "Type": "HardBounce",
+            "Email": email["To"],
+            "Metadata": email["Metadata"],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[actix_web::test]
+async fn a_reported_bounce_is_attributed_to_the_issue_it_was_sent_for() {
+    // Arrange
+    let app = spawn_app().await;
+    let sent = send_issue(
+        &app,
+        serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }),
+    )
+    .await;
+    let (issue_id, _) = get_issue_status(&app).await;
+    assert_eq!(sent["Metadata"]["issue_id"], issue_id.to_string());
+
+    // Act
+    bounce_back(&app, &sent).await;
+
+    // Assert
+    let attributed_to = sqlx::query_scalar!(
+        "SELECT issue_id FROM email_events WHERE event_type = 'bounce'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(attributed_to, Some(issue_id));
+}
+
+#[actix_web::test]
+async fn a_high_bounce_rate_pauses_the_issue() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.newsletter.delivery_alarm.min_deliveries = 1;
+        c.webhooks.target_url = Some("http://127.0.0.1/hooks".into());
+        c.webhooks.events = vec![WebhookEvent::IssueDeliveryAlarm];
+    })
+    .await;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    deliver_at_most(&app, 1).await;
+
+    // Act - the provider reports the first email as bounced
+    let sent = sent_emails(&app.email_server).await.pop().unwrap();
+    bounce_back(&app, &sent).await;
+    deliver_at_most(&app, 1).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - the alarm fired after the next batch and held back the rest
+    let (_, status) = get_issue_status(&app).await;
+    assert_eq!(status, "paused");
+    assert_eq!(count_queued_deliveries(&app).await, 1);
+    let alarms = sqlx::query!(
+        r#"SELECT count(*) AS "n!" FROM webhook_delivery_queue
+        WHERE event_type = 'issue.delivery_alarm'"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .n;
+    assert_eq!(alarms, 1);
+}
+
+#[actix_web::test]
+async fn a_paused_issue_can_be_resumed() {
+    // Arrange
+    let app =
+        spawn_app_with(|c| c.newsletter.delivery_alarm.min_deliveries = 1)
+            .await;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    deliver_at_most(&app, 1).await;
+    let sent = sent_emails(&app.email_server).await.pop().unwrap();
+    bounce_back(&app, &sent).await;
+    deliver_at_most(&app, 1).await;
+    let (issue_id, status) = get_issue_status(&app).await;
+    assert_eq!(status, "paused");
+
+    // Act
+    let response = app.post_resume_newsletter(issue_id).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>Delivery of the newsletter issue has resumed.</i></p>"
+    ));
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - the alarm only fires once, so the rest goes out
+    let (_, status) = get_issue_status(&app).await;
+    assert_eq!(status, "published");
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+}
+
+#[actix_web::test]
+async fn a_low_bounce_rate_does_not_pause_the_issue() {
+    // Arrange
+    let app =
+        spawn_app_with(|c| c.newsletter.delivery_alarm.min_deliveries = 2)
+            .await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let (_, status) = get_issue_status(&app).await;
+    assert_eq!(status, "published");
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+}
+
+async fn break_idempotency_store(app: &TestApp) {
+    sqlx::query!("ALTER TABLE idempotency RENAME TO idempotency_unavailable")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+#[actix_web::test]
+async fn publishing_fails_when_the_idempotency_store_is_down_by_default() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    break_idempotency_store(&app).await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+
+    assert_eq!(response.status().as_u16(), 500);
+}
+
+#[actix_web::test]
+async fn publishing_does_not_fail_open_when_the_idempotency_store_is_broken() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.idempotency.failure_mode = FailureMode::FailOpen;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    // Reachable, so not a reason to drop duplicate protection
+    break_idempotency_store(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 500);
+    app.dispatch_all_pending_emails().await;
+}
+
+#[actix_web::test]
+async fn concurrent_duplicates_are_published_once_when_failing_open() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.idempotency.failure_mode = FailureMode::FailOpen;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+
+    // Act
+    let (response1, response2) = futures::join!(
+        app.post_publish_newsletter(&newsletter_request_body),
+        app.post_publish_newsletter(&newsletter_request_body)
+    );
+
+    // Assert
+    for response in [response1, response2] {
+        let status = response.status().as_u16();
+        assert!(status == 303 || status == 409, "Got {status}");
+    }
+    let issues = sqlx::query!(r#"SELECT count(*) AS "n!" FROM issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(issues, 1);
+    app.dispatch_all_pending_emails().await;
+}
+
+#[actix_web::test]
+async fn unsubscribes_are_attributed_to_the_issue_whose_link_was_used() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+    let (issue_id, _) = get_issue_status(&app).await;
+    let newsletter_email = sent_emails(&app.email_server).await.pop().unwrap();
+    let unsubscribe_link = app
+        .get_links_to(&newsletter_email, "/subscriptions/unsubscribe")
+        .html;
+    assert_eq!(
+        unsubscribe_link
+            .query_pairs()
+            .find(|(k, _)| k == "issue_id"),
+        Some(("issue_id".into(), issue_id.to_string().into()))
+    );
+
+    // Act
+    let response = reqwest::get(unsubscribe_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let event = sqlx::query!("SELECT issue_id, event_type FROM email_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(event.issue_id, Some(issue_id));
+    assert_eq!(event.event_type, "unsubscribe");
+    let subscriber = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(subscriber.status, "unsubscribed");
+
+    let response = app.get_newsletter_unsubscribes().await;
+    let stats: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(stats[0]["issue_id"], issue_id.to_string());
+    assert_eq!(stats[0]["unsubscribes"], 1);
+}
+
+#[actix_web::test]
+async fn unsubscribing_twice_succeeds_without_recording_a_second_opt_out() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+    let newsletter_email = sent_emails(&app.email_server).await.pop().unwrap();
+    let unsubscribe_link = app
+        .get_links_to(&newsletter_email, "/subscriptions/unsubscribe")
+        .html;
+    let response = reqwest::get(unsubscribe_link.clone()).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Act
+    let response = reqwest::get(unsubscribe_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("You have been unsubscribed.")
+    );
+    let events = sqlx::query_scalar!(
+        r#"SELECT count(*) AS "n!" FROM email_events
+        WHERE event_type = 'unsubscribe'"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(events, 1);
+}
+
+#[actix_web::test]
+async fn newsletter_html_is_sanitized_before_it_is_stored() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": concat!(
+            r#"<table style="width:100%"><tbody><tr><td>Hi</td></tr></tbody></table>"#,
+            r#"<img src="https://example.com/a.png" onerror="steal()">"#,
+            "<script>steal()</script>"
+        ),
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let issue = sqlx::query!("SELECT html_content FROM issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        issue.html_content,
+        concat!(
+            r#"<table style="width:100%"><tbody><tr><td>Hi</td></tr></tbody></table>"#,
+            r#"<img src="https://example.com/a.png">"#
+        )
+    );
+}
+
+/// Publish an issue to a single confirmed subscriber and return the body of
+/// the email sent to them
+async fn send_issue(
+    app: &TestApp,
+    newsletter_request_body: serde_json::Value,
+) -> serde_json::Value {
+    create_confirmed_subscriber(app).await;
+    app.test_user.login(app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+    sent_emails(&app.email_server).await.pop().unwrap()
+}
+
+#[actix_web::test]
+async fn the_preheader_is_sent_as_hidden_preview_text() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let body = send_issue(
+        &app,
+        serde_json::json!({
+            "title": "Newsletter title",
+            "preheader": "New this week",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }),
+    )
+    .await;
+
+    // Assert
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert!(
+        html.starts_with(
+            r#"<div style="display:none;max-height:0;overflow:hidden;mso-hide:all">New this week</div><p>"#
+        ),
+        "{html}"
+    );
+    let text = body["TextBody"].as_str().unwrap();
+    assert!(text.starts_with("New this week\n\n"), "{text}");
+}
+
+#[actix_web::test]
+async fn without_a_preheader_the_first_text_line_is_the_preview_text() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let body = send_issue(
+        &app,
+        serde_json::json!({
+            "title": "Newsletter title",
+            "preheader": "",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }),
+    )
+    .await;
+
+    // Assert
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert!(
+        html.starts_with(
+            r#"<div style="display:none;max-height:0;overflow:hidden;mso-hide:all">Newsletter body as plain text</div>"#
+        ),
+        "{html}"
+    );
+    let text = body["TextBody"].as_str().unwrap();
+    assert!(text.starts_with("Newsletter body as plain text"), "{text}");
+}
+
+#[actix_web::test]
+async fn the_confirm_page_unsubscribes_only_once_the_form_is_posted() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.newsletter.unsubscribe_link = UnsubscribeLink::ConfirmPage;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+    let body = sent_emails(&app.email_server).await.pop().unwrap();
+    let one_click = body["Headers"][0]["Value"].as_str().unwrap();
+    assert_eq!(body["Headers"][0]["Name"], "List-Unsubscribe");
+    assert!(one_click.contains("/subscriptions/unsubscribe?"));
+    let confirm_page =
+        app.get_links_to(&body, "/subscriptions/unsubscribe").html;
+    assert_eq!(confirm_page.path(), "/subscriptions/unsubscribe/confirm");
+
+    // Act - Part 1 - Open the confirm page
+    let html_page = reqwest::get(confirm_page.clone())
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains(r#"action="/subscriptions/unsubscribe""#));
+    let status = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .status;
+    assert_eq!(status, "confirmed");
+
+    // Act - Part 2 - Submit the form
+    let form: Vec<(String, String)> = confirm_page
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/unsubscribe", &app.address))
+        .form(&form)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("You have been unsubscribed.")
+    );
+    let status = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .status;
+    assert_eq!(status, "unsubscribed");
+    let event = sqlx::query!("SELECT event_type FROM email_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(event.event_type, "unsubscribe");
+}
+
+#[actix_web::test]
+async fn the_author_is_sent_a_summary_once_an_issue_is_delivered() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.newsletter.max_delivery_retries = 1;
+        c.newsletter.completion_summary.enabled = true;
+        c.newsletter.completion_summary.recipient = "author@example.com".into();
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // One delivery fails for good, the other and the summary go through
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(
+            serde_json::json!([
+                { "ErrorCode": 0, "Message": "OK", "MessageID": "a" },
+                { "ErrorCode": 406, "Message": "Inactive recipient" },
+            ]),
+        ))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let summary_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let summary: serde_json::Value =
+        serde_json::from_slice(&summary_request.body).unwrap();
+    assert_eq!(summary["To"], "author@example.com");
+    assert_eq!(
+        summary["TextBody"],
+        "Your newsletter 'Newsletter title' was sent to 1 subscriber(s), \
+        1 bounced."
+    );
+}
+
+#[actix_web::test]
+async fn bounces_reported_during_delivery_are_counted_in_the_summary() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.newsletter.completion_summary.enabled = true;
+        c.newsletter.completion_summary.recipient = "author@example.com".into();
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    deliver_at_most(&app, 1).await;
+
+    // Act - the provider reports the first email as bounced
+    let sent = sent_emails(&app.email_server).await.pop().unwrap();
+    bounce_back(&app, &sent).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let summary = sent_emails(&app.email_server).await.pop().unwrap();
+    assert_eq!(summary["To"], "author@example.com");
+    assert_eq!(
+        summary["TextBody"],
+        "Your newsletter 'Newsletter title' was sent to 2 subscriber(s), \
+        1 bounced."
+    );
+}
+
+#[actix_web::test]
+async fn no_summary_is_sent_when_it_is_disabled() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - the mocks expect only the newsletter itself
+}
+
+#[actix_web::test]
+async fn invalid_newsletter_titles_are_rejected() {
+    // Arrange
+    let app = spawn_app_with(|c| c.newsletter.max_title_length = 20).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(any())
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let test_cases = vec![
+        ("   ", "The newsletter title cannot be empty."),
+        (
+            "A title that is far too long",
+            "The newsletter title cannot be longer than 20 characters.",
+        ),
+        (
+            "Hi\u{0}there",
+            "The newsletter title cannot contain control characters.",
+        ),
+    ];
+
+    for (title, error_message) in test_cases {
+        // Act
+        let response = app
+            .post_publish_newsletter(&serde_json::json!({
+                "title": title,
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+                "idempotency_key": Uuid::new_v4().to_string()
+            }))
+            .await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        let html_page = app.get_publish_newsletter_html().await;
+        assert!(
+            html_page.contains(&format!("<p><i>{error_message}</i></p>")),
+            "The title {title:?} was not rejected with {error_message:?}"
+        );
+    }
+    let n_issues = sqlx::query!(r#"SELECT count(*) AS "n!" FROM issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_issues, 0);
+}
+
+#[actix_web::test]
+async fn line_breaks_are_stripped_from_the_email_subject() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Hello\r\nBcc: everyone@example.com",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let body = sent_emails(&app.email_server).await.pop().unwrap();
+    assert_eq!(body["Subject"], "Hello Bcc: everyone@example.com");
+}
+
+#[actix_web::test]
+async fn deliveries_carry_the_request_id_of_the_publish_request() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    // Assert
+    let request_id: Uuid = response
+        .headers()
+        .get("X-Request-Id")
+        .expect("The response has no request id")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let issue = sqlx::query!("SELECT correlation_id FROM issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.correlation_id, Some(request_id));
+    let delivery =
+        sqlx::query!("SELECT correlation_id FROM issue_delivery_queue")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(delivery.correlation_id, Some(request_id));
+    app.dispatch_all_pending_emails().await;
+}
+
+#[actix_web::test]
+async fn newsletters_carry_one_click_unsubscribe_headers_but_confirmations_do_not()
+ {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let body = send_issue(
+        &app,
+        serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(body["Headers"][0]["Name"], "List-Unsubscribe");
+    assert_eq!(body["Headers"][1]["Name"], "List-Unsubscribe-Post");
+    assert_eq!(body["Headers"][1]["Value"], "List-Unsubscribe=One-Click");
+    let confirmation_email =
+        &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation: serde_json::Value =
+        serde_json::from_slice(&confirmation_email.body).unwrap();
+    assert!(
+        confirmation["TextBody"]
+            .as_str()
+            .unwrap()
+            .contains("confirm")
+    );
+    assert!(confirmation.get("Headers").is_none());
+}
+
+#[actix_web::test]
+async fn a_one_click_post_with_a_tampered_token_is_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = send_issue(
+        &app,
+        serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }),
+    )
+    .await;
+    let mut link = reqwest::Url::parse(
+        body["Headers"][0]["Value"]
+            .as_str()
+            .unwrap()
+            .trim_matches(['<', '>']),
+    )
+    .unwrap();
+    link.set_port(Some(app.port)).unwrap();
+    let tampered: Vec<(String, String)> = link
+        .query_pairs()
+        .map(|(key, value)| {
+            if key == "token" {
+                (key.into_owned(), format!("{value}x"))
+            } else {
+                (key.into_owned(), value.into_owned())
+            }
+        })
+        .collect();
+    link.query_pairs_mut().clear().extend_pairs(tampered);
+
+    // Act
+    let response = reqwest::Client::new()
+        .post(link)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let status = sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "confirmed");
+}
+
+#[actix_web::test]
+async fn a_confirmation_token_is_not_accepted_as_an_unsubscribe_token() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = sqlx::query_scalar!(
+        "SELECT subscription_token FROM subscription_tokens"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = reqwest::get(format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        app.address, token
+    ))
+    .await
+    .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let status = sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "confirmed");
+}
+
+/// Unsubscribe links sent before they got tokens of their own carry the
+/// confirmation token
+async fn legacy_unsubscribe_link(app: &TestApp, path: &str) -> String {
+    let token = sqlx::query_scalar!(
+        "SELECT subscription_token FROM subscription_tokens"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    format!("{}{}?subscription_token={}", app.address, path, token)
+}
+
+async fn subscriber_status(app: &TestApp) -> String {
+    sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn legacy_unsubscribe_links_still_unsubscribe() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let link =
+        legacy_unsubscribe_link(&app, "/subscriptions/unsubscribe").await;
+
+    // Act
+    let response = reqwest::get(link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "unsubscribed");
+}
+
+#[actix_web::test]
+async fn a_one_click_post_to_a_legacy_unsubscribe_link_unsubscribes() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let link =
+        legacy_unsubscribe_link(&app, "/subscriptions/unsubscribe").await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .post(link)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "unsubscribed");
+}
+
+#[actix_web::test]
+async fn the_confirm_page_of_a_legacy_link_posts_the_legacy_token_back() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let link =
+        legacy_unsubscribe_link(&app, "/subscriptions/unsubscribe/confirm")
+            .await;
+
+    // Act
+    let html_page = reqwest::get(link).await.unwrap().text().await.unwrap();
+
+    // Assert
+    assert!(html_page.contains(r#"name="subscription_token""#));
+    assert_eq!(subscriber_status(&app).await, "confirmed");
+}
+
+#[actix_web::test]
+async fn a_legacy_token_can_only_unsubscribe() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = sqlx::query_scalar!(
+        "SELECT subscription_token FROM subscription_tokens"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let snooze = app.post_snooze(&token, "2999-01-01").await;
+    let preferences =
+        reqwest::get(format!("{}/preferences?token={}", app.address, token))
+            .await
+            .unwrap();
+
+    // Assert
+    assert_eq!(snooze.status().as_u16(), 401);
+    assert_eq!(preferences.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn a_one_click_post_to_the_list_unsubscribe_link_unsubscribes() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = send_issue(
+        &app,
+        serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }),
+    )
+    .await;
+    let mut link = reqwest::Url::parse(
+        body["Headers"][0]["Value"]
+            .as_str()
+            .unwrap()
+            .trim_matches(['<', '>']),
+    )
+    .unwrap();
+    link.set_port(Some(app.port)).unwrap();
+
+    // Act - what a mail client does per RFC 8058
+    let response = reqwest::Client::new()
+        .post(link)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let status = sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "unsubscribed");
+}
@@ -0,0 +1,182 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+use wiremock::Mock;
+use wiremock::matchers::{method, path};
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, email_accepted, spawn_app,
+};
+
+struct QueuedStep {
+    step: String,
+    execute_after: DateTime<Utc>,
+    sent_at: Option<DateTime<Utc>>,
+}
+
+/// Subscribe and confirm, returning the subscriber id.
+async fn confirmed_subscriber(app: &TestApp) -> Uuid {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=Ursula&email=ursula%40example.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    let request = &app.email_server.received_requests().await.unwrap()[0];
+    let link = app.get_confirmation_links(request).html;
+    reqwest::get(link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    sqlx::query_scalar!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+async fn queued_steps(app: &TestApp) -> Vec<QueuedStep> {
+    sqlx::query_as!(
+        QueuedStep,
+        r#"
+        SELECT step, execute_after, sent_at
+        FROM onboarding_queue
+        ORDER BY execute_after
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+async fn sent_subjects(app: &TestApp) -> Vec<String> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| {
+            let body: serde_json::Value =
+                serde_json::from_slice(&r.body).unwrap();
+            body["Subject"].as_str().unwrap().to_owned()
+        })
+        .collect()
+}
+
+#[actix_web::test]
+async fn confirming_schedules_the_full_sequence_at_the_configured_delays() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let before = Utc::now();
+    confirmed_subscriber(&app).await;
+    let after = Utc::now();
+
+    // Assert
+    let queued = queued_steps(&app).await;
+    assert_eq!(queued.len(), app.onboarding_settings.steps.len());
+    for step in &app.onboarding_settings.steps {
+        let queued = queued.iter().find(|q| q.step == step.key).unwrap();
+        let delay = Duration::hours(step.delay_hours.into());
+        assert!(queued.execute_after >= before + delay - Duration::seconds(1));
+        assert!(queued.execute_after <= after + delay + Duration::seconds(1));
+        assert!(queued.sent_at.is_none());
+    }
+}
+
+#[actix_web::test]
+async fn confirming_again_does_not_duplicate_the_sequence() {
+    // Arrange
+    let app = spawn_app().await;
+    confirmed_subscriber(&app).await;
+    let scheduled = queued_steps(&app).await;
+
+    // Act
+    let request = &app.email_server.received_requests().await.unwrap()[0];
+    let link = app.get_confirmation_links(request).html;
+    reqwest::get(link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let queued = queued_steps(&app).await;
+    assert_eq!(queued.len(), scheduled.len());
+    for (queued, scheduled) in queued.iter().zip(&scheduled) {
+        assert_eq!(queued.execute_after, scheduled.execute_after);
+    }
+}
+
+#[actix_web::test]
+async fn only_due_onboarding_emails_are_sent_and_only_once() {
+    // Arrange
+    let app = spawn_app().await;
+    confirmed_subscriber(&app).await;
+
+    // Act
+    app.dispatch_all_due_onboarding_emails().await;
+    app.dispatch_all_due_onboarding_emails().await;
+
+    // Assert - the confirmation email, then the day 0 welcome
+    assert_eq!(
+        sent_subjects(&app).await,
+        ["Welcome!", "Welcome to Melierx, Ursula!"]
+    );
+    let queued = queued_steps(&app).await;
+    assert!(queued[0].sent_at.is_some());
+    assert!(queued[1].sent_at.is_none());
+}
+
+#[actix_web::test]
+async fn an_admin_can_send_the_sequence_again() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = confirmed_subscriber(&app).await;
+    app.dispatch_all_due_onboarding_emails().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_restart_onboarding(subscriber_id).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let queued = queued_steps(&app).await;
+    assert_eq!(queued.len(), app.onboarding_settings.steps.len());
+    assert!(queued.iter().all(|q| q.sent_at.is_none()));
+    app.dispatch_all_due_onboarding_emails().await;
+    let welcomes = sent_subjects(&app)
+        .await
+        .into_iter()
+        .filter(|s| s.starts_with("Welcome to Melierx"))
+        .count();
+    assert_eq!(welcomes, 2);
+}
+
+#[actix_web::test]
+async fn restarting_the_sequence_of_an_unknown_subscriber_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_restart_onboarding(Uuid::new_v4()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_restart_the_sequence() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_restart_onboarding(Uuid::new_v4()).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
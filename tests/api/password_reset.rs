@@ -0,0 +1,264 @@
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, email_accepted, spawn_app, spawn_app_with,
+};
+
+/// Give the test user an email address and request a reset link for it
+async fn request_reset_token(app: &TestApp) -> String {
+    sqlx::query!(
+        "UPDATE users SET email = 'admin@example.com' WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({
+            "username": &app.test_user.username,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let email_request = &app.wait_for_emails(1).await[0];
+    let link = app.get_confirmation_links(email_request).html;
+    assert_eq!(link.path(), "/password-reset");
+    link.query_pairs()
+        .find(|(name, _)| name == "token")
+        .map(|(_, token)| token.into_owned())
+        .unwrap()
+}
+
+async fn reset_to(
+    app: &TestApp,
+    token: &str,
+    new_password: &str,
+) -> reqwest::Response {
+    app.post_password_reset(&serde_json::json!({
+        "token": token,
+        "new_password": new_password,
+        "new_password_check": new_password,
+    }))
+    .await
+}
+
+async fn can_log_in_with(app: &TestApp, password: &str) -> bool {
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": password,
+        }))
+        .await;
+    response.headers().get("Location").unwrap() == "/admin/dashboard"
+}
+
+#[actix_web::test]
+async fn a_reset_link_sets_a_new_password() {
+    let app = spawn_app().await;
+    let new_password = Uuid::new_v4().to_string();
+    let token = request_reset_token(&app).await;
+
+    let response = reset_to(&app, &token, &new_password).await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Your password has been reset."));
+    assert!(can_log_in_with(&app, &new_password).await);
+}
+
+#[actix_web::test]
+async fn a_reset_logs_out_existing_sessions() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = request_reset_token(&app).await;
+
+    reset_to(&app, &token, &Uuid::new_v4().to_string()).await;
+
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn the_reset_link_can_also_be_requested_by_email_address() {
+    let app = spawn_app().await;
+    sqlx::query!(
+        "UPDATE users SET email = 'admin@example.com' WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({
+            "username": "Admin@Example.com",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+    app.wait_for_emails(1).await;
+}
+
+#[actix_web::test]
+async fn an_unknown_account_gets_the_same_answer_and_no_email() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({
+            "username": "nobody",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("a password reset link has been sent to it"));
+}
+
+#[actix_web::test]
+async fn reset_requests_are_throttled_per_address() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.max_failures_per_ip = 2;
+    })
+    .await;
+    for i in 0..2 {
+        let response = app
+            .post_password_reset_request(&serde_json::json!({
+                "username": format!("guess-{i}"),
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/login");
+    }
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({
+            "username": &app.test_user.username,
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/password-reset/request");
+    let html_page = app.get_password_reset_request_html().await;
+    assert!(html_page.contains("Too many password reset requests"));
+}
+
+#[actix_web::test]
+async fn an_expired_reset_link_is_rejected() {
+    let app = spawn_app().await;
+    let token = request_reset_token(&app).await;
+    sqlx::query!(
+        "UPDATE password_reset_tokens \
+        SET expires_at = now() - interval '1 minute'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = reset_to(&app, &token, &Uuid::new_v4().to_string()).await;
+
+    assert_is_redirect_to(&response, "/password-reset/request");
+    assert!(can_log_in_with(&app, &app.test_user.password).await);
+}
+
+#[actix_web::test]
+async fn a_reset_link_that_expired_within_the_clock_skew_is_accepted() {
+    let app = spawn_app_with(|c| c.clock_skew.tolerance_seconds = 60).await;
+    let new_password = Uuid::new_v4().to_string();
+    let token = request_reset_token(&app).await;
+    sqlx::query!(
+        "UPDATE password_reset_tokens \
+        SET expires_at = now() - interval '10 seconds'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = reset_to(&app, &token, &new_password).await;
+
+    assert_is_redirect_to(&response, "/login");
+    assert!(can_log_in_with(&app, &new_password).await);
+}
+
+#[actix_web::test]
+async fn a_reset_link_cannot_be_used_twice() {
+    let app = spawn_app().await;
+    let first_password = Uuid::new_v4().to_string();
+    let second_password = Uuid::new_v4().to_string();
+    let token = request_reset_token(&app).await;
+    let response = reset_to(&app, &token, &first_password).await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = reset_to(&app, &token, &second_password).await;
+
+    assert_is_redirect_to(&response, "/password-reset/request");
+    assert!(!can_log_in_with(&app, &second_password).await);
+    assert!(can_log_in_with(&app, &first_password).await);
+}
+
+#[actix_web::test]
+async fn a_too_short_password_is_rejected_without_using_up_the_link() {
+    let app = spawn_app().await;
+    let token = request_reset_token(&app).await;
+
+    let response = reset_to(&app, &token, "short").await;
+
+    assert_eq!(response.status().as_u16(), 303);
+    let new_password = Uuid::new_v4().to_string();
+    let response = reset_to(&app, &token, &new_password).await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+async fn password_hash(app: &TestApp) -> String {
+    sqlx::query_scalar!(
+        "SELECT password_hash FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn a_reset_that_fails_midway_changes_nothing() {
+    let app = spawn_app().await;
+    let token = request_reset_token(&app).await;
+    let old_hash = password_hash(&app).await;
+    // Break revoking the user's sessions, the step after the new password
+    sqlx::query!("ALTER TABLE user_sessions RENAME TO user_sessions_broken")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = reset_to(&app, &token, &Uuid::new_v4().to_string()).await;
+
+    assert_eq!(response.status().as_u16(), 500);
+    assert_eq!(password_hash(&app).await, old_hash);
+    sqlx::query!("ALTER TABLE user_sessions_broken RENAME TO user_sessions")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let new_password = Uuid::new_v4().to_string();
+    let response = reset_to(&app, &token, &new_password).await;
+    assert_is_redirect_to(&response, "/login");
+    assert!(can_log_in_with(&app, &new_password).await);
+}
@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use wiremock::Mock;
+use wiremock::matchers::{method, path};
+
+use melierx_backend::configuration::{PendingSweepSettings, SchedulerSettings};
+use melierx_backend::pending_sweeper::{
+    run_pending_sweeper, sweep_stale_pending_subscribers,
+};
+use melierx_backend::scheduler::LeaderElection;
+
+use crate::helpers::{TestApp, email_accepted, spawn_app};
+
+fn settings() -> PendingSweepSettings {
+    PendingSweepSettings {
+        ttl_hours: 24,
+        ..PendingSweepSettings::default()
+    }
+}
+
+async fn subscribe(app: &TestApp, name: &str) {
+    app.post_subscriptions(format!("name={name}&email={name}%40example.com"))
+        .await
+        .error_for_status()
+        .unwrap();
+}
+
+async fn age_subscriber(app: &TestApp, name: &str, hours: i32) {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET created_at = created_at - make_interval(hours => $2)
+        WHERE name = $1
+        "#,
+        name,
+        hours
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+async fn remaining_subscribers(app: &TestApp) -> Vec<String> {
+    sqlx::query_scalar!("SELECT name FROM subscriptions ORDER BY name")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn stale_pending_subscribers_are_deleted_with_their_tokens() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    subscribe(&app, "stale").await;
+    subscribe(&app, "recent").await;
+    age_subscriber(&app, "stale", 25).await;
+
+    // Act
+    let deleted = sweep_stale_pending_subscribers(&app.db_pool, &settings())
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(deleted, 1);
+    assert_eq!(remaining_subscribers(&app).await, vec!["recent"]);
+    let tokens = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM subscription_tokens"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(tokens, 1);
+}
+
+#[actix_web::test]
+async fn confirmed_subscribers_are_never_swept() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    subscribe(&app, "confirmed").await;
+    let confirmation_links = app.get_confirmation_links(
+        &app.email_server.received_requests().await.unwrap()[0],
+    );
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    age_subscriber(&app, "confirmed", 25).await;
+
+    // Act
+    let deleted = sweep_stale_pending_subscribers(&app.db_pool, &settings())
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(deleted, 0);
+    assert_eq!(remaining_subscribers(&app).await, vec!["confirmed"]);
+}
+
+#[actix_web::test]
+async fn a_resent_confirmation_restarts_the_ttl() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    subscribe(&app, "resent").await;
+    age_subscriber(&app, "resent", 25).await;
+    app.post_resend_confirmation("resent@example.com").await;
+
+    // Act
+    let deleted = sweep_stale_pending_subscribers(&app.db_pool, &settings())
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(deleted, 0);
+    assert_eq!(remaining_subscribers(&app).await, vec!["resent"]);
+}
+
+#[actix_web::test]
+async fn only_the_elected_instance_sweeps() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    subscribe(&app, "stale").await;
+    age_subscriber(&app, "stale", 25).await;
+    let sweep = PendingSweepSettings {
+        interval_seconds: 1,
+        ..settings()
+    };
+    let scheduler = SchedulerSettings {
+        leader_election: true,
+        lock_key: 7401,
+    };
+    // Another instance leads the sweeps
+    let mut other = LeaderElection::new(
+        app.db_pool.clone(),
+        SchedulerSettings {
+            lock_key: sweep.lock_key,
+            ..scheduler.clone()
+        },
+    );
+    assert!(other.is_leader().await);
+
+    // Act
+    let sweeper =
+        rt::spawn(run_pending_sweeper(app.db_pool.clone(), sweep, scheduler));
+    rt::time::sleep(Duration::from_millis(1500)).await;
+
+    // Assert - nothing is swept until the leader goes away
+    assert_eq!(remaining_subscribers(&app).await, vec!["stale"]);
+    drop(other);
+    let mut remaining = vec!["stale".to_string()];
+    for _ in 0..50 {
+        remaining = remaining_subscribers(&app).await;
+        if remaining.is_empty() {
+            break;
+        }
+        rt::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(remaining.is_empty());
+    sweeper.abort();
+}
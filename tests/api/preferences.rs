@@ -0,0 +1,498 @@
+use uuid::Uuid;
+use wiremock::Mock;
+use wiremock::matchers::{method, path};
+
+use melierx_backend::subscriber_token::SubscriberAction;
+
+use crate::helpers::{
+    TestApp, batch_accepted, email_accepted, sent_emails, spawn_app,
+    spawn_app_with,
+};
+
+/// Subscribe `ursula_le_guin@gmail.com` and return their subscriber ID.
+async fn create_subscriber(app: &TestApp) -> Uuid {
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .named("Create subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(
+        "name=le%20guin&email=ursula_le_guin%40gmail.com".into(),
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+
+    sqlx::query_scalar!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+/// The token the subscriber was sent to confirm their subscription with.
+async fn confirmation_token(app: &TestApp) -> String {
+    sqlx::query_scalar!("SELECT subscription_token FROM subscription_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+async fn confirm_subscriber(app: &TestApp) {
+    reqwest::get(format!(
+        "{}/subscriptions/confirm?subscription_token={}",
+        app.address,
+        confirmation_token(app).await
+    ))
+    .await
+    .unwrap()
+    .error_for_status()
+    .unwrap();
+}
+
+async fn saved_email(app: &TestApp) -> String {
+    sqlx::query_scalar!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn email_is_switched_only_after_the_new_address_is_confirmed() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app).await;
+    let token =
+        app.subscriber_token(subscriber_id, SubscriberAction::ChangeEmail);
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Request the change
+    let response = app.post_email_change(&token, "le_guin@example.com").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Assert - the old address is still the active one
+    assert_eq!(saved_email(&app).await, "ursula_le_guin@gmail.com");
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "le_guin@example.com");
+
+    // Act - Part 2 - Confirm the new address
+    let links = app.get_confirmation_links(&email_request);
+    let response = reqwest::get(links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Assert
+    assert_eq!(saved_email(&app).await, "le_guin@example.com");
+}
+
+#[actix_web::test]
+async fn email_change_confirmation_links_can_only_be_used_once() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app).await;
+    let token =
+        app.subscriber_token(subscriber_id, SubscriberAction::ChangeEmail);
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_email_change(&token, "le_guin@example.com")
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let links = app.get_confirmation_links(&email_request);
+    reqwest::get(links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = reqwest::get(links.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn an_expired_email_change_confirmation_link_is_rejected() {
+    // Arrange
+    let app = spawn_app_with(|c| c.email_change.token_ttl_minutes = 60).await;
+    let subscriber_id = create_subscriber(&app).await;
+    let token =
+        app.subscriber_token(subscriber_id, SubscriberAction::ChangeEmail);
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_email_change(&token, "le_guin@example.com")
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let links = app.get_confirmation_links(&email_request);
+    sqlx::query!(
+        "UPDATE email_change_requests
+        SET requested_at = now() - interval '61 minutes'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = reqwest::get(links.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(saved_email(&app).await, "ursula_le_guin@gmail.com");
+}
+
+#[actix_web::test]
+async fn email_change_with_an_unknown_token_is_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_email_change("notarealtoken", "le_guin@example.com")
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn email_change_to_an_invalid_address_is_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app).await;
+    let token =
+        app.subscriber_token(subscriber_id, SubscriberAction::ChangeEmail);
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_email_change(&token, "not-an-email").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(saved_email(&app).await, "ursula_le_guin@gmail.com");
+}
+
+#[actix_web::test]
+async fn email_change_to_an_address_already_subscribed_is_rejected_with_a_409()
+{
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app).await;
+    let token =
+        app.subscriber_token(subscriber_id, SubscriberAction::ChangeEmail);
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=other&email=taken%40example.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app.post_email_change(&token, "taken@example.com").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+async fn publish_newsletter(app: &TestApp) {
+    app.test_user.login(app).await;
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+}
+
+#[actix_web::test]
+async fn snoozed_subscribers_get_newsletters_only_once_the_snooze_ends() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app).await;
+    confirm_subscriber(&app).await;
+    let token = app.subscriber_token(subscriber_id, SubscriberAction::Snooze);
+    let snooze_until = chrono::Utc::now() + chrono::Duration::days(7);
+
+    // Act - Part 1 - Snooze and publish
+    let response = app.post_snooze(&token, &snooze_until.to_rfc3339()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let delivery_guard = Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(0)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - the delivery is held back until the snooze ends
+    let execute_after =
+        sqlx::query_scalar!("SELECT execute_after FROM issue_delivery_queue")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert!(execute_after >= snooze_until - chrono::Duration::seconds(1));
+    drop(delivery_guard);
+
+    // Act - Part 2 - The snooze runs out
+    sqlx::query!(
+        "UPDATE subscriptions SET snoozed_until = now() - interval '1 second'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let pending = sqlx::query_scalar!(
+        r#"SELECT count(*) AS "n!" FROM issue_delivery_queue"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(pending, 0);
+}
+
+#[actix_web::test]
+async fn subscribers_who_unsubscribe_while_snoozed_get_no_newsletter() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app).await;
+    confirm_subscriber(&app).await;
+    let token = app.subscriber_token(subscriber_id, SubscriberAction::Snooze);
+    let snooze_until = chrono::Utc::now() + chrono::Duration::days(7);
+    let response = app.post_snooze(&token, &snooze_until.to_rfc3339()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act - unsubscribe, then the snooze runs out
+    reqwest::get(format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        app.address,
+        app.subscriber_token(subscriber_id, SubscriberAction::Unsubscribe)
+    ))
+    .await
+    .unwrap()
+    .error_for_status()
+    .unwrap();
+    sqlx::query!(
+        "UPDATE subscriptions SET snoozed_until = now() - interval '1 second'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - the delivery is dropped without an email going out
+    let pending = sqlx::query_scalar!(
+        r#"SELECT count(*) AS "n!" FROM issue_delivery_queue"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(pending, 0);
+}
+
+#[actix_web::test]
+async fn snoozing_into_the_past_is_rejected_with_a_400() {
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app).await;
+    let token = app.subscriber_token(subscriber_id, SubscriberAction::Snooze);
+
+    let response = app.post_snooze(&token, "2000-01-01").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn snoozing_with_an_unknown_token_is_rejected_with_a_401() {
+    let app = spawn_app().await;
+
+    let response = app.post_snooze("notarealtoken", "2999-01-01").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn the_confirmation_token_cannot_snooze_or_change_the_address() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber(&app).await;
+    let token = confirmation_token(&app).await;
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let snooze = app.post_snooze(&token, "2999-01-01").await;
+    let email_change =
+        app.post_email_change(&token, "le_guin@example.com").await;
+
+    // Assert
+    assert_eq!(snooze.status().as_u16(), 401);
+    assert_eq!(email_change.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn a_snooze_token_cannot_change_the_address() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app).await;
+    let token = app.subscriber_token(subscriber_id, SubscriberAction::Snooze);
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_email_change(&token, "le_guin@example.com").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(saved_email(&app).await, "ursula_le_guin@gmail.com");
+}
+
+/// The tokens posted by the preferences page's forms, in page order
+fn form_tokens(html_page: &str) -> Vec<String> {
+    html_page
+        .split(r#"name="token" value=""#)
+        .skip(1)
+        .map(|rest| rest.split('"').next().unwrap().to_owned())
+        .collect()
+}
+
+#[actix_web::test]
+async fn newsletters_link_to_a_page_for_snoozing_and_changing_the_address() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber(&app).await;
+    confirm_subscriber(&app).await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(batch_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+    let newsletter = sent_emails(&app.email_server).await.pop().unwrap();
+    let preferences_link = app.get_links_to(&newsletter, "/preferences").html;
+
+    // Act - Part 1 - Open the page
+    let response = reqwest::get(preferences_link).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(r#"action="/preferences/snooze""#));
+    assert!(html_page.contains(r#"action="/preferences/email""#));
+    let [snooze_token, change_email_token] =
+        <[String; 2]>::try_from(form_tokens(&html_page)).unwrap();
+
+    // Act - Part 2 - Post the forms
+    let snooze = app.post_snooze(&snooze_token, "2999-01-01").await;
+    let email_change = app
+        .post_email_change(&change_email_token, "le_guin@example.com")
+        .await;
+
+    // Assert
+    assert_eq!(snooze.status().as_u16(), 200);
+    assert_eq!(email_change.status().as_u16(), 200);
+    let snoozed_until =
+        sqlx::query_scalar!("SELECT snoozed_until FROM subscriptions")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert!(snoozed_until.is_some());
+}
+
+#[actix_web::test]
+async fn the_preferences_page_rejects_other_tokens_with_a_401() {
+    let app = spawn_app().await;
+    let subscriber_id = create_subscriber(&app).await;
+    let token =
+        app.subscriber_token(subscriber_id, SubscriberAction::Unsubscribe);
+
+    let response =
+        reqwest::get(format!("{}/preferences?token={}", app.address, token))
+            .await
+            .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+}
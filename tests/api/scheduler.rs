@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use actix_web::rt;
+
+use melierx_backend::configuration::SchedulerSettings;
+use melierx_backend::issue_delivery_worker::release_scheduled_issues;
+use melierx_backend::scheduler::LeaderElection;
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, spawn_app};
+
+fn settings(leader_election: bool) -> SchedulerSettings {
+    SchedulerSettings {
+        leader_election,
+        lock_key: 7401,
+    }
+}
+
+/// Run the scheduled jobs the way a worker instance does.
+async fn run_scheduled_jobs(
+    app: &TestApp,
+    election: &mut LeaderElection,
+    runs: &AtomicU64,
+) {
+    if election.is_leader().await {
+        runs.fetch_add(1, Ordering::SeqCst);
+        release_scheduled_issues(
+            &app.db_pool,
+            &app.webhook_settings,
+            &app.newsletter_settings.send_time_optimization,
+        )
+        .await
+        .unwrap();
+    }
+}
+
+async fn create_due_issue(app: &TestApp) {
+    app.test_user.login(app).await;
+    let scheduled_at = chrono::Utc::now() + chrono::Duration::hours(1);
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "scheduled_at": scheduled_at.to_rfc3339(),
+    }))
+    .await;
+    sqlx::query!(
+        "UPDATE issues SET scheduled_at = now() - interval '1 minute'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[actix_web::test]
+async fn only_one_contending_instance_runs_scheduled_jobs() {
+    // Arrange
+    let app = spawn_app().await;
+    create_due_issue(&app).await;
+    let mut first = LeaderElection::new(app.db_pool.clone(), settings(true));
+    let mut second = LeaderElection::new(app.db_pool.clone(), settings(true));
+    let runs = AtomicU64::new(0);
+
+    // Act
+    futures::join!(
+        run_scheduled_jobs(&app, &mut first, &runs),
+        run_scheduled_jobs(&app, &mut second, &runs),
+    );
+    // The leader keeps its leadership on the next tick
+    futures::join!(
+        run_scheduled_jobs(&app, &mut first, &runs),
+        run_scheduled_jobs(&app, &mut second, &runs),
+    );
+
+    // Assert
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    assert_ne!(first.is_leader().await, second.is_leader().await);
+    let status = sqlx::query_scalar!("SELECT status FROM issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "published");
+}
+
+#[actix_web::test]
+async fn another_instance_takes_over_when_the_leader_steps_down() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut first = LeaderElection::new(app.db_pool.clone(), settings(true));
+    let mut second = LeaderElection::new(app.db_pool.clone(), settings(true));
+    assert!(first.is_leader().await);
+    assert!(!second.is_leader().await);
+
+    // Act
+    first.step_down().await;
+
+    // Assert
+    assert!(second.is_leader().await);
+    assert!(!first.is_leader().await);
+}
+
+#[actix_web::test]
+async fn dropping_the_leader_releases_leadership() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut first = LeaderElection::new(app.db_pool.clone(), settings(true));
+    let mut second = LeaderElection::new(app.db_pool.clone(), settings(true));
+    assert!(first.is_leader().await);
+
+    // Act - e.g. the worker task is cancelled on shutdown
+    drop(first);
+
+    // Assert - the server notices the closed session shortly after
+    let mut took_over = false;
+    for _ in 0..50 {
+        if second.is_leader().await {
+            took_over = true;
+            break;
+        }
+        rt::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(took_over);
+}
+
+#[actix_web::test]
+async fn every_instance_runs_scheduled_jobs_without_leader_election() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut first = LeaderElection::new(app.db_pool.clone(), settings(false));
+    let mut second = LeaderElection::new(app.db_pool.clone(), settings(false));
+
+    // Act & Assert
+    assert!(first.is_leader().await);
+    assert!(second.is_leader().await);
+}
@@ -0,0 +1,63 @@
+use secrecy::SecretString;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use melierx_backend::configuration::{Settings, get_configuration};
+use melierx_backend::email_client::SenderVerification;
+use melierx_backend::startup::Application;
+
+use crate::helpers::configure_database;
+
+/// Build the application against a mock provider that reports the
+/// configured sender as confirmed or not.
+async fn build_app(
+    policy: SenderVerification,
+    confirmed: bool,
+) -> Result<Application, anyhow::Error> {
+    let email_server = MockServer::start().await;
+    let configuration: Settings = {
+        let mut c = get_configuration().expect("Failed to read configuration.");
+        c.database.database_name = Uuid::new_v4().to_string();
+        c.application.port = 0;
+        c.email_client.base_url = email_server.uri();
+        c.email_client.sender_verification = policy;
+        c.email_client.account_token = Some(SecretString::from("token"));
+        c
+    };
+    Mock::given(path("/senders"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(
+            serde_json::json!({
+                "TotalCount": 1,
+                "SenderSignatures": [{
+                    "EmailAddress": configuration.email_client.sender_email,
+                    "Confirmed": confirmed,
+                }]
+            }),
+        ))
+        .expect(1)
+        .mount(&email_server)
+        .await;
+    configure_database(&configuration.database).await;
+
+    Application::build(configuration).await
+}
+
+#[actix_web::test]
+async fn startup_succeeds_when_the_sender_is_verified() {
+    let app = build_app(SenderVerification::Fail, true).await;
+    assert!(app.is_ok());
+}
+
+#[actix_web::test]
+async fn startup_fails_for_an_unverified_sender_when_configured_to() {
+    let app = build_app(SenderVerification::Fail, false).await;
+    assert!(app.is_err());
+}
+
+#[actix_web::test]
+async fn an_unverified_sender_only_logs_a_warning_by_default() {
+    let app = build_app(SenderVerification::Warn, false).await;
+    assert!(app.is_ok());
+}
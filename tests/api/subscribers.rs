@@ -0,0 +1,1148 @@
+use melierx_backend::idempotency::FailureMode;
+use melierx_backend::webhooks::WebhookEvent;
+use uuid::Uuid;
+use wiremock::Mock;
+use wiremock::matchers::{method, path};
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, email_accepted, spawn_app, spawn_app_with,
+};
+
+/// Insert a subscriber directly, returning its id
+async fn insert_subscriber(
+    app: &TestApp,
+    email: &str,
+    status: &str,
+    subscribed_at: &str,
+) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO subscriptions (id, email, name, status, subscribed_at)
+        VALUES ($1, $2, 'Name', $3, $4::timestamp)",
+    )
+    .bind(id)
+    .bind(email)
+    .bind(status)
+    .bind(subscribed_at)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO subscription_tokens (subscription_token, subscriber_id)
+        VALUES ($1, $2)",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(id)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    id
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_merge_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_merge_subscribers(&serde_json::json!({
+            "primary_id": Uuid::new_v4().to_string(),
+            "duplicate_id": Uuid::new_v4().to_string(),
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn merging_consolidates_the_duplicate_into_the_primary() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let primary_id = insert_subscriber(
+        &app,
+        "ursula@example.com",
+        "pending_confirmation",
+        "2026-03-01 10:00:00",
+    )
+    .await;
+    let duplicate_id = insert_subscriber(
+        &app,
+        "Ursula@Example.com",
+        "confirmed",
+        "2026-01-01 10:00:00",
+    )
+    .await;
+    let issue_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO issues (issue_id, title, text_content, html_content)
+        VALUES ($1, 'Title', 'Text', '<p>Html</p>')",
+    )
+    .bind(issue_id)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO issue_delivery_queue (issue_id, subscriber_email)
+        VALUES ($1, 'Ursula@Example.com')",
+    )
+    .bind(issue_id)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app
+        .post_merge_subscribers(&serde_json::json!({
+            "primary_id": primary_id.to_string(),
+            "duplicate_id": duplicate_id.to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let subscribers = sqlx::query!(
+        "SELECT id, status, subscribed_at::text AS subscribed_at
+        FROM subscriptions"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(subscribers.len(), 1);
+    assert_eq!(subscribers[0].id, primary_id);
+    assert_eq!(subscribers[0].status, "confirmed");
+    assert_eq!(
+        subscribers[0].subscribed_at.as_deref(),
+        Some("2026-01-01 10:00:00")
+    );
+    let n_tokens = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM subscription_tokens WHERE subscriber_id = $1",
+        primary_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(n_tokens, Some(2));
+    let queued = sqlx::query_scalar!(
+        "SELECT subscriber_email FROM issue_delivery_queue"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(queued, vec!["ursula@example.com".to_string()]);
+}
+
+#[actix_web::test]
+async fn merging_consolidates_the_duplicates_delivery_history() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let primary_id = insert_subscriber(
+        &app,
+        "ursula@example.com",
+        "confirmed",
+        "2026-01-01 10:00:00",
+    )
+    .await;
+    let duplicate_id = insert_subscriber(
+        &app,
+        "Ursula@Example.com",
+        "confirmed",
+        "2026-02-01 10:00:00",
+    )
+    .await;
+    let (shared_issue, other_issue) = (Uuid::new_v4(), Uuid::new_v4());
+    for issue_id in [shared_issue, other_issue] {
+        sqlx::query(
+            "INSERT INTO issues (issue_id, title, text_content, html_content)
+            VALUES ($1, 'Title', 'Text', '<p>Html</p>')",
+        )
+        .bind(issue_id)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+    // Both addresses received the shared issue; only the duplicate the other
+    sqlx::query(
+        "INSERT INTO issue_deliveries (issue_id, subscriber_email, message_id)
+        VALUES
+            ($1, 'ursula@example.com', 'primary-shared'),
+            ($1, 'Ursula@Example.com', 'duplicate-shared'),
+            ($2, 'Ursula@Example.com', 'duplicate-other')",
+    )
+    .bind(shared_issue)
+    .bind(other_issue)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO delivery_attempts (
+            issue_id, subscriber_email, attempt, outcome
+        )
+        VALUES ($1, 'Ursula@Example.com', 1, 'delivered')",
+    )
+    .bind(other_issue)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO email_events (
+            id, issue_id, subscriber_email, event_type
+        )
+        VALUES ($1, $2, 'Ursula@Example.com', 'open')",
+    )
+    .bind(Uuid::new_v4())
+    .bind(other_issue)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    // Both addresses were dead-lettered for the shared issue
+    sqlx::query(
+        "INSERT INTO issue_delivery_dead_letter (
+            issue_id, subscriber_email, n_retries, last_error, failed_at
+        )
+        VALUES
+            ($1, 'ursula@example.com', 1, 'primary-shared', now()),
+            ($1, 'Ursula@Example.com', 1, 'duplicate-shared', now()),
+            ($2, 'Ursula@Example.com', 1, 'duplicate-other', now())",
+    )
+    .bind(shared_issue)
+    .bind(other_issue)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app
+        .post_merge_subscribers(&serde_json::json!({
+            "primary_id": primary_id.to_string(),
+            "duplicate_id": duplicate_id.to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let deliveries = sqlx::query!(
+        "SELECT subscriber_email, message_id
+        FROM issue_deliveries
+        ORDER BY message_id"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    let deliveries: Vec<_> = deliveries
+        .into_iter()
+        .map(|d| (d.subscriber_email, d.message_id.unwrap()))
+        .collect();
+    assert_eq!(
+        deliveries,
+        vec![
+            (
+                "ursula@example.com".to_string(),
+                "duplicate-other".to_string()
+            ),
+            (
+                "ursula@example.com".to_string(),
+                "primary-shared".to_string()
+            ),
+        ]
+    );
+    let attempts =
+        sqlx::query_scalar!("SELECT subscriber_email FROM delivery_attempts")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(attempts, vec!["ursula@example.com".to_string()]);
+    let events =
+        sqlx::query_scalar!("SELECT subscriber_email FROM email_events")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(events, vec!["ursula@example.com".to_string()]);
+    let dead_letters = sqlx::query!(
+        "SELECT subscriber_email, last_error
+        FROM issue_delivery_dead_letter
+        ORDER BY last_error"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    let dead_letters: Vec<_> = dead_letters
+        .into_iter()
+        .map(|d| (d.subscriber_email, d.last_error))
+        .collect();
+    assert_eq!(
+        dead_letters,
+        vec![
+            (
+                "ursula@example.com".to_string(),
+                "duplicate-other".to_string()
+            ),
+            (
+                "ursula@example.com".to_string(),
+                "primary-shared".to_string()
+            ),
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn merging_does_not_requeue_an_issue_the_primary_received() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let primary_id = insert_subscriber(
+        &app,
+        "ursula@example.com",
+        "confirmed",
+        "2026-01-01 10:00:00",
+    )
+    .await;
+    let duplicate_id = insert_subscriber(
+        &app,
+        "Ursula@Example.com",
+        "confirmed",
+        "2026-02-01 10:00:00",
+    )
+    .await;
+    let (received_issue, pending_issue) = (Uuid::new_v4(), Uuid::new_v4());
+    for issue_id in [received_issue, pending_issue] {
+        sqlx::query(
+            "INSERT INTO issues (issue_id, title, text_content, html_content)
+            VALUES ($1, 'Title', 'Text', '<p>Html</p>')",
+        )
+        .bind(issue_id)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+    sqlx::query(
+        "INSERT INTO issue_deliveries (issue_id, subscriber_email, message_id)
+        VALUES ($1, 'ursula@example.com', 'primary-received')",
+    )
+    .bind(received_issue)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    // The duplicate is still queued for both, one of them after retries
+    sqlx::query(
+        "INSERT INTO issue_delivery_queue (
+            issue_id, subscriber_email, n_retries, execute_after
+        )
+        VALUES
+            ($1, 'Ursula@Example.com', 0, now()),
+            ($2, 'Ursula@Example.com', 3, '2030-01-01 00:00:00+00')",
+    )
+    .bind(received_issue)
+    .bind(pending_issue)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app
+        .post_merge_subscribers(&serde_json::json!({
+            "primary_id": primary_id.to_string(),
+            "duplicate_id": duplicate_id.to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let queued = sqlx::query!(
+        "SELECT issue_id, subscriber_email, n_retries,
+            execute_after::text AS execute_after
+        FROM issue_delivery_queue"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].issue_id, pending_issue);
+    assert_eq!(queued[0].subscriber_email, "ursula@example.com");
+    assert_eq!(queued[0].n_retries, 3);
+    assert_eq!(
+        queued[0].execute_after.as_deref(),
+        Some("2030-01-01 00:00:00+00")
+    );
+}
+
+#[actix_web::test]
+async fn merging_keeps_the_latest_snooze_and_engagement() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let primary_id = insert_subscriber(
+        &app,
+        "ursula@example.com",
+        "confirmed",
+        "2026-01-01 10:00:00",
+    )
+    .await;
+    let duplicate_id = insert_subscriber(
+        &app,
+        "Ursula@Example.com",
+        "confirmed",
+        "2026-02-01 10:00:00",
+    )
+    .await;
+    // The primary engaged more recently, the duplicate snoozed for longer
+    sqlx::query(
+        "UPDATE subscriptions
+        SET
+            snoozed_until = '2026-11-01 00:00:00+00',
+            last_engaged_at = '2026-10-01 00:00:00+00'
+        WHERE id = $1",
+    )
+    .bind(primary_id)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "UPDATE subscriptions
+        SET
+            snoozed_until = '2026-12-01 00:00:00+00',
+            last_engaged_at = '2026-09-01 00:00:00+00'
+        WHERE id = $1",
+    )
+    .bind(duplicate_id)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app
+        .post_merge_subscribers(&serde_json::json!({
+            "primary_id": primary_id.to_string(),
+            "duplicate_id": duplicate_id.to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let merged = sqlx::query!(
+        r#"SELECT
+            snoozed_until::text AS "snoozed_until!",
+            last_engaged_at::text AS "last_engaged_at!"
+        FROM subscriptions"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(merged.snoozed_until, "2026-12-01 00:00:00+00");
+    assert_eq!(merged.last_engaged_at, "2026-10-01 00:00:00+00");
+}
+
+#[actix_web::test]
+async fn merging_drops_the_duplicates_pending_email_change() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let primary_id = insert_subscriber(
+        &app,
+        "ursula@example.com",
+        "confirmed",
+        "2026-01-01 10:00:00",
+    )
+    .await;
+    let duplicate_id = insert_subscriber(
+        &app,
+        "Ursula@Example.com",
+        "confirmed",
+        "2026-02-01 10:00:00",
+    )
+    .await;
+    sqlx::query(
+        "INSERT INTO email_change_requests (
+            confirmation_token, subscriber_id, new_email, requested_at
+        )
+        VALUES ('change-token', $1, 'ursula.new@example.com', now())",
+    )
+    .bind(duplicate_id)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app
+        .post_merge_subscribers(&serde_json::json!({
+            "primary_id": primary_id.to_string(),
+            "duplicate_id": duplicate_id.to_string(),
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let n_changes =
+        sqlx::query_scalar!("SELECT COUNT(*) FROM email_change_requests")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(n_changes, Some(0));
+    let emails = sqlx::query_scalar!("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(emails, vec!["ursula@example.com".to_string()]);
+}
+
+#[actix_web::test]
+async fn an_opt_out_or_invalid_address_survives_a_merge() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let cases = [
+        ("confirmed", "unsubscribed", "unsubscribed"),
+        ("unsubscribed", "pending_confirmation", "unsubscribed"),
+        ("pending_confirmation", "invalid", "invalid"),
+        ("invalid", "confirmed", "invalid"),
+    ];
+    for (i, (primary_status, duplicate_status, merged_status)) in
+        cases.into_iter().enumerate()
+    {
+        let primary_id = insert_subscriber(
+            &app,
+            &format!("primary-{i}@example.com"),
+            primary_status,
+            "2026-01-01 10:00:00",
+        )
+        .await;
+        let duplicate_id = insert_subscriber(
+            &app,
+            &format!("duplicate-{i}@example.com"),
+            duplicate_status,
+            "2026-01-01 10:00:00",
+        )
+        .await;
+
+        let response = app
+            .post_merge_subscribers(&serde_json::json!({
+                "primary_id": primary_id.to_string(),
+                "duplicate_id": duplicate_id.to_string(),
+            }))
+            .await;
+
+        assert_is_redirect_to(&response, "/admin/dashboard");
+        let status = sqlx::query_scalar!(
+            "SELECT status FROM subscriptions WHERE id = $1",
+            primary_id
+        )
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            status, merged_status,
+            "{primary_status} merged with {duplicate_status}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn merging_a_subscriber_into_itself_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let id = insert_subscriber(
+        &app,
+        "ursula@example.com",
+        "confirmed",
+        "2026-01-01 10:00:00",
+    )
+    .await;
+
+    let response = app
+        .post_merge_subscribers(&serde_json::json!({
+            "primary_id": id.to_string(),
+            "duplicate_id": id.to_string(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn merging_an_unknown_subscriber_returns_404() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let id = insert_subscriber(
+        &app,
+        "ursula@example.com",
+        "confirmed",
+        "2026-01-01 10:00:00",
+    )
+    .await;
+
+    let response = app
+        .post_merge_subscribers(&serde_json::json!({
+            "primary_id": id.to_string(),
+            "duplicate_id": Uuid::new_v4().to_string(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+/// Insert three subscribers whose email, status and subscription date
+/// each sort in a different order
+async fn insert_listing_fixture(app: &TestApp) -> [Uuid; 3] {
+    let b = insert_subscriber(
+        app,
+        "b@example.com",
+        "pending_confirmation",
+        "2026-01-01 10:00:00",
+    )
+    .await;
+    let c = insert_subscriber(
+        app,
+        "c@example.com",
+        "confirmed",
+        "2026-02-01 10:00:00",
+    )
+    .await;
+    let a = insert_subscriber(
+        app,
+        "a@example.com",
+        "unsubscribed",
+        "2026-03-01 10:00:00",
+    )
+    .await;
+    [a, b, c]
+}
+
+async fn listed_emails(app: &TestApp, query: &str) -> Vec<String> {
+    let response = app.get_subscribers(query).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let page: serde_json::Value = response.json().await.unwrap();
+    page["subscribers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["email"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_list_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app.get_subscribers("").await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn subscribers_can_be_sorted_by_subscription_date() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    insert_listing_fixture(&app).await;
+
+    assert_eq!(
+        listed_emails(&app, "sort=subscribed_at&order=asc").await,
+        ["b@example.com", "c@example.com", "a@example.com"]
+    );
+    assert_eq!(
+        listed_emails(&app, "sort=subscribed_at&order=desc").await,
+        ["a@example.com", "c@example.com", "b@example.com"]
+    );
+}
+
+#[actix_web::test]
+async fn subscribers_can_be_sorted_by_email() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    insert_listing_fixture(&app).await;
+
+    assert_eq!(
+        listed_emails(&app, "sort=email&order=asc").await,
+        ["a@example.com", "b@example.com", "c@example.com"]
+    );
+    assert_eq!(
+        listed_emails(&app, "sort=email&order=desc").await,
+        ["c@example.com", "b@example.com", "a@example.com"]
+    );
+}
+
+#[actix_web::test]
+async fn subscribers_can_be_sorted_by_status() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    insert_listing_fixture(&app).await;
+
+    assert_eq!(
+        listed_emails(&app, "sort=status&order=asc").await,
+        ["c@example.com", "b@example.com", "a@example.com"]
+    );
+    assert_eq!(
+        listed_emails(&app, "sort=status&order=desc").await,
+        ["a@example.com", "b@example.com", "c@example.com"]
+    );
+}
+
+#[actix_web::test]
+async fn the_configured_default_sort_is_used_when_none_is_given() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    insert_listing_fixture(&app).await;
+
+    // The base configuration lists the newest subscribers first
+    assert_eq!(
+        listed_emails(&app, "").await,
+        ["a@example.com", "c@example.com", "b@example.com"]
+    );
+}
+
+#[actix_web::test]
+async fn an_invalid_sort_field_is_rejected_with_a_400() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    for query in ["sort=name", "order=sideways"] {
+        let response = app.get_subscribers(query).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The listing did not reject `{}`.",
+            query
+        );
+    }
+}
+
+#[actix_web::test]
+async fn paging_with_ties_neither_skips_nor_repeats_subscribers() {
+    let app = spawn_app_with(|c| c.subscriber_listing.page_size = 2).await;
+    app.test_user.login(&app).await;
+    let mut expected = Vec::new();
+    for i in 0..5 {
+        // Every subscriber shares the same status, so only the id breaks ties
+        expected.push(
+            insert_subscriber(
+                &app,
+                &format!("{i}@example.com"),
+                "confirmed",
+                "2026-01-01 10:00:00",
+            )
+            .await,
+        );
+    }
+    expected.sort();
+
+    let mut seen = Vec::new();
+    let mut query = "sort=status&order=asc".to_string();
+    loop {
+        let page: serde_json::Value =
+            app.get_subscribers(&query).await.json().await.unwrap();
+        for subscriber in page["subscribers"].as_array().unwrap() {
+            seen.push(
+                subscriber["id"].as_str().unwrap().parse::<Uuid>().unwrap(),
+            );
+        }
+        match page["next_cursor"].as_str() {
+            Some(cursor) => {
+                query = format!("sort=status&order=asc&after={cursor}")
+            }
+            None => break,
+        }
+    }
+    assert_eq!(seen, expected);
+}
+
+/// Insert subscribers with a spread of engagement, returning the emails of
+/// those inactive for 180 days, longest inactive first
+async fn insert_engagement_fixture(app: &TestApp) -> Vec<String> {
+    let long_ago = "2020-01-01 10:00:00";
+    let just_now = chrono::Utc::now().naive_utc().to_string();
+    let fixture = [
+        ("engaged@example.com", "confirmed", long_ago, Some(10)),
+        ("lapsed@example.com", "confirmed", long_ago, Some(200)),
+        ("silent@example.com", "confirmed", long_ago, None),
+        ("newcomer@example.com", "confirmed", just_now.as_str(), None),
+        ("gone@example.com", "unsubscribed", long_ago, None),
+    ];
+    for (email, status, subscribed_at, engaged_days_ago) in fixture {
+        let id = insert_subscriber(app, email, status, subscribed_at).await;
+        sqlx::query(
+            "UPDATE subscriptions
+            SET last_engaged_at = now() - make_interval(days => $2)
+            WHERE id = $1 AND $2 IS NOT NULL",
+        )
+        .bind(id)
+        .bind(engaged_days_ago)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+    vec!["silent@example.com".into(), "lapsed@example.com".into()]
+}
+
+async fn inactive_emails(app: &TestApp, query: &str) -> Vec<String> {
+    let response = app.get_inactive_subscribers(query).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    body["subscribers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["email"].as_str().unwrap().to_owned())
+        .collect()
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_list_inactive_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app.get_inactive_subscribers("").await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn subscribers_without_engagement_in_the_window_are_inactive() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let inactive = insert_engagement_fixture(&app).await;
+
+    assert_eq!(inactive_emails(&app, "").await, inactive);
+}
+
+#[actix_web::test]
+async fn the_inactivity_window_can_be_narrowed() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    insert_engagement_fixture(&app).await;
+
+    let emails = inactive_emails(&app, "days=5").await;
+
+    assert_eq!(
+        emails,
+        [
+            "silent@example.com",
+            "lapsed@example.com",
+            "engaged@example.com"
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn inactive_subscribers_can_be_suppressed() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let inactive = insert_engagement_fixture(&app).await;
+
+    let response = app.post_suppress_inactive_subscribers("").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["suppressed"], 2);
+    let unsubscribed: Vec<String> = sqlx::query_scalar(
+        "SELECT email FROM subscriptions
+        WHERE status = 'unsubscribed' AND email <> 'gone@example.com'
+        ORDER BY email DESC",
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(unsubscribed, inactive);
+    assert!(inactive_emails(&app, "").await.is_empty());
+}
+
+#[actix_web::test]
+async fn suppressing_records_an_unsubscribe_and_a_webhook_per_subscriber() {
+    let app = spawn_app_with(|c| {
+        c.webhooks.target_url = Some("http://127.0.0.1:1/hooks".into());
+        c.webhooks.events = vec![WebhookEvent::SubscriberUnsubscribed];
+    })
+    .await;
+    app.test_user.login(&app).await;
+    let mut inactive = insert_engagement_fixture(&app).await;
+
+    let response = app.post_suppress_inactive_subscribers("").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let mut unsubscribes: Vec<String> = sqlx::query_scalar!(
+        "SELECT subscriber_email FROM email_events
+        WHERE event_type = 'unsubscribe'"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    let mut webhooks: Vec<String> = sqlx::query_scalar!(
+        r#"SELECT payload -> 'data' ->> 'email' AS "email!"
+        FROM webhook_delivery_queue
+        WHERE event_type = 'subscriber.unsubscribed'"#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    inactive.sort();
+    unsubscribes.sort();
+    webhooks.sort();
+    assert_eq!(unsubscribes, inactive);
+    assert_eq!(webhooks, inactive);
+}
+
+#[actix_web::test]
+async fn an_inactivity_window_under_a_day_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    insert_engagement_fixture(&app).await;
+
+    let listed = app.get_inactive_subscribers("days=0").await;
+    let suppressed = app.post_suppress_inactive_subscribers("days=0").await;
+
+    assert_eq!(listed.status().as_u16(), 400);
+    assert_eq!(suppressed.status().as_u16(), 400);
+    let confirmed: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM subscriptions WHERE status = 'confirmed'",
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(confirmed, 4);
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_export_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app.get_subscriber_export().await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn every_subscriber_is_exported_in_subscription_order() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    insert_listing_fixture(&app).await;
+
+    let response = app.get_subscriber_export().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let export: serde_json::Value = response.json().await.unwrap();
+    let emails: Vec<&str> = export["subscribers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["email"].as_str().unwrap())
+        .collect();
+    assert_eq!(emails, ["b@example.com", "c@example.com", "a@example.com"]);
+}
+
+#[actix_web::test]
+async fn an_export_over_the_row_cap_is_refused_with_a_413() {
+    let app =
+        spawn_app_with(|c| c.subscriber_listing.export_max_rows = 2).await;
+    app.test_user.login(&app).await;
+    insert_listing_fixture(&app).await;
+
+    let response = app.get_subscriber_export().await;
+
+    assert_eq!(response.status().as_u16(), 413);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("limited to 2 subscribers"));
+}
+
+async fn get_subscribers_accepting_gzip(app: &TestApp) -> reqwest::Response {
+    app.api_client
+        .get(format!("{}/admin/subscribers", &app.address))
+        .header("Accept-Encoding", "gzip")
+        .send()
+        .await
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn large_listings_are_gzipped_when_the_client_accepts_it() {
+    // Arrange
+    let app = spawn_app().await;
+    for i in 0..50 {
+        let email = format!("subscriber{i}@example.com");
+        insert_subscriber(&app, &email, "confirmed", "2026-01-01 00:00:00")
+            .await;
+    }
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = get_subscribers_accepting_gzip(&app).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Encoding"], "gzip");
+}
+
+#[actix_web::test]
+async fn content_types_not_configured_for_compression_are_sent_as_is() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.compression.content_types = vec!["text/html".into()];
+    })
+    .await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = get_subscribers_accepting_gzip(&app).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Encoding"], "identity");
+    let _: serde_json::Value = response.json().await.unwrap();
+}
+
+#[actix_web::test]
+async fn responses_are_not_compressed_when_compression_is_disabled() {
+    // Arrange
+    let app =
+        spawn_app_with(|c| c.application.compression.enabled = false).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = get_subscribers_accepting_gzip(&app).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers().get("Content-Encoding").is_none());
+}
+
+#[actix_web::test]
+async fn you_must_be_logged_in_to_add_a_subscriber() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_add_subscriber(&serde_json::json!({
+            "email": "ursula_le_guin@gmail.com",
+            "name": "Ursula",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn an_imported_signup_date_is_stored_verbatim() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_add_subscriber(&serde_json::json!({
+            "email": "ursula_le_guin@gmail.com",
+            "name": "Ursula",
+            "subscribed_at": "2019-03-14T15:09:26Z",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+    let subscribed_at: chrono::NaiveDateTime = sqlx::query_scalar(
+        "SELECT subscribed_at FROM subscriptions WHERE email = $1",
+    )
+    .bind("ursula_le_guin@gmail.com")
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(subscribed_at.to_string(), "2019-03-14 15:09:26");
+}
+
+#[actix_web::test]
+async fn a_signup_date_in_the_future_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let tomorrow = chrono::Utc::now() + chrono::Duration::days(1);
+
+    let response = app
+        .post_add_subscriber(&serde_json::json!({
+            "email": "ursula_le_guin@gmail.com",
+            "name": "Ursula",
+            "subscribed_at": tomorrow,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[actix_web::test]
+async fn retrying_an_add_with_the_same_idempotency_key_replays_the_response() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let body = serde_json::json!({
+        "email": "ursula_le_guin@gmail.com",
+        "name": "Ursula",
+        "idempotency_key": "import-2026-10-15",
+    });
+
+    let first = app.post_add_subscriber(&body).await;
+    let second = app.post_add_subscriber(&body).await;
+
+    assert_eq!(first.status().as_u16(), 201);
+    assert_eq!(second.status().as_u16(), 201);
+    let first: serde_json::Value = first.json().await.unwrap();
+    let second: serde_json::Value = second.json().await.unwrap();
+    assert_eq!(first["id"], second["id"]);
+}
+
+#[actix_web::test]
+async fn concurrent_duplicate_adds_register_once_when_failing_open() {
+    let app = spawn_app_with(|c| {
+        c.idempotency.failure_mode = FailureMode::FailOpen;
+    })
+    .await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let body = serde_json::json!({
+        "email": "ursula_le_guin@gmail.com",
+        "name": "Ursula",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+
+    let (first, second) = futures::join!(
+        app.post_add_subscriber(&body),
+        app.post_add_subscriber(&body)
+    );
+
+    for response in [first, second] {
+        let status = response.status().as_u16();
+        assert!(status == 201 || status == 409, "Got {status}");
+    }
+}
+
+#[actix_web::test]
+async fn the_same_idempotency_key_on_two_endpoints_does_not_collide() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    let idempotency_key = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": idempotency_key,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let response = app
+        .post_add_subscriber(&serde_json::json!({
+            "email": "ursula_le_guin@gmail.com",
+            "name": "Ursula",
+            "idempotency_key": idempotency_key,
+        }))
+        .await;
+
+    // Not the publish endpoint's saved redirect
+    assert_eq!(response.status().as_u16(), 201);
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM subscriptions WHERE email = $1",
+    )
+    .bind("ursula_le_guin@gmail.com")
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(count, 1);
+}
@@ -1,7 +1,13 @@
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+use wiremock::Mock;
 use wiremock::matchers::{method, path};
-use wiremock::{Mock, ResponseTemplate};
 
-use crate::helpers::spawn_app;
+use melierx_backend::configuration::ConcurrencyLimit;
+use melierx_backend::routes::CountedSubscribers;
+
+use crate::helpers::{email_accepted, spawn_app, spawn_app_with};
 
 #[actix_web::test]
 async fn subscribe_returns_a_200_for_valid_form_data() {
@@ -10,7 +16,7 @@ async fn subscribe_returns_a_200_for_valid_form_data() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(email_accepted())
         .mount(&app.email_server)
         .await;
 
@@ -58,12 +64,36 @@ async fn subscribe_sends_a_confirmation_email_for_valid_data() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into()).await;
+}
+
+#[actix_web::test]
+async fn the_configured_subject_prefix_is_prepended_to_sent_emails() {
+    let app = spawn_app_with(|c| {
+        c.email_client.subject_prefix = Some("[STAGING]".into());
+    })
+    .await;
+    let body = "name=FirstName%20LastName&email=mynickname%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
         .expect(1)
         .mount(&app.email_server)
         .await;
 
     app.post_subscriptions(body.into()).await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let email: serde_json::Value =
+        serde_json::from_slice(&email_request.body).unwrap();
+    let subject = email["Subject"].as_str().unwrap();
+    assert!(subject.starts_with("[STAGING] "), "{subject}");
 }
 
 #[actix_web::test]
@@ -73,7 +103,7 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(email_accepted())
         .mount(&app.email_server)
         .await;
 
@@ -127,3 +157,373 @@ async fn subscribe_returns_a_400_when_fields_are_present_but_invalid() {
         );
     }
 }
+
+#[actix_web::test]
+async fn rapid_signups_from_one_ip_are_throttled() {
+    let app =
+        spawn_app_with(|c| c.signup_velocity.max_signups_per_ip = 2).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+
+    for (i, email) in ["ursula", "le.guin", "octavia"].iter().enumerate() {
+        let body = format!("name=Name&email={email}%40gmail.com");
+        let response = app.post_subscriptions(body).await;
+        let expected = if i < 2 { 200 } else { 429 };
+        assert_eq!(expected, response.status().as_u16());
+    }
+}
+
+#[actix_web::test]
+async fn bursts_of_sequential_emails_are_throttled() {
+    let app = spawn_app_with(|c| {
+        c.signup_velocity.max_signups_per_ip = 100;
+        c.signup_velocity.max_signups_per_email_pattern = 2;
+    })
+    .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+
+    for i in 1..=3 {
+        let body = format!("name=Bot&email=bot{i}%40gmail.com");
+        let response = app.post_subscriptions(body).await;
+        let expected = if i <= 2 { 200 } else { 429 };
+        assert_eq!(expected, response.status().as_u16());
+    }
+    // Unrelated addresses from the same IP still get through
+    let response = app
+        .post_subscriptions("name=Ursula&email=ursula%40gmail.com".into())
+        .await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn subscribe_rejects_non_utf8_charsets_with_a_415() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", &app.address))
+        .header(
+            "Content-Type",
+            "application/x-www-form-urlencoded; charset=ISO-8859-1",
+        )
+        .body("name=Jos%E9&email=jose%40gmail.com")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(415, response.status().as_u16());
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(saved.is_none());
+}
+
+#[actix_web::test]
+async fn subscribe_rejects_form_data_that_is_not_valid_utf8() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriptions("name=Jos%E9&email=jose%40gmail.com".into())
+        .await;
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn subscribe_accepts_an_explicit_utf8_charset() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", &app.address))
+        .header(
+            "Content-Type",
+            "application/x-www-form-urlencoded; charset=UTF-8",
+        )
+        .body("name=Jos%C3%A9&email=jose%40gmail.com")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    let saved = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "José");
+}
+
+#[actix_web::test]
+async fn only_confirmed_subscribers_count_toward_the_cap_by_default() {
+    // Arrange
+    let app =
+        spawn_app_with(|c| c.subscriber_limit.max_subscribers = Some(1)).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Pending signups are not capped
+    for email in ["ursula", "octavia"] {
+        let body = format!("name=Name&email={email}%40gmail.com");
+        let response = app.post_subscriptions(body).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    // Act - Part 2 - Only the first confirmation fits under the cap
+    let requests = app.email_server.received_requests().await.unwrap();
+    let mut statuses = Vec::new();
+    for request in &requests {
+        let link = app.get_confirmation_links(request).html;
+        statuses.push(reqwest::get(link).await.unwrap().status().as_u16());
+    }
+
+    // Assert
+    assert_eq!(statuses, [200, 403]);
+}
+
+#[actix_web::test]
+async fn pending_subscribers_count_toward_the_cap_when_configured() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.subscriber_limit.max_subscribers = Some(1);
+        c.subscriber_limit.counted = CountedSubscribers::ConfirmedAndPending;
+    })
+    .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let first = app
+        .post_subscriptions("name=Name&email=ursula%40gmail.com".into())
+        .await;
+    let second = app
+        .post_subscriptions("name=Name&email=octavia%40gmail.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(200, first.status().as_u16());
+    assert_eq!(403, second.status().as_u16());
+    // The pending subscriber can still confirm
+    let request = &app.email_server.received_requests().await.unwrap()[0];
+    let link = app.get_confirmation_links(request).html;
+    assert_eq!(200, reqwest::get(link).await.unwrap().status().as_u16());
+}
+
+#[actix_web::test]
+async fn subscribe_returns_a_403_when_public_signup_is_disabled() {
+    // Arrange
+    let app =
+        spawn_app_with(|c| c.application.public_signup_enabled = false).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=Ursula&email=ursula%40gmail.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(403, response.status().as_u16());
+    assert!(response.text().await.unwrap().contains("invite-only"));
+    let subscribers = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(subscribers.is_empty());
+}
+
+#[actix_web::test]
+async fn confirmation_emails_are_sent_a_bounded_number_at_a_time() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.email_client.max_concurrent_confirmation_sends =
+            ConcurrencyLimit::try_from(2).unwrap();
+        c.signup_velocity.max_signups_per_email_pattern = 100;
+    })
+    .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted().set_delay(Duration::from_millis(500)))
+        .expect(6)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let started = Instant::now();
+    let responses = join_all((0..6).map(|i| {
+        app.post_subscriptions(format!(
+            "name=Signup%20{i}&email=signup{i}%40example.com"
+        ))
+    }))
+    .await;
+
+    // Assert
+    for response in responses {
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    // Two at a time, six sends take at least three provider round trips.
+    assert!(started.elapsed() >= Duration::from_millis(1500));
+}
+
+#[actix_web::test]
+async fn resending_the_confirmation_reuses_the_pending_subscribers_link() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=Ursula&email=ursula%40example.com".into())
+        .await;
+
+    let response = app.post_resend_confirmation("ursula@example.com").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let requests = app.email_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        app.get_confirmation_links(&requests[1]).html,
+        app.get_confirmation_links(&requests[0]).html
+    );
+}
+
+#[actix_web::test]
+async fn confirmed_subscribers_are_not_sent_the_confirmation_again() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=Ursula&email=ursula%40example.com".into())
+        .await;
+    let confirmation_links = app.get_confirmation_links(
+        &app.email_server.received_requests().await.unwrap()[0],
+    );
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let response = app.post_resend_confirmation("ursula@example.com").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.email_server.received_requests().await.unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn resending_to_an_unknown_address_succeeds_without_sending() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_resend_confirmation("ursula@example.com").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[actix_web::test]
+async fn confirmations_are_resent_at_most_once_per_cooldown() {
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=Ursula&email=ursula%40example.com".into())
+        .await;
+
+    for _ in 0..3 {
+        let response = app.post_resend_confirmation("ursula@example.com").await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    assert_eq!(app.email_server.received_requests().await.unwrap().len(), 2);
+}
+
+#[actix_web::test]
+async fn a_filled_in_honeypot_is_answered_but_nothing_is_stored_or_sent() {
+    let app = spawn_app_with(|c| {
+        c.honeypot.enabled = true;
+        c.honeypot.field_name = "website".into();
+    })
+    .await;
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com\
+        &website=http%3A%2F%2Fspam.example.com";
+
+    let response = app.post_subscriptions(body.into()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(saved.is_none());
+}
+
+#[actix_web::test]
+async fn an_empty_honeypot_subscribes_as_usual() {
+    let app = spawn_app_with(|c| {
+        c.honeypot.enabled = true;
+        c.honeypot.field_name = "website".into();
+    })
+    .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&website=";
+
+    let response = app.post_subscriptions(body.into()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+}
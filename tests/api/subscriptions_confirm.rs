@@ -1,8 +1,10 @@
 use reqwest::get;
+use wiremock::Mock;
 use wiremock::matchers::{method, path};
-use wiremock::{Mock, ResponseTemplate};
 
-use crate::helpers::spawn_app;
+use melierx_backend::configuration::EmailStorage;
+
+use crate::helpers::{email_accepted, spawn_app, spawn_app_with};
 
 #[actix_web::test]
 async fn confirm_without_token_are_rejected_with_a_400() {
@@ -20,7 +22,7 @@ async fn the_link_returned_by_subscribe_returns_a_200_if_called() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(email_accepted())
         .mount(&app.email_server)
         .await;
 
@@ -40,7 +42,7 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(email_accepted())
         .mount(&app.email_server)
         .await;
 
@@ -63,3 +65,106 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     assert_eq!(saved.name, "FirstName LastName");
     assert_eq!(saved.status, "confirmed");
 }
+
+#[actix_web::test]
+async fn subscribers_are_stored_hashed_and_confirmed_when_hashing_is_enabled() {
+    let app = spawn_app_with(|c| {
+        c.subscriber_emails.storage = EmailStorage::Hashed;
+    })
+    .await;
+    let body = "name=FirstName%20LastName&email=mynickname%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let sent_to: serde_json::Value =
+        serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(sent_to["To"], "mynickname@gmail.com");
+    let confirmation_links = app.get_confirmation_links(email_request);
+
+    get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let saved = sqlx::query!(
+        "SELECT email, encrypted_email, status FROM subscriptions",
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved subscription.");
+
+    assert!(!saved.email.contains("mynickname"));
+    assert_eq!(
+        saved.email,
+        app.email_vault.lookup_key("mynickname@gmail.com")
+    );
+    let encrypted_email = saved.encrypted_email.unwrap();
+    assert_eq!(
+        app.email_vault
+            .reveal(&saved.email, Some(&encrypted_email))
+            .unwrap(),
+        "mynickname@gmail.com"
+    );
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[actix_web::test]
+async fn unsubscribing_with_an_unknown_token_is_rejected_with_a_400() {
+    let app = spawn_app().await;
+    let response = get(&format!(
+        "{}/subscriptions/unsubscribe?token=notarealtoken",
+        app.address
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn the_unsubscribe_confirm_page_rejects_an_unknown_token_with_a_400() {
+    let app = spawn_app().await;
+    let response = get(&format!(
+        "{}/subscriptions/unsubscribe/confirm?token=notarealtoken",
+        app.address
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn an_old_confirmation_link_does_not_resubscribe_an_unsubscribed_subscriber()
+ {
+    let app = spawn_app().await;
+    let body = "name=FirstName%20LastName&email=mynickname%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 409);
+    let status = sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "unsubscribed");
+}
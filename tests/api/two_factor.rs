@@ -0,0 +1,209 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, spawn_app, spawn_app_with,
+};
+
+/// Decode an unpadded RFC 4648 base32 string
+fn base32_decode(encoded: &str) -> Vec<u8> {
+    const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in encoded.chars() {
+        buffer = (buffer << 5) | ALPHABET.find(c).unwrap() as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    bytes
+}
+
+/// The code an authenticator app shows `steps_ahead` 30-second steps from
+/// now
+fn totp_code(secret: &str, steps_ahead: i64) -> String {
+    let step = (chrono::Utc::now().timestamp() / 30 + steps_ahead) as u64;
+    let mut mac =
+        <Hmac<Sha1> as Mac>::new_from_slice(&base32_decode(secret)).unwrap();
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = usize::from(digest[19] & 0x0f);
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    format!("{:06}", binary % 1_000_000)
+}
+
+/// Log in and enroll the test user, returning the secret and the code that
+/// confirmed it
+async fn enable_two_factor(app: &TestApp) -> (String, String) {
+    app.test_user.login(app).await;
+    let response = app.post_totp("enroll", &serde_json::json!({})).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let secret = body["secret"].as_str().unwrap().to_owned();
+    let code = totp_code(&secret, 0);
+    let response = app
+        .post_totp("confirm", &serde_json::json!({ "code": &code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    (secret, code)
+}
+
+async fn log_in_with_password(app: &TestApp) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password,
+    }))
+    .await
+}
+
+#[actix_web::test]
+async fn enrolling_returns_an_otpauth_uri_for_the_secret() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.post_totp("enroll", &serde_json::json!({})).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let secret = body["secret"].as_str().unwrap();
+    let uri = body["otpauth_uri"].as_str().unwrap();
+    assert!(uri.starts_with(&format!(
+        "otpauth://totp/Melierx:{}?secret={}",
+        app.test_user.username, secret
+    )));
+    // Not enabled until a code confirms it
+    app.post_logout().await;
+    let response = log_in_with_password(&app).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[actix_web::test]
+async fn a_wrong_code_does_not_confirm_the_enrollment() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let response = app.post_totp("enroll", &serde_json::json!({})).await;
+    let body: serde_json::Value = response.json().await.unwrap();
+    let secret = body["secret"].as_str().unwrap();
+
+    let response = app
+        .post_totp(
+            "confirm",
+            &serde_json::json!({ "code": wrong_code(secret) }),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn login_asks_for_a_code_once_two_factor_is_enabled() {
+    let app = spawn_app().await;
+    let (secret, _) = enable_two_factor(&app).await;
+    app.post_logout().await;
+
+    let response = log_in_with_password(&app).await;
+    assert_is_redirect_to(&response, "/login/totp");
+    // Not logged in yet
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+
+    // The confirmation used the current code; the next one is in the window
+    let response = app.post_login_totp(&totp_code(&secret, 1)).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[actix_web::test]
+async fn a_wrong_code_at_login_is_rejected() {
+    let app = spawn_app().await;
+    let (secret, _) = enable_two_factor(&app).await;
+    app.post_logout().await;
+    log_in_with_password(&app).await;
+
+    let response = app.post_login_totp(&wrong_code(&secret)).await;
+
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+    // The password has to be entered again before the next attempt
+    let response = app.post_login_totp(&totp_code(&secret, 1)).await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn repeated_wrong_codes_lock_the_account() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.max_failures_per_username = 3;
+    })
+    .await;
+    let (secret, _) = enable_two_factor(&app).await;
+    app.post_logout().await;
+    for _ in 0..3 {
+        let response = log_in_with_password(&app).await;
+        assert_is_redirect_to(&response, "/login/totp");
+        app.post_login_totp(&wrong_code(&secret)).await;
+    }
+
+    let response = log_in_with_password(&app).await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts"));
+}
+
+#[actix_web::test]
+async fn a_code_cannot_be_used_twice() {
+    let app = spawn_app().await;
+    let (_, confirmation_code) = enable_two_factor(&app).await;
+    app.post_logout().await;
+
+    log_in_with_password(&app).await;
+    let response = app.post_login_totp(&confirmation_code).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[actix_web::test]
+async fn disabling_two_factor_requires_the_current_password() {
+    let app = spawn_app().await;
+    enable_two_factor(&app).await;
+
+    let response = app
+        .post_totp(
+            "disable",
+            &serde_json::json!({ "current_password": "wrong-password" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .post_totp(
+            "disable",
+            &serde_json::json!({ "current_password": &app.test_user.password }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    app.post_logout().await;
+    let response = log_in_with_password(&app).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+/// A code no step in the accepted window produces
+fn wrong_code(secret: &str) -> String {
+    let valid: Vec<_> = (-1..=2).map(|step| totp_code(secret, step)).collect();
+    (0..)
+        .map(|n| format!("{:06}", n))
+        .find(|code| !valid.contains(code))
+        .unwrap()
+}
@@ -0,0 +1,274 @@
+use reqwest::get;
+use secrecy::SecretString;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use melierx_backend::configuration::EmailStorage;
+use melierx_backend::subscriber_token::SubscriberAction;
+use melierx_backend::webhooks::{SIGNATURE_HEADER, sign_payload};
+use melierx_backend::webhooks::{SignupNotificationTrigger, WebhookEvent};
+
+use crate::helpers::{TestApp, email_accepted, spawn_app_with};
+
+async fn spawn_app_with_webhooks(
+    webhook_server: &MockServer,
+    max_retries: i16,
+) -> TestApp {
+    let target_url = format!("{}/hooks", webhook_server.uri());
+    spawn_app_with(|c| {
+        c.webhooks.target_url = Some(target_url);
+        c.webhooks.events = vec![WebhookEvent::SubscriberConfirmed];
+        c.webhooks.max_retries = max_retries;
+    })
+    .await
+}
+
+async fn subscribe_and_confirm(app: &TestApp) {
+    let body = "name=FirstName%20LastName&email=mynickname%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[actix_web::test]
+async fn confirming_a_subscriber_fires_a_signed_webhook() {
+    let webhook_server = MockServer::start().await;
+    let app = spawn_app_with_webhooks(&webhook_server, 5).await;
+
+    Mock::given(path("/hooks"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&webhook_server)
+        .await;
+
+    subscribe_and_confirm(&app).await;
+    app.dispatch_all_pending_webhooks().await;
+
+    let request = &webhook_server.received_requests().await.unwrap()[0];
+    let payload: serde_json::Value =
+        serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(payload["event"], "subscriber.confirmed");
+    assert_eq!(payload["data"]["email"], "mynickname@gmail.com");
+
+    let secret =
+        SecretString::from("webhook-signing-secret-shared-with-the-receiver");
+    let expected_signature =
+        format!("sha256={}", sign_payload(&secret, &request.body));
+    assert_eq!(
+        request.headers.get(SIGNATURE_HEADER).unwrap(),
+        expected_signature.as_str()
+    );
+}
+
+#[actix_web::test]
+async fn webhooks_that_keep_failing_are_dead_lettered() {
+    let webhook_server = MockServer::start().await;
+    let app = spawn_app_with_webhooks(&webhook_server, 1).await;
+
+    Mock::given(path("/hooks"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&webhook_server)
+        .await;
+
+    subscribe_and_confirm(&app).await;
+    app.dispatch_all_pending_webhooks().await;
+
+    let dead_letter =
+        sqlx::query!("SELECT event_type, n_retries FROM webhook_dead_letter")
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch dead-lettered webhook.");
+    assert_eq!(dead_letter.event_type, "subscriber.confirmed");
+    assert_eq!(dead_letter.n_retries, 1);
+
+    let n_queued =
+        sqlx::query!(r#"SELECT count(*) AS "n!" FROM webhook_delivery_queue"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .n;
+    assert_eq!(n_queued, 0);
+}
+
+#[actix_web::test]
+async fn disabled_events_are_not_queued() {
+    let webhook_server = MockServer::start().await;
+    let target_url = format!("{}/hooks", webhook_server.uri());
+    let app = spawn_app_with(|c| {
+        c.webhooks.target_url = Some(target_url);
+        c.webhooks.events = vec![WebhookEvent::IssueSent];
+    })
+    .await;
+
+    Mock::given(path("/hooks"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&webhook_server)
+        .await;
+
+    subscribe_and_confirm(&app).await;
+    app.dispatch_all_pending_webhooks().await;
+}
+
+#[actix_web::test]
+async fn the_signup_notification_fires_on_confirmation() {
+    let webhook_server = MockServer::start().await;
+    let notification_url = format!("{}/signups", webhook_server.uri());
+    let app = spawn_app_with(|c| {
+        c.webhooks.signup_notification_url = Some(notification_url);
+        c.webhooks.signup_notification_trigger =
+            SignupNotificationTrigger::Confirm;
+    })
+    .await;
+
+    Mock::given(path("/signups"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&webhook_server)
+        .await;
+
+    subscribe_and_confirm(&app).await;
+    app.dispatch_all_pending_webhooks().await;
+
+    let request = &webhook_server.received_requests().await.unwrap()[0];
+    let payload: serde_json::Value =
+        serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(payload["email"], "mynickname@gmail.com");
+    assert!(payload["timestamp"].is_string());
+    assert_eq!(payload["text"], "New subscriber: mynickname@gmail.com");
+}
+
+#[actix_web::test]
+async fn the_signup_notification_can_fire_before_confirmation() {
+    let webhook_server = MockServer::start().await;
+    let notification_url = format!("{}/signups", webhook_server.uri());
+    let app = spawn_app_with(|c| {
+        c.webhooks.signup_notification_url = Some(notification_url);
+        c.webhooks.signup_notification_trigger =
+            SignupNotificationTrigger::Subscribe;
+    })
+    .await;
+
+    Mock::given(path("/signups"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&webhook_server)
+        .await;
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+
+    let body = "name=FirstName%20LastName&email=mynickname%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_webhooks().await;
+}
+
+#[actix_web::test]
+async fn webhooks_carry_the_address_when_addresses_are_stored_hashed() {
+    let webhook_server = MockServer::start().await;
+    let target_url = format!("{}/hooks", webhook_server.uri());
+    let notification_url = format!("{}/signups", webhook_server.uri());
+    let app = spawn_app_with(|c| {
+        c.subscriber_emails.storage = EmailStorage::Hashed;
+        c.webhooks.target_url = Some(target_url);
+        c.webhooks.events = vec![
+            WebhookEvent::SubscriberConfirmed,
+            WebhookEvent::SubscriberUnsubscribed,
+        ];
+        c.webhooks.signup_notification_url = Some(notification_url);
+        c.webhooks.signup_notification_trigger =
+            SignupNotificationTrigger::Confirm;
+    })
+    .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&webhook_server)
+        .await;
+
+    subscribe_and_confirm(&app).await;
+    let subscriber_id = sqlx::query_scalar!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    get(format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        app.address,
+        app.subscriber_token(subscriber_id, SubscriberAction::Unsubscribe)
+    ))
+    .await
+    .unwrap()
+    .error_for_status()
+    .unwrap();
+    app.dispatch_all_pending_webhooks().await;
+
+    let payloads: Vec<serde_json::Value> = webhook_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect();
+    let events: Vec<_> = payloads
+        .iter()
+        .filter(|payload| payload["event"].is_string())
+        .collect();
+    assert_eq!(events.len(), 2);
+    for event in events {
+        assert_eq!(event["data"]["email"], "mynickname@gmail.com");
+    }
+    let notification = payloads
+        .iter()
+        .find(|payload| payload["text"].is_string())
+        .unwrap();
+    assert_eq!(notification["email"], "mynickname@gmail.com");
+    assert_eq!(notification["text"], "New subscriber: mynickname@gmail.com");
+}
+
+#[actix_web::test]
+async fn the_signup_notification_carries_the_address_when_stored_hashed() {
+    let webhook_server = MockServer::start().await;
+    let notification_url = format!("{}/signups", webhook_server.uri());
+    let app = spawn_app_with(|c| {
+        c.subscriber_emails.storage = EmailStorage::Hashed;
+        c.webhooks.signup_notification_url = Some(notification_url);
+        c.webhooks.signup_notification_trigger =
+            SignupNotificationTrigger::Subscribe;
+    })
+    .await;
+    Mock::given(path("/signups"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&webhook_server)
+        .await;
+    Mock::given(path("/email"))
+        .respond_with(email_accepted())
+        .mount(&app.email_server)
+        .await;
+
+    let body = "name=FirstName%20LastName&email=mynickname%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_webhooks().await;
+
+    let request = &webhook_server.received_requests().await.unwrap()[0];
+    let payload: serde_json::Value =
+        serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(payload["email"], "mynickname@gmail.com");
+}